@@ -0,0 +1,28 @@
+//! Buys the token 0 of the NFT contract for 1000 units of the native value
+//! on the local node with the `//Alice` account:
+//!
+//! `cargo run --example buy_item -- <marketplace program id> <NFT contract id>`
+
+use gclient::GearApi;
+use gear_core::ids::ProgramId;
+use gstd::ActorId;
+use market_client::{MarketClient, Result};
+
+fn program_id(hex: &str) -> ProgramId {
+    let bytes = hex::decode(hex.trim_start_matches("0x")).expect("The id must be hex");
+    ProgramId::from(bytes.as_ref())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let market = program_id(&args.next().expect("The marketplace id is missing"));
+    let nft = program_id(&args.next().expect("The NFT contract id is missing"));
+
+    let client = MarketClient::new(GearApi::dev().await?, market);
+    let event = client
+        .buy_item(ActorId::new(nft.into_bytes()), 0.into(), 1_000)
+        .await?;
+    println!("{event:?}");
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! The typed client of the NFT marketplace for bots and backend services.
+//!
+//! The client sends [`MarketAction`]s through [`gclient`], waits for the replies
+//! and decodes them into [`MarketEvent`]s, so the SCALE payloads aren't built by hand.
+
+use codec::{Decode, Encode};
+use gclient::{EventListener, EventProcessor, GearApi, GearEvent};
+use gear_core::ids::{MessageId, ProgramId};
+use gstd::ActorId;
+use market_io::*;
+use primitive_types::U256;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The node or the transaction failed.
+    Client(gclient::Error),
+    /// The marketplace replied with an error (e.g. the action panicked).
+    Reply(String),
+    /// The reply or the state couldn't be decoded.
+    Decode(codec::Error),
+}
+
+impl From<gclient::Error> for Error {
+    fn from(error: gclient::Error) -> Self {
+        Self::Client(error)
+    }
+}
+
+impl From<codec::Error> for Error {
+    fn from(error: codec::Error) -> Self {
+        Self::Decode(error)
+    }
+}
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// The marketplace program addressed with the account of the API.
+pub struct MarketClient {
+    api: GearApi,
+    program_id: ProgramId,
+}
+
+impl MarketClient {
+    pub fn new(api: GearApi, program_id: ProgramId) -> Self {
+        Self { api, program_id }
+    }
+
+    pub fn program_id(&self) -> ProgramId {
+        self.program_id
+    }
+
+    /// Sends the action with the attached value and returns the event the marketplace replies with.
+    /// The gas limit is estimated before sending.
+    pub async fn send(&self, action: MarketAction, value: u128) -> Result<MarketEvent> {
+        let payload = action.encode();
+        let gas_info = self
+            .api
+            .calculate_handle_gas(None, self.program_id, payload.clone(), value, true)
+            .await?;
+        let mut listener = self.api.subscribe().await?;
+        let (message_id, _) = self
+            .api
+            .send_message_bytes(self.program_id, payload, gas_info.min_limit, value)
+            .await?;
+        reply(&mut listener, message_id).await
+    }
+
+    /// Lists the NFT for the fixed price (`None` to list it without a price).
+    pub async fn list_item(
+        &self,
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: Option<u128>,
+    ) -> Result<MarketEvent> {
+        self.send(
+            MarketAction::AddMarketData {
+                nft_contract_id,
+                ft_contract_id,
+                token_id,
+                price,
+                gate: None,
+                payees: vec![],
+                beneficiary: None,
+                metadata: None,
+            },
+            0,
+        )
+        .await
+    }
+
+    /// Buys the item, `value` is the price for the items sold for the native value.
+    pub async fn buy_item(
+        &self,
+        nft_contract_id: ActorId,
+        token_id: U256,
+        value: u128,
+    ) -> Result<MarketEvent> {
+        self.send(
+            MarketAction::BuyItem {
+                nft_contract_id,
+                token_id,
+                recipient: None,
+                relist_at: None,
+            },
+            value,
+        )
+        .await
+    }
+
+    /// Reads the marketplace state with the SCALE-encoded query,
+    /// e.g. `State::ItemInfo { .. }` decoded into `StateReply`.
+    pub async fn read_state<Q: Encode, R: Decode>(&self, query: Q) -> Result<R> {
+        let state = self
+            .api
+            .read_state_bytes(self.program_id, query.encode())
+            .await?;
+        Ok(R::decode(&mut state.as_ref())?)
+    }
+
+    /// Subscribes to the events the marketplace sends to the accounts.
+    pub async fn subscribe_events(&self) -> Result<MarketEvents> {
+        Ok(MarketEvents {
+            listener: self.api.subscribe().await?,
+            program_id: self.program_id,
+        })
+    }
+}
+
+/// The events sent by the marketplace (the replies and the notifications).
+pub struct MarketEvents {
+    listener: EventListener,
+    program_id: ProgramId,
+}
+
+impl MarketEvents {
+    /// Waits for the next event of the marketplace, the payloads that aren't events are skipped.
+    pub async fn next(&mut self) -> Result<(ActorId, MarketEvent)> {
+        let program_id = self.program_id.into_bytes();
+        let event = self
+            .listener
+            .proc(|event| match event {
+                gclient::Event::Gear(GearEvent::UserMessageSent { message, .. })
+                    if message.source.0 == program_id =>
+                {
+                    MarketEvent::decode(&mut message.payload.as_ref())
+                        .ok()
+                        .map(|event| (ActorId::new(message.destination.0), event))
+                }
+                _ => None,
+            })
+            .await?;
+        Ok(event)
+    }
+}
+
+async fn reply(listener: &mut EventListener, message_id: MessageId) -> Result<MarketEvent> {
+    let (_, reply) = listener.reply_bytes_on(message_id).await?;
+    let payload = reply.map_err(Error::Reply)?;
+    Ok(MarketEvent::decode(&mut payload.as_ref())?)
+}
@@ -0,0 +1,38 @@
+//! The tests run against the marketplace deployed on a local node:
+//! `MARKET_ID=0x.. cargo test -- --ignored`
+
+use gclient::GearApi;
+use gear_core::ids::ProgramId;
+use market_client::*;
+use market_io::*;
+
+async fn client() -> MarketClient {
+    let id = std::env::var("MARKET_ID").expect("MARKET_ID isn't set");
+    let id = hex::decode(id.trim_start_matches("0x")).expect("MARKET_ID must be hex");
+    let api = GearApi::dev().await.expect("The node isn't running");
+    MarketClient::new(api, ProgramId::from(id.as_ref()))
+}
+
+#[tokio::test]
+#[ignore]
+async fn unknown_item_is_an_error_reply() {
+    let client = client().await;
+    let res = client
+        .send(
+            MarketAction::Item {
+                nft_contract_id: 2.into(),
+                token_id: u64::MAX.into(),
+            },
+            0,
+        )
+        .await;
+    assert!(matches!(res, Err(Error::Reply(_))));
+}
+
+#[tokio::test]
+#[ignore]
+async fn buying_unlisted_item_fails() {
+    let client = client().await;
+    let res = client.buy_item(2.into(), u64::MAX.into(), 0).await;
+    assert!(matches!(res, Err(Error::Reply(_))));
+}
@@ -1,20 +1,405 @@
 #![no_std]
-use gstd::{prelude::*, ActorId};
+use core::fmt;
+use gstd::{prelude::*, ActorId, CodeId};
 use primitive_types::{H256, U256};
 
+pub type TransactionId = u64;
+
+/// The maximum size in bytes of the metadata of listings and offers.
+pub const MAX_METADATA_LEN: usize = 256;
+
+/// The maximum length of the item slug (see [`MarketAction::SetSlug`]).
+pub const MAX_SLUG_LEN: usize = 64;
+
+/// The maximum size in bytes of the message with an action,
+/// checked before the action is decoded.
+pub const MAX_PAYLOAD_SIZE: usize = 128 * 1024;
+
+/// The maximum numbers of entries in the actions (see [`MarketAction::check_bounds`]).
+pub const MAX_PAYEES: usize = 10;
+pub const MAX_TREASURIES: usize = 10;
+pub const MAX_FEE_TIERS: usize = 20;
+pub const MAX_AUCTION_CURRENCIES: usize = 10;
+/// The maximum number of tokens in one OTC deal.
+pub const MAX_DEAL_ITEMS: usize = 10;
+/// The maximum number of tokens in a drop or a launch.
+pub const MAX_BATCH_LEN: usize = 100;
+/// The maximum number of rounds in the closing window of a candle auction.
+pub const MAX_CANDLE_ROUNDS: usize = 100;
+/// The number of blocks the marketplace waits for the reply of the NFT or FT contract,
+/// after which the call fails and the item is unlocked.
+pub const REPLY_TIMEOUT: u32 = 100;
+
+/// The minimum time in milliseconds without the admin heartbeat (7 days)
+/// after which the emergency mode is activated.
+pub const MIN_EMERGENCY_TIMELOCK: u64 = 604_800_000;
+
+/// The maximum number of open offers on an item unless the admin sets another limit.
+pub const DEFAULT_MAX_OFFERS: u32 = 50;
+
+/// The action categories that can be paused with [`MarketAction::SetPausedActions`].
+pub const PAUSE_LISTINGS: u32 = 1;
+pub const PAUSE_SALES: u32 = 1 << 1;
+pub const PAUSE_AUCTIONS: u32 = 1 << 2;
+pub const PAUSE_SETTLEMENTS: u32 = 1 << 3;
+pub const PAUSE_OFFERS: u32 = 1 << 4;
+pub const PAUSE_ORDERS: u32 = 1 << 5;
+pub const PAUSE_DROPS: u32 = 1 << 6;
+
 #[derive(Debug, Encode, Decode, TypeInfo)]
 pub struct InitMarket {
     pub admin_id: ActorId,
     pub treasury_id: ActorId,
     pub treasury_fee: u8,
+    /// The auction duration bounds (if it is `None` then the default bounds are used).
+    pub auction_bounds: Option<AuctionBounds>,
+}
+
+/// The emergency mode armed by the admin.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct EmergencySwitch {
+    /// The time in milliseconds without the admin heartbeat after which the mode is active.
+    pub timelock: u64,
+    pub heartbeat_at: u64,
+}
+
+/// The governance program that changes the marketplace parameters instead of the admin.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct Governance {
+    pub program_id: ActorId,
+    /// The time in milliseconds between proposing the change and executing it.
+    pub timelock: u64,
+}
+
+/// The parameter change proposed by the governance program.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct PendingChange {
+    pub change_id: ChangeId,
+    pub action: MarketAction,
+    /// The block timestamp from which the change can be executed.
+    pub executable_at: u64,
+}
+
+/// The bounds of auction parameters in milliseconds.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct AuctionBounds {
+    pub min_duration: u64,
+    pub max_duration: u64,
+    pub min_bid_period: u64,
+}
+
+impl Default for AuctionBounds {
+    fn default() -> Self {
+        Self {
+            // 1 minute
+            min_duration: 60_000,
+            // 30 days
+            max_duration: 2_592_000_000,
+            // 1 minute
+            min_bid_period: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub enum MarketErr {
+    /// The zero address was indicated.
+    ZeroAddress,
+    /// The item owner tried to buy his own item.
+    SelfPurchase,
+    /// The item owner tried to bid in his own auction.
+    SelfBid,
+    /// The item owner tried to make an offer on his own item.
+    SelfOffer,
+    /// The marketplace itself was indicated as the treasury.
+    TreasuryIsMarketplace,
+    /// The actor is banned on the marketplace.
+    BannedActor,
+    /// The message to the NFT contract failed.
+    NftCall(NftCallError),
+    /// The payment transfer failed.
+    PaymentFailed,
+    /// The price feed program isn't registered.
+    PriceFeedNotSet,
+    /// The price feed program failed to reply with the rate.
+    PriceFeedFailed,
+    /// The rate of the price feed is older than the allowed staleness.
+    StalePrice,
+    /// The price is less than the minimum price of the currency or exceeds its maximum price.
+    PriceOutOfBounds,
+    /// The NFT contract doesn't report the marketplace as approved for the token.
+    MarketplaceNotApproved,
+    /// The currency of the bid doesn't match the currency of the auction.
+    WrongCurrency,
+    /// The signature of the order doesn't match the seller.
+    InvalidSignature,
+    /// The account doesn't own the token or the token is held in the marketplace custody for another owner.
+    NotOwner,
+    /// The NFTs of the collection are soulbound or transfer-locked.
+    NonTransferable,
+    /// The message with the action exceeds [`MAX_PAYLOAD_SIZE`].
+    PayloadTooLarge,
+    /// A vector or a string of the action exceeds its maximum length.
+    LimitExceeded,
+}
+
+/// The price bounds of the currency.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct CurrencyConfig {
+    /// The minimum price in the smallest units of the currency.
+    pub min_price: u128,
+    /// The decimal precision of the currency: prices can't exceed 10^15 whole units.
+    pub decimals: u8,
+    /// Whether the FT contract deducts a fee from the transferred amount.
+    /// The payments escrowed in such a currency are measured by the balance of the marketplace,
+    /// so the sales, offers and orders are based on the received amount.
+    /// The auctions in such a currency aren't allowed.
+    pub fee_on_transfer: bool,
+}
+
+/// The price feed program that converts the reference prices of listings
+/// into the amounts of native value or fungible tokens.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct PriceFeed {
+    pub program_id: ActorId,
+    /// The maximum age of the rate in milliseconds.
+    pub max_staleness: u64,
+}
+
+/// The action the price feed program must handle.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum PriceFeedAction {
+    /// Requests the latest rate of the currency (`None` for a native value).
+    LatestRate { ft_contract_id: Option<ActorId> },
+}
+
+/// The reply of the price feed program.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum PriceFeedEvent {
+    LatestRate {
+        /// The amount of the currency (in its smallest units) equal to one unit of the reference price.
+        rate: u128,
+        /// The timestamp of the rate update in milliseconds.
+        updated_at: u64,
+    },
+}
+
+/// The actions the yield program must handle.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum YieldAction {
+    /// Notifies that the marketplace has transferred the escrowed tokens to the program,
+    /// the program must reply with [`YieldEvent::Deposited`].
+    Deposit {
+        ft_contract_id: ActorId,
+        amount: u128,
+    },
+    /// Requests the program to transfer the deposited tokens with the accumulated yield
+    /// back to the marketplace.
+    Withdraw {
+        ft_contract_id: ActorId,
+        amount: u128,
+    },
+}
+
+/// The reply of the yield program to [`YieldAction`].
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum YieldEvent {
+    /// The tokens are transferred to the marketplace.
+    Withdrawn {
+        amount: u128,
+        /// The accumulated yield transferred in addition to `amount`
+        /// (the marketplace pays only the yield it actually received).
+        yield_amount: u128,
+    },
+    /// The deposit is credited, the marketplace requires it before the offer is deposited.
+    Deposited { amount: u128 },
+}
+
+/// The listing encoded in the payload of the NFT transfer to the marketplace
+/// (see [`MarketAction::OnNftReceived`]).
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct TransferListing {
+    /// The FT contract address (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    /// The NFT price (if it is `None` then the item is not on the sale).
+    pub price: Option<u128>,
+}
+
+/// The action the registry program must handle.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RegistryAction {
+    /// Requests the vetted NFT and FT contracts.
+    VettedContracts,
+}
+
+/// The reply of the registry program.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RegistryEvent {
+    VettedContracts {
+        nft_contracts: Vec<ActorId>,
+        ft_contracts: Vec<ActorId>,
+    },
+}
+
+/// The action the randomness provider must handle.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RandomnessAction {
+    /// Requests the random seed for the subject (the hash of the [`ItemId`] of the auction)
+    /// bound to the first block after `timestamp` (the end of the auction fixed at its creation).
+    /// The provider must refuse the requests before the timestamp
+    /// and reply with the same seed to the requests with the same subject and timestamp.
+    Random { subject: H256, timestamp: u64 },
+}
+
+/// The reply of the randomness provider.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+pub enum RandomnessEvent {
+    Random(H256),
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub enum NftCallError {
+    /// The message couldn't be sent to the NFT contract.
+    SendError,
+    /// The NFT contract didn't reply in time.
+    Timeout,
+    /// The NFT contract failed to execute the message.
+    Rejected,
+    /// The reply of the NFT contract can't be decoded or is not the expected one.
+    WrongReply,
+}
+
+/// Defines how royalties from `NFTPayout` replies are applied on sales.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub enum RoyaltyMode {
+    /// Royalties are always paid.
+    #[default]
+    Enforced,
+    /// Royalties are paid unless the buyer has opted out.
+    Optional,
+    /// Royalties are never paid, the whole amount goes to the seller.
+    Disabled,
+}
+
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
+pub struct RoyaltyConfig {
+    pub mode: RoyaltyMode,
+    /// The maximum total royalty in basis points of the sale amount
+    /// (if it is `None` then royalties are not capped).
+    pub cap: Option<u16>,
+}
+
+/// The treasury fee discount for traders with the indicated cumulative volume.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct FeeTier {
+    /// The minimum traded volume to reach the tier.
+    pub volume: u128,
+    /// The treasury fee in basis points.
+    pub fee: u16,
+}
+
+/// The custom settings of the collection.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct CollectionConfig {
+    /// The treasury fee in basis points that replaces the regular fee
+    /// and the volume discounts on sales of the collection.
+    pub treasury_fee: Option<u16>,
+    /// The program that reports the traits of the collection tokens for trait offers.
+    pub metadata_program: Option<ActorId>,
+    /// The NFT contract reported no royalties on a sale of the collection, so the NFTs
+    /// are transferred without requesting the payouts (it is cached after the first sale).
+    pub royalty_free: bool,
+    /// The NFTs of the collection can't be transferred, so they can't be listed or bought.
+    pub soulbound: bool,
+}
+
+pub type OrderId = u64;
+pub type OfferId = u64;
+pub type DropId = u64;
+pub type OperationId = u64;
+pub type VestingId = u64;
+pub type TradeId = u64;
+pub type ChangeId = u64;
+
+/// The tokens of the collection sold at a fixed price on a first-come-first-served basis.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct TokenDrop {
+    pub drop_id: DropId,
+    pub creator: ActorId,
+    pub nft_contract_id: ActorId,
+    pub ft_contract_id: Option<ActorId>,
+    pub price: u128,
+    /// The tokens that haven't been sold yet.
+    pub token_ids: Vec<U256>,
+    /// The maximum number of tokens that one account can buy.
+    pub wallet_limit: u32,
+    /// The sale window in milliseconds.
+    pub starts_at: u64,
+    pub ends_at: u64,
+    /// The number of tokens bought by each account.
+    pub purchases: BTreeMap<ActorId, u32>,
+}
+
+/// A standing order in the collection order book.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct Order {
+    pub id: OrderId,
+    pub owner: ActorId,
+    /// The token for sale (`None` for buy orders that accept any token of the collection).
+    pub token_id: Option<U256>,
+    pub price: u128,
+}
+
+/// The standing buy order for the token that is executed when the token is listed
+/// at the same or lower price.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct LimitOrder {
+    pub id: OrderId,
+    pub owner: ActorId,
+    pub ft_contract_id: Option<ActorId>,
+    /// The escrowed maximum price.
+    pub price: u128,
+    pub expires_at: u64,
+}
+
+/// The attribute of a token reported by the metadata program of the collection.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct TokenTrait {
+    pub name: String,
+    pub value: String,
+}
+
+/// The escrowed offer for any token of the collection that has the trait.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct TraitOffer {
+    /// The trait offers share the ids with the orders.
+    pub offer_id: OrderId,
+    pub owner: ActorId,
+    pub ft_contract_id: Option<ActorId>,
+    pub price: u128,
+    pub token_trait: TokenTrait,
+}
+
+/// Standing buy and sell orders for one collection in one currency.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
+pub struct OrderBook {
+    pub buy_orders: Vec<Order>,
+    pub sell_orders: Vec<Order>,
 }
 
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub struct Offer {
+    pub offer_id: OfferId,
+    /// The hash of the currency and the price (offers are addressed by `offer_id`).
     pub hash: H256,
     pub id: ActorId,
     pub ft_contract_id: Option<ActorId>,
     pub price: u128,
+    /// The account that receives the NFT if the offer is accepted
+    /// (if it is `None` then the offer author receives it).
+    pub recipient: Option<ActorId>,
+    /// The opaque data of the offer author, e.g. a campaign id.
+    pub metadata: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
@@ -24,6 +409,66 @@ pub struct Auction {
     pub ended_at: u64,
     pub current_price: u128,
     pub current_winner: ActorId,
+    /// The number of times the auction was extended by late bids.
+    pub extensions: u32,
+    /// The currencies accepted for bids in a multi-currency auction
+    /// (if it is empty then only the item currency is accepted).
+    /// The item currency is the currency of the current bid.
+    pub currencies: Vec<Option<ActorId>>,
+    /// The rounds of the closing window of a candle auction.
+    pub candle: Option<Candle>,
+}
+
+/// The closing window of a candle auction divided into rounds.
+/// The round 0 is the time before the closing window.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
+pub struct Candle {
+    pub rounds: u32,
+    /// The duration of the closing window in milliseconds before the auction end.
+    pub closing_window: u64,
+    /// The rounds in which the lead changed and the leading bids at their end,
+    /// the last one is the bid of the current winner.
+    /// Their escrows are held until the settlement.
+    pub leaders: Vec<(u32, Bid)>,
+    /// The round drawn at the settlement.
+    pub drawn_round: Option<u32>,
+    /// The minimum price of the auction, it becomes the current price
+    /// if no bid leads at the end of the drawn round.
+    pub min_price: u128,
+}
+
+impl Candle {
+    /// Returns the round of the bid made at `timestamp` in the auction ending at `ended_at`.
+    pub fn round_at(&self, ended_at: u64, timestamp: u64) -> u32 {
+        let window_start = ended_at.saturating_sub(self.closing_window);
+        if timestamp < window_start {
+            return 0;
+        }
+        let round_duration = self.closing_window / u64::from(self.rounds);
+        let round = (timestamp - window_start) / round_duration + 1;
+        round.min(u64::from(self.rounds)) as u32
+    }
+
+    /// Returns the end of the round in the auction ending at `ended_at`.
+    pub fn round_end(&self, ended_at: u64, round: u32) -> u64 {
+        if round == self.rounds {
+            return ended_at;
+        }
+        let round_duration = self.closing_window / u64::from(self.rounds);
+        ended_at.saturating_sub(self.closing_window) + u64::from(round) * round_duration
+    }
+
+    /// Returns the escrow of the leading bids held in addition to the bid of the current winner.
+    pub fn outbid_escrow(&self, current_winner: &ActorId) -> u128 {
+        let winning = match self.leaders.last() {
+            Some((_, bid)) if bid.id == *current_winner => 1,
+            _ => 0,
+        };
+        self.leaders[..self.leaders.len() - winning]
+            .iter()
+            .map(|(_, bid)| bid.price)
+            .sum()
+    }
 }
 
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
@@ -32,16 +477,174 @@ pub struct Bid {
     pub price: u128,
 }
 
+/// The paid promotion of a listing.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
+pub struct Promotion {
+    /// The total value paid for the promotion.
+    pub paid: u128,
+    /// The block number until which the listing is featured.
+    pub ends_at: u32,
+}
+
 #[derive(Debug, Encode, Decode, TypeInfo, Clone, Default)]
 pub struct Item {
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
     pub owner_id: ActorId,
     pub ft_contract_id: Option<ActorId>,
     pub price: Option<u128>,
     pub auction: Option<Auction>,
     pub offers: Vec<Offer>,
+    /// The account and the id of the transaction that currently
+    /// processes the item in a multi-message flow.
+    pub locked_by: Option<(ActorId, TransactionId)>,
+    pub featured: Option<Promotion>,
+    /// The fungible token contract address and the minimum balance
+    /// of that token required to buy the item.
+    pub gate: Option<(ActorId, u128)>,
+    /// The accounts that share the seller's proceeds and their shares in basis points
+    /// (if it is empty then the owner receives all proceeds).
+    pub payees: Vec<(ActorId, u16)>,
+    /// The account that receives the seller's proceeds instead of the owner
+    /// (`None` for the owner).
+    pub beneficiary: Option<ActorId>,
+    /// The price in the units of the price feed reference (e.g. USD cents),
+    /// the amount to pay is computed at the time of purchase.
+    pub reference_price: Option<u128>,
+    /// The item is listed by the collection creator: the sale is royalty-free
+    /// and the primary sale treasury fee is applied.
+    pub primary_sale: bool,
+    /// The opaque data of the listing, e.g. a campaign id (it is cleared on the sale).
+    pub metadata: Option<Vec<u8>>,
+    /// The offers that are accepted at once when they are made (it is cleared on the sale).
+    pub auto_accept: Option<AutoAccept>,
+    /// The scheduled discount of the listed price
+    /// (it is cleared on the sale and when the price or the currency is changed).
+    pub flash_sale: Option<FlashSale>,
+    /// Whether the NFT is held by the marketplace until the auction is over.
+    pub custody: bool,
+    /// The unique human-readable name of the item, e.g. for its URL.
+    pub slug: Option<String>,
 }
 
-#[derive(Debug, Encode, Decode, TypeInfo)]
+/// The state of the listing derived from the price, the auction and the lock of the item.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub enum ListingStatus {
+    /// The item can be bought for the fixed or the reference price.
+    OnSale,
+    /// The item is listed without a price, only the offers can be accepted.
+    NotForSale,
+    /// The auction is ongoing.
+    InAuction,
+    /// The item is processed in a multi-message flow, e.g. the payment is escrowed.
+    Escrowed,
+    /// The auction is over (no bids are accepted) and waits for the settlement.
+    Expired,
+}
+
+impl Item {
+    pub fn id(&self) -> ItemId {
+        ItemId::new(self.nft_contract_id, self.token_id)
+    }
+
+    /// Returns the status of the listing at the indicated block timestamp.
+    pub fn status(&self, now: u64) -> ListingStatus {
+        if self.locked_by.is_some() {
+            return ListingStatus::Escrowed;
+        }
+        match &self.auction {
+            // the bids are accepted until the end of the auction inclusive
+            Some(auction) if auction.ended_at < now => ListingStatus::Expired,
+            Some(_) => ListingStatus::InAuction,
+            None if self.price.is_some() || self.reference_price.is_some() => ListingStatus::OnSale,
+            None => ListingStatus::NotForSale,
+        }
+    }
+}
+
+/// The address of the item: the NFT contract and the token id.
+/// The marketplace storage is keyed by it.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ItemId {
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
+}
+
+impl ItemId {
+    pub fn new(nft_contract_id: ActorId, token_id: U256) -> Self {
+        Self {
+            nft_contract_id,
+            token_id,
+        }
+    }
+
+    /// Returns the BLAKE2b-256 hash of the SCALE-encoded item id,
+    /// e.g. to address the item off-chain with a fixed-size key.
+    pub fn hash(&self) -> H256 {
+        sp_core_hashing::blake2_256(&self.encode()).into()
+    }
+}
+
+impl From<(ActorId, U256)> for ItemId {
+    fn from((nft_contract_id, token_id): (ActorId, U256)) -> Self {
+        Self::new(nft_contract_id, token_id)
+    }
+}
+
+impl From<ItemId> for (ActorId, U256) {
+    fn from(item_id: ItemId) -> Self {
+        (item_id.nft_contract_id, item_id.token_id)
+    }
+}
+
+/// Formats the item id as the full hex address of the NFT contract and the token id
+/// separated by `/`, e.g. `0x01..ff/42`.
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#x}/{}",
+            H256::from_slice(self.nft_contract_id.as_ref()),
+            self.token_id
+        )
+    }
+}
+
+/// The temporary price of the listed item.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct FlashSale {
+    /// The discounted price in the currency of the item.
+    pub price: u128,
+    /// The sale window in milliseconds: the listed price applies again after `ends_at`.
+    pub starts_at: u64,
+    pub ends_at: u64,
+}
+
+/// The interest of a user in a token or a collection.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub nft_contract_id: ActorId,
+    /// The watched token (if it is `None` then the whole collection is watched).
+    pub token_id: Option<U256>,
+    /// The currency of `max_price`.
+    pub ft_contract_id: Option<ActorId>,
+    /// The price threshold: the listings at a higher price or in another currency are ignored
+    /// (if it is `None` then every listing at a fixed price triggers the watch).
+    pub max_price: Option<u128>,
+    /// The program that is notified along with the watcher.
+    pub notify_program: Option<ActorId>,
+}
+
+/// The standing sell order of the item owner.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct AutoAccept {
+    /// The currency of the accepted offers (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    /// The lowest price of the accepted offers.
+    pub min_price: u128,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub enum MarketAction {
     /// Adds NFT contract addresses that can be listed on marketplace.
     ///
@@ -69,11 +672,21 @@ pub enum MarketAction {
     /// * [`msg::source()`](gstd::msg::source) must be the NFT owner
     /// * `nft_contract_id` must be in the list of `approved_nft_contracts`
     /// * if item already exists, then it cannot be changed if there is an active auction
+    /// * the NFT contract must report the marketplace as approved for the token
+    /// after the approval request (otherwise fails with [`MarketErr::MarketplaceNotApproved`])
     ///
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
     /// * `token_id`: the NFT id
     /// * `price`: the NFT price (if it is `None` then the item is not on the sale)
+    /// * `gate`: the fungible token contract address and the minimum balance of that token
+    /// the buyer must hold (if it is `None` then anyone can buy the NFT)
+    /// * `payees`: the accounts that share the seller's proceeds and their shares in basis points
+    /// (if it is empty then the owner receives all proceeds, otherwise the shares must sum to 10000)
+    /// * `beneficiary`: the account that receives all proceeds instead of the owner,
+    /// e.g. a DAO treasury listing via an operator (it can't be set together with `payees`)
+    /// * `metadata`: the opaque data echoed in the event, e.g. a campaign id
+    /// (up to [`MAX_METADATA_LEN`] bytes)
     ///
     /// On success replies [`MarketEvent::MarketDataAdded`].
     AddMarketData {
@@ -81,33 +694,53 @@ pub enum MarketAction {
         ft_contract_id: Option<ActorId>,
         token_id: U256,
         price: Option<u128>,
+        gate: Option<(ActorId, u128)>,
+        payees: Vec<(ActorId, u16)>,
+        beneficiary: Option<ActorId>,
+        metadata: Option<Vec<u8>>,
     },
 
     /// Sells the NFT.
     ///
     /// # Requirements:
     /// * The NFT item must exists and be on sale.
-    /// * If the NFT is sold for a native Gear value, then a buyer must attach value equals to the price.
+    /// * If the NFT is sold for a native Gear value, then a buyer must attach value not less than the price.
     /// * If the NFT is sold for fungible tokens then a buyer must have enough tokens in the fungible token contract.
     /// * There must be no an opened auction on the item.
     ///
     /// Arguments:
     /// * `nft_contract_id`: NFT contract address
     /// * `token_id`: the token ID
+    /// * `recipient`: the account that receives the NFT, e.g. as a gift (`msg::source()` if it is `None`)
+    /// * `relist_at`: the price at which the bought item is listed by the buyer in the same currency
+    /// (it can't be indicated with a `recipient` other than the buyer)
     ///
-    /// On success replies [`MarketEvent::ItemSold`].
+    /// On success replies [`MarketEvent::ItemSold`]. If the item is relisted then
+    /// the buyer also receives [`MarketEvent::MarketDataAdded`]; the purchase
+    /// doesn't fail if the NFT contract rejects the approval of the relisted item.
+    /// The value attached in excess of the price (or to a purchase in fungible tokens) is returned.
     BuyItem {
         nft_contract_id: ActorId,
         token_id: U256,
+        recipient: Option<ActorId>,
+        relist_at: Option<u128>,
     },
 
     /// Creates an auction for selected item.
-    /// If the NFT item doesn't exist on the marketplace then it will be listed
+    /// If the NFT item doesn't exist on the marketplace then it will be listed.
+    /// The NFT is transferred to the marketplace and held in custody until the auction is over,
+    /// so the seller can't transfer it away during the auction.
+    /// The auction supersedes the fixed price of the listing in any currency: the price
+    /// isn't restored after the auction. The offers made before remain on the item
+    /// and can be withdrawn, but they can't be accepted until the auction is over.
     ///
     /// Requirements:
     /// * Only the item owner can start auction.
     /// * `nft_contract_id` must be in the list of `approved_nft_contracts`
     /// *  There must be no active auction.
+    /// * `duration` and `bid_period` must be within the auction bounds (see [`MarketAction::SetAuctionBounds`]).
+    /// * The NFT contract must report the marketplace as approved for the token
+    /// (otherwise fails with [`MarketErr::MarketplaceNotApproved`]).
     ///
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
@@ -116,8 +749,14 @@ pub enum MarketAction {
     /// * `min_price`: the starting price
     /// * `bid_period`: the time interval. If the auction ends before `exec::blocktimestamp() + bid_period`
     /// then the auction end time is delayed for `bid_period`.
+    /// * `beneficiary`: the account that receives the proceeds instead of the owner
+    /// (if it is set then the payees of the listing are removed)
+    /// * `auto_seed_from_offers`: the best offer on the item in `ft_contract_id`
+    /// that isn't less than `min_price` becomes the opening bid, so the auction doesn't start
+    /// below the standing offers (the offers with a recipient or deposited into a yield program are skipped)
     ///
-    /// On success replies [`MarketEvent::AuctionCreated`].
+    /// On success replies [`MarketEvent::AuctionCreated`],
+    /// if the transfer into custody fails replies [`MarketEvent::TransactionFailed`].
     CreateAuction {
         nft_contract_id: ActorId,
         ft_contract_id: Option<ActorId>,
@@ -125,6 +764,8 @@ pub enum MarketAction {
         min_price: u128,
         bid_period: u64,
         duration: u64,
+        beneficiary: Option<ActorId>,
+        auto_seed_from_offers: bool,
     },
 
     /// Adds a bid to an ongoing auction.
@@ -132,23 +773,31 @@ pub enum MarketAction {
     /// # Requirements:
     /// * The item must extsts.
     /// * The auction must exists on the item.
-    /// * If the NFT is sold for a native Gear value, then a buyer must attach value equals to the price indicated in the arguments.
+    /// * If the NFT is sold for a native Gear value, then a buyer must attach value not less than the price indicated in the arguments.
     /// * If the NFT is sold for fungible tokens then a buyer must have   enough tokens in the fungible token contract.
+    /// * `ft_contract_id` must be the currency of the auction, otherwise the bid fails with [`MarketErr::WrongCurrency`].
     /// * `price` must be greater then the current offered price for that item.
     ///
     /// # Arguments
     /// * `nft_contract_id`: the NFT contract address.
     /// * `token_id`: the NFT id.
+    /// * `ft_contract_id`: the currency of the bid (`None` for a native value).
     /// * `price`: the offered price.
     ///
     /// On success replies [`MarketEvent::BidAdded`].
+    /// If the price doesn't exceed the maximum bid of the current winner (see [`MarketAction::AddMaxBid`])
+    /// replies [`MarketEvent::BidOutbid`] and the bid value is credited to the claimable balance.
+    /// The value attached in excess of the price (or to a bid in fungible tokens) is returned.
     AddBid {
         nft_contract_id: ActorId,
         token_id: U256,
+        ft_contract_id: Option<ActorId>,
         price: u128,
     },
 
     /// Settles the auction.
+    /// Anyone can settle the auction: the NFT goes to the winner and the payment
+    /// goes to the seller regardless of the caller.
     ///
     /// Requirements:
     /// * The auction must be over.
@@ -158,7 +807,10 @@ pub enum MarketAction {
     /// * `token_id`: the NFT id
     ///
     /// On successful auction replies [`MarketEvent::AuctionSettled`].
-    /// If no bids were made replies [`MarketEvent::AuctionCancelled`].
+    /// If no bids were made or the seller no longer owns the NFT
+    /// replies [`MarketEvent::AuctionCancelled`] (the winner's bid is returned).
+    /// The NFT held in custody is returned to the seller on the cancellation,
+    /// if the return fails it is recorded as [`TransactionStep::NftReturn`].
     SettleAuction {
         nft_contract_id: ActorId,
         token_id: U256,
@@ -169,23 +821,34 @@ pub enum MarketAction {
     /// Requirements:
     /// * NFT item must exists and be listed on the marketplace.
     /// * There must be no an ongoing auction on the item.
-    /// * If a user makes an offer in native Gear value, then he must attach value equals to the price indicated in the arguments.
+    /// * If a user makes an offer in native Gear value, then he must attach value not less than the price indicated in the arguments.
     /// * If a user makes an offer in fungible tokens then he must have  enough tokens in the fungible token contract.
-    /// * The price can not be equal to 0.
-    /// * There must be no identical offers on the item.
+    /// * The price can not be equal to 0 and must be within the price bounds of the currency
+    /// (the minimum price of [`CurrencyConfig`] is the minimum escrow of the offers).
+    /// * If the item has the maximum number of offers (see [`MarketAction::SetMaxOffers`]),
+    /// the price must exceed the lowest offer in the same currency: that offer is evicted
+    /// and its escrow is returned with [`MarketEvent::OfferEvicted`] sent to its author.
     ///
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
     /// * `ft_contract_id`: the FT contract address
     /// * `token_id`: the NFT id
     /// * `price`: the offer price
+    /// * `recipient`: the account that receives the NFT if the offer is accepted (`msg::source()` if it is `None`)
+    /// * `metadata`: the opaque data echoed in the event, e.g. a campaign id
+    /// (up to [`MAX_METADATA_LEN`] bytes)
     ///
     /// On success replies [`MarketEvent::OfferAdded`].
+    /// If the offer meets the auto-accept threshold of the owner (see [`MarketAction::SetAutoAccept`])
+    /// then it's accepted at once and [`MarketEvent::OfferAccepted`] is replied instead.
+    /// The value attached in excess of the price (or to an offer in fungible tokens) is returned.
     AddOffer {
         nft_contract_id: ActorId,
         ft_contract_id: Option<ActorId>,
         token_id: U256,
         price: u128,
+        recipient: Option<ActorId>,
+        metadata: Option<Vec<u8>>,
     },
 
     /// Withdraws tokens.
@@ -193,100 +856,2240 @@ pub enum MarketAction {
     /// Requirements:
     /// * NFT item must exists and be listed on the marketplace.
     /// * Only the offer creator can withdraw his tokens.
-    /// * The offer with indicated hash must exist.
+    /// * The offer with indicated id must exist.
     ///
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
     /// * `token_id`: the NFT id
-    /// * `offer_hash`: the offer hash that includes the offer price and the address of fungible token contract.
+    /// * `offer_id`: the offer id
     ///
     /// On success replies [`MarketEvent::TokensWithdrawn`].
     Withdraw {
         nft_contract_id: ActorId,
         token_id: U256,
-        hash: H256,
+        offer_id: OfferId,
     },
 
     /// Accepts an offer.
+    /// The ongoing auction without bids is cancelled in favor of the offer.
     ///
     /// Requirements:
     /// * NFT item must exists and be listed on the marketplace.
     /// * Only owner can accept offer.
-    /// * There must be no ongoing auction.
-    /// * The offer with indicated hash must exist.
+    /// * There must be no ongoing auction with bids.
+    /// * The offer with indicated id must exist.
     ///
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
     /// * `token_id`: the NFT id
-    /// * `offer_hash`: the offer hash that includes the offer price and the address of fungible token contract.
+    /// * `offer_id`: the offer id
     ///
-    /// On success replies [`MarketEvent::OfferAccepted`].
+    /// On success replies [`MarketEvent::OfferAccepted`]. If the auction is cancelled
+    /// also sends [`MarketEvent::AuctionCancelled`] to the owner.
     AcceptOffer {
         nft_contract_id: ActorId,
         token_id: U256,
-        offer_hash: H256,
+        offer_id: OfferId,
     },
 
     Item {
         nft_contract_id: ActorId,
         token_id: U256,
     },
-}
 
-#[derive(Debug, Encode, Decode, TypeInfo)]
-pub enum MarketEvent {
-    MarketDataAdded {
-        nft_contract_id: ActorId,
-        owner: ActorId,
-        token_id: U256,
-        price: Option<u128>,
-    },
-    ItemSold {
-        owner: ActorId,
-        nft_contract_id: ActorId,
-        token_id: U256,
-    },
-    BidAdded {
+    /// Sets the fee per block for featuring a listing.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the promotion fee.
+    ///
+    /// # Arguments:
+    /// * `fee`: the fee per block in native value (if it is 0 then promotions are disabled)
+    SetPromotionFee(u128),
+
+    /// Marks the listing as featured for the indicated number of blocks.
+    /// If the listing is already featured, the promotion is extended.
+    ///
+    /// # Requirements:
+    /// * Only the item owner can promote the listing.
+    /// * The item must be on sale.
+    /// * The attached value must be equal to the promotion fee multiplied by `blocks`.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `blocks`: the number of blocks the listing is featured for
+    ///
+    /// On success replies [`MarketEvent::ItemPromoted`].
+    PromoteItem {
         nft_contract_id: ActorId,
         token_id: U256,
-        price: u128,
+        blocks: u32,
     },
-    AuctionCreated {
+
+    /// Sets the royalty mode and the royalty cap applied on all sales.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the royalty config.
+    /// * `cap` can't be greater than 10000 basis points.
+    ///
+    /// # Arguments:
+    /// * `config`: the royalty config
+    SetRoyaltyConfig(RoyaltyConfig),
+
+    /// Sets whether the buyer pays royalties when the royalty mode is [`RoyaltyMode::Optional`].
+    ///
+    /// # Arguments:
+    /// * `pay_royalties`: the buyer's choice (royalties are paid by default)
+    SetRoyaltyPreference { pay_royalties: bool },
+
+    /// Places a buy order for any token of the collection.
+    /// If there is a sell order at the same or lower price, the trade is executed at the sell order price
+    /// and the difference is returned to the buyer.
+    ///
+    /// # Requirements:
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`.
+    /// * If the order is in native Gear value, then the buyer must attach value not less than the price.
+    /// * If the order is in fungible tokens, then the buyer must have enough tokens in the fungible token contract.
+    /// * The price can not be equal to 0.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `price`: the maximum price the buyer pays
+    ///
+    /// On success replies [`MarketEvent::OrderPlaced`] or [`MarketEvent::OrdersMatched`].
+    PlaceBuyOrder {
         nft_contract_id: ActorId,
-        token_id: U256,
+        ft_contract_id: Option<ActorId>,
         price: u128,
     },
-    AuctionSettled {
+
+    /// Places a sell order for the token.
+    /// If there is a buy order at the same or higher price, the trade is executed at the buy order price.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the NFT owner.
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`.
+    /// * The price can not be equal to 0.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `token_id`: the NFT id
+    /// * `price`: the minimum price the seller accepts
+    ///
+    /// On success replies [`MarketEvent::OrderPlaced`] or [`MarketEvent::OrdersMatched`].
+    PlaceSellOrder {
         nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
         token_id: U256,
         price: u128,
     },
-    AuctionCancelled {
+
+    /// Removes the order from the order book.
+    /// The escrowed payment of a buy order is returned to the buyer.
+    ///
+    /// # Requirements:
+    /// * Only the order owner can remove the order.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address
+    /// * `order_id`: the order id
+    ///
+    /// On success replies [`MarketEvent::OrderRemoved`].
+    RemoveOrder {
         nft_contract_id: ActorId,
-        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        order_id: OrderId,
     },
-    NFTListed {
+
+    /// Accepts the highest offer in the indicated currency.
+    /// Among offers with equal prices the oldest one is accepted.
+    ///
+    /// Requirements:
+    /// * NFT item must exists and be listed on the marketplace.
+    /// * Only owner can accept offer.
+    /// * There must be no ongoing auction.
+    /// * There must be an offer in the indicated currency with the price not less than `min_price`.
+    ///
+    /// Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `ft_contract_id`: the fungible token contract address of the accepted offer (`None` for native value)
+    /// * `min_price`: the lowest acceptable offer price
+    ///
+    /// On success replies [`MarketEvent::OfferAccepted`].
+    AcceptBestOffer {
         nft_contract_id: ActorId,
-        owner: ActorId,
         token_id: U256,
-        price: Option<u128>,
-    },
-    ItemInfo(Item),
-    OfferAdded {
-        nft_contract_id: ActorId,
         ft_contract_id: Option<ActorId>,
-        token_id: U256,
-        price: u128,
+        min_price: u128,
     },
-    OfferAccepted {
+
+    /// Sends the caller's claimable balance of native value.
+    /// The native value that the marketplace fails to send during settlements
+    /// (including amounts below the existential minimum) is credited to the recipient's claimable balance.
+    /// The outbid and returned auction bids in native value are always credited to the bidder's claimable balance.
+    ///
+    /// Requirements:
+    /// * The claimable balance must be greater than the existential minimum (500).
+    ///
+    /// On success replies [`MarketEvent::Claimed`].
+    Claim,
+
+    /// Bans the actor from listing, bidding, making offers and buying.
+    ///
+    /// # Requirements:
+    /// * Only admin can ban actors.
+    ///
+    /// # Arguments:
+    /// * `actor_id`: the banned actor
+    BanActor(ActorId),
+
+    /// Removes the actor from the ban list.
+    ///
+    /// # Requirements:
+    /// * Only admin can unban actors.
+    ///
+    /// # Arguments:
+    /// * `actor_id`: the unbanned actor
+    UnbanActor(ActorId),
+
+    /// Removes the listing of a banned actor from the marketplace.
+    /// The escrowed funds (offers and the current auction bid) are returned to their owners.
+    ///
+    /// Requirements:
+    /// * Only admin can delist items.
+    /// * The item owner must be banned.
+    ///
+    /// Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    ///
+    /// On success replies [`MarketEvent::ItemDelisted`].
+    ForceDelist {
         nft_contract_id: ActorId,
         token_id: U256,
-        new_owner: ActorId,
-        price: u128,
     },
-    TokensWithdrawn {
-        nft_contract_id: ActorId,
-        token_id: U256,
-        price: u128,
+
+    /// Sets the treasury fee discount tiers based on the traded volume.
+    /// The highest tier reached by the seller's volume determines the treasury fee of the sale.
+    ///
+    /// # Requirements:
+    /// * Only admin can set fee tiers.
+    /// * Tiers must be sorted by the strictly increasing volume.
+    /// * Fees can't increase with the volume and can't exceed the base treasury fee.
+    ///
+    /// # Arguments:
+    /// * `fee_tiers`: the fee tiers (if empty then the base treasury fee is always applied)
+    SetFeeTiers(Vec<FeeTier>),
+
+    /// Subscribes the caller to the collection events.
+    /// Listing, sale and auction events of the collection are forwarded to subscribers
+    /// as messages with a limited amount of gas.
+    ///
+    /// # Requirements:
+    /// * The NFT contract must be approved.
+    /// * The caller can't be subscribed to more than 10 collections.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    SubscribeCollection(ActorId),
+
+    /// Unsubscribes the caller from the collection events.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    UnsubscribeCollection(ActorId),
+
+    /// Lists the NFT on behalf of its owner.
+    /// It is sent by the NFT contract when the owner approves the marketplace,
+    /// so approving and listing take a single interaction of the owner.
+    ///
+    /// # Requirements
+    /// * [`msg::source()`](gstd::msg::source) must be an approved NFT contract
+    /// that has already approved the marketplace for the token
+    /// * if item already exists, then it cannot be changed if there is an active auction
+    ///
+    /// Arguments:
+    /// * `owner_id`: the NFT owner
+    /// * `token_id`: the NFT id
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `price`: the NFT price (if it is `None` then the item is not on the sale)
+    ///
+    /// On success replies [`MarketEvent::MarketDataAdded`].
+    ListFromNft {
+        owner_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: Option<u128>,
+    },
+
+    /// Sets the bounds of auction parameters validated in [`MarketAction::CreateAuction`].
+    ///
+    /// # Requirements:
+    /// * Only admin can set the bounds.
+    /// * The minimum duration can't be greater than the maximum one.
+    /// * The minimum duration and bid period can't be equal to zero.
+    ///
+    /// # Arguments:
+    /// * `bounds`: the minimum and maximum auction duration and the minimum bid period in milliseconds
+    SetAuctionBounds(AuctionBounds),
+
+    /// Sets the keeper bounty: the share of the treasury fee paid to the account
+    /// that settles an auction after its end.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the keeper bounty.
+    /// * The bounty can't exceed 10000 basis points.
+    ///
+    /// # Arguments:
+    /// * `bounty`: the share of the treasury fee in basis points (0 disables rewards)
+    SetKeeperBounty(u16),
+
+    /// Registers the price feed program used for the listings with a reference price.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the price feed.
+    /// * `program_id` can't be the zero address.
+    ///
+    /// # Arguments:
+    /// * `price_feed`: the price feed program address and the maximum age of its rate
+    SetPriceFeed(PriceFeed),
+
+    /// Lists the NFT for the price denominated in the price feed reference (e.g. USD cents).
+    /// The buyer pays the amount of the currency computed with the latest rate of the price feed.
+    /// The buyer in native value attaches at least that amount and the excess is returned.
+    /// If the item exists then it replaces the fixed price of the item.
+    ///
+    /// # Requirements
+    /// * The price feed must be registered (see [`MarketAction::SetPriceFeed`]).
+    /// * [`msg::source()`](gstd::msg::source) must be the NFT owner
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`
+    /// * `reference_price` must be greater than zero
+    /// * if item already exists, then it cannot be changed if there is an active auction
+    /// * the NFT contract must report the marketplace as approved for the token
+    /// after the approval request (otherwise fails with [`MarketErr::MarketplaceNotApproved`])
+    ///
+    /// Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `token_id`: the NFT id
+    /// * `reference_price`: the price in the units of the reference
+    ///
+    /// On success replies [`MarketEvent::MarketDataAdded`] with no price.
+    AddReferencePricedData {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        reference_price: u128,
+    },
+
+    /// Registers the monitoring program that receives [`MarketEvent::Metrics`]
+    /// every `interval` blocks, or stops the heartbeats if it is `None`.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the monitor.
+    /// * The monitor can't be the zero address and the interval can't be equal to zero.
+    ///
+    /// # Arguments:
+    /// * `monitor`: the monitoring program address and the heartbeat interval
+    SetMonitor(Option<Monitor>),
+
+    /// Sends the metrics to the monitor and schedules the next heartbeat.
+    ///
+    /// # Requirements:
+    /// * Only the marketplace itself can send the heartbeat.
+    ///
+    /// # Arguments:
+    /// * the id of the heartbeat chain (heartbeats of the previous monitors are ignored)
+    Heartbeat(u64),
+
+    /// Retries the failed refund (see [`TransactionStep::Refund`])
+    /// or the failed return of the NFT held in custody (see [`TransactionStep::NftReturn`]).
+    ///
+    /// # Requirements:
+    /// * Only admin can retry transactions.
+    /// * The failed transaction must exist and its step must be a refund or a return of the NFT.
+    ///
+    /// # Arguments:
+    /// * `transaction_id`: the id of the failed transaction
+    ///
+    /// On success replies [`MarketEvent::TransactionRetried`],
+    /// if the transfer fails again replies [`MarketEvent::TransactionFailed`].
+    RetryTransaction(TransactionId),
+
+    /// Removes the failed transaction from the queue without retrying it.
+    ///
+    /// # Requirements:
+    /// * Only admin can abort transactions.
+    /// * The failed transaction must exist.
+    ///
+    /// # Arguments:
+    /// * `transaction_id`: the id of the failed transaction
+    ///
+    /// On success replies [`MarketEvent::TransactionAborted`].
+    AbortTransaction(TransactionId),
+
+    /// Registers the creator of the collection whose listings are primary sales
+    /// (royalty-free and with the primary sale treasury fee).
+    ///
+    /// # Requirements:
+    /// * Only admin can set collection creators.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `creator`: the collection creator (if it is `None` then the creator is removed)
+    SetCollectionCreator {
+        nft_contract_id: ActorId,
+        creator: Option<ActorId>,
+    },
+
+    /// Sets the treasury fee of primary sales.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the primary sale fee.
+    /// * The fee can't exceed the base treasury fee.
+    ///
+    /// # Arguments:
+    /// * `fee`: the fee in basis points (if it is `None` then the regular fee is applied)
+    SetPrimarySaleFee(Option<u16>),
+
+    /// Creates a drop: the tokens are sold at a fixed price to the first buyers
+    /// within the sale window, the drop closes when all tokens are sold.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the owner of the tokens.
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`.
+    /// * The tokens can't be listed on the marketplace or in another drop.
+    /// * There must be from 1 to 100 tokens.
+    /// * `price` and `wallet_limit` must be greater than zero.
+    /// * The sale window must end after it starts and after the current time.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `token_ids`: the tokens of the drop sold in the indicated order
+    /// * `price`: the price of one token
+    /// * `wallet_limit`: the maximum number of tokens that one account can buy
+    /// * `starts_at`, `ends_at`: the sale window in milliseconds
+    ///
+    /// On success replies [`MarketEvent::DropCreated`].
+    CreateDrop {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_ids: Vec<U256>,
+        price: u128,
+        wallet_limit: u32,
+        starts_at: u64,
+        ends_at: u64,
+    },
+
+    /// Buys the next token of the drop.
+    ///
+    /// # Requirements:
+    /// * The drop must exist and the sale window must be open.
+    /// * The buyer can't exceed the wallet limit of the drop.
+    /// * The buyer in native value must attach the value not less than the drop price.
+    ///
+    /// # Arguments:
+    /// * `drop_id`: the drop id
+    ///
+    /// On success replies [`MarketEvent::DropPurchased`]
+    /// and sends [`MarketEvent::DropSoldOut`] to the creator when the last token is sold.
+    BuyFromDrop(DropId),
+
+    /// Sets the exchange rate of the currency used to compare bids in multi-currency auctions.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    /// * The currency must be approved.
+    /// * `rate` can't be equal to zero.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `rate`: the value of the smallest unit of the currency in the common unit,
+    /// e.g. the same rate for the stablecoins pegged 1:1 (`None` removes the rate)
+    SetExchangeRate {
+        ft_contract_id: Option<ActorId>,
+        rate: Option<u128>,
+    },
+
+    /// Creates an auction that accepts bids in several currencies.
+    /// The bids are compared by the exchange rates at the time of bidding
+    /// (see [`MarketAction::SetExchangeRate`]) and the outbid bidder
+    /// is refunded in the currency of his bid.
+    ///
+    /// # Requirements:
+    /// * The same as for [`MarketAction::CreateAuction`].
+    /// * All currencies must be approved and have exchange rates.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the currency of `min_price`
+    /// * `currencies`: the other accepted currencies (`None` for a native value)
+    /// * the other arguments are the same as for [`MarketAction::CreateAuction`].
+    ///
+    /// On success replies [`MarketEvent::AuctionCreated`].
+    CreateMultiCurrencyAuction {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        currencies: Vec<Option<ActorId>>,
+        token_id: U256,
+        min_price: u128,
+        bid_period: u64,
+        duration: u64,
+        beneficiary: Option<ActorId>,
+        auto_seed_from_offers: bool,
+    },
+
+    /// Adds a bid in the indicated currency to an ongoing auction.
+    ///
+    /// # Requirements:
+    /// * The same as for [`MarketAction::AddBid`].
+    /// * The auction must accept the currency. In a multi-currency auction
+    /// the bid value must be greater than the value of the current bid.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address.
+    /// * `token_id`: the NFT id.
+    /// * `ft_contract_id`: the currency of the bid (`None` for a native value).
+    /// * `price`: the offered price.
+    ///
+    /// On success replies [`MarketEvent::BidAdded`].
+    AddBidInCurrency {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    },
+
+    /// Sets the custom treasury fee of the collection, e.g. 0% for a launch partner.
+    ///
+    /// # Requirements:
+    /// * Only admin can set collection fees.
+    /// * The fee can't exceed the maximum treasury fee.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `fee`: the fee in basis points (if it is `None` then the regular fee is applied)
+    SetCollectionFee {
+        nft_contract_id: ActorId,
+        fee: Option<u16>,
+    },
+
+    /// Sets the price bounds of the currency.
+    /// The listing prices, offers and auction starting prices out of the bounds
+    /// are rejected with [`MarketErr::PriceOutOfBounds`].
+    ///
+    /// # Requirements:
+    /// * Only admin can set currency configs.
+    /// * The currency must be approved.
+    /// * `decimals` can't exceed 22.
+    /// * The native value can't take a fee on transfer.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `config`: the price bounds and the transfer fee flag (if it is `None` then prices aren't restricted)
+    SetCurrencyConfig {
+        ft_contract_id: Option<ActorId>,
+        config: Option<CurrencyConfig>,
+    },
+
+    /// Withdraws all the caller's offers in the indicated currency.
+    ///
+    /// # Requirements:
+    /// * The offers on items locked by other transactions are skipped.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the currency of the offers (`None` for a native value)
+    ///
+    /// On success replies [`MarketEvent::OffersWithdrawn`]. At most 20 offers are withdrawn
+    /// by one message: the rest are withdrawn by the operation that the marketplace continues itself
+    /// (see [`MarketAction::ContinueOperation`]). If a transfer fails the action must be repeated
+    /// while the reply has a `cursor`.
+    WithdrawAllOffers { ft_contract_id: Option<ActorId> },
+
+    /// Changes the price of the listed item.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the item owner.
+    /// * There must be no ongoing auction on the item.
+    /// * The price must be within the bounds of the item currency.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `price`: the new price (if it is `None` then the item is not on the sale)
+    ///
+    /// On success replies [`MarketEvent::PriceUpdated`].
+    UpdatePrice {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        price: Option<u128>,
+    },
+
+    /// Changes the currency of the listed item keeping its price.
+    /// The offers in the previous currency remain valid.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the item owner.
+    /// * There must be no ongoing auction on the item.
+    /// * The currency must be approved.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `ft_contract_id`: the new currency (`None` for a native value)
+    ///
+    /// On success replies [`MarketEvent::CurrencyUpdated`].
+    UpdateCurrency {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+    },
+
+    /// Arms the emergency mode or disarms it if `timelock` is `None`.
+    /// Arming also records the admin heartbeat.
+    ///
+    /// # Requirements:
+    /// * Only admin can arm the emergency mode.
+    /// * `timelock` can't be less than [`MIN_EMERGENCY_TIMELOCK`].
+    ///
+    /// # Arguments:
+    /// * `timelock`: the time in milliseconds without the admin heartbeat
+    /// after which the escrows can be reclaimed with [`MarketAction::EmergencyReclaim`]
+    ArmEmergency { timelock: Option<u64> },
+
+    /// Records the admin heartbeat, so the emergency mode isn't activated.
+    ///
+    /// # Requirements:
+    /// * Only admin can send the heartbeat.
+    /// * The emergency mode must be armed.
+    EmergencyHeartbeat,
+
+    /// Returns the caller's escrows on the item: the offers and the winning bid.
+    /// The auction won by the caller stays open without a winner.
+    ///
+    /// # Requirements:
+    /// * The emergency mode must be armed and no admin heartbeat must be sent
+    /// during the timelock.
+    /// * The item must not be locked by another transaction.
+    /// * The caller must have escrows on the item.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    ///
+    /// On success replies [`MarketEvent::EmergencyReclaimed`].
+    /// The escrows whose transfer failed are kept and left out of the reply.
+    EmergencyReclaim {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+
+    /// Registers the staking program that receives a share of the treasury fees,
+    /// or stops the accrual of the share if it is `None`.
+    /// The fees that are already accrued can be distributed once a program is registered.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the staking program.
+    /// * The program can't be the zero address and the share can't exceed 10000.
+    ///
+    /// # Arguments:
+    /// * `staking`: the staking program address, its share and the distribution epoch
+    SetStaking(Option<Staking>),
+
+    /// Sends the fees accrued for the stakers in the indicated currency
+    /// to the staking program with [`MarketEvent::FeesDistributed`].
+    /// Anyone can trigger the distribution.
+    ///
+    /// # Requirements:
+    /// * The staking program must be registered.
+    /// * The epoch must pass since the previous distribution in that currency.
+    /// * The accrued fees must be greater than zero.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the currency of the fees (`None` for a native value)
+    ///
+    /// On success replies [`MarketEvent::FeesDistributed`].
+    /// If the transfer fails then the fees stay accrued and [`MarketEvent::FeesDistributionFailed`] is replied.
+    DistributeFees { ft_contract_id: Option<ActorId> },
+
+    /// Adds the maximum bid to an ongoing auction: the whole `max_price` is escrowed
+    /// and the bid automatically outbids the competing bids by the minimum increment
+    /// up to `max_price`. Only the effective price of the current bid is revealed.
+    ///
+    /// # Requirements:
+    /// * The auction must exist on the item and must accept only the item currency.
+    /// * The caller must not be the current winner.
+    /// * If the NFT is sold for a native Gear value, then a bidder must attach value not less than `max_price`.
+    /// * `max_price` must be greater than the current price.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `max_price`: the maximum price the bidder is willing to pay
+    ///
+    /// On success replies [`MarketEvent::BidAdded`] with the effective price.
+    /// If `max_price` doesn't exceed the maximum bid of the current winner
+    /// replies [`MarketEvent::BidOutbid`] and the bid value is credited to the claimable balance.
+    /// On settlement the winner pays the effective price and the rest of the maximum is returned.
+    AddMaxBid {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        max_price: u128,
+    },
+
+    /// Sets the threshold at which the offers on the item are accepted at once,
+    /// so the listing acts as a standing sell order.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the item owner.
+    /// * There must be no ongoing auction on the item.
+    /// * The currency must be approved.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `auto_accept`: the currency and the lowest price of the accepted offers
+    /// (if it is `None` then the offers wait for the owner)
+    ///
+    /// On success replies [`MarketEvent::AutoAcceptSet`]. The offer that meets the threshold
+    /// is accepted when it is made, see [`MarketAction::AddOffer`].
+    SetAutoAccept {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        auto_accept: Option<AutoAccept>,
+    },
+
+    /// Registers the sponsor program (e.g. a gas-voucher proxy) that is trusted
+    /// to forward purchases on behalf of buyers with [`MarketAction::ForwardedBuyItem`].
+    ///
+    /// # Requirements:
+    /// * Only admin can register forwarders.
+    ///
+    /// # Arguments:
+    /// * `forwarder`: the forwarder address
+    AddTrustedForwarder(ActorId),
+
+    /// Removes the forwarder from the trusted forwarders.
+    ///
+    /// # Requirements:
+    /// * Only admin can remove forwarders.
+    ///
+    /// # Arguments:
+    /// * `forwarder`: the forwarder address
+    RemoveTrustedForwarder(ActorId),
+
+    /// Buys the item on behalf of the buyer attested by the trusted forwarder,
+    /// so the buyer doesn't pay for the gas. The buyer pays the price in fungible tokens
+    /// and receives the NFT, the same as with [`MarketAction::BuyItem`].
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be a trusted forwarder.
+    /// * The item must be sold for fungible tokens.
+    /// * The buyer must approve tokens in advance.
+    ///
+    /// # Arguments:
+    /// * `buyer`: the account that pays for the item and receives the NFT
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    ///
+    /// On success replies [`MarketEvent::ItemSold`].
+    ForwardedBuyItem {
+        buyer: ActorId,
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+
+    /// Sets the NFT contract in which a receipt token is minted to the buyer on each sale,
+    /// or stops minting receipts if it is `None`.
+    /// The marketplace must be allowed to mint tokens in that contract.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the receipts contract.
+    ///
+    /// # Arguments:
+    /// * `receipts_contract_id`: the receipts NFT contract address
+    SetReceiptsContract(Option<ActorId>),
+
+    /// Mints the receipt of the sale and transfers it to the buyer.
+    /// The marketplace sends it to itself after the sale, so a failed mint doesn't affect the sale.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the marketplace itself.
+    ///
+    /// # Arguments:
+    /// * `receipt`: the sale details recorded in the receipt metadata
+    ///
+    /// On success sends [`MarketEvent::ReceiptMinted`] to the buyer.
+    MintReceipt(SaleReceipt),
+
+    /// Continues the operation that is split across several messages.
+    /// The marketplace checkpoints the progress of the operation in the state
+    /// and sends this action to itself until the operation is done,
+    /// so it's only needed to resume the operation manually.
+    ///
+    /// # Requirements:
+    /// * The operation must exist.
+    /// * [`msg::source()`](gstd::msg::source) must be the account that started the operation or the marketplace itself
+    /// (anyone can continue the payouts of a sale).
+    ///
+    /// # Arguments:
+    /// * `operation_id`: the operation id.
+    ///
+    /// On success sends the event of the operation to the account that started it
+    /// (e.g. [`MarketEvent::OffersWithdrawn`]) or replies [`MarketEvent::PayoutsPaid`].
+    ContinueOperation(OperationId),
+
+    /// Sets the code of the NFT contracts deployed by [`MarketAction::LaunchCollection`].
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// # Arguments:
+    /// * `code_id`: the code id of the NFT contract (if it is `None` then launches are disabled)
+    SetCollectionCode(Option<CodeId>),
+
+    /// Deploys a new NFT contract and lists its first tokens on the marketplace.
+    /// The NFT contract is added to `approved_nft_contracts` and the caller is registered
+    /// as the collection creator, so the first sales are primary sales.
+    /// The tokens are minted to the marketplace and kept by it until they are sold.
+    ///
+    /// # Requirements:
+    /// * The collection code must be set by the admin.
+    /// * There must be from 1 to 100 tokens.
+    /// * `ft_contract_id` must be in the list of `approved_ft_contracts`.
+    /// * `price` must be within the price bounds of the currency.
+    ///
+    /// # Arguments:
+    /// * `name`, `symbol`, `base_uri`: the collection settings of the NFT contract
+    /// * `tokens`: the metadata of the tokens listed first
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `price`: the price of each token
+    ///
+    /// On success replies [`MarketEvent::CollectionLaunched`]. If a mint fails
+    /// the rest of the tokens aren't minted and the reply contains the listed ones.
+    LaunchCollection {
+        name: String,
+        symbol: String,
+        base_uri: String,
+        tokens: Vec<LaunchToken>,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    },
+
+    /// Sets how many trades are kept by [`MarketAction::PruneTrades`].
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// # Arguments:
+    /// * `retention`: the retention of the trade history (if it is `None` then all the trades are kept)
+    SetTradeRetention(Option<TradeRetention>),
+
+    /// Removes the oldest trades that exceed the retention of the trade history.
+    /// Indexers can archive the trades with the `Trades` state query before pruning.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    /// * The trade retention must be set.
+    ///
+    /// On success replies [`MarketEvent::TradesPruned`]. At most 50 trades are removed
+    /// and 20 items are checked by one message: the action must be repeated while the reply has `remaining`.
+    PruneTrades,
+
+    /// Buys the token with the seller's sell order signed off-chain,
+    /// so the token doesn't need to be listed on the marketplace.
+    ///
+    /// # Requirements:
+    /// * The seller must sign the SCALE-encoded `(marketplace_id, epoch, order)` with its sr25519 key
+    /// and the `substrate` signing context, otherwise the purchase fails with [`MarketErr::InvalidSignature`].
+    /// `epoch` is the seller's order epoch (see [`MarketAction::CancelAllOrders`]), it starts from 0.
+    /// * The seller must own the token and approve the marketplace in the NFT contract.
+    /// * The order must not be expired and its nonce must not be used.
+    /// * The NFT and FT contracts must be approved. The token must not be on auction or in a drop.
+    /// * If the order is in a native Gear value, then the buyer must attach value not less than the price,
+    /// otherwise the buyer must approve the tokens in advance.
+    ///
+    /// # Arguments:
+    /// * `seller`: the account that signed the order
+    /// * `order`: the signed order
+    /// * `signature`: the seller's signature
+    ///
+    /// On success replies [`MarketEvent::ItemSold`].
+    FillSignedOrder {
+        seller: ActorId,
+        order: SignedOrder,
+        signature: [u8; 64],
+    },
+
+    /// Cancels the caller's signed order with the nonce (see [`MarketAction::FillSignedOrder`]).
+    ///
+    /// # Requirements:
+    /// * The nonce must not be used.
+    ///
+    /// On success replies [`MarketEvent::OrderCancelled`].
+    CancelOrder(u64),
+
+    /// Cancels all the caller's outstanding signed orders by moving to the next order epoch
+    /// (see [`MarketAction::FillSignedOrder`]). The used nonces remain used.
+    ///
+    /// On success replies [`MarketEvent::AllOrdersCancelled`].
+    CancelAllOrders,
+
+    /// Sets the yield program that receives the deposited escrows of offers.
+    /// The offers deposited before are recalled from the program they were deposited into.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// # Arguments:
+    /// * `yield_program_id`: the yield program address (if it is `None` then new deposits are disabled)
+    SetYieldProgram(Option<ActorId>),
+
+    /// Deposits the escrow of the offer in fungible tokens into the yield program.
+    /// The escrow is recalled when the offer is accepted or withdrawn,
+    /// the accumulated yield is paid to the author of the offer.
+    /// If the yield program doesn't return the escrow the acceptance or withdrawal fails
+    /// and can be repeated later. Only the yield actually received by the marketplace is paid.
+    ///
+    /// # Requirements:
+    /// * The yield program must be set.
+    /// * [`msg::source()`](gstd::msg::source) must be the author of the offer.
+    /// * The offer must be in fungible tokens and must not be deposited.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `offer_id`: the offer id
+    ///
+    /// On success replies [`MarketEvent::OfferDeposited`].
+    /// If the yield program doesn't acknowledge the deposit with [`YieldEvent::Deposited`]
+    /// it is recorded as [`TransactionStep::Deposit`] and the offer isn't deposited.
+    DepositOffer {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        offer_id: OfferId,
+    },
+
+    /// Schedules a temporary discount of the listed item. [`MarketAction::BuyItem`]
+    /// uses the discounted price within the sale window and the listed price after it.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the item owner.
+    /// * There must be no ongoing auction on the item and the item must be listed at a fixed price.
+    /// * The discounted price must be less than the listed price and within the price bounds of the currency.
+    /// * The sale window must end after it starts and after the current time.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `flash_sale`: the discounted price and the sale window (if it is `None` then the discount is cancelled)
+    ///
+    /// On success replies [`MarketEvent::FlashSaleSet`].
+    SetFlashSale {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        flash_sale: Option<FlashSale>,
+    },
+
+    /// Pauses the action categories or resumes them.
+    /// The admin actions, withdrawals, cancellations and claims are never paused.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the admin.
+    ///
+    /// # Arguments:
+    /// * `paused_actions`: the bitwise OR of the paused categories (e.g. [`PAUSE_AUCTIONS`]), 0 resumes all of them
+    SetPausedActions(u32),
+
+    /// Adds the caller's watch of the token or the collection
+    /// (the caller's previous watch of the same token or collection is replaced).
+    /// When the watched item is listed or its price is changed to one not exceeding the threshold,
+    /// [`MarketEvent::WatchTriggered`] is sent to the caller and to the caller's program
+    /// with a limited amount of gas.
+    ///
+    /// # Requirements:
+    /// * The NFT contract and the currency must be approved.
+    /// * The caller can't have more than 20 watches.
+    /// * `notify_program` can't be the zero address or the marketplace.
+    ///
+    /// # Arguments:
+    /// * `watch`: the watched token or collection, the price threshold and the program to notify
+    AddWatch(Watch),
+
+    /// Removes the caller's watch of the token or the collection.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the watched token (`None` for the watch of the whole collection)
+    RemoveWatch {
+        nft_contract_id: ActorId,
+        token_id: Option<U256>,
+    },
+
+    /// Sets the metadata program that reports the traits of the collection tokens.
+    ///
+    /// # Requirements:
+    /// * Only admin can set metadata programs.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `metadata_program`: the metadata program address (if it is `None` then trait offers can't be accepted)
+    SetMetadataProgram {
+        nft_contract_id: ActorId,
+        metadata_program: Option<ActorId>,
+    },
+
+    /// Adds an escrowed offer for any token of the collection that has the trait.
+    ///
+    /// # Requirements:
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`.
+    /// * The collection must have the metadata program.
+    /// * If the offer is in native Gear value, then the buyer must attach value not less than the price.
+    /// * If the offer is in fungible tokens, then the buyer must have enough tokens in the fungible token contract.
+    /// * The price can not be equal to 0 and must be within the price bounds of the currency.
+    /// * The trait can't exceed [`MAX_METADATA_LEN`] bytes.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `token_trait`: the trait the token must have
+    /// * `price`: the offered price
+    ///
+    /// On success replies [`MarketEvent::TraitOfferAdded`].
+    AddTraitOffer {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_trait: TokenTrait,
+        price: u128,
+    },
+
+    /// Accepts the trait offer selling the caller's token.
+    /// The trait of the token is checked by the metadata program of the collection.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the NFT owner.
+    /// * The metadata program must report that the token has the trait.
+    /// * There must be no ongoing auction on the token.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `offer_id`: the trait offer id
+    /// * `token_id`: the sold NFT id
+    ///
+    /// On success replies [`MarketEvent::TraitOfferAccepted`],
+    /// if the sale fails replies [`MarketEvent::OrderTransactionFailed`] and the offer remains.
+    AcceptTraitOffer {
+        nft_contract_id: ActorId,
+        offer_id: OrderId,
+        token_id: U256,
+    },
+
+    /// Withdraws the caller's trait offer and returns its escrow.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `offer_id`: the trait offer id
+    ///
+    /// On success replies [`MarketEvent::TraitOfferWithdrawn`],
+    /// if the escrow isn't returned replies [`MarketEvent::OrderTransactionFailed`] and the offer remains.
+    WithdrawTraitOffer {
+        nft_contract_id: ActorId,
+        offer_id: OrderId,
+    },
+
+    /// Sets the maximum number of open offers on an item.
+    /// The items that already have more offers keep them.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the limit.
+    /// * The limit can't be equal to zero.
+    ///
+    /// # Arguments:
+    /// * `max_offers`: the limit (if it is `None` then [`DEFAULT_MAX_OFFERS`] is applied)
+    SetMaxOffers(Option<u32>),
+
+    /// Compares the native value held for the accounts with the balance of the marketplace.
+    ///
+    /// # Requirements:
+    /// * Only admin can check the balance.
+    ///
+    /// On success replies [`MarketEvent::NativeBalance`], if the balance is less than
+    /// the held value replies [`MarketEvent::BalanceMismatch`] and sends it to the monitor.
+    CheckNativeBalance,
+
+    /// Sets whether the NFTs of the collection are sold without requesting the royalty payouts.
+    /// It is used to clear the cached flag after the NFT contract enables royalties.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the flag.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `royalty_free`: if it is `false` then the payouts are requested on the next sale
+    SetRoyaltyFree {
+        nft_contract_id: ActorId,
+        royalty_free: bool,
+    },
+
+    /// Hands the parameter changes over to the governance program or takes them back.
+    /// In the governance mode the actions of [`MarketAction::is_parameter_change`]
+    /// are applied only by executing the changes proposed by the governance program.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the governance if it isn't set,
+    /// otherwise it is changed only by an executed change.
+    /// * The governance program can't be the zero address.
+    ///
+    /// # Arguments:
+    /// * `governance`: the governance program and the timelock of the changes
+    /// (if it is `None` then the admin changes the parameters)
+    SetGovernance(Option<Governance>),
+
+    /// Proposes the parameter change that can be executed after the timelock.
+    ///
+    /// # Requirements:
+    /// * Only the governance program can propose changes.
+    /// * The action must be a parameter change.
+    ///
+    /// # Arguments:
+    /// * `action`: the parameter change
+    ///
+    /// On success replies [`MarketEvent::ChangeProposed`].
+    ProposeChange(Box<MarketAction>),
+
+    /// Executes the proposed change.
+    /// The change is applied by the message the marketplace sends to itself,
+    /// so the invalid change fails without affecting the execution.
+    ///
+    /// # Requirements:
+    /// * Only the governance program can execute changes.
+    /// * The timelock of the change must be over.
+    ///
+    /// # Arguments:
+    /// * `change_id`: the id of the change
+    ///
+    /// On success replies [`MarketEvent::ChangeExecuted`].
+    ExecuteChange(ChangeId),
+
+    /// Cancels the proposed change.
+    ///
+    /// # Requirements:
+    /// * Only the governance program can cancel changes.
+    ///
+    /// # Arguments:
+    /// * `change_id`: the id of the change
+    ///
+    /// On success replies [`MarketEvent::ChangeCancelled`].
+    CancelChange(ChangeId),
+
+    /// Splits the treasury fees among several treasuries, e.g. a DAO, the developers and an insurance fund.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the treasuries.
+    /// * The treasuries can't be the zero address or the marketplace.
+    /// * The weights must sum to 10000 basis points.
+    ///
+    /// # Arguments:
+    /// * `treasuries`: the treasuries and their weights in basis points
+    /// (if it is empty then the treasury indicated at the initialization receives all fees)
+    SetTreasuries(Vec<(ActorId, u16)>),
+
+    /// Places a limit buy order for the token even if it isn't listed.
+    /// The payment is escrowed and if the owner lists the token (see [`MarketAction::AddMarketData`])
+    /// in the same currency at the same or lower price before the order expires,
+    /// the trade is executed at the listing price and the difference is returned to the buyer.
+    ///
+    /// # Requirements:
+    /// * `nft_contract_id` must be in the list of `approved_nft_contracts`.
+    /// * If the order is in native Gear value, then the buyer must attach value not less than the price.
+    /// * If the order is in fungible tokens, then the buyer must have enough tokens in the fungible token contract.
+    /// * The price and the duration can not be equal to 0.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `price`: the maximum price the buyer pays
+    /// * `duration`: the order duration in milliseconds
+    ///
+    /// On success replies [`MarketEvent::LimitOrderPlaced`].
+    PlaceLimitOrder {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+        duration: u64,
+    },
+
+    /// Removes the limit order and returns the escrowed payment to the buyer.
+    ///
+    /// # Requirements:
+    /// * Only the order owner can remove the order before it expires,
+    /// anyone can remove the expired order.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `order_id`: the order id
+    ///
+    /// On success replies [`MarketEvent::LimitOrderRemoved`].
+    RemoveLimitOrder {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        order_id: OrderId,
+    },
+
+    /// Creates an OTC deal: the seller offers one or several tokens to the counterparty at a fixed price.
+    /// The deal is addressed by the hash of its terms (see [`Deal`]) that is given to the counterparty.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must own the tokens.
+    /// * The NFT and FT contracts must be approved. The tokens must not be on auction or in a drop.
+    /// * The deal must have from 1 to 10 different tokens, the price can not be equal to 0.
+    /// * The counterparty can't be the zero address or the seller.
+    ///
+    /// # Arguments:
+    /// * `counterparty`: the account that can accept the deal
+    /// * `items`: the NFT contracts and the ids of the tokens
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `price`: the price of all the tokens
+    /// * `expires_at`: the timestamp after which the deal can't be accepted
+    /// * `vesting`: the schedule of the seller's proceeds (see [`MarketAction::ClaimVested`])
+    ///
+    /// On success replies [`MarketEvent::DealCreated`].
+    CreateDeal {
+        counterparty: ActorId,
+        items: Vec<(ActorId, U256)>,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+        expires_at: u64,
+        vesting: Option<Vesting>,
+    },
+
+    /// Accepts the deal with the hash.
+    /// The price is split equally among the tokens (the last token receives the rounding remainder)
+    /// and the shares of the tokens that fail to be sold are returned to the counterparty.
+    ///
+    /// # Requirements:
+    /// * Only the counterparty can accept the deal before it expires.
+    /// * If the deal is in a native Gear value, then the counterparty must attach value not less than the price,
+    /// otherwise the counterparty must approve the tokens in advance.
+    ///
+    /// On success replies [`MarketEvent::DealAccepted`].
+    AcceptDeal(H256),
+
+    /// Cancels the deal with the hash.
+    ///
+    /// # Requirements:
+    /// * Only the seller can cancel the deal before it expires, anyone can cancel the expired deal.
+    ///
+    /// On success replies [`MarketEvent::DealCancelled`].
+    CancelDeal(H256),
+
+    /// Registers the unique human-readable slug of the item, so frontends can resolve it
+    /// to the collection and the token with the `ResolveSlug` state query.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be the item owner.
+    /// * The slug must have from 1 to [`MAX_SLUG_LEN`] lowercase ASCII letters, digits or hyphens.
+    /// * The slug must not be registered for another item.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `slug`: the slug (if it is `None` then the slug of the item is removed)
+    ///
+    /// On success replies [`MarketEvent::SlugSet`].
+    SetSlug {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        slug: Option<String>,
+    },
+
+    /// Sets whether the NFTs of the collection are soulbound or transfer-locked.
+    /// The NFT contracts don't report the lock, so the marketplace refuses to list
+    /// and to sell the NFTs of the marked collection instead of failing at the transfer.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the flag.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `soulbound`: whether the NFTs can't be transferred
+    SetSoulbound {
+        nft_contract_id: ActorId,
+        soulbound: bool,
+    },
+
+    /// Pays the seller the unlocked instalments of the vested proceeds of a deal
+    /// (see [`MarketAction::CreateDeal`]).
+    ///
+    /// # Requirements:
+    /// * Only the seller can claim the vested proceeds.
+    /// * At least one instalment must be unlocked and not claimed.
+    ///
+    /// # Arguments:
+    /// * `vesting_id`: the id of the vested proceeds
+    ///
+    /// On success replies [`MarketEvent::VestedClaimed`].
+    ClaimVested(VestingId),
+
+    /// Sets the registry program that reports the vetted NFT and FT contracts,
+    /// so several marketplaces can share one curation source.
+    ///
+    /// # Requirements:
+    /// * Only admin can set the registry program.
+    ///
+    /// # Arguments:
+    /// * `registry_program_id`: the registry program address (if it is `None` then the sync is disabled)
+    SetRegistryProgram(Option<ActorId>),
+
+    /// Queries the registry program for the vetted contracts (see [`RegistryAction::VettedContracts`])
+    /// and merges them into the approved NFT and FT contracts.
+    /// The contracts approved by the admin stay approved even if the registry doesn't list them.
+    ///
+    /// # Requirements:
+    /// * The registry program must be set.
+    ///
+    /// On success replies [`MarketEvent::ContractsSynced`].
+    SyncApprovedContracts,
+
+    /// Lists the NFT transferred to the marketplace and holds it in custody for the previous owner.
+    /// It is sent by the NFT contract after the transfer with a payload (the safe-transfer callback),
+    /// so the owner lists the NFT without approving the marketplace first.
+    /// The NFT contract must revert the transfer if the action fails.
+    ///
+    /// # Requirements:
+    /// * [`msg::source()`](gstd::msg::source) must be an approved NFT contract
+    /// that has already transferred the token to the marketplace
+    /// * `payload` must be an encoded [`TransferListing`]
+    /// * if item already exists, then it cannot be changed if there is an active auction
+    ///
+    /// # Arguments:
+    /// * `from`: the previous NFT owner
+    /// * `token_id`: the NFT id
+    /// * `payload`: the payload of the transfer
+    ///
+    /// On success replies [`MarketEvent::MarketDataAdded`].
+    OnNftReceived {
+        from: ActorId,
+        token_id: U256,
+        payload: Vec<u8>,
+    },
+
+    /// Returns the NFT held in custody to the item owner and takes the item off the sale.
+    ///
+    /// # Requirements:
+    /// * Only the item owner can withdraw the NFT.
+    /// * The NFT must be held in custody and there must be no auction.
+    /// * The item must not be locked by another transaction.
+    ///
+    /// # Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    ///
+    /// On success replies [`MarketEvent::NftWithdrawn`].
+    /// If the transfer fails replies [`MarketEvent::TransactionFailed`].
+    WithdrawNft {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+
+    /// Deposits fungible tokens or native value into the caller's escrow balance
+    /// that covers the caller's balance offers (see [`MarketAction::AddBalanceOffer`]).
+    ///
+    /// # Requirements:
+    /// * The FT contract must be approved.
+    /// * The amount can't be zero and the native value not less than the amount must be attached.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `amount`: the deposited amount
+    ///
+    /// On success replies [`MarketEvent::BalanceDeposited`] with the received amount.
+    DepositBalance {
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+
+    /// Withdraws the amount from the caller's escrow balance.
+    ///
+    /// # Requirements:
+    /// * The balance must cover the amount.
+    ///
+    /// # Arguments:
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `amount`: the withdrawn amount
+    ///
+    /// On success replies [`MarketEvent::BalanceWithdrawn`].
+    /// If the transfer fails replies [`MarketEvent::BalanceWithdrawalFailed`] and the amount stays on the balance.
+    WithdrawBalance {
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+
+    /// Adds a price offer covered by the caller's escrow balance instead of its own escrow,
+    /// so one deposit covers several offers. The balance is debited when the offer is accepted:
+    /// if the balance doesn't cover it anymore then the offer is cancelled
+    /// with [`MarketEvent::OfferCancelled`].
+    ///
+    /// # Requirements:
+    /// * The balance in the currency must cover the price.
+    /// * The other requirements are the same as for [`MarketAction::AddOffer`].
+    ///
+    /// # Arguments:
+    /// * the arguments are the same as for [`MarketAction::AddOffer`].
+    ///
+    /// On success replies [`MarketEvent::OfferAdded`].
+    AddBalanceOffer {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        price: u128,
+        recipient: Option<ActorId>,
+        metadata: Option<Vec<u8>>,
+    },
+
+    /// Replies with the item addressed by its id, the same as [`MarketAction::Item`].
+    ///
+    /// # Requirements:
+    /// * The item must be listed on the marketplace.
+    ///
+    /// # Arguments:
+    /// * `item_id`: the NFT contract address and the token id
+    ///
+    /// On success replies [`MarketEvent::ItemInfo`].
+    ItemById(ItemId),
+
+    /// Sets the randomness provider that draws the end of the candle auctions
+    /// (see [`RandomnessAction::Random`]).
+    ///
+    /// # Requirements:
+    /// * Only admin can set the randomness provider.
+    ///
+    /// # Arguments:
+    /// * `randomness_provider`: the provider address (if it is `None` then the candle auctions can't be created)
+    SetRandomnessProvider(Option<ActorId>),
+
+    /// Creates a candle auction: the closing window before `ended_at` is divided into `rounds`
+    /// and the auction is considered ended at the end of the round drawn at the settlement,
+    /// so the bids made at the last moment can be too late. The late bids don't extend the auction.
+    ///
+    /// The leading bid of every round stays escrowed until the settlement,
+    /// then the bids that didn't win are credited to the escrow balances of the bidders
+    /// (see [`MarketAction::WithdrawBalance`]).
+    ///
+    /// # Requirements:
+    /// * The randomness provider must be set.
+    /// * `rounds` must be from 1 to [`MAX_CANDLE_ROUNDS`].
+    /// * The closing window must be at least `rounds` milliseconds and not longer than the duration.
+    /// * The other requirements are the same as for [`MarketAction::CreateAuction`].
+    ///
+    /// # Arguments:
+    /// * `rounds`: the number of rounds in the closing window
+    /// * `closing_window`: the duration of the closing window in milliseconds
+    /// * the other arguments are the same as for [`MarketAction::CreateAuction`].
+    ///
+    /// On success replies [`MarketEvent::AuctionCreated`].
+    CreateCandleAuction {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        min_price: u128,
+        duration: u64,
+        rounds: u32,
+        closing_window: u64,
+    },
+}
+
+impl MarketAction {
+    /// Returns the pause category of the action (0 if the action can't be paused).
+    pub fn pause_category(&self) -> u32 {
+        match self {
+            MarketAction::AddMarketData { .. }
+            | MarketAction::ListFromNft { .. }
+            | MarketAction::OnNftReceived { .. }
+            | MarketAction::AddReferencePricedData { .. }
+            | MarketAction::UpdatePrice { .. }
+            | MarketAction::UpdateCurrency { .. }
+            | MarketAction::SetAutoAccept { .. }
+            | MarketAction::SetFlashSale { .. }
+            | MarketAction::PromoteItem { .. }
+            | MarketAction::LaunchCollection { .. } => PAUSE_LISTINGS,
+            MarketAction::BuyItem { .. }
+            | MarketAction::ForwardedBuyItem { .. }
+            | MarketAction::FillSignedOrder { .. }
+            | MarketAction::CreateDeal { .. }
+            | MarketAction::AcceptDeal(_) => PAUSE_SALES,
+            MarketAction::CreateAuction { .. }
+            | MarketAction::CreateMultiCurrencyAuction { .. }
+            | MarketAction::CreateCandleAuction { .. }
+            | MarketAction::AddBid { .. }
+            | MarketAction::AddBidInCurrency { .. }
+            | MarketAction::AddMaxBid { .. } => PAUSE_AUCTIONS,
+            MarketAction::SettleAuction { .. } => PAUSE_SETTLEMENTS,
+            MarketAction::AddOffer { .. }
+            | MarketAction::AddBalanceOffer { .. }
+            | MarketAction::DepositBalance { .. }
+            | MarketAction::AcceptOffer { .. }
+            | MarketAction::AcceptBestOffer { .. }
+            | MarketAction::DepositOffer { .. } => PAUSE_OFFERS,
+            MarketAction::PlaceBuyOrder { .. }
+            | MarketAction::PlaceSellOrder { .. }
+            | MarketAction::PlaceLimitOrder { .. } => PAUSE_ORDERS,
+            MarketAction::CreateDrop { .. } | MarketAction::BuyFromDrop { .. } => PAUSE_DROPS,
+            _ => 0,
+        }
+    }
+
+    /// Whether the action changes the marketplace parameters (fees, durations,
+    /// approved contracts and programs), so it is made by the governance if it is set.
+    /// The operational admin actions (bans, failed transactions, pauses, emergency) aren't.
+    pub fn is_parameter_change(&self) -> bool {
+        matches!(
+            self,
+            MarketAction::AddNftContract(_)
+                | MarketAction::AddFTContract(_)
+                | MarketAction::SetPromotionFee(_)
+                | MarketAction::SetRoyaltyConfig(_)
+                | MarketAction::SetFeeTiers(_)
+                | MarketAction::SetAuctionBounds(_)
+                | MarketAction::SetKeeperBounty(_)
+                | MarketAction::SetPriceFeed(_)
+                | MarketAction::SetCollectionCreator { .. }
+                | MarketAction::SetPrimarySaleFee(_)
+                | MarketAction::SetExchangeRate { .. }
+                | MarketAction::SetCollectionFee { .. }
+                | MarketAction::SetCurrencyConfig { .. }
+                | MarketAction::SetStaking(_)
+                | MarketAction::AddTrustedForwarder(_)
+                | MarketAction::RemoveTrustedForwarder(_)
+                | MarketAction::SetReceiptsContract(_)
+                | MarketAction::SetCollectionCode(_)
+                | MarketAction::SetTradeRetention(_)
+                | MarketAction::SetYieldProgram(_)
+                | MarketAction::SetMetadataProgram { .. }
+                | MarketAction::SetMaxOffers(_)
+                | MarketAction::SetRoyaltyFree { .. }
+                | MarketAction::SetSoulbound { .. }
+                | MarketAction::SetRegistryProgram(_)
+                | MarketAction::SetRandomnessProvider(_)
+                | MarketAction::SetGovernance(_)
+                | MarketAction::SetTreasuries(_)
+        )
+    }
+
+    /// Checks the lengths of the vectors and strings of the action,
+    /// so the oversized actions are rejected before any work is done.
+    pub fn check_bounds(&self) -> Result<(), MarketErr> {
+        let within = |len: usize, max: usize| {
+            if len > max {
+                Err(MarketErr::LimitExceeded)
+            } else {
+                Ok(())
+            }
+        };
+        let metadata_len = |metadata: &Option<Vec<u8>>| metadata.as_ref().map_or(0, Vec::len);
+        match self {
+            MarketAction::AddMarketData {
+                payees, metadata, ..
+            } => {
+                within(payees.len(), MAX_PAYEES)?;
+                within(metadata_len(metadata), MAX_METADATA_LEN)
+            }
+            MarketAction::OnNftReceived { payload, .. } => within(payload.len(), MAX_METADATA_LEN),
+            MarketAction::AddOffer { metadata, .. }
+            | MarketAction::AddBalanceOffer { metadata, .. } => {
+                within(metadata_len(metadata), MAX_METADATA_LEN)
+            }
+            MarketAction::SetFeeTiers(tiers) => within(tiers.len(), MAX_FEE_TIERS),
+            MarketAction::SetTreasuries(treasuries) => within(treasuries.len(), MAX_TREASURIES),
+            MarketAction::CreateDrop { token_ids, .. } => within(token_ids.len(), MAX_BATCH_LEN),
+            MarketAction::CreateMultiCurrencyAuction { currencies, .. } => {
+                within(currencies.len(), MAX_AUCTION_CURRENCIES)
+            }
+            MarketAction::LaunchCollection {
+                name,
+                symbol,
+                base_uri,
+                tokens,
+                ..
+            } => {
+                within(tokens.len(), MAX_BATCH_LEN)?;
+                for text in [name, symbol, base_uri]
+                    .into_iter()
+                    .chain(tokens.iter().flat_map(|token| {
+                        [
+                            &token.name,
+                            &token.description,
+                            &token.media,
+                            &token.reference,
+                        ]
+                    }))
+                {
+                    within(text.len(), MAX_METADATA_LEN)?;
+                }
+                Ok(())
+            }
+            MarketAction::AddTraitOffer { token_trait, .. } => within(
+                token_trait.name.len() + token_trait.value.len(),
+                MAX_METADATA_LEN,
+            ),
+            MarketAction::CreateDeal { items, .. } => within(items.len(), MAX_DEAL_ITEMS),
+            MarketAction::CreateCandleAuction { rounds, .. } => {
+                within(*rounds as usize, MAX_CANDLE_ROUNDS)
+            }
+            MarketAction::SetSlug { slug, .. } => {
+                within(slug.as_ref().map_or(0, String::len), MAX_SLUG_LEN)
+            }
+            MarketAction::ProposeChange(action) => action.check_bounds(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The operation that is split across several messages, with its checkpoint.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// The withdrawal of the account's offers in the currency (see [`MarketAction::WithdrawAllOffers`]).
+    WithdrawOffers {
+        account: ActorId,
+        ft_contract_id: Option<ActorId>,
+        /// The first offer that remains to be withdrawn.
+        from: OfferId,
+    },
+    /// The payouts of the sale that don't fit into the settlement message.
+    Payouts {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        /// The payouts that remain to be paid.
+        payouts: Vec<(ActorId, u128)>,
+    },
+}
+
+/// The metadata of the token minted by [`MarketAction::LaunchCollection`].
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct LaunchToken {
+    pub name: String,
+    pub description: String,
+    pub media: String,
+    pub reference: String,
+}
+
+/// The details of a completed sale recorded in its receipt token.
+/// The SCALE-encoded receipt is stored hex-encoded in the `reference` of the token metadata.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct SaleReceipt {
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
+    /// The currency of the sale (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    pub price: u128,
+    pub seller: ActorId,
+    pub buyer: ActorId,
+    /// The block timestamp of the sale.
+    pub sold_at: u64,
+}
+
+/// The seller's sell order that is signed off-chain.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct SignedOrder {
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
+    /// The currency of the order (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    pub price: u128,
+    /// The seller's nonce that can be used by one order only.
+    pub nonce: u64,
+    /// The timestamp after which the order is no longer valid.
+    pub expires_at: u64,
+}
+
+/// The executions of an action and the gas they burned.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct GasUsage {
+    pub executions: u64,
+    /// The executions completed within one message, so their gas is measured.
+    pub measured: u64,
+    /// The gas burned by the measured executions.
+    pub total_gas: u64,
+    pub max_gas: u64,
+}
+
+/// The schedule of the seller's proceeds: the upfront share is paid on the sale
+/// and the rest is released in equal instalments.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct Vesting {
+    /// The share of the proceeds in basis points paid on the sale.
+    pub upfront: u16,
+    pub instalments: u32,
+    /// The time in milliseconds after which each next instalment is unlocked.
+    pub period: u64,
+}
+
+/// The seller's proceeds kept on the marketplace until the instalments unlock.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct VestedProceeds {
+    pub seller: ActorId,
+    pub ft_contract_id: Option<ActorId>,
+    /// The vested amount without the upfront share.
+    pub amount: u128,
+    pub claimed: u128,
+    /// The timestamp of the sale.
+    pub starts_at: u64,
+    pub instalments: u32,
+    pub period: u64,
+}
+
+impl VestedProceeds {
+    /// Returns the amount of the instalments unlocked at the indicated block timestamp
+    /// (the last instalment receives the rounding remainder).
+    pub fn unlocked(&self, now: u64) -> u128 {
+        let unlocked =
+            (now.saturating_sub(self.starts_at) / self.period).min(self.instalments as u64);
+        if unlocked == self.instalments as u64 {
+            self.amount
+        } else {
+            self.amount / self.instalments as u128 * unlocked as u128
+        }
+    }
+}
+
+/// The negotiated trade of the seller's tokens with the counterparty.
+/// The deal is addressed by the hash of its SCALE-encoded terms.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct Deal {
+    pub seller: ActorId,
+    /// The only account that can accept the deal.
+    pub counterparty: ActorId,
+    /// The NFT contracts and the ids of the tokens.
+    pub items: Vec<(ActorId, U256)>,
+    /// The currency of the deal (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    /// The price of all the tokens.
+    pub price: u128,
+    /// The timestamp after which the deal can't be accepted.
+    pub expires_at: u64,
+    /// The schedule of the seller's proceeds negotiated with the counterparty
+    /// (if it is `None` then the proceeds are paid on the sale).
+    pub vesting: Option<Vesting>,
+}
+
+impl Deal {
+    /// Returns the BLAKE2b-256 hash of the SCALE-encoded deal.
+    pub fn hash(&self) -> H256 {
+        sp_core_hashing::blake2_256(&self.encode()).into()
+    }
+}
+
+/// The number of the latest trades kept in the trade history.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct TradeRetention {
+    /// The number of trades kept per item.
+    pub per_item: u32,
+    /// The number of trades kept in total.
+    pub total: u32,
+}
+
+/// The staking program that receives a share of the treasury fees.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct Staking {
+    pub program_id: ActorId,
+    /// The share of the treasury fee in basis points.
+    pub share: u16,
+    /// The minimum time in milliseconds between the distributions in a currency.
+    pub epoch: u64,
+}
+
+/// The treasury fees accrued for the stakers in a currency.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct StakingFees {
+    pub owed: u128,
+    /// The block timestamp of the last distribution.
+    pub distributed_at: u64,
+}
+
+/// The monitoring program that periodically receives the marketplace metrics.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    pub monitor_id: ActorId,
+    /// The number of blocks between the heartbeats.
+    pub interval: u32,
+}
+
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct MarketMetrics {
+    pub items: u32,
+    pub active_auctions: u32,
+    /// The escrowed offers, auction bids and buy orders per currency (`None` for a native value).
+    pub escrow: Vec<(Option<ActorId>, u128)>,
+    /// The total claimable balance of native value.
+    pub claimable: u128,
+    /// The number of failed transactions waiting for the admin's retry or abort.
+    pub failed_transactions: u32,
+    /// The total size in bytes of the listings' and offers' metadata.
+    pub metadata_bytes: u64,
+}
+
+/// The native value the marketplace holds for the accounts.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub struct HeldValue {
+    /// The escrows of the current auction winners (including the maximum bids).
+    pub bids: u128,
+    /// The escrows of the offers and trait offers (except the offers deposited into yield programs).
+    pub offers: u128,
+    /// The escrow balances covering the balance offers.
+    pub balances: u128,
+    /// The escrows of the buy orders.
+    pub buy_orders: u128,
+    pub claimable: u128,
+    /// The treasury fees owed to the stakers.
+    pub staking_fees: u128,
+    /// The refunds of the failed transactions waiting for the retry.
+    pub refunds: u128,
+    /// The payouts of the sales that remain to be paid by the operations.
+    pub payouts: u128,
+    /// The vested proceeds that aren't claimed yet.
+    pub vested: u128,
+}
+
+impl HeldValue {
+    pub fn total(&self) -> u128 {
+        self.bids
+            .saturating_add(self.offers)
+            .saturating_add(self.balances)
+            .saturating_add(self.buy_orders)
+            .saturating_add(self.claimable)
+            .saturating_add(self.staking_fees)
+            .saturating_add(self.refunds)
+            .saturating_add(self.payouts)
+            .saturating_add(self.vested)
+    }
+}
+
+/// The step of the transaction at which the cross-contract call failed.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub enum TransactionStep {
+    /// Approving the NFT to the marketplace when listing it or creating an auction.
+    NftApproval,
+    /// Transferring the payment from the caller to the marketplace.
+    Escrow,
+    /// Paying the treasury fee, transferring the NFT and paying the payouts.
+    Sale {
+        seller: ActorId,
+        buyer: ActorId,
+        price: u128,
+    },
+    /// Returning the escrowed payment that is no longer tracked by the item
+    /// (e.g. the bid of the outbid bidder). That step can be retried.
+    Refund {
+        to: ActorId,
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+    /// Returning the offer to its author (the offer remains on the item).
+    Withdrawal { offer_id: OfferId },
+    /// Returning the escrowed funds of the item being force delisted.
+    Delisting,
+    /// Checking the NFT owner before settling the auction.
+    OwnershipCheck,
+    /// Depositing the escrow of the offer into the yield program.
+    Deposit { offer_id: OfferId },
+    /// Recalling the escrow of the accepted offer from the yield program.
+    Recall { offer_id: OfferId },
+    /// Transferring the NFT into the marketplace custody when creating an auction.
+    Custody,
+    /// Returning the NFT held in custody to the seller after the auction is cancelled.
+    /// That step can be retried.
+    NftReturn { to: ActorId },
+    /// Requesting the seed of the candle auction from the randomness provider at the settlement.
+    /// The auction can be settled again.
+    Randomness,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct FailedTransaction {
+    pub transaction_id: TransactionId,
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
+    /// The account that sent the failed action.
+    pub account: ActorId,
+    pub step: TransactionStep,
+    pub failed_at: u64,
+}
+
+/// The sequence number of the event the marketplace replied with or sent.
+pub type EventSeq = u64;
+
+/// The event the marketplace replied with or sent, kept for the frontends polling the recent events.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct EventRecord {
+    /// The sequence number (the first event has the number 1).
+    pub seq: EventSeq,
+    /// The block timestamp of the event.
+    pub timestamp: u64,
+    /// The account whose message the event was replied or sent in response to.
+    pub source: ActorId,
+    pub event: MarketEvent,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum MarketEvent {
+    MarketDataAdded {
+        nft_contract_id: ActorId,
+        owner: ActorId,
+        token_id: U256,
+        price: Option<u128>,
+        /// The account that receives the proceeds instead of the owner.
+        beneficiary: Option<ActorId>,
+        metadata: Option<Vec<u8>>,
+    },
+    ItemSold {
+        owner: ActorId,
+        nft_contract_id: ActorId,
+        token_id: U256,
+        /// The buyer who paid for the NFT.
+        payer: ActorId,
+    },
+    BidAdded {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        price: u128,
+    },
+    AuctionCreated {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        /// The seller who created the auction.
+        owner: ActorId,
+        /// The account that receives the proceeds instead of the owner.
+        beneficiary: Option<ActorId>,
+        price: u128,
+        ft_contract_id: Option<ActorId>,
+        /// The currency and the fixed price superseded by the auction
+        /// (the price isn't restored after the auction).
+        superseded_price: Option<(Option<ActorId>, u128)>,
+        /// The number of offers that remain on the item but can't be accepted until the auction is over.
+        suspended_offers: u32,
+        /// The offer that became the opening bid.
+        seeded_offer: Option<OfferId>,
+    },
+    AuctionSettled {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        price: u128,
+    },
+    AuctionCancelled {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    NFTListed {
+        nft_contract_id: ActorId,
+        owner: ActorId,
+        token_id: U256,
+        price: Option<u128>,
+    },
+    ItemInfo(Item),
+    OfferAdded {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        price: u128,
+        metadata: Option<Vec<u8>>,
+    },
+    OfferAccepted {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        new_owner: ActorId,
+        price: u128,
+        /// The offer author.
+        payer: ActorId,
+    },
+    TokensWithdrawn {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        price: u128,
+    },
+    TransactionFailed {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        transaction_id: TransactionId,
+    },
+    ItemPromoted {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ends_at: u32,
+    },
+    OrderPlaced {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        order_id: OrderId,
+        price: u128,
+    },
+    OrdersMatched {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        buyer: ActorId,
+        seller: ActorId,
+        price: u128,
+    },
+    OrderRemoved {
+        nft_contract_id: ActorId,
+        order_id: OrderId,
+    },
+    OrderTransactionFailed {
+        nft_contract_id: ActorId,
+        order_id: OrderId,
+    },
+    Claimed {
+        amount: u128,
+    },
+    ItemDelisted {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    AuctionExtended {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        new_ended_at: u64,
+    },
+    KeeperRewarded {
+        keeper: ActorId,
+        reward: u128,
+    },
+    Metrics(MarketMetrics),
+    TransactionRetried {
+        transaction_id: TransactionId,
+    },
+    TransactionAborted {
+        transaction_id: TransactionId,
+    },
+    DropCreated {
+        drop_id: DropId,
+        nft_contract_id: ActorId,
+        amount: u32,
+    },
+    DropPurchased {
+        drop_id: DropId,
+        token_id: U256,
+        buyer: ActorId,
+    },
+    DropSoldOut {
+        drop_id: DropId,
+    },
+    OffersWithdrawn {
+        ft_contract_id: Option<ActorId>,
+        offer_ids: Vec<OfferId>,
+        /// The first offer that remains to be withdrawn
+        /// (if it is `None` then all the offers in the currency are withdrawn).
+        cursor: Option<OfferId>,
+        /// The operation that withdraws the remaining offers.
+        operation_id: Option<OperationId>,
+    },
+    PriceUpdated {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        price: Option<u128>,
+    },
+    CurrencyUpdated {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+    },
+    EmergencyReclaimed {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        offer_ids: Vec<OfferId>,
+        /// The returned winning bid.
+        bid: Option<u128>,
+    },
+    /// It is also sent to the staking program with the distributed fees.
+    FeesDistributed {
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+    FeesDistributionFailed {
+        ft_contract_id: Option<ActorId>,
+    },
+    /// The bid was outbid by the maximum bid of the current winner.
+    BidOutbid {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        /// The new price of the auction.
+        price: u128,
+    },
+    AutoAcceptSet {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        auto_accept: Option<AutoAccept>,
+    },
+    ReceiptMinted {
+        receipts_contract_id: ActorId,
+        receipt_id: U256,
+        receipt: SaleReceipt,
+    },
+    CollectionLaunched {
+        nft_contract_id: ActorId,
+        creator: ActorId,
+        token_ids: Vec<U256>,
+    },
+    TradesPruned {
+        amount: u32,
+        /// Whether the trade history still exceeds the retention.
+        remaining: bool,
+    },
+    OrderCancelled {
+        account: ActorId,
+        nonce: u64,
+    },
+    AllOrdersCancelled {
+        account: ActorId,
+        /// The new order epoch of the account.
+        epoch: u64,
+    },
+    OfferDeposited {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        offer_id: OfferId,
+    },
+    FlashSaleSet {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        flash_sale: Option<FlashSale>,
+    },
+    WatchTriggered {
+        watcher: ActorId,
+        nft_contract_id: ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    },
+    TraitOfferAdded {
+        nft_contract_id: ActorId,
+        offer: TraitOffer,
+    },
+    TraitOfferAccepted {
+        nft_contract_id: ActorId,
+        offer_id: OrderId,
+        token_id: U256,
+        seller: ActorId,
+        buyer: ActorId,
+        price: u128,
+    },
+    TraitOfferWithdrawn {
+        nft_contract_id: ActorId,
+        offer_id: OrderId,
+    },
+    OfferEvicted {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        offer_id: OfferId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    },
+    NativeBalance {
+        held: HeldValue,
+        /// The balance of the marketplace without the value attached to the check.
+        available: u128,
+    },
+    BalanceMismatch {
+        held: HeldValue,
+        available: u128,
+    },
+    ChangeProposed {
+        change_id: ChangeId,
+        executable_at: u64,
+    },
+    ChangeExecuted(ChangeId),
+    ChangeCancelled(ChangeId),
+    LimitOrderPlaced {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        order_id: OrderId,
+        price: u128,
+        expires_at: u64,
+    },
+    LimitOrderRemoved {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        order_id: OrderId,
+    },
+    LimitOrderExecuted {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        order_id: OrderId,
+        buyer: ActorId,
+        price: u128,
+    },
+    /// The hash of the deal terms.
+    DealCreated(H256),
+    DealAccepted {
+        hash: H256,
+        /// The tokens that failed to be sold, their shares of the price are returned.
+        failed_items: Vec<ItemId>,
+    },
+    DealCancelled(H256),
+    PayoutsPaid {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        /// The number of the payouts paid by the message.
+        paid: u32,
+        /// The operation that pays the remaining payouts.
+        operation_id: Option<OperationId>,
+    },
+    SlugSet {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        slug: Option<String>,
+    },
+    VestedClaimed {
+        vesting_id: VestingId,
+        amount: u128,
+    },
+    /// The transfer of the unlocked instalments failed, they stay claimable.
+    VestedClaimFailed(VestingId),
+    ContractsSynced {
+        /// The number of the newly approved NFT contracts.
+        nft_added: u32,
+        /// The number of the newly approved FT contracts.
+        ft_added: u32,
+    },
+    NftWithdrawn {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    BalanceDeposited {
+        ft_contract_id: Option<ActorId>,
+        /// The received amount.
+        amount: u128,
+    },
+    BalanceWithdrawn {
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+    /// The transfer of the withdrawn amount failed, so it stays on the balance.
+    BalanceWithdrawalFailed {
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    },
+    /// The balance offer was accepted but the escrow balance of its author doesn't cover it.
+    OfferCancelled {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        offer_id: OfferId,
+    },
+    /// The end of the candle auction is drawn at the settlement.
+    CandleEndDrawn {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        /// The drawn round of the closing window (from 1).
+        round: u32,
+        /// The timestamp at which the auction is considered ended.
+        ended_at: u64,
     },
 }
@@ -1,37 +1,88 @@
 use crate::{
-    nft_messages::{nft_approve, nft_transfer},
-    payment::{check_attached_value, transfer_payment},
-    Item, Market, MarketEvent, BASE_PERCENT,
+    candle::record_candle_bid,
+    events,
+    nft_messages::{nft_owner, nft_transfer_without_payout},
+    payment::check_attached_value,
+    validation::{check_not_owner, check_not_zero, market_err},
+    Item, ItemId, Market, MarketEvent,
 };
-use gstd::{debug, exec, msg, prelude::*, ActorId};
+use core::cmp::Reverse;
+use gstd::{exec, msg, prelude::*, ActorId};
 use market_io::*;
-use primitive_types::{H256, U256};
-const MIN_BID_PERIOD: u64 = 60_000;
+use primitive_types::U256;
 const ZERO_ID: ActorId = ActorId::new([0u8; 32]);
+/// The amount by which a maximum bid outbids the competing bid.
+pub const BID_INCREMENT: u128 = 1;
 
 impl Market {
+    /// Sets the bounds of auction parameters.
+    /// Requirements:
+    /// * Only admin can set the bounds
+    /// * The minimum duration can't be greater than the maximum one
+    /// * The minimum duration and bid period can't be equal to zero
+    pub fn set_auction_bounds(&mut self, bounds: AuctionBounds) {
+        self.check_admin();
+        check_auction_bounds(&bounds);
+        self.auction_bounds = bounds;
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_auction(
         &mut self,
         nft_contract_id: &ActorId,
         ft_contract_id: Option<ActorId>,
+        mut currencies: Vec<Option<ActorId>>,
         token_id: U256,
         min_price: u128,
         bid_period: u64,
         duration: u64,
+        beneficiary: Option<ActorId>,
+        auto_seed_from_offers: bool,
+        candle: Option<Candle>,
     ) {
+        self.check_not_banned(&msg::source());
+        if let Some(beneficiary) = &beneficiary {
+            check_not_zero(beneficiary);
+        }
         self.check_approved_nft_contract(nft_contract_id);
+        self.check_transferable(nft_contract_id);
         self.check_approved_ft_contract(ft_contract_id);
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        self.on_auction(&contract_and_token_id);
-        if bid_period < MIN_BID_PERIOD || duration < MIN_BID_PERIOD {
-            panic!("bid period or auction duration can't be less than 1 minute");
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(nft_contract_id, token_id);
+        let bounds = self.auction_bounds;
+        if bid_period < bounds.min_bid_period {
+            panic!("bid period can't be less than the minimum bid period");
+        }
+        if duration < bounds.min_duration || duration > bounds.max_duration {
+            panic!("auction duration is out of the allowed bounds");
         }
         if min_price == 0 {
             panic!("price can't be equal to zero");
         }
+        self.check_price_bounds(ft_contract_id, min_price);
+        self.check_auction_currency(ft_contract_id);
+        if !currencies.is_empty() {
+            if !currencies.contains(&ft_contract_id) {
+                currencies.push(ft_contract_id);
+            }
+            for currency in &currencies {
+                self.check_approved_ft_contract(*currency);
+                self.check_exchange_rate(*currency);
+                self.check_auction_currency(*currency);
+            }
+        }
+        let owner = self.unlocked_owner(&item_id);
+
         // approve nft to trade on the marketplace
-        nft_approve(nft_contract_id, &exec::program_id(), token_id).await;
+        if !self.approve_marketplace(nft_contract_id, token_id).await {
+            return;
+        }
+        // the item could be changed while waiting for the NFT contract
+        self.on_auction(&item_id);
+        if self.unlocked_owner(&item_id) != owner {
+            panic!("The item was changed by another transaction");
+        }
 
         let auction = Auction {
             bid_period,
@@ -39,34 +90,92 @@ impl Market {
             ended_at: exec::block_timestamp() + duration,
             current_price: min_price,
             current_winner: ZERO_ID,
+            extensions: 0,
+            currencies,
+            candle,
         };
+        let primary_sale = self.is_collection_creator(nft_contract_id, &msg::source());
+        let snapshot = self.items.get(&item_id).cloned();
+        let superseded_price = snapshot
+            .as_ref()
+            .and_then(|item| item.price.map(|price| (item.ft_contract_id, price)));
+        let mut suspended_offers = snapshot
+            .as_ref()
+            .map(|item| item.offers.len() as u32)
+            .unwrap_or_default();
         self.items
-            .entry(contract_and_token_id)
+            .entry(item_id)
             .and_modify(|item| {
                 item.price = None;
+                item.reference_price = None;
                 item.auction = Some(auction.clone());
-                item.ft_contract_id = ft_contract_id
+                item.ft_contract_id = ft_contract_id;
+                item.primary_sale = primary_sale;
+                item.metadata = None;
+                item.auto_accept = None;
+                item.flash_sale = None;
+                if beneficiary.is_some() {
+                    item.payees.clear();
+                }
+                item.beneficiary = beneficiary;
             })
             .or_insert(Item {
+                nft_contract_id: *nft_contract_id,
+                token_id,
                 owner_id: msg::source(),
                 ft_contract_id,
-                price: None,
                 auction: Some(auction),
-                offers: Vec::new(),
+                beneficiary,
+                primary_sale,
+                ..Default::default()
             });
 
-        msg::reply(
-            MarketEvent::AuctionCreated {
-                nft_contract_id: *nft_contract_id,
+        // the NFT is held by the marketplace until the auction is over
+        let transaction_id = self.lock_item(&item_id);
+        if nft_transfer_without_payout(nft_contract_id, &exec::program_id(), token_id)
+            .await
+            .is_err()
+        {
+            self.items.remove(&item_id);
+            self.restore_item(&item_id, snapshot);
+            self.transaction_failed(
+                nft_contract_id,
                 token_id,
-                price: min_price,
-            },
-            0,
-        )
-        .expect("Error in reply [MarketEvent::AuctionCreated]");
+                transaction_id,
+                TransactionStep::Custody,
+            );
+            return;
+        }
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.custody = true;
+            item.locked_by = None;
+        }
+        let seeded_offer = if auto_seed_from_offers {
+            self.seed_from_offers(nft_contract_id, token_id, min_price)
+        } else {
+            None
+        };
+        if seeded_offer.is_some() {
+            suspended_offers -= 1;
+        }
+
+        let event = MarketEvent::AuctionCreated {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            owner: msg::source(),
+            beneficiary,
+            price: min_price,
+            ft_contract_id,
+            superseded_price,
+            suspended_offers,
+            seeded_offer,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::AuctionCreated]");
     }
 
     /// Settles the auction.
+    /// Anyone can settle it since the parties of the sale don't depend on the caller.
     ///
     /// Requirements:
     /// * The auction must be over.
@@ -75,137 +184,549 @@ impl Market {
     /// * `nft_contract_id`: the NFT contract address
     /// * `token_id`: the NFT id
     ///
-    /// On success auction replies [`MarketEvent::AuctionSettled`].
-    /// If no bids were made replies [`MarketEvent::AuctionCancelled`].
+    /// On success auction replies [`MarketEvent::AuctionSettled`]
+    /// and sends [`MarketEvent::KeeperRewarded`] to the caller if the keeper bounty is paid.
+    /// If no bids were made or the seller no longer owns the NFT
+    /// replies [`MarketEvent::AuctionCancelled`] and returns the winner's bid.
+    /// The end of a candle auction is drawn first and sent as [`MarketEvent::CandleEndDrawn`].
+    /// If the randomness provider fails it is recorded as [`TransactionStep::Randomness`].
 
     pub async fn settle_auction(&mut self, nft_contract_id: &ActorId, token_id: U256) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
 
         let auction = item.auction.clone().expect("Auction doesn not exist");
 
         if auction.ended_at > exec::block_timestamp() {
             panic!("Auction is not over");
         }
+        let item = if matches!(&auction.candle, Some(candle) if candle.drawn_round.is_none()) {
+            if !self.draw_candle_end(nft_contract_id, token_id).await {
+                return;
+            }
+            self.items.get(&item_id).expect("Item does not exist")
+        } else {
+            item
+        };
+        let auction = item.auction.clone().expect("Auction doesn not exist");
         let winner = auction.current_winner;
         let price = auction.current_price;
+        let ft_contract_id = item.ft_contract_id;
+        let seller = item.owner_id;
+        let custody = item.custody;
+        let escrow = self.winner_escrow(&item_id);
 
         if winner == ZERO_ID {
-            msg::reply(
-                MarketEvent::AuctionCancelled {
-                    nft_contract_id: *nft_contract_id,
+            let transaction_id = self.lock_item(&item_id);
+            self.return_custody(nft_contract_id, token_id, transaction_id)
+                .await;
+            self.unlock_item(&item_id);
+            self.cancel_auction(nft_contract_id, token_id);
+            return;
+        }
+
+        let transaction_id = self.lock_item(&item_id);
+
+        // the NFT that isn't held in custody could be transferred out of the marketplace
+        // since the auction was created
+        let owner = if custody {
+            seller
+        } else {
+            match nft_owner(nft_contract_id, token_id).await {
+                Ok(owner) => owner,
+                Err(_) => {
+                    self.transaction_failed(
+                        nft_contract_id,
+                        token_id,
+                        transaction_id,
+                        TransactionStep::OwnershipCheck,
+                    );
+                    return;
+                }
+            }
+        };
+        if owner != seller {
+            if self
+                .refund_payment(&winner, ft_contract_id, escrow)
+                .await
+                .is_err()
+            {
+                self.transaction_failed(
+                    nft_contract_id,
+                    token_id,
+                    transaction_id,
+                    TransactionStep::Refund {
+                        to: winner,
+                        ft_contract_id,
+                        amount: escrow,
+                    },
+                );
+                return;
+            }
+            self.unlock_item(&item_id);
+            self.cancel_auction(nft_contract_id, token_id);
+            self.reindex_bid(&winner, &item_id);
+            return;
+        }
+
+        // the auction is closed before the payouts
+        let snapshot = self.items.get(&item_id).cloned();
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.auction = None;
+        }
+
+        let reward = match self
+            .execute_sale(
+                &exec::program_id(),
+                nft_contract_id,
+                ft_contract_id,
+                token_id,
+                &seller,
+                &winner,
+                price,
+                Some(&msg::source()),
+                None,
+            )
+            .await
+        {
+            Ok(reward) => reward,
+            Err(_) => {
+                self.rollback_sale(&item_id, &seller, snapshot);
+                self.reindex_bid(&winner, &item_id);
+                self.transaction_failed(
+                    nft_contract_id,
                     token_id,
+                    transaction_id,
+                    TransactionStep::Sale {
+                        seller,
+                        buyer: winner,
+                        price,
+                    },
+                );
+                return;
+            }
+        };
+
+        // the rest of the winner's maximum bid is returned
+        self.max_bids.remove(&item_id);
+        if escrow > price
+            && self
+                .refund_payment(&winner, ft_contract_id, escrow - price)
+                .await
+                .is_err()
+        {
+            self.reindex_bid(&winner, &item_id);
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Refund {
+                    to: winner,
+                    ft_contract_id,
+                    amount: escrow - price,
+                },
+            );
+            return;
+        }
+
+        self.unlock_item(&item_id);
+        self.reindex_bid(&winner, &item_id);
+        if reward > 0 {
+            events::send(
+                msg::source(),
+                MarketEvent::KeeperRewarded {
+                    keeper: msg::source(),
+                    reward,
                 },
                 0,
             )
-            .expect("Error in reply [MarketEvent::AuctionCancelled]");
+            .expect("Error in sending [MarketEvent::KeeperRewarded]");
+        }
+        let event = MarketEvent::AuctionSettled {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            price,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::AuctionSettled]");
+    }
 
+    /// Adds a bid in the indicated currency.
+    /// In a multi-currency auction the bids are compared by the exchange rates
+    /// and the previous winner is refunded in the currency of his bid.
+    pub async fn add_bid_in_currency(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) {
+        self.check_not_banned(&msg::source());
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+
+        let item = self.items.get(&item_id).expect("Item does not exist");
+
+        let auction = item.auction.clone().expect("Auction doesn not exist");
+        if auction.ended_at < exec::block_timestamp() {
+            panic!("Auction has already ended");
+        }
+        let previous_currency = item.ft_contract_id;
+        check_not_owner(&item.owner_id, &msg::source(), MarketErr::SelfBid);
+
+        check_attached_value(ft_contract_id, price);
+
+        let previous_price = auction.current_price;
+        let outbids = if auction.currencies.is_empty() {
+            if ft_contract_id != previous_currency {
+                market_err(MarketErr::WrongCurrency);
+            }
+            price > previous_price
+        } else {
+            if !auction.currencies.contains(&ft_contract_id) {
+                market_err(MarketErr::WrongCurrency);
+            }
+            self.bid_value(ft_contract_id, price)
+                > self.bid_value(previous_currency, previous_price)
+        };
+        if !outbids {
+            panic!("Cant offer less or equal to the current bid price")
+        }
+        self.return_change(ft_contract_id, price);
+
+        if self.defend_bid(nft_contract_id, token_id, price) {
             return;
         }
+        self.place_bid(nft_contract_id, token_id, ft_contract_id, price, None)
+            .await;
+    }
 
-        // fee for treasury
-        let treasury_fee = price * (self.treasury_fee * BASE_PERCENT) as u128 / 10_000u128;
-        transfer_payment(
-            &exec::program_id(),
-            &self.treasury_id,
-            item.ft_contract_id,
-            treasury_fee,
-        )
-        .await;
+    /// Adds the maximum bid that is escrowed up front.
+    /// The bid is placed at the lowest price that outbids the current winner
+    /// and then outbids the competing bids automatically up to the maximum.
+    pub async fn add_max_bid(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        max_price: u128,
+    ) {
+        self.check_not_banned(&msg::source());
+        let item_id = ItemId::new(*nft_contract_id, token_id);
 
-        // transfer NFT and pay royalties
-        let payouts = nft_transfer(nft_contract_id, &winner, token_id, price - treasury_fee).await;
-        debug!("payouts {:?}", payouts);
+        let item = self.items.get(&item_id).expect("Item does not exist");
 
-        for (account, amount) in payouts.iter() {
-            debug!("account {:?} amount {:?}", account, amount);
-            transfer_payment(&exec::program_id(), account, item.ft_contract_id, *amount).await;
+        let auction = item.auction.clone().expect("Auction doesn not exist");
+        if auction.ended_at < exec::block_timestamp() {
+            panic!("Auction has already ended");
         }
+        if !auction.currencies.is_empty() {
+            panic!("Maximum bids aren't accepted in multi-currency auctions");
+        }
+        if auction.candle.is_some() {
+            panic!("Maximum bids aren't accepted in candle auctions");
+        }
+        let ft_contract_id = item.ft_contract_id;
+        check_not_owner(&item.owner_id, &msg::source(), MarketErr::SelfBid);
+
+        check_attached_value(ft_contract_id, max_price);
+
+        if auction.current_winner == msg::source() {
+            panic!("The current bid is already yours");
+        }
+        if max_price <= auction.current_price {
+            panic!("Cant offer less or equal to the current bid price")
+        }
+        self.return_change(ft_contract_id, max_price);
 
-        item.owner_id = winner;
-        item.auction = None;
-        msg::reply(
-            MarketEvent::AuctionSettled {
+        if self.defend_bid(nft_contract_id, token_id, max_price) {
+            return;
+        }
+        let price = self
+            .winner_escrow(&item_id)
+            .saturating_add(BID_INCREMENT)
+            .min(max_price);
+        self.place_bid(
+            nft_contract_id,
+            token_id,
+            ft_contract_id,
+            price,
+            Some(max_price),
+        )
+        .await;
+    }
+
+    // converts the best offer in the auction currency into the opening bid,
+    // the escrow of the offer is held by the marketplace, so it becomes the escrow of the bid
+    fn seed_from_offers(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        min_price: u128,
+    ) -> Option<OfferId> {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id)?;
+        let index = item
+            .offers
+            .iter()
+            .enumerate()
+            .filter(|(_, offer)| {
+                offer.ft_contract_id == item.ft_contract_id
+                    && offer.price >= min_price
+                    && offer.recipient.is_none()
+                    && !self.deposited_offers.contains_key(&offer.offer_id)
+                    && !self.balance_offers.contains(&offer.offer_id)
+            })
+            // the earlier offer wins if the prices are equal
+            .max_by_key(|(_, offer)| (offer.price, Reverse(offer.offer_id)))
+            .map(|(index, _)| index)?;
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        let offer = item.offers.remove(index);
+        let auction = item.auction.as_mut().expect("Auction doesn not exist");
+        auction.current_price = offer.price;
+        auction.current_winner = offer.id;
+        self.unindex_offer(&offer.id, offer.offer_id);
+        self.reindex_bid(&offer.id, &item_id);
+
+        events::send(
+            offer.id,
+            MarketEvent::BidAdded {
                 nft_contract_id: *nft_contract_id,
                 token_id,
-                price,
+                price: offer.price,
             },
             0,
         )
-        .expect("Error in reply [MarketEvent::AuctionSettled]");
+        .expect("Error in sending [MarketEvent::BidAdded]");
+        Some(offer.offer_id)
     }
 
-    pub async fn add_bid(&mut self, nft_contract_id: &ActorId, token_id: U256, price: u128) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+    /// Returns the amount escrowed by the current winner of the auction:
+    /// the maximum bid or the current price.
+    pub fn winner_escrow(&self, item_id: &ItemId) -> u128 {
+        if let Some(max_price) = self.max_bids.get(item_id) {
+            return *max_price;
+        }
+        self.items
+            .get(item_id)
+            .and_then(|item| item.auction.as_ref())
+            .map(|auction| auction.current_price)
+            .unwrap_or_default()
+    }
 
-        let mut auction = item.auction.clone().expect("Auction doesn not exist");
-        if auction.ended_at < exec::block_timestamp() {
-            panic!("Auction has already ended");
+    // raises the auction price if the maximum bid of the winner isn't exceeded
+    // and returns the attached value of the outbid bid
+    fn defend_bid(&mut self, nft_contract_id: &ActorId, token_id: U256, price: u128) -> bool {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let max_price = match self.max_bids.get(&item_id) {
+            Some(max_price) if price <= *max_price => *max_price,
+            _ => return false,
+        };
+        self.unlocked_owner(&item_id);
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        let auction = item.auction.as_mut().expect("Auction doesn not exist");
+        // the earlier bid wins if the maximums are equal
+        auction.current_price = price.saturating_add(BID_INCREMENT).min(max_price);
+        let current_price = auction.current_price;
+        if item.ft_contract_id.is_none() {
+            *self.claimable.entry(msg::source()).or_default() += price;
         }
 
-        check_attached_value(item.ft_contract_id, price);
+        let event = MarketEvent::BidOutbid {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            price: current_price,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::BidOutbid]");
+        true
+    }
 
-        let previous_price = auction.current_price;
+    // escrows the bid, makes the caller the winner and refunds the previous winner
+    async fn place_bid(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+        max_price: Option<u128>,
+    ) {
+        // the transfer fee could be enabled after the auction was created
+        self.check_auction_currency(ft_contract_id);
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        let mut auction = item.auction.clone().expect("Auction doesn not exist");
+        let previous_currency = item.ft_contract_id;
         let previous_winner = auction.current_winner;
+        let previous_escrow = self.winner_escrow(&item_id);
+        let escrow = max_price.unwrap_or(price);
 
-        if price <= previous_price {
-            panic!("Cant offer less or equal to the current bid price")
+        let transaction_id = self.lock_item(&item_id);
+
+        // transfer payment from the current account to the marketplace contract
+        if self
+            .transfer_payment(&msg::source(), &exec::program_id(), ft_contract_id, escrow)
+            .await
+            .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Escrow,
+            );
+            return;
         }
 
-        if auction.ended_at <= exec::block_timestamp() + auction.bid_period {
+        let extended = auction.candle.is_none()
+            && auction.ended_at <= exec::block_timestamp() + auction.bid_period;
+        if extended {
             auction.ended_at = exec::block_timestamp() + auction.bid_period;
+            auction.extensions += 1;
         }
+        let new_ended_at = auction.ended_at;
+        let previous_held = record_candle_bid(
+            &mut auction,
+            exec::block_timestamp(),
+            Bid {
+                id: msg::source(),
+                price,
+            },
+        );
 
         auction.current_price = price;
         auction.current_winner = msg::source();
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
         item.auction = Some(auction);
-        // transfer payment from the current account to the marketplace contract
-        transfer_payment(
-            &msg::source(),
-            &exec::program_id(),
-            item.ft_contract_id,
-            price,
-        )
-        .await;
+        item.ft_contract_id = ft_contract_id;
+        match max_price {
+            Some(max_price) => self.max_bids.insert(item_id, max_price),
+            None => self.max_bids.remove(&item_id),
+        };
+        self.reindex_bid(&previous_winner, &item_id);
+        self.reindex_bid(&msg::source(), &item_id);
 
-        if previous_winner != ZERO_ID {
+        if previous_winner != ZERO_ID && !previous_held {
             // transfer payment back to the previous winner
-            transfer_payment(
-                &exec::program_id(),
-                &previous_winner,
-                item.ft_contract_id,
-                previous_price,
-            )
-            .await;
+            if self
+                .refund_payment(&previous_winner, previous_currency, previous_escrow)
+                .await
+                .is_err()
+            {
+                self.transaction_failed(
+                    nft_contract_id,
+                    token_id,
+                    transaction_id,
+                    TransactionStep::Refund {
+                        to: previous_winner,
+                        ft_contract_id: previous_currency,
+                        amount: previous_escrow,
+                    },
+                );
+                return;
+            }
         }
 
-        msg::reply(
-            MarketEvent::BidAdded {
+        self.unlock_item(&item_id);
+        if extended {
+            let event = MarketEvent::AuctionExtended {
                 nft_contract_id: *nft_contract_id,
                 token_id,
-                price,
-            },
-            0,
-        )
-        .expect("Error in reply [MarketEvent::BidAdded]");
+                new_ended_at,
+            };
+            self.notify_subscribers(nft_contract_id, &event);
+            events::send(msg::source(), event, 0)
+                .expect("Error in sending [MarketEvent::AuctionExtended]");
+        }
+        let event = MarketEvent::BidAdded {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            price,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::BidAdded]");
+    }
+
+    /// Updates the bid index of the account according to the auction of the item.
+    pub fn reindex_bid(&mut self, account: &ActorId, item_id: &ItemId) {
+        let winning = self
+            .items
+            .get(item_id)
+            .and_then(|item| item.auction.as_ref())
+            .map(|auction| auction.current_winner == *account)
+            .unwrap_or(false);
+        if winning {
+            self.bids_by_user
+                .entry(*account)
+                .or_default()
+                .insert(*item_id);
+        } else if let Some(bids) = self.bids_by_user.get_mut(account) {
+            bids.remove(item_id);
+            if bids.is_empty() {
+                self.bids_by_user.remove(account);
+            }
+        }
+    }
+
+    // removes the auction from the item and replies that it's cancelled
+    /// Returns the NFT held in custody to the item owner.
+    /// If the transfer fails, the NFT remains in custody
+    /// and the return is recorded as a failed transaction that can be retried.
+    pub async fn return_custody(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        transaction_id: TransactionId,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let owner = match self.items.get(&item_id) {
+            Some(item) if item.custody => item.owner_id,
+            _ => return,
+        };
+        if nft_transfer_without_payout(nft_contract_id, &owner, token_id)
+            .await
+            .is_err()
+        {
+            self.record_failed_transaction(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::NftReturn { to: owner },
+            );
+            return;
+        }
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.custody = false;
+        }
+    }
+
+    fn cancel_auction(&mut self, nft_contract_id: &ActorId, token_id: U256) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.auction = None;
+        }
+        self.max_bids.remove(&item_id);
+        let event = MarketEvent::AuctionCancelled {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::AuctionCancelled]");
     }
 
     // checks that there is an active auction
-    pub fn on_auction(&self, contract_and_token_id: &String) {
-        if let Some(item) = self.items.get(contract_and_token_id) {
+    pub fn on_auction(&self, item_id: &ItemId) {
+        if let Some(item) = self.items.get(item_id) {
             if item.auction.is_some() {
                 panic!("There is an opened auction");
             }
         }
     }
 }
+
+pub fn check_auction_bounds(bounds: &AuctionBounds) {
+    if bounds.min_duration == 0 || bounds.min_bid_period == 0 {
+        panic!("auction duration and bid period bounds can't be equal to zero");
+    }
+    if bounds.min_duration > bounds.max_duration {
+        panic!("the minimum auction duration can't be greater than the maximum one");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{events, payment::*, validation::market_err, Market};
+use gstd::{exec, msg, ActorId};
+use market_io::*;
+
+impl Market {
+    pub fn balance(&self, account: &ActorId, ft_contract_id: Option<ActorId>) -> u128 {
+        self.balances
+            .get(&(*account, ft_contract_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn credit_balance(
+        &mut self,
+        account: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    ) {
+        *self.balances.entry((*account, ft_contract_id)).or_default() += amount;
+    }
+
+    /// Debits the balance and returns whether it covers the amount.
+    pub fn debit_balance(
+        &mut self,
+        account: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    ) -> bool {
+        let key = (*account, ft_contract_id);
+        match self.balances.get_mut(&key) {
+            Some(balance) if *balance >= amount => {
+                *balance -= amount;
+                if *balance == 0 {
+                    self.balances.remove(&key);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Deposits the caller's tokens or the attached value into the escrow balance
+    /// that covers the caller's balance offers.
+    /// Requirements:
+    /// * The currency must be approved and the amount can't be zero
+    pub async fn deposit_balance(&mut self, ft_contract_id: Option<ActorId>, amount: u128) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_ft_contract(ft_contract_id);
+        if amount == 0 {
+            panic!("Can't deposit zero amount");
+        }
+        check_attached_value(ft_contract_id, amount);
+        self.return_change(ft_contract_id, amount);
+
+        // nothing is changed before the transfer, so its failure fails the message
+        let amount = match self
+            .escrow_payment(&msg::source(), ft_contract_id, amount)
+            .await
+        {
+            Ok(received) => received,
+            Err(err) => market_err(err),
+        };
+        self.credit_balance(&msg::source(), ft_contract_id, amount);
+        events::reply(
+            MarketEvent::BalanceDeposited {
+                ft_contract_id,
+                amount,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::BalanceDeposited]");
+    }
+
+    /// Withdraws the amount from the caller's escrow balance.
+    /// The balance offers it no longer covers are cancelled when they are accepted.
+    pub async fn withdraw_balance(&mut self, ft_contract_id: Option<ActorId>, amount: u128) {
+        if amount == 0 {
+            panic!("Can't withdraw zero amount");
+        }
+        // the balance is debited before the transfer, so it can't be withdrawn twice
+        if !self.debit_balance(&msg::source(), ft_contract_id, amount) {
+            panic!("The balance is less than the amount");
+        }
+        if self
+            .transfer_payment(&exec::program_id(), &msg::source(), ft_contract_id, amount)
+            .await
+            .is_err()
+        {
+            self.credit_balance(&msg::source(), ft_contract_id, amount);
+            events::reply(
+                MarketEvent::BalanceWithdrawalFailed {
+                    ft_contract_id,
+                    amount,
+                },
+                0,
+            )
+            .expect("Error in reply [MarketEvent::BalanceWithdrawalFailed]");
+            return;
+        }
+        events::reply(
+            MarketEvent::BalanceWithdrawn {
+                ft_contract_id,
+                amount,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::BalanceWithdrawn]");
+    }
+
+    /// Returns the escrow of the offer to the account.
+    /// The balance offers don't have their own escrows, so nothing is returned for them.
+    pub async fn return_offer_escrow(
+        &mut self,
+        item_id: &ItemId,
+        offer: &Offer,
+        to: &ActorId,
+    ) -> Result<(), MarketErr> {
+        if self.balance_offers.contains(&offer.offer_id) {
+            return Ok(());
+        }
+        self.recall_offer(item_id, offer).await?;
+        self.transfer_payment(&exec::program_id(), to, offer.ft_contract_id, offer.price)
+            .await
+    }
+}
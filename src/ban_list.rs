@@ -0,0 +1,109 @@
+use crate::{
+    events, validation::market_err, ItemId, Market, MarketErr, MarketEvent, TransactionStep,
+};
+use gstd::ActorId;
+use primitive_types::U256;
+
+impl Market {
+    pub fn ban_actor(&mut self, actor_id: &ActorId) {
+        self.check_admin();
+        self.banned_actors.insert(*actor_id);
+    }
+
+    pub fn unban_actor(&mut self, actor_id: &ActorId) {
+        self.check_admin();
+        self.banned_actors.remove(actor_id);
+    }
+
+    pub fn check_not_banned(&self, actor_id: &ActorId) {
+        if self.banned_actors.contains(actor_id) {
+            market_err(MarketErr::BannedActor);
+        }
+    }
+
+    /// Removes the listing of a banned actor and returns the escrowed funds
+    /// (offers and the current auction bid) to their owners.
+    /// Requirements:
+    /// * Only admin can delist items
+    /// * The item owner must be banned
+    /// Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    pub async fn force_delist(&mut self, nft_contract_id: &ActorId, token_id: U256) {
+        self.check_admin();
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if !self.banned_actors.contains(&item.owner_id) {
+            panic!("Only listings of banned actors can be delisted");
+        }
+        let ft_contract_id = item.ft_contract_id;
+        let auction = item.auction.clone();
+        let escrow = self.winner_escrow(&item_id);
+
+        let transaction_id = self.lock_item(&item_id);
+        // the outbid leading bids of a candle auction are credited to the escrow balances
+        self.release_candle_bids(&item_id, None);
+
+        // return the current auction bid
+        if let Some(auction) = auction {
+            if auction.current_winner != ActorId::zero() {
+                if self
+                    .refund_payment(&auction.current_winner, ft_contract_id, escrow)
+                    .await
+                    .is_err()
+                {
+                    self.transaction_failed(
+                        nft_contract_id,
+                        token_id,
+                        transaction_id,
+                        TransactionStep::Delisting,
+                    );
+                    return;
+                }
+                let item = self.items.get_mut(&item_id).expect("Item does not exist");
+                item.auction = None;
+                self.max_bids.remove(&item_id);
+                self.reindex_bid(&auction.current_winner, &item_id);
+            }
+        }
+
+        // return the escrowed offers one by one,
+        // so the offers that aren't returned stay withdrawable on failure
+        while let Some(offer) = self
+            .items
+            .get(&item_id)
+            .and_then(|item| item.offers.first().cloned())
+        {
+            if self
+                .return_offer_escrow(&item_id, &offer, &offer.id)
+                .await
+                .is_err()
+            {
+                self.transaction_failed(
+                    nft_contract_id,
+                    token_id,
+                    transaction_id,
+                    TransactionStep::Delisting,
+                );
+                return;
+            }
+            if let Some(item) = self.items.get_mut(&item_id) {
+                item.offers.remove(0);
+            }
+            self.unindex_offer(&offer.id, offer.offer_id);
+        }
+
+        // the NFT held in custody is returned to the banned owner
+        self.return_custody(nft_contract_id, token_id, transaction_id)
+            .await;
+        self.items.remove(&item_id);
+        events::reply(
+            MarketEvent::ItemDelisted {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::ItemDelisted]");
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{events, validation::check_not_zero, ItemId, Market};
+use gstd::{msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::{H256, U256};
+
+impl Market {
+    pub fn set_randomness_provider(&mut self, randomness_provider: Option<ActorId>) {
+        self.check_admin();
+        if let Some(randomness_provider) = &randomness_provider {
+            check_not_zero(randomness_provider);
+        }
+        self.randomness_provider = randomness_provider;
+    }
+
+    /// Creates the auction whose end is drawn within the closing window at the settlement.
+    /// Requirements:
+    /// * The randomness provider must be set
+    /// * The number of rounds must be from 1 to `MAX_CANDLE_ROUNDS`
+    /// * The closing window must be at least `rounds` milliseconds and fit in the duration
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_candle_auction(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        min_price: u128,
+        duration: u64,
+        rounds: u32,
+        closing_window: u64,
+    ) {
+        if self.randomness_provider.is_none() {
+            panic!("The randomness provider isn't set");
+        }
+        if rounds == 0 || rounds as usize > MAX_CANDLE_ROUNDS {
+            panic!("The number of rounds is out of the allowed bounds");
+        }
+        if closing_window < u64::from(rounds) || closing_window > duration {
+            panic!("The closing window doesn't fit the rounds or the duration");
+        }
+        let candle = Candle {
+            rounds,
+            closing_window,
+            min_price,
+            ..Default::default()
+        };
+        // the late bids don't extend a candle auction, so the bid period isn't used
+        let bid_period = self.auction_bounds.min_bid_period;
+        self.create_auction(
+            nft_contract_id,
+            ft_contract_id,
+            vec![],
+            token_id,
+            min_price,
+            bid_period,
+            duration,
+            None,
+            false,
+            Some(candle),
+        )
+        .await;
+    }
+
+    /// Draws the end of the candle auction with the seed of the randomness provider,
+    /// makes the bid leading at the end of the drawn round the winner
+    /// and credits the other leading bids to the escrow balances of the bidders.
+    /// The seed is bound to the end of the auction that is fixed at the creation,
+    /// so it's unknown while bidding and doesn't depend on the time of the settlement.
+    /// Returns whether the end is drawn, otherwise the failed transaction is replied.
+    pub async fn draw_candle_end(&mut self, nft_contract_id: &ActorId, token_id: U256) -> bool {
+        let randomness_provider = self
+            .randomness_provider
+            .expect("The randomness provider isn't set");
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let ended_at = self
+            .items
+            .get(&item_id)
+            .and_then(|item| item.auction.as_ref())
+            .expect("Auction doesn not exist")
+            .ended_at;
+        // no bids or other settlements are made while waiting for the provider
+        let transaction_id = self.lock_item(&item_id);
+        let seed = match request_seed(&randomness_provider, item_id.hash(), ended_at).await {
+            Ok(seed) => seed,
+            Err(_) => {
+                self.transaction_failed(
+                    nft_contract_id,
+                    token_id,
+                    transaction_id,
+                    TransactionStep::Randomness,
+                );
+                return false;
+            }
+        };
+        self.unlock_item(&item_id);
+
+        let item = match self.items.get_mut(&item_id) {
+            Some(item) => item,
+            None => return false,
+        };
+        let ft_contract_id = item.ft_contract_id;
+        let auction = match item.auction.as_mut() {
+            Some(auction) => auction,
+            None => return false,
+        };
+        let candle = match auction.candle.as_mut() {
+            Some(candle) if candle.drawn_round.is_none() => candle,
+            _ => return false,
+        };
+        let min_price = candle.min_price;
+        let mut random = [0u8; 8];
+        random.copy_from_slice(&seed.as_bytes()[..8]);
+        let round = (u64::from_le_bytes(random) % u64::from(candle.rounds)) as u32 + 1;
+        candle.drawn_round = Some(round);
+        let round_end = candle.round_end(ended_at, round);
+
+        let leaders = mem::take(&mut candle.leaders);
+        let winner = leaders
+            .iter()
+            .rposition(|(bid_round, _)| *bid_round <= round);
+        match winner {
+            Some(index) => {
+                auction.current_winner = leaders[index].1.id;
+                auction.current_price = leaders[index].1.price;
+            }
+            None => {
+                auction.current_winner = ActorId::zero();
+                auction.current_price = min_price;
+            }
+        }
+        for (index, (_, bid)) in leaders.iter().enumerate() {
+            if Some(index) != winner {
+                self.credit_balance(&bid.id, ft_contract_id, bid.price);
+            }
+            self.reindex_bid(&bid.id, &item_id);
+        }
+
+        let event = MarketEvent::CandleEndDrawn {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            round,
+            ended_at: round_end,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::send(msg::source(), event, 0)
+            .expect("Error in sending [MarketEvent::CandleEndDrawn]");
+        true
+    }
+
+    /// Credits the escrows of the leading bids of the candle auction
+    /// that aren't the bid of the current winner to the escrow balances of the bidders,
+    /// e.g. when the auction is closed without the settlement.
+    /// If `account` is set then only the bids of the account are credited.
+    pub fn release_candle_bids(&mut self, item_id: &ItemId, account: Option<&ActorId>) {
+        let item = match self.items.get_mut(item_id) {
+            Some(item) => item,
+            None => return,
+        };
+        let ft_contract_id = item.ft_contract_id;
+        let (current_winner, candle) = match item.auction.as_mut() {
+            Some(auction) => match auction.candle.as_mut() {
+                Some(candle) => (auction.current_winner, candle),
+                None => return,
+            },
+            None => return,
+        };
+        let last = candle.leaders.len().saturating_sub(1);
+        let mut released = vec![];
+        let mut index = 0;
+        candle.leaders.retain(|(_, bid)| {
+            let winning = index == last && bid.id == current_winner;
+            index += 1;
+            if winning || matches!(account, Some(account) if bid.id != *account) {
+                return true;
+            }
+            released.push(bid.clone());
+            false
+        });
+        for bid in released {
+            self.credit_balance(&bid.id, ft_contract_id, bid.price);
+        }
+    }
+}
+
+async fn request_seed(
+    randomness_provider: &ActorId,
+    subject: H256,
+    timestamp: u64,
+) -> Result<H256, ()> {
+    let reply: RandomnessEvent = msg::send_for_reply_as(
+        *randomness_provider,
+        RandomnessAction::Random { subject, timestamp },
+        0,
+    )
+    .map_err(|_| ())?
+    .up_to(Some(REPLY_TIMEOUT))
+    .map_err(|_| ())?
+    .await
+    .map_err(|_| ())?;
+    let RandomnessEvent::Random(seed) = reply;
+    Ok(seed)
+}
+
+/// Records the bid in the round it is made in (before the bidder becomes the current winner)
+/// and returns whether the escrow of the previous winner is held until the settlement:
+/// the previous bid is refunded only if it leads in the same round.
+pub fn record_candle_bid(auction: &mut Auction, timestamp: u64, bid: Bid) -> bool {
+    let ended_at = auction.ended_at;
+    let current_winner = auction.current_winner;
+    let candle = match auction.candle.as_mut() {
+        Some(candle) => candle,
+        None => return false,
+    };
+    let round = candle.round_at(ended_at, timestamp);
+    match candle.leaders.last_mut() {
+        Some((last_round, leader)) if *last_round == round && leader.id == current_winner => {
+            *leader = bid;
+            false
+        }
+        Some(_) => {
+            candle.leaders.push((round, bid));
+            true
+        }
+        None => {
+            candle.leaders.push((round, bid));
+            false
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    validation::market_err, CollectionConfig, Market, MarketErr, BASE_PERCENT, MAX_TREASURT_FEE,
+};
+use gstd::ActorId;
+
+impl Market {
+    /// Sets the custom treasury fee of the collection.
+    /// Requirements:
+    /// * Only admin can set collection fees
+    /// * The fee can't exceed the maximum treasury fee
+    pub fn set_collection_fee(&mut self, nft_contract_id: &ActorId, fee: Option<u16>) {
+        self.check_admin();
+        if let Some(fee) = fee {
+            if fee > MAX_TREASURT_FEE as u16 * BASE_PERCENT as u16 {
+                panic!("Collection fee can't exceed the maximum treasury fee");
+            }
+        }
+        let config = self.collection_configs.entry(*nft_contract_id).or_default();
+        config.treasury_fee = fee;
+        if config == &CollectionConfig::default() {
+            self.collection_configs.remove(nft_contract_id);
+        }
+    }
+
+    /// Sets whether the collection is sold without requesting the royalty payouts.
+    /// Requirements:
+    /// * Only admin can set the flag
+    pub fn set_royalty_free(&mut self, nft_contract_id: &ActorId, royalty_free: bool) {
+        self.check_admin();
+        self.cache_royalty_free(nft_contract_id, royalty_free);
+    }
+
+    pub fn cache_royalty_free(&mut self, nft_contract_id: &ActorId, royalty_free: bool) {
+        let config = self.collection_configs.entry(*nft_contract_id).or_default();
+        config.royalty_free = royalty_free;
+        if config == &CollectionConfig::default() {
+            self.collection_configs.remove(nft_contract_id);
+        }
+    }
+
+    pub fn is_royalty_free(&self, nft_contract_id: &ActorId) -> bool {
+        self.collection_configs
+            .get(nft_contract_id)
+            .map(|config| config.royalty_free)
+            .unwrap_or(false)
+    }
+
+    /// Sets whether the NFTs of the collection can't be transferred.
+    /// Requirements:
+    /// * Only admin can set the flag
+    pub fn set_soulbound(&mut self, nft_contract_id: &ActorId, soulbound: bool) {
+        self.check_admin();
+        let config = self.collection_configs.entry(*nft_contract_id).or_default();
+        config.soulbound = soulbound;
+        if config == &CollectionConfig::default() {
+            self.collection_configs.remove(nft_contract_id);
+        }
+    }
+
+    /// Checks that the NFTs of the collection can be transferred,
+    /// so they are neither listed nor paid for.
+    pub fn check_transferable(&self, nft_contract_id: &ActorId) {
+        if self.is_soulbound(nft_contract_id) {
+            market_err(MarketErr::NonTransferable);
+        }
+    }
+
+    pub fn is_soulbound(&self, nft_contract_id: &ActorId) -> bool {
+        self.collection_configs
+            .get(nft_contract_id)
+            .map(|config| config.soulbound)
+            .unwrap_or(false)
+    }
+
+    pub fn collection_fee(&self, nft_contract_id: &ActorId) -> Option<u16> {
+        self.collection_configs
+            .get(nft_contract_id)
+            .and_then(|config| config.treasury_fee)
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    events,
+    ft_messages::{ft_balance, transfer_tokens},
+    nft_messages::{nft_owner, nft_transfer_without_payout},
+    validation::market_err,
+    Market,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::U256;
+
+impl Market {
+    /// Checks that the account owns the token.
+    /// The token owned by the marketplace is owned by the account only if it is held in custody for it
+    /// (e.g. the NFT of the cancelled auction that failed to be returned),
+    /// so the escrowed tokens can't be listed or sold by anyone else.
+    pub async fn check_token_owner(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        account: &ActorId,
+    ) {
+        let owner = match nft_owner(nft_contract_id, token_id).await {
+            Ok(owner) => owner,
+            Err(err) => market_err(MarketErr::NftCall(err)),
+        };
+        if owner == exec::program_id() {
+            self.check_custody_owner(nft_contract_id, token_id, account);
+        } else if owner != *account {
+            market_err(MarketErr::NotOwner);
+        }
+    }
+
+    /// Returns whether the account owns the token, directly or in custody,
+    /// e.g. to skip the stale orders of the previous owners.
+    pub async fn is_token_owner(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        account: &ActorId,
+    ) -> bool {
+        match nft_owner(nft_contract_id, token_id).await {
+            Ok(owner) if owner == exec::program_id() => {
+                let item_id = ItemId::new(*nft_contract_id, token_id);
+                matches!(self.items.get(&item_id), Some(item) if item.custody && item.owner_id == *account)
+            }
+            Ok(owner) => owner == *account,
+            Err(_) => false,
+        }
+    }
+
+    /// Checks that the token held by the marketplace is held in custody for the account.
+    pub fn check_custody_owner(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        account: &ActorId,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        match self.items.get(&item_id) {
+            Some(item) if item.custody && item.owner_id == *account => {}
+            _ => market_err(MarketErr::NotOwner),
+        }
+    }
+
+    /// Lists the NFT transferred to the marketplace with the listing encoded in the payload.
+    /// The listing is checked before any state change, so the NFT contract can revert the transfer
+    /// if it fails.
+    pub fn on_nft_received(&mut self, from: &ActorId, token_id: U256, payload: &[u8]) {
+        let listing =
+            TransferListing::decode(&mut &payload[..]).expect("Could not decode the listing");
+        self.list_from_nft(from, token_id, listing.ft_contract_id, listing.price, true);
+    }
+
+    /// Returns the NFT held in custody to the item owner and takes the item off the sale.
+    /// Requirements:
+    /// * Only the item owner can withdraw the NFT
+    /// * The NFT must be held in custody and there must be no auction
+    pub async fn withdraw_nft(&mut self, nft_contract_id: &ActorId, token_id: U256) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if item.owner_id != msg::source() {
+            panic!("Only the item owner can withdraw the NFT");
+        }
+        if !item.custody {
+            panic!("The NFT isn't held in custody");
+        }
+        if item.auction.is_some() {
+            panic!("There is an opened auction");
+        }
+        let owner = item.owner_id;
+
+        let transaction_id = self.lock_item(&item_id);
+        if nft_transfer_without_payout(nft_contract_id, &owner, token_id)
+            .await
+            .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::NftReturn { to: owner },
+            );
+            return;
+        }
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.custody = false;
+            item.price = None;
+            item.reference_price = None;
+            item.flash_sale = None;
+            item.featured = None;
+        }
+        self.unlock_item(&item_id);
+        events::reply(
+            MarketEvent::NftWithdrawn {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::NftWithdrawn]");
+    }
+
+    pub fn set_yield_program(&mut self, yield_program_id: Option<ActorId>) {
+        self.check_admin();
+        self.yield_program_id = yield_program_id;
+    }
+
+    /// Deposits the escrow of the caller's offer in fungible tokens into the yield program.
+    /// Requirements:
+    /// * The yield program must be set
+    /// * Only the author of the offer can deposit it
+    /// * The offer must be in fungible tokens and must not be deposited
+    /// * The yield program must acknowledge the deposit
+    pub async fn deposit_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        offer_id: OfferId,
+    ) {
+        let yield_program_id = self.yield_program_id.expect("The yield program isn't set");
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        let offer = item
+            .offers
+            .iter()
+            .find(|offer| offer.offer_id == offer_id)
+            .expect("The offer with that id does not exist")
+            .clone();
+        if msg::source() != offer.id {
+            panic!("can't deposit other user's tokens");
+        }
+        let ft_contract_id = offer
+            .ft_contract_id
+            .expect("Only offers in fungible tokens can be deposited");
+        if self.deposited_offers.contains_key(&offer_id) {
+            panic!("The offer is already deposited");
+        }
+        if self.balance_offers.contains(&offer_id) {
+            panic!("The balance offers don't have escrows to deposit");
+        }
+
+        let transaction_id = self.lock_item(&item_id);
+        if transfer_tokens(
+            &ft_contract_id,
+            &exec::program_id(),
+            &yield_program_id,
+            offer.price,
+        )
+        .await
+        .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Deposit { offer_id },
+            );
+            return;
+        }
+        // the offer is deposited only if the yield program credits the deposit,
+        // otherwise its escrow couldn't be withdrawn from the program
+        if !acknowledge_deposit(&yield_program_id, ft_contract_id, offer.price).await {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Deposit { offer_id },
+            );
+            return;
+        }
+        self.deposited_offers.insert(offer_id, yield_program_id);
+
+        self.unlock_item(&item_id);
+        events::reply(
+            MarketEvent::OfferDeposited {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                offer_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::OfferDeposited]");
+    }
+
+    /// Recalls the deposited escrow of the offer from the yield program
+    /// and pays the accumulated yield to the author of the offer.
+    /// The escrow and the yield are measured by the balance of the marketplace,
+    /// so only the tokens actually received are paid.
+    ///
+    /// The offer stays deposited if the yield program doesn't return the whole escrow,
+    /// so the caller fails the transaction and the recall can be repeated.
+    /// The failed payment of the yield is recorded as a failed refund.
+    pub async fn recall_offer(&mut self, item_id: &ItemId, offer: &Offer) -> Result<(), MarketErr> {
+        let yield_program_id = match self.deposited_offers.get(&offer.offer_id) {
+            Some(yield_program_id) => *yield_program_id,
+            None => return Ok(()),
+        };
+        let ft_contract_id = offer
+            .ft_contract_id
+            .expect("Only offers in fungible tokens are deposited");
+        let before = ft_balance(&ft_contract_id, &exec::program_id())
+            .await
+            .map_err(|_| MarketErr::PaymentFailed)?;
+        let reply = msg::send_for_reply_as::<_, YieldEvent>(
+            yield_program_id,
+            YieldAction::Withdraw {
+                ft_contract_id,
+                amount: offer.price,
+            },
+            0,
+        )
+        .map_err(|_| MarketErr::PaymentFailed)?
+        .up_to(Some(REPLY_TIMEOUT))
+        .map_err(|_| MarketErr::PaymentFailed)?
+        .await
+        .map_err(|_| MarketErr::PaymentFailed)?;
+        if !matches!(reply, YieldEvent::Withdrawn { amount, .. } if amount == offer.price) {
+            return Err(MarketErr::PaymentFailed);
+        }
+        let after = ft_balance(&ft_contract_id, &exec::program_id())
+            .await
+            .map_err(|_| MarketErr::PaymentFailed)?;
+        let received = after.saturating_sub(before);
+        if received < offer.price {
+            return Err(MarketErr::PaymentFailed);
+        }
+        self.deposited_offers.remove(&offer.offer_id);
+
+        // the yield isn't a part of the escrow, so its failed transfer doesn't fail the recall
+        let yield_amount = received - offer.price;
+        if yield_amount > 0 {
+            self.pay_or_record(
+                &item_id.nft_contract_id,
+                item_id.token_id,
+                &exec::program_id(),
+                &offer.id,
+                Some(ft_contract_id),
+                yield_amount,
+            )
+            .await;
+        }
+        Ok(())
+    }
+}
+
+// notifies the yield program of the deposit and returns whether the program credited it
+async fn acknowledge_deposit(
+    yield_program_id: &ActorId,
+    ft_contract_id: ActorId,
+    amount: u128,
+) -> bool {
+    let reply = match msg::send_for_reply_as::<_, YieldEvent>(
+        *yield_program_id,
+        YieldAction::Deposit {
+            ft_contract_id,
+            amount,
+        },
+        0,
+    )
+    .and_then(|future| future.up_to(Some(REPLY_TIMEOUT)))
+    {
+        Ok(future) => future.await,
+        Err(_) => return false,
+    };
+    matches!(reply, Ok(YieldEvent::Deposited { amount: deposited }) if deposited == amount)
+}
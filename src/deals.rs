@@ -0,0 +1,190 @@
+use crate::{
+    events,
+    nft_messages::nft_approve,
+    payment::*,
+    validation::{check_not_owner, check_not_zero, market_err},
+    vesting::check_vesting,
+    Market,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::{H256, U256};
+
+impl Market {
+    pub async fn create_deal(
+        &mut self,
+        counterparty: &ActorId,
+        items: Vec<(ActorId, U256)>,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+        expires_at: u64,
+        vesting: Option<Vesting>,
+    ) {
+        let seller = msg::source();
+        self.check_not_banned(&seller);
+        self.check_not_banned(counterparty);
+        check_not_zero(counterparty);
+        check_not_owner(&seller, counterparty, MarketErr::SelfPurchase);
+        self.check_approved_ft_contract(ft_contract_id);
+        if items.is_empty() || items.len() > MAX_DEAL_ITEMS {
+            panic!("The deal must have from 1 to {MAX_DEAL_ITEMS} tokens");
+        }
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        if expires_at <= exec::block_timestamp() {
+            panic!("The deal has expired");
+        }
+        if let Some(vesting) = &vesting {
+            check_vesting(vesting);
+        }
+        for (index, (nft_contract_id, token_id)) in items.iter().enumerate() {
+            if items[..index].contains(&(*nft_contract_id, *token_id)) {
+                panic!("The token is already in the deal");
+            }
+            self.check_approved_nft_contract(nft_contract_id);
+            self.check_transferable(nft_contract_id);
+            self.check_deal_item(nft_contract_id, *token_id);
+        }
+
+        let deal = Deal {
+            seller,
+            counterparty: *counterparty,
+            items,
+            ft_contract_id,
+            price,
+            expires_at,
+            vesting,
+        };
+        let hash = deal.hash();
+        if self.deals.contains_key(&hash) {
+            panic!("The deal already exists");
+        }
+
+        // approve nfts to trade on the marketplace
+        for (nft_contract_id, token_id) in deal.items.iter() {
+            if let Err(err) = nft_approve(nft_contract_id, &exec::program_id(), *token_id).await {
+                market_err(MarketErr::NftCall(err));
+            }
+            self.check_token_owner(nft_contract_id, *token_id, &seller)
+                .await;
+        }
+
+        self.deals.insert(hash, deal);
+        events::reply(MarketEvent::DealCreated(hash), 0)
+            .expect("Error in reply [MarketEvent::DealCreated]");
+    }
+
+    /// Executes the deal: the payment is split equally among the tokens
+    /// (the last token receives the rounding remainder) and each token is sold for its share.
+    /// The shares of the tokens that fail to be sold are returned to the counterparty.
+    pub async fn accept_deal(&mut self, hash: H256) {
+        let buyer = msg::source();
+        self.check_not_banned(&buyer);
+        let deal = self.deals.get(&hash).expect("The deal does not exist");
+        if deal.counterparty != buyer {
+            panic!("Only the counterparty can accept the deal");
+        }
+        if deal.expires_at < exec::block_timestamp() {
+            panic!("The deal has expired");
+        }
+        self.check_not_banned(&deal.seller);
+        for (nft_contract_id, token_id) in deal.items.iter() {
+            self.check_transferable(nft_contract_id);
+            self.check_deal_item(nft_contract_id, *token_id);
+        }
+        check_attached_value(deal.ft_contract_id, deal.price);
+        self.return_change(deal.ft_contract_id, deal.price);
+        // the deal is removed before any transfer, so it can't be accepted twice
+        let deal = self.deals.remove(&hash).expect("The deal does not exist");
+
+        // the buyer's tokens are escrowed, so the sales are paid by the marketplace
+        let price = self
+            .escrow_payment(&buyer, deal.ft_contract_id, deal.price)
+            .await
+            .expect("Error in escrowing the deal payment");
+
+        let share = price / deal.items.len() as u128;
+        let mut failed_items = vec![];
+        let mut refund = 0;
+        for (index, (nft_contract_id, token_id)) in deal.items.iter().enumerate() {
+            let item_price = if index + 1 == deal.items.len() {
+                price - share * index as u128
+            } else {
+                share
+            };
+            match self
+                .execute_sale(
+                    &exec::program_id(),
+                    nft_contract_id,
+                    deal.ft_contract_id,
+                    *token_id,
+                    &deal.seller,
+                    &buyer,
+                    item_price,
+                    None,
+                    deal.vesting.as_ref(),
+                )
+                .await
+            {
+                Ok(_) => {
+                    let event = MarketEvent::ItemSold {
+                        owner: buyer,
+                        nft_contract_id: *nft_contract_id,
+                        token_id: *token_id,
+                        payer: buyer,
+                    };
+                    self.notify_subscribers(nft_contract_id, &event);
+                }
+                Err(_) => {
+                    failed_items.push(ItemId::new(*nft_contract_id, *token_id));
+                    refund += item_price;
+                }
+            }
+        }
+
+        if refund > 0
+            && self
+                .transfer_payment(&exec::program_id(), &buyer, deal.ft_contract_id, refund)
+                .await
+                .is_err()
+        {
+            // the refund is recorded, so the admin can retry it
+            let transaction_id = self.next_transaction_id();
+            self.record_failed_transaction(
+                &deal.items[0].0,
+                deal.items[0].1,
+                transaction_id,
+                TransactionStep::Refund {
+                    to: buyer,
+                    ft_contract_id: deal.ft_contract_id,
+                    amount: refund,
+                },
+            );
+        }
+        events::reply(MarketEvent::DealAccepted { hash, failed_items }, 0)
+            .expect("Error in reply [MarketEvent::DealAccepted]");
+    }
+
+    /// Removes the deal. The seller can remove the deal at any time, anyone else only after it expires.
+    pub fn cancel_deal(&mut self, hash: H256) {
+        let deal = self.deals.get(&hash).expect("The deal does not exist");
+        if deal.seller != msg::source() && deal.expires_at >= exec::block_timestamp() {
+            panic!("Only the seller can cancel the deal before it expires");
+        }
+        self.deals.remove(&hash);
+        events::reply(MarketEvent::DealCancelled(hash), 0)
+            .expect("Error in reply [MarketEvent::DealCancelled]");
+    }
+
+    fn check_deal_item(&self, nft_contract_id: &ActorId, token_id: U256) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(nft_contract_id, token_id);
+        if let Some(item) = self.items.get(&item_id) {
+            if item.locked_by.is_some() {
+                panic!("The item is locked by another transaction");
+            }
+        }
+    }
+}
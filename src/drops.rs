@@ -0,0 +1,181 @@
+use crate::{
+    events, nft_messages::nft_approve, payment::check_attached_value, validation::*, DropId,
+    ItemId, Market, MarketErr, MarketEvent, TokenDrop, TransactionStep, MAX_BATCH_LEN,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    pub fn next_drop_id(&mut self) -> DropId {
+        let drop_id = self.drop_id;
+        self.drop_id = self.drop_id.wrapping_add(1);
+        drop_id
+    }
+
+    // checks that the token isn't sold in a drop
+    pub fn check_not_in_drop(&self, nft_contract_id: &ActorId, token_id: U256) {
+        if self.drops.values().any(|drop| {
+            drop.nft_contract_id == *nft_contract_id && drop.token_ids.contains(&token_id)
+        }) {
+            panic!("The token is sold in a drop");
+        }
+    }
+
+    pub async fn create_drop(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_ids: Vec<U256>,
+        price: u128,
+        wallet_limit: u32,
+        (starts_at, ends_at): (u64, u64),
+    ) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_nft_contract(nft_contract_id);
+        self.check_transferable(nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        if token_ids.is_empty() || token_ids.len() > MAX_BATCH_LEN {
+            panic!("A drop must have from 1 to {MAX_BATCH_LEN} tokens");
+        }
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        if wallet_limit == 0 {
+            panic!("wallet limit can't be equal to zero");
+        }
+        if starts_at >= ends_at || ends_at <= exec::block_timestamp() {
+            panic!("Wrong sale window");
+        }
+        for (index, token_id) in token_ids.iter().enumerate() {
+            if token_ids[..index].contains(token_id) {
+                panic!("The tokens of a drop must be unique");
+            }
+            let item_id = ItemId::new(*nft_contract_id, *token_id);
+            if self.items.contains_key(&item_id) {
+                panic!("The token is listed on the marketplace");
+            }
+            self.check_not_in_drop(nft_contract_id, *token_id);
+        }
+
+        // approve nfts to trade on the marketplace
+        for token_id in token_ids.iter() {
+            if let Err(err) = nft_approve(nft_contract_id, &exec::program_id(), *token_id).await {
+                market_err(MarketErr::NftCall(err));
+            }
+        }
+        // the tokens could be listed while waiting for the replies
+        for token_id in token_ids.iter() {
+            self.check_not_in_drop(nft_contract_id, *token_id);
+        }
+
+        let drop_id = self.next_drop_id();
+        let amount = token_ids.len() as u32;
+        self.drops.insert(
+            drop_id,
+            TokenDrop {
+                drop_id,
+                creator: msg::source(),
+                nft_contract_id: *nft_contract_id,
+                ft_contract_id,
+                price,
+                token_ids,
+                wallet_limit,
+                starts_at,
+                ends_at,
+                purchases: BTreeMap::new(),
+            },
+        );
+        events::reply(
+            MarketEvent::DropCreated {
+                drop_id,
+                nft_contract_id: *nft_contract_id,
+                amount,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::DropCreated]");
+    }
+
+    pub async fn buy_from_drop(&mut self, drop_id: DropId) {
+        let buyer = msg::source();
+        self.check_not_banned(&buyer);
+        let drop = self.drops.get_mut(&drop_id).expect("Drop does not exist");
+        let now = exec::block_timestamp();
+        if now < drop.starts_at || now > drop.ends_at {
+            panic!("The drop sale window is closed");
+        }
+        check_not_owner(&drop.creator, &buyer, MarketErr::SelfPurchase);
+        check_attached_value(drop.ft_contract_id, drop.price);
+        let purchases = drop.purchases.entry(buyer).or_default();
+        if *purchases >= drop.wallet_limit {
+            panic!("The wallet limit of the drop is reached");
+        }
+
+        // the token and the purchase are reserved while the sale is executed
+        *purchases += 1;
+        let token_id = drop.token_ids.remove(0);
+        let (creator, nft_contract_id, ft_contract_id, price) = (
+            drop.creator,
+            drop.nft_contract_id,
+            drop.ft_contract_id,
+            drop.price,
+        );
+        self.return_change(ft_contract_id, price);
+
+        if self
+            .execute_sale(
+                &buyer,
+                &nft_contract_id,
+                ft_contract_id,
+                token_id,
+                &creator,
+                &buyer,
+                price,
+                None,
+                None,
+            )
+            .await
+            .is_err()
+        {
+            if let Some(drop) = self.drops.get_mut(&drop_id) {
+                drop.token_ids.insert(0, token_id);
+                if let Some(purchases) = drop.purchases.get_mut(&buyer) {
+                    *purchases -= 1;
+                }
+            }
+            let transaction_id = self.next_transaction_id();
+            self.transaction_failed(
+                &nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Sale {
+                    seller: creator,
+                    buyer,
+                    price,
+                },
+            );
+            return;
+        }
+
+        // the drop closes when it's sold out
+        if self
+            .drops
+            .get(&drop_id)
+            .map(|drop| drop.token_ids.is_empty())
+            .unwrap_or(false)
+        {
+            self.drops.remove(&drop_id);
+            events::send(creator, MarketEvent::DropSoldOut { drop_id }, 0)
+                .expect("Error in sending [MarketEvent::DropSoldOut]");
+        }
+        events::reply(
+            MarketEvent::DropPurchased {
+                drop_id,
+                token_id,
+                buyer,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::DropPurchased]");
+    }
+}
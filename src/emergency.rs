@@ -0,0 +1,145 @@
+use crate::{events, Market};
+use gstd::{exec, msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::U256;
+
+const ZERO_ID: ActorId = ActorId::new([0u8; 32]);
+
+impl Market {
+    /// Arms the emergency mode or disarms it.
+    /// Requirements:
+    /// * Only admin can arm the emergency mode
+    /// * The timelock can't be less than the minimum timelock
+    pub fn arm_emergency(&mut self, timelock: Option<u64>) {
+        self.check_admin();
+        if let Some(timelock) = timelock {
+            if timelock < MIN_EMERGENCY_TIMELOCK {
+                panic!("The timelock can't be less than the minimum emergency timelock");
+            }
+        }
+        self.emergency = timelock.map(|timelock| EmergencySwitch {
+            timelock,
+            heartbeat_at: exec::block_timestamp(),
+        });
+    }
+
+    pub fn emergency_heartbeat(&mut self) {
+        self.check_admin();
+        let emergency = self
+            .emergency
+            .as_mut()
+            .expect("The emergency mode isn't armed");
+        emergency.heartbeat_at = exec::block_timestamp();
+    }
+
+    pub fn check_emergency(&self) {
+        let active = self
+            .emergency
+            .map(|emergency| {
+                exec::block_timestamp() >= emergency.heartbeat_at.saturating_add(emergency.timelock)
+            })
+            .unwrap_or(false);
+        if !active {
+            panic!("The emergency mode isn't active");
+        }
+    }
+
+    /// Returns the caller's offers and winning bid on the item.
+    ///
+    /// The escrows are removed from the item before the transfers,
+    /// and the escrows whose transfer failed are put back.
+    pub async fn emergency_reclaim(&mut self, nft_contract_id: &ActorId, token_id: U256) {
+        self.check_emergency();
+        let account = msg::source();
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.lock_item(&item_id);
+        // the caller's outbid leading bids of a candle auction are credited to the escrow balance
+        self.release_candle_bids(&item_id, Some(&account));
+
+        let escrow = self.winner_escrow(&item_id);
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        let offers: Vec<Offer> = item
+            .offers
+            .iter()
+            .filter(|offer| offer.id == account)
+            .cloned()
+            .collect();
+        item.offers.retain(|offer| offer.id != account);
+        let bid = match &mut item.auction {
+            Some(auction) if auction.current_winner == account => {
+                auction.current_winner = ZERO_ID;
+                Some((item.ft_contract_id, escrow))
+            }
+            _ => None,
+        };
+        if offers.is_empty() && bid.is_none() {
+            panic!("Nothing to reclaim");
+        }
+        self.reindex_bid(&account, &item_id);
+
+        let mut offer_ids = vec![];
+        for offer in offers {
+            if self
+                .return_offer_escrow(&item_id, &offer, &account)
+                .await
+                .is_ok()
+            {
+                self.unindex_offer(&account, offer.offer_id);
+                offer_ids.push(offer.offer_id);
+            } else {
+                self.restore_offer(&item_id, offer);
+            }
+        }
+        let mut returned_bid = None;
+        if let Some((ft_contract_id, price)) = bid {
+            if self
+                .transfer_payment(&exec::program_id(), &account, ft_contract_id, price)
+                .await
+                .is_ok()
+            {
+                self.max_bids.remove(&item_id);
+                if let Some(candle) = self
+                    .items
+                    .get_mut(&item_id)
+                    .and_then(|item| item.auction.as_mut())
+                    .and_then(|auction| auction.candle.as_mut())
+                {
+                    candle.leaders.pop();
+                }
+                returned_bid = Some(price);
+            } else {
+                self.restore_winner(&item_id, &account);
+            }
+        }
+
+        self.unlock_item(&item_id);
+        events::reply(
+            MarketEvent::EmergencyReclaimed {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                offer_ids,
+                bid: returned_bid,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::EmergencyReclaimed]");
+    }
+
+    fn restore_offer(&mut self, item_id: &ItemId, offer: Offer) {
+        if let Some(item) = self.items.get_mut(item_id) {
+            item.offers.push(offer);
+            item.offers.sort_by_key(|offer| offer.offer_id);
+        }
+    }
+
+    fn restore_winner(&mut self, item_id: &ItemId, account: &ActorId) {
+        if let Some(auction) = self
+            .items
+            .get_mut(item_id)
+            .and_then(|item| item.auction.as_mut())
+        {
+            auction.current_winner = *account;
+        }
+        self.reindex_bid(account, item_id);
+    }
+}
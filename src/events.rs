@@ -0,0 +1,56 @@
+use gstd::{errors::Result, exec, msg, prelude::*, ActorId, MessageId};
+use market_io::*;
+
+/// The number of recent events kept for the `EventsSince` query.
+const MAX_EVENTS: usize = 100;
+
+#[derive(Default)]
+struct EventLog {
+    seq: EventSeq,
+    events: Vec<EventRecord>,
+}
+
+// the log is kept apart from the market, so the event is recorded
+// wherever it is replied, even while the market is borrowed
+static mut EVENT_LOG: Option<EventLog> = None;
+
+/// Replies with the event and records it in the log of the recent events,
+/// replacing the oldest event if the log is full.
+pub fn reply(event: MarketEvent, value: u128) -> Result<MessageId> {
+    let message_id = msg::reply(&event, value)?;
+    record(event);
+    Ok(message_id)
+}
+
+/// Sends the event to the account and records it the same way as [`reply`].
+pub fn send(to: ActorId, event: MarketEvent, value: u128) -> Result<MessageId> {
+    let message_id = msg::send(to, &event, value)?;
+    record(event);
+    Ok(message_id)
+}
+
+fn record(event: MarketEvent) {
+    let log = unsafe { EVENT_LOG.get_or_insert(EventLog::default()) };
+    log.seq = log.seq.wrapping_add(1);
+    if log.events.len() == MAX_EVENTS {
+        log.events.remove(0);
+    }
+    log.events.push(EventRecord {
+        seq: log.seq,
+        timestamp: exec::block_timestamp(),
+        source: msg::source(),
+        event,
+    });
+}
+
+/// The recent events with the sequence number greater than `seq`.
+pub fn events_since(seq: EventSeq) -> Vec<EventRecord> {
+    let Some(log) = (unsafe { EVENT_LOG.as_ref() }) else {
+        return vec![];
+    };
+    log.events
+        .iter()
+        .filter(|record| record.seq > seq)
+        .cloned()
+        .collect()
+}
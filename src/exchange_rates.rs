@@ -0,0 +1,40 @@
+use crate::Market;
+use gstd::ActorId;
+
+impl Market {
+    /// Sets the exchange rate used to compare bids in multi-currency auctions.
+    /// Requirements:
+    /// * Only admin can set exchange rates
+    /// * The currency must be approved and the rate can't be equal to zero
+    /// Arguments:
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `rate`: the value of the smallest unit of the currency in the common unit
+    /// (if it is `None` then the rate is removed)
+    pub fn set_exchange_rate(&mut self, ft_contract_id: Option<ActorId>, rate: Option<u128>) {
+        self.check_admin();
+        self.check_approved_ft_contract(ft_contract_id);
+        match rate {
+            Some(0) => panic!("Exchange rate can't be equal to zero"),
+            Some(rate) => {
+                self.exchange_rates.insert(ft_contract_id, rate);
+            }
+            None => {
+                self.exchange_rates.remove(&ft_contract_id);
+            }
+        }
+    }
+
+    pub fn check_exchange_rate(&self, ft_contract_id: Option<ActorId>) {
+        if !self.exchange_rates.contains_key(&ft_contract_id) {
+            panic!("The exchange rate of the currency is not set");
+        }
+    }
+
+    /// Returns the amount of the currency in the common unit.
+    pub fn bid_value(&self, ft_contract_id: Option<ActorId>, price: u128) -> u128 {
+        self.check_exchange_rate(ft_contract_id);
+        price
+            .checked_mul(self.exchange_rates[&ft_contract_id])
+            .expect("Overflow in the bid value")
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    events, nft_messages::nft_transfer_without_payout, ItemId, Market, MarketEvent, TransactionId,
+    TransactionStep,
+};
+
+impl Market {
+    /// Retries the failed refund or the failed return of the NFT held in custody.
+    /// Requirements:
+    /// * Only admin can retry transactions
+    /// * The failed transaction must exist and its step must be a refund or a return of the NFT
+    pub async fn retry_transaction(&mut self, transaction_id: TransactionId) {
+        self.check_admin();
+        let failed = self
+            .failed_transactions
+            .get(&transaction_id)
+            .expect("The failed transaction does not exist")
+            .clone();
+        let retried = match failed.step {
+            TransactionStep::Refund {
+                to,
+                ft_contract_id,
+                amount,
+            } => {
+                // the transaction is removed while waiting for the reply so it can't be retried twice
+                self.failed_transactions.remove(&transaction_id);
+                self.refund_payment(&to, ft_contract_id, amount)
+                    .await
+                    .is_ok()
+            }
+            TransactionStep::NftReturn { to } => {
+                self.failed_transactions.remove(&transaction_id);
+                let returned =
+                    nft_transfer_without_payout(&failed.nft_contract_id, &to, failed.token_id)
+                        .await
+                        .is_ok();
+                let item_id = ItemId::new(failed.nft_contract_id, failed.token_id);
+                match self.items.get_mut(&item_id) {
+                    Some(item) if returned && item.owner_id == to => item.custody = false,
+                    _ => (),
+                }
+                returned
+            }
+            _ => panic!("Only refunds and returns of NFTs can be retried"),
+        };
+        if !retried {
+            self.failed_transactions
+                .insert(transaction_id, failed.clone());
+            events::reply(
+                MarketEvent::TransactionFailed {
+                    nft_contract_id: failed.nft_contract_id,
+                    token_id: failed.token_id,
+                    transaction_id,
+                },
+                0,
+            )
+            .expect("Error in reply [MarketEvent::TransactionFailed]");
+            return;
+        }
+
+        events::reply(MarketEvent::TransactionRetried { transaction_id }, 0)
+            .expect("Error in reply [MarketEvent::TransactionRetried]");
+    }
+
+    /// Removes the failed transaction from the queue.
+    /// Requirements:
+    /// * Only admin can abort transactions
+    /// * The failed transaction must exist
+    pub fn abort_transaction(&mut self, transaction_id: TransactionId) {
+        self.check_admin();
+        self.failed_transactions
+            .remove(&transaction_id)
+            .expect("The failed transaction does not exist");
+        events::reply(MarketEvent::TransactionAborted { transaction_id }, 0)
+            .expect("Error in reply [MarketEvent::TransactionAborted]");
+    }
+}
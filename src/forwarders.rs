@@ -0,0 +1,40 @@
+use crate::{validation::check_not_zero, ItemId, Market};
+use gstd::{msg, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    pub fn add_trusted_forwarder(&mut self, forwarder: &ActorId) {
+        self.check_admin();
+        check_not_zero(forwarder);
+        self.trusted_forwarders.insert(*forwarder);
+    }
+
+    pub fn remove_trusted_forwarder(&mut self, forwarder: &ActorId) {
+        self.check_admin();
+        self.trusted_forwarders.remove(forwarder);
+    }
+
+    /// Buys the item on behalf of the buyer attested by the trusted forwarder.
+    /// Requirements:
+    /// * Only a trusted forwarder can forward purchases
+    /// * The item must be sold for fungible tokens, since the value attached
+    /// by the forwarder doesn't belong to the buyer
+    pub async fn forwarded_buy_item(
+        &mut self,
+        buyer: &ActorId,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+    ) {
+        if !self.trusted_forwarders.contains(&msg::source()) {
+            panic!("Only trusted forwarders can forward purchases");
+        }
+        check_not_zero(buyer);
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if item.ft_contract_id.is_none() || msg::value() > 0 {
+            panic!("Only items sold for fungible tokens can be bought by forwarders");
+        }
+        self.buy_item_as(buyer, nft_contract_id, token_id, None, None)
+            .await;
+    }
+}
@@ -0,0 +1,39 @@
+use ft_io::*;
+use gstd::{
+    errors::{ContractError, Result},
+    msg, ActorId,
+};
+use market_io::REPLY_TIMEOUT;
+
+pub async fn transfer_tokens(
+    contract_id: &ActorId,
+    from: &ActorId,
+    to: &ActorId,
+    amount: u128,
+) -> Result<()> {
+    msg::send_for_reply(
+        *contract_id,
+        FTAction::Transfer {
+            from: *from,
+            to: *to,
+            amount,
+        },
+        0,
+    )?
+    .up_to(Some(REPLY_TIMEOUT))?
+    .await?;
+    Ok(())
+}
+
+pub async fn ft_balance(contract_id: &ActorId, account: &ActorId) -> Result<u128> {
+    let reply: FTEvent = msg::send_for_reply_as(*contract_id, FTAction::BalanceOf(*account), 0)?
+        .up_to(Some(REPLY_TIMEOUT))?
+        .await?;
+    if let FTEvent::Balance(balance) = reply {
+        Ok(balance)
+    } else {
+        Err(ContractError::Convert(
+            "Unexpected reply to the balance request",
+        ))
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{events, validation::check_not_zero, Market};
+use gstd::{exec, msg};
+use market_io::*;
+
+// the flag is kept apart from the market, so it isn't a part of the state
+// and is reset if the applied change fails
+static mut EXECUTING_CHANGE: bool = false;
+
+/// Whether the executed change of the governance is being applied,
+/// so the admin checks of the change pass.
+pub fn executing_change() -> bool {
+    unsafe { EXECUTING_CHANGE }
+}
+
+impl Market {
+    /// Sets the governance program or returns the parameter changes to the admin.
+    /// Requirements:
+    /// * Only admin can set the governance if it isn't set,
+    /// otherwise only the executed change can set it
+    pub fn set_governance(&mut self, governance: Option<Governance>) {
+        self.check_admin();
+        if let Some(governance) = &governance {
+            check_not_zero(&governance.program_id);
+        }
+        self.governance = governance;
+    }
+
+    /// Checks that the parameter change in the governance mode is made by an executed change.
+    pub fn check_governance(&self, action: &MarketAction) {
+        if self.governance.is_some() && action.is_parameter_change() {
+            panic!("The parameters are changed by the governance");
+        }
+    }
+
+    fn check_governance_program(&self) -> Governance {
+        match self.governance {
+            Some(governance) if governance.program_id == msg::source() => governance,
+            _ => panic!("Only the governance program can make that action"),
+        }
+    }
+
+    pub fn propose_change(&mut self, action: MarketAction) {
+        let governance = self.check_governance_program();
+        if !action.is_parameter_change() {
+            panic!("The action isn't a parameter change");
+        }
+        let change_id = self.change_id;
+        self.change_id = self.change_id.wrapping_add(1);
+        let executable_at = exec::block_timestamp().saturating_add(governance.timelock);
+        self.pending_changes.insert(
+            change_id,
+            PendingChange {
+                change_id,
+                action,
+                executable_at,
+            },
+        );
+        events::reply(
+            MarketEvent::ChangeProposed {
+                change_id,
+                executable_at,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::ChangeProposed]");
+    }
+
+    /// Applies the change as if it was made by the admin.
+    /// If the change fails then it stays pending, so it can be cancelled.
+    pub fn execute_change(&mut self, change_id: ChangeId) {
+        self.check_governance_program();
+        let change = self
+            .pending_changes
+            .get(&change_id)
+            .expect("The change does not exist");
+        if change.executable_at > exec::block_timestamp() {
+            panic!("The timelock of the change is not over");
+        }
+        let change = self
+            .pending_changes
+            .remove(&change_id)
+            .expect("The change does not exist");
+        unsafe { EXECUTING_CHANGE = true };
+        self.apply_change(change.action);
+        unsafe { EXECUTING_CHANGE = false };
+        events::reply(MarketEvent::ChangeExecuted(change_id), 0)
+            .expect("Error in reply [MarketEvent::ChangeExecuted]");
+    }
+
+    fn apply_change(&mut self, action: MarketAction) {
+        match action {
+            MarketAction::AddNftContract(nft_contract_id) => {
+                self.add_nft_contract(&nft_contract_id);
+            }
+            MarketAction::AddFTContract(ft_contract_id) => {
+                self.add_ft_contract(&ft_contract_id);
+            }
+            MarketAction::SetPromotionFee(fee) => self.set_promotion_fee(fee),
+            MarketAction::SetRoyaltyConfig(config) => self.set_royalty_config(config),
+            MarketAction::SetFeeTiers(fee_tiers) => self.set_fee_tiers(fee_tiers),
+            MarketAction::SetAuctionBounds(bounds) => self.set_auction_bounds(bounds),
+            MarketAction::SetKeeperBounty(bounty) => self.set_keeper_bounty(bounty),
+            MarketAction::SetPriceFeed(price_feed) => self.set_price_feed(price_feed),
+            MarketAction::SetCollectionCreator {
+                nft_contract_id,
+                creator,
+            } => self.set_collection_creator(&nft_contract_id, creator),
+            MarketAction::SetPrimarySaleFee(fee) => self.set_primary_sale_fee(fee),
+            MarketAction::SetExchangeRate {
+                ft_contract_id,
+                rate,
+            } => self.set_exchange_rate(ft_contract_id, rate),
+            MarketAction::SetCollectionFee {
+                nft_contract_id,
+                fee,
+            } => self.set_collection_fee(&nft_contract_id, fee),
+            MarketAction::SetCurrencyConfig {
+                ft_contract_id,
+                config,
+            } => self.set_currency_config(ft_contract_id, config),
+            MarketAction::SetStaking(staking) => self.set_staking(staking),
+            MarketAction::AddTrustedForwarder(forwarder) => self.add_trusted_forwarder(&forwarder),
+            MarketAction::RemoveTrustedForwarder(forwarder) => {
+                self.remove_trusted_forwarder(&forwarder)
+            }
+            MarketAction::SetReceiptsContract(receipts_contract_id) => {
+                self.set_receipts_contract(receipts_contract_id)
+            }
+            MarketAction::SetCollectionCode(code_id) => self.set_collection_code(code_id),
+            MarketAction::SetTradeRetention(retention) => self.set_trade_retention(retention),
+            MarketAction::SetYieldProgram(yield_program_id) => {
+                self.set_yield_program(yield_program_id)
+            }
+            MarketAction::SetMetadataProgram {
+                nft_contract_id,
+                metadata_program,
+            } => self.set_metadata_program(&nft_contract_id, metadata_program),
+            MarketAction::SetMaxOffers(max_offers) => self.set_max_offers(max_offers),
+            MarketAction::SetRoyaltyFree {
+                nft_contract_id,
+                royalty_free,
+            } => self.set_royalty_free(&nft_contract_id, royalty_free),
+            MarketAction::SetSoulbound {
+                nft_contract_id,
+                soulbound,
+            } => self.set_soulbound(&nft_contract_id, soulbound),
+            MarketAction::SetRegistryProgram(registry_program_id) => {
+                self.set_registry_program(registry_program_id)
+            }
+            MarketAction::SetRandomnessProvider(randomness_provider) => {
+                self.set_randomness_provider(randomness_provider)
+            }
+            MarketAction::SetGovernance(governance) => self.set_governance(governance),
+            MarketAction::SetTreasuries(treasuries) => self.set_treasuries(treasuries),
+            _ => panic!("The action isn't a parameter change"),
+        }
+    }
+
+    pub fn cancel_change(&mut self, change_id: ChangeId) {
+        self.check_governance_program();
+        if self.pending_changes.remove(&change_id).is_none() {
+            panic!("The change does not exist");
+        }
+        events::reply(MarketEvent::ChangeCancelled(change_id), 0)
+            .expect("Error in reply [MarketEvent::ChangeCancelled]");
+    }
+}
@@ -0,0 +1,28 @@
+use crate::Market;
+use gstd::ActorId;
+
+/// The maximum keeper bounty in basis points (the whole treasury fee).
+const MAX_KEEPER_BOUNTY: u16 = 10_000;
+
+impl Market {
+    /// Sets the share of the treasury fee paid to keepers.
+    /// Requirements:
+    /// * Only admin can set the keeper bounty
+    /// * The bounty can't exceed the whole treasury fee
+    pub fn set_keeper_bounty(&mut self, bounty: u16) {
+        self.check_admin();
+        if bounty > MAX_KEEPER_BOUNTY {
+            panic!("Keeper bounty can't exceed the treasury fee");
+        }
+        self.keeper_bounty = bounty;
+    }
+
+    pub fn keeper_reward(&self, treasury_fee: u128) -> u128 {
+        treasury_fee * self.keeper_bounty as u128 / 10_000u128
+    }
+
+    pub fn record_keeper_reward(&mut self, keeper: &ActorId, reward: u128) {
+        let rewards = self.keeper_rewards.entry(*keeper).or_default();
+        *rewards = rewards.saturating_add(reward);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{events, nft_messages::nft_mint, Item, Market};
+use gear_lib::non_fungible_token::token::TokenMetadata;
+use gstd::{msg, prelude::*, prog, ActorId, CodeId};
+use market_io::*;
+use nft_io::InitNFT;
+
+impl Market {
+    pub fn set_collection_code(&mut self, code_id: Option<CodeId>) {
+        self.check_admin();
+        self.collection_code_id = code_id;
+    }
+
+    /// Deploys the NFT contract, mints the tokens to the marketplace and lists them.
+    ///
+    /// The tokens are listed one by one after each mint,
+    /// so a failed mint keeps the tokens that are already listed.
+    pub async fn launch_collection(
+        &mut self,
+        (name, symbol, base_uri): (String, String, String),
+        tokens: Vec<LaunchToken>,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) {
+        let creator = msg::source();
+        self.check_not_banned(&creator);
+        let code_id = self
+            .collection_code_id
+            .expect("The collection code isn't set");
+        self.check_approved_ft_contract(ft_contract_id);
+        if tokens.is_empty() || tokens.len() > MAX_BATCH_LEN {
+            panic!("A launch must have from 1 to {MAX_BATCH_LEN} tokens");
+        }
+        self.check_price_bounds(ft_contract_id, price);
+
+        // the message id is unique, so it is used as the salt
+        let init = InitNFT {
+            name,
+            symbol,
+            base_uri,
+            royalties: None,
+        };
+        let (_, nft_contract_id) = prog::create_program(code_id, msg::id().encode(), init, 0)
+            .expect("Error in creating the NFT contract");
+        self.approved_nft_contracts.insert(nft_contract_id);
+        self.collection_creators.insert(nft_contract_id, creator);
+
+        let mut token_ids = vec![];
+        for token in tokens {
+            let token_metadata = TokenMetadata {
+                name: token.name,
+                description: token.description,
+                media: token.media,
+                reference: token.reference,
+            };
+            let token_id = match nft_mint(&nft_contract_id, token_metadata).await {
+                Ok(token_id) => token_id,
+                Err(_) => break,
+            };
+            let item_id = ItemId::new(nft_contract_id, token_id);
+            self.items.insert(
+                item_id,
+                Item {
+                    nft_contract_id,
+                    token_id,
+                    owner_id: creator,
+                    ft_contract_id,
+                    price: Some(price),
+                    primary_sale: true,
+                    ..Default::default()
+                },
+            );
+            let event = MarketEvent::MarketDataAdded {
+                nft_contract_id,
+                owner: creator,
+                token_id,
+                price: Some(price),
+                beneficiary: None,
+                metadata: None,
+            };
+            self.notify_subscribers(&nft_contract_id, &event);
+            token_ids.push(token_id);
+        }
+
+        events::reply(
+            MarketEvent::CollectionLaunched {
+                nft_contract_id,
+                creator,
+                token_ids,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::CollectionLaunched]");
+    }
+}
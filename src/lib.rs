@@ -1,18 +1,59 @@
 #![no_std]
 
-use gstd::{exec, msg, prelude::*, ActorId};
+use gstd::{exec, msg, prelude::*, ActorId, CodeId};
 pub use market_io::*;
 use primitive_types::{H256, U256};
 pub mod nft_messages;
 use nft_messages::*;
 pub mod auction;
+pub mod balances;
+pub mod ban_list;
+pub mod candle;
+pub mod collection_config;
+pub mod custody;
+pub mod deals;
+pub mod drops;
+pub mod emergency;
+pub mod events;
+pub mod exchange_rates;
+pub mod failed_transactions;
+pub mod forwarders;
+pub mod ft_messages;
+pub mod governance;
+pub mod keepers;
+pub mod launchpad;
+pub mod limit_orders;
+pub mod listing;
+pub mod loyalty;
+pub mod metadata_messages;
+pub mod metrics;
 pub mod offers;
+pub mod operations;
+pub mod oracle;
+pub mod order_book;
 pub mod payment;
+pub mod price_bounds;
+pub mod price_history;
+pub mod primary_sales;
+use price_history::PricePoint;
+pub mod promotion;
+pub mod receipts;
+pub mod registry;
+pub mod royalties;
 pub mod sale;
+pub mod signed_orders;
+pub mod simulation;
+pub mod slugs;
+pub mod staking;
 pub mod state;
+pub mod subscriptions;
+pub mod telemetry;
+pub mod trait_offers;
 use state::*;
-
-pub type ContractAndTokenId = String;
+pub mod validation;
+pub mod vesting;
+use validation::*;
+pub mod watchlist;
 
 const MIN_TREASURY_FEE: u8 = 0;
 const MAX_TREASURT_FEE: u8 = 5;
@@ -24,10 +65,113 @@ pub const BASE_PERCENT: u8 = 100;
 pub struct Market {
     pub admin_id: ActorId,
     pub treasury_id: ActorId,
+    /// The treasuries that share the fees and their weights in basis points
+    /// (if it is empty then `treasury_id` receives all fees).
+    pub treasuries: Vec<(ActorId, u16)>,
     pub treasury_fee: u8,
-    pub items: BTreeMap<ContractAndTokenId, Item>,
+    pub items: BTreeMap<ItemId, Item>,
     pub approved_nft_contracts: BTreeSet<ActorId>,
     pub approved_ft_contracts: BTreeSet<ActorId>,
+    pub transaction_id: TransactionId,
+    pub promotion_fee: u128,
+    pub royalty_config: RoyaltyConfig,
+    pub royalty_opt_outs: BTreeSet<ActorId>,
+    pub order_books: BTreeMap<(ActorId, Option<ActorId>), OrderBook>,
+    pub order_id: OrderId,
+    /// The limit buy orders per token.
+    pub limit_orders: BTreeMap<ItemId, Vec<LimitOrder>>,
+    /// The OTC deals by the hashes of their terms.
+    pub deals: BTreeMap<H256, Deal>,
+    /// The gas usage per action by the SCALE index of its variant.
+    pub gas_usage: BTreeMap<u8, GasUsage>,
+    /// The items by their slugs.
+    pub slugs: BTreeMap<String, ItemId>,
+    /// The vested proceeds of the sellers.
+    pub vested_proceeds: BTreeMap<VestingId, VestedProceeds>,
+    pub vesting_id: VestingId,
+    /// The program that reports the vetted NFT and FT contracts.
+    pub registry_program_id: Option<ActorId>,
+    /// The program that draws the end of the candle auctions.
+    pub randomness_provider: Option<ActorId>,
+    pub offer_id: OfferId,
+    pub claimable: BTreeMap<ActorId, u128>,
+    pub banned_actors: BTreeSet<ActorId>,
+    pub fee_tiers: Vec<FeeTier>,
+    pub traded_volume: BTreeMap<ActorId, u128>,
+    pub subscribers: BTreeMap<ActorId, BTreeSet<ActorId>>,
+    /// The watches of the collections and the watchers.
+    pub watches: BTreeMap<ActorId, Vec<(ActorId, Watch)>>,
+    /// Recent sales per collection and currency.
+    pub price_history: BTreeMap<(ActorId, Option<ActorId>), Vec<PricePoint>>,
+    /// Royalty rates in basis points reported by NFT contracts on the last sale of tokens.
+    pub royalty_rates: BTreeMap<ItemId, Vec<(ActorId, u16)>>,
+    pub auction_bounds: AuctionBounds,
+    /// The share of the treasury fee in basis points paid to the keepers.
+    pub keeper_bounty: u16,
+    pub keeper_rewards: BTreeMap<ActorId, u128>,
+    pub price_feed: Option<PriceFeed>,
+    pub monitor: Option<Monitor>,
+    /// The id of the current heartbeat chain.
+    pub heartbeat_id: u64,
+    pub failed_transactions: BTreeMap<TransactionId, FailedTransaction>,
+    /// The creators of collections whose listings are primary sales.
+    pub collection_creators: BTreeMap<ActorId, ActorId>,
+    /// The treasury fee of primary sales in basis points
+    /// (if it is `None` then the regular fee is applied).
+    pub primary_sale_fee: Option<u16>,
+    pub drops: BTreeMap<DropId, TokenDrop>,
+    pub drop_id: DropId,
+    /// The exchange rates of currencies accepted in multi-currency auctions.
+    pub exchange_rates: BTreeMap<Option<ActorId>, u128>,
+    pub collection_configs: BTreeMap<ActorId, CollectionConfig>,
+    pub currency_configs: BTreeMap<Option<ActorId>, CurrencyConfig>,
+    /// The offers of each account and the items they are made on.
+    pub offers_by_user: BTreeMap<ActorId, BTreeMap<OfferId, ItemId>>,
+    /// The items whose auctions are currently won by each account.
+    pub bids_by_user: BTreeMap<ActorId, BTreeSet<ItemId>>,
+    pub emergency: Option<EmergencySwitch>,
+    pub staking: Option<Staking>,
+    /// The treasury fees accrued for the stakers per currency.
+    pub staking_fees: BTreeMap<Option<ActorId>, StakingFees>,
+    /// The maximum bids escrowed by the current winners of auctions.
+    /// They aren't exposed by the item state, only the current prices are.
+    pub max_bids: BTreeMap<ItemId, u128>,
+    /// The sponsor programs that forward purchases on behalf of buyers.
+    pub trusted_forwarders: BTreeSet<ActorId>,
+    /// The NFT contract in which the receipts of sales are minted.
+    pub receipts_contract_id: Option<ActorId>,
+    /// The checkpoints of the operations split across several messages.
+    pub operations: BTreeMap<OperationId, Operation>,
+    pub operation_id: OperationId,
+    /// The code of the NFT contracts deployed by the launchpad.
+    pub collection_code_id: Option<CodeId>,
+    /// The trade history and its index by item.
+    pub trades: BTreeMap<TradeId, SaleReceipt>,
+    pub trades_by_item: BTreeMap<ItemId, BTreeSet<TradeId>>,
+    pub trade_id: TradeId,
+    pub trade_retention: Option<TradeRetention>,
+    /// The item from which the next pruning of the trades per item starts.
+    pub prune_cursor: Option<ItemId>,
+    /// The nonces of the signed orders that are filled.
+    pub used_nonces: BTreeMap<ActorId, BTreeSet<u64>>,
+    /// The order epochs of the accounts: the orders signed in the previous epochs are cancelled.
+    pub order_epochs: BTreeMap<ActorId, u64>,
+    pub yield_program_id: Option<ActorId>,
+    /// The offers whose escrows are deposited and the yield programs they are deposited into.
+    pub deposited_offers: BTreeMap<OfferId, ActorId>,
+    /// The escrow balances of the accounts per currency covering their balance offers.
+    pub balances: BTreeMap<(ActorId, Option<ActorId>), u128>,
+    /// The offers covered by the escrow balances of their authors.
+    pub balance_offers: BTreeSet<OfferId>,
+    /// The trait offers of the collections.
+    pub trait_offers: BTreeMap<ActorId, Vec<TraitOffer>>,
+    /// The maximum number of open offers on an item (`None` for the default limit).
+    pub max_offers: Option<u32>,
+    pub governance: Option<Governance>,
+    pub pending_changes: BTreeMap<ChangeId, PendingChange>,
+    pub change_id: ChangeId,
+    /// The bitwise OR of the paused action categories.
+    pub paused_actions: u32,
 }
 
 static mut MARKET: Option<Market> = None;
@@ -35,61 +179,335 @@ static mut MARKET: Option<Market> = None;
 impl Market {
     fn add_nft_contract(&mut self, nft_contract_id: &ActorId) {
         self.check_admin();
+        check_not_zero(nft_contract_id);
         self.approved_nft_contracts.insert(*nft_contract_id);
     }
 
     fn add_ft_contract(&mut self, ft_contract_id: &ActorId) {
         self.check_admin();
+        check_not_zero(ft_contract_id);
         self.approved_ft_contracts.insert(*ft_contract_id);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_market_data(
         &mut self,
         nft_contract_id: &ActorId,
         ft_contract_id: Option<ActorId>,
         token_id: U256,
         price: Option<u128>,
+        gate: Option<(ActorId, u128)>,
+        payees: Vec<(ActorId, u16)>,
+        beneficiary: Option<ActorId>,
+        reference_price: Option<u128>,
+        metadata: Option<Vec<u8>>,
     ) {
+        self.check_not_banned(&msg::source());
         self.check_approved_nft_contract(nft_contract_id);
+        self.check_transferable(nft_contract_id);
         self.check_approved_ft_contract(ft_contract_id);
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        self.on_auction(&contract_and_token_id);
+        if let Some((gate_contract_id, _)) = &gate {
+            check_not_zero(gate_contract_id);
+        }
+        check_payees(&payees);
+        if let Some(beneficiary) = &beneficiary {
+            check_not_zero(beneficiary);
+            if !payees.is_empty() {
+                panic!("The proceeds can't go both to the beneficiary and to the payees");
+            }
+        }
+        check_metadata(&metadata);
+        if let Some(price) = price {
+            self.check_price_bounds(ft_contract_id, price);
+        }
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(nft_contract_id, token_id);
 
-        nft_approve(nft_contract_id, &exec::program_id(), token_id).await;
+        let owner = self.unlocked_owner(&item_id);
+        if !self.approve_marketplace(nft_contract_id, token_id).await {
+            return;
+        }
+        self.check_token_owner(nft_contract_id, token_id, &msg::source())
+            .await;
+        // the item could be changed while waiting for the NFT contract
+        self.on_auction(&item_id);
+        if self.unlocked_owner(&item_id) != owner {
+            panic!("The item was changed by another transaction");
+        }
 
+        let primary_sale = self.is_collection_creator(nft_contract_id, &msg::source());
         self.items
-            .entry(contract_and_token_id)
+            .entry(item_id)
             .and_modify(|item| {
+                // the promotion ends with the delisting or with the new owner
+                if item.owner_id != msg::source() || price.is_none() {
+                    item.featured = None;
+                }
+                // the token could be transferred outside the marketplace
+                item.owner_id = msg::source();
                 item.price = price;
-                item.ft_contract_id = ft_contract_id
+                item.ft_contract_id = ft_contract_id;
+                item.gate = gate;
+                item.payees = payees.clone();
+                item.beneficiary = beneficiary;
+                item.reference_price = reference_price;
+                item.primary_sale = primary_sale;
+                item.metadata = metadata.clone();
+                item.flash_sale = None;
             })
             .or_insert(Item {
+                nft_contract_id: *nft_contract_id,
+                token_id,
                 owner_id: msg::source(),
                 ft_contract_id,
                 price,
-                auction: None,
-                offers: Vec::new(),
+                gate,
+                payees,
+                beneficiary,
+                reference_price,
+                primary_sale,
+                metadata: metadata.clone(),
+                ..Default::default()
             });
 
-        msg::reply(
-            MarketEvent::MarketDataAdded {
+        // the listing executes the matching limit order
+        if let Some(price) = price {
+            if let Some(event) = self
+                .match_limit_order(
+                    nft_contract_id,
+                    token_id,
+                    ft_contract_id,
+                    &msg::source(),
+                    price,
+                )
+                .await
+            {
+                self.notify_subscribers(nft_contract_id, &event);
+                events::reply(event, 0).expect("Error in reply [MarketEvent::LimitOrderExecuted]");
+                return;
+            }
+        }
+
+        let event = MarketEvent::MarketDataAdded {
+            nft_contract_id: *nft_contract_id,
+            owner: msg::source(),
+            token_id,
+            price,
+            beneficiary,
+            metadata,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        self.notify_watchers(nft_contract_id, token_id, ft_contract_id, price);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::MarketDataAdded]");
+    }
+
+    /// Lists the NFT on behalf of its owner.
+    /// It is called by the NFT contract right after the owner has approved the marketplace,
+    /// so a single interaction of the owner both approves and lists the NFT.
+    /// The NFT transferred to the marketplace is held in `custody` for the owner.
+    pub fn list_from_nft(
+        &mut self,
+        owner_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: Option<u128>,
+        custody: bool,
+    ) {
+        let nft_contract_id = msg::source();
+        self.check_approved_nft_contract(&nft_contract_id);
+        self.check_transferable(&nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        check_not_zero(owner_id);
+        self.check_not_banned(owner_id);
+        let item_id = ItemId::new(nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(&nft_contract_id, token_id);
+
+        // the NFT contract reports the marketplace as the owner of the tokens held in custody
+        if *owner_id == exec::program_id() {
+            market_err(MarketErr::NotOwner);
+        }
+
+        let primary_sale = self.is_collection_creator(&nft_contract_id, owner_id);
+        let item = self.items.entry(item_id).or_insert(Item {
+            nft_contract_id,
+            token_id,
+            ..Default::default()
+        });
+        if item.locked_by.is_some() {
+            panic!("The item is locked by another transaction");
+        }
+        let owner_changed = item.owner_id != *owner_id;
+        // the beneficiary is indicated by the previous owner
+        if owner_changed {
+            item.beneficiary = None;
+        }
+        // the promotion ends with the delisting and isn't inherited by the new owner
+        if owner_changed || price.is_none() {
+            item.featured = None;
+        }
+        item.owner_id = *owner_id;
+        item.ft_contract_id = ft_contract_id;
+        item.price = price;
+        item.reference_price = None;
+        item.primary_sale = primary_sale;
+        item.metadata = None;
+        item.auto_accept = None;
+        item.flash_sale = None;
+        item.custody = custody;
+
+        let event = MarketEvent::MarketDataAdded {
+            nft_contract_id,
+            owner: *owner_id,
+            token_id,
+            price,
+            beneficiary: item.beneficiary,
+            metadata: None,
+        };
+        if owner_changed {
+            self.remove_sell_orders(&nft_contract_id, token_id, owner_id);
+        }
+        self.notify_subscribers(&nft_contract_id, &event);
+        self.notify_watchers(&nft_contract_id, token_id, ft_contract_id, price);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::MarketDataAdded]");
+    }
+
+    pub fn next_transaction_id(&mut self) -> TransactionId {
+        let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        transaction_id
+    }
+
+    /// Approves the marketplace to transfer the NFT and checks that the NFT contract
+    /// reports the approval, so an item is never listed without it.
+    ///
+    /// The item isn't locked while waiting for the NFT contract,
+    /// so the caller checks the item again after the approval.
+    /// Returns `false` if the approval failed and the failed transaction is recorded.
+    pub async fn approve_marketplace(&mut self, nft_contract_id: &ActorId, token_id: U256) -> bool {
+        let transaction_id = self.next_transaction_id();
+        if nft_approve(nft_contract_id, &exec::program_id(), token_id)
+            .await
+            .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::NftApproval,
+            );
+            return false;
+        }
+        match nft_is_approved(nft_contract_id, &exec::program_id(), token_id).await {
+            Ok(true) => true,
+            Ok(false) => market_err(MarketErr::MarketplaceNotApproved),
+            Err(err) => market_err(MarketErr::NftCall(err)),
+        }
+    }
+
+    pub fn item_info(&self, item_id: &ItemId) {
+        let item = self
+            .items
+            .get(item_id)
+            .expect("Item does not exist")
+            .clone();
+        events::reply(MarketEvent::ItemInfo(item), 0)
+            .expect("Error in reply [MarketEvent::ItemInfo]");
+    }
+
+    /// Returns the owner of the listed item (`None` if it isn't listed).
+    /// Requirements:
+    /// * The item must not be locked by another transaction
+    pub fn unlocked_owner(&self, item_id: &ItemId) -> Option<ActorId> {
+        let item = self.items.get(item_id)?;
+        if item.locked_by.is_some() {
+            panic!("The item is locked by another transaction");
+        }
+        Some(item.owner_id)
+    }
+
+    /// Locks the item for the duration of a multi-message flow
+    /// and returns the id of the transaction that holds the lock.
+    pub fn lock_item(&mut self, item_id: &ItemId) -> TransactionId {
+        let transaction_id = self.next_transaction_id();
+        let item = self.items.get_mut(item_id).expect("Item does not exist");
+        if item.locked_by.is_some() {
+            panic!("The item is locked by another transaction");
+        }
+        item.locked_by = Some((msg::source(), transaction_id));
+        transaction_id
+    }
+
+    pub fn unlock_item(&mut self, item_id: &ItemId) {
+        if let Some(item) = self.items.get_mut(item_id) {
+            item.locked_by = None;
+        }
+    }
+
+    /// Releases the item lock, records the failed transaction
+    /// and replies that the cross-contract part of the transaction failed.
+    pub fn transaction_failed(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        transaction_id: TransactionId,
+        step: TransactionStep,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.unlock_item(&item_id);
+        self.record_failed_transaction(nft_contract_id, token_id, transaction_id, step);
+        events::reply(
+            MarketEvent::TransactionFailed {
                 nft_contract_id: *nft_contract_id,
-                owner: msg::source(),
                 token_id,
-                price,
+                transaction_id,
             },
             0,
         )
-        .expect("Error in reply [MarketEvent::MarketDataAdded]");
+        .expect("Error in reply [MarketEvent::TransactionFailed]");
     }
 
+    /// Records the failed transaction without replying,
+    /// so the caller can finish the rest of the transaction.
+    pub fn record_failed_transaction(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        transaction_id: TransactionId,
+        step: TransactionStep,
+    ) {
+        self.failed_transactions.insert(
+            transaction_id,
+            FailedTransaction {
+                transaction_id,
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                account: msg::source(),
+                step,
+                failed_at: exec::block_timestamp(),
+            },
+        );
+    }
+
+    /// The executed changes of the governance are applied as if they were made by the admin.
     pub fn check_admin(&self) {
-        if msg::source() != self.admin_id {
+        if msg::source() != self.admin_id && !governance::executing_change() {
             panic!("Only owner can make that action");
         }
     }
 
+    pub fn set_paused_actions(&mut self, paused_actions: u32) {
+        self.check_admin();
+        self.paused_actions = paused_actions;
+    }
+
+    pub fn check_not_paused(&self, action: &MarketAction) {
+        if self.paused_actions & action.pause_category() != 0 {
+            panic!("The action is paused");
+        }
+    }
+
     pub fn check_approved_nft_contract(&self, nft_contract_id: &ActorId) {
         if !self.approved_nft_contracts.contains(nft_contract_id) {
             panic!("that nft contract is not approved");
@@ -109,8 +527,17 @@ impl Market {
 
 #[gstd::async_main]
 async fn main() {
+    if msg::size() > MAX_PAYLOAD_SIZE {
+        market_err(MarketErr::PayloadTooLarge);
+    }
     let action: MarketAction = msg::load().expect("Could not load Action");
+    if let Err(err) = action.check_bounds() {
+        market_err(err);
+    }
+    let meter = telemetry::GasMeter::start(&action);
     let market: &mut Market = unsafe { MARKET.get_or_insert(Market::default()) };
+    market.check_not_paused(&action);
+    market.check_governance(&action);
     match action {
         MarketAction::AddNftContract(nft_contract_id) => {
             market.add_nft_contract(&nft_contract_id);
@@ -123,55 +550,140 @@ async fn main() {
             ft_contract_id,
             token_id,
             price,
+            gate,
+            payees,
+            beneficiary,
+            metadata,
         } => {
             market
-                .add_market_data(&nft_contract_id, ft_contract_id, token_id, price)
+                .add_market_data(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    price,
+                    gate,
+                    payees,
+                    beneficiary,
+                    None,
+                    metadata,
+                )
                 .await;
         }
+        MarketAction::ListFromNft {
+            owner_id,
+            token_id,
+            ft_contract_id,
+            price,
+        } => market.list_from_nft(&owner_id, token_id, ft_contract_id, price, false),
+        MarketAction::OnNftReceived {
+            from,
+            token_id,
+            payload,
+        } => market.on_nft_received(&from, token_id, &payload),
+        MarketAction::WithdrawNft {
+            nft_contract_id,
+            token_id,
+        } => market.withdraw_nft(&nft_contract_id, token_id).await,
         MarketAction::BuyItem {
             nft_contract_id,
             token_id,
+            recipient,
+            relist_at,
         } => {
-            market.buy_item(&nft_contract_id, token_id).await;
+            market
+                .buy_item(&nft_contract_id, token_id, recipient, relist_at)
+                .await;
         }
         MarketAction::Item {
             nft_contract_id,
             token_id,
+        } => market.item_info(&ItemId::new(nft_contract_id, token_id)),
+        MarketAction::ItemById(item_id) => market.item_info(&item_id),
+        MarketAction::AddOffer {
+            nft_contract_id,
+            ft_contract_id,
+            token_id,
+            price,
+            recipient,
+            metadata,
         } => {
-            let contract_and_token_id =
-                format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-            let item = market
-                .items
-                .get(&contract_and_token_id)
-                .expect("Item does not exist")
-                .clone();
-            msg::reply(MarketEvent::ItemInfo(item), 0)
-                .expect("Error in reply [MarketEvent::ItemInfo]");
+            market
+                .add_offer(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    price,
+                    recipient,
+                    metadata,
+                    false,
+                )
+                .await
         }
-        MarketAction::AddOffer {
+        MarketAction::AddBalanceOffer {
             nft_contract_id,
             ft_contract_id,
             token_id,
             price,
+            recipient,
+            metadata,
         } => {
             market
-                .add_offer(&nft_contract_id, ft_contract_id, token_id, price)
+                .add_offer(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    price,
+                    recipient,
+                    metadata,
+                    true,
+                )
                 .await
         }
+        MarketAction::DepositBalance {
+            ft_contract_id,
+            amount,
+        } => market.deposit_balance(ft_contract_id, amount).await,
+        MarketAction::WithdrawBalance {
+            ft_contract_id,
+            amount,
+        } => market.withdraw_balance(ft_contract_id, amount).await,
+        MarketAction::UpdatePrice {
+            nft_contract_id,
+            token_id,
+            price,
+        } => market.update_price(&nft_contract_id, token_id, price),
+        MarketAction::UpdateCurrency {
+            nft_contract_id,
+            token_id,
+            ft_contract_id,
+        } => market.update_currency(&nft_contract_id, token_id, ft_contract_id),
+        MarketAction::WithdrawAllOffers { ft_contract_id } => {
+            market.withdraw_all_offers(ft_contract_id).await
+        }
         MarketAction::AcceptOffer {
             nft_contract_id,
             token_id,
-            offer_hash,
+            offer_id,
+        } => {
+            market
+                .accept_offer(&nft_contract_id, token_id, offer_id)
+                .await
+        }
+        MarketAction::AcceptBestOffer {
+            nft_contract_id,
+            token_id,
+            ft_contract_id,
+            min_price,
         } => {
             market
-                .accept_offer(&nft_contract_id, token_id, offer_hash)
+                .accept_best_offer(&nft_contract_id, token_id, ft_contract_id, min_price)
                 .await
         }
         MarketAction::Withdraw {
             nft_contract_id,
             token_id,
-            hash,
-        } => market.withdraw(&nft_contract_id, token_id, hash).await,
+            offer_id,
+        } => market.withdraw(&nft_contract_id, token_id, offer_id).await,
         MarketAction::CreateAuction {
             nft_contract_id,
             ft_contract_id,
@@ -179,23 +691,70 @@ async fn main() {
             min_price,
             bid_period,
             duration,
+            beneficiary,
+            auto_seed_from_offers,
         } => {
             market
                 .create_auction(
                     &nft_contract_id,
                     ft_contract_id,
+                    vec![],
                     token_id,
                     min_price,
                     bid_period,
                     duration,
+                    beneficiary,
+                    auto_seed_from_offers,
+                    None,
+                )
+                .await;
+        }
+        MarketAction::CreateMultiCurrencyAuction {
+            nft_contract_id,
+            ft_contract_id,
+            currencies,
+            token_id,
+            min_price,
+            bid_period,
+            duration,
+            beneficiary,
+            auto_seed_from_offers,
+        } => {
+            market
+                .create_auction(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    currencies,
+                    token_id,
+                    min_price,
+                    bid_period,
+                    duration,
+                    beneficiary,
+                    auto_seed_from_offers,
+                    None,
                 )
                 .await;
         }
         MarketAction::AddBid {
             nft_contract_id,
             token_id,
+            ft_contract_id,
+            price,
+        }
+        | MarketAction::AddBidInCurrency {
+            nft_contract_id,
+            token_id,
+            ft_contract_id,
             price,
-        } => market.add_bid(&nft_contract_id, token_id, price).await,
+        } => {
+            market
+                .add_bid_in_currency(&nft_contract_id, token_id, ft_contract_id, price)
+                .await
+        }
+        MarketAction::SetExchangeRate {
+            ft_contract_id,
+            rate,
+        } => market.set_exchange_rate(ft_contract_id, rate),
 
         MarketAction::SettleAuction {
             nft_contract_id,
@@ -203,7 +762,332 @@ async fn main() {
         } => {
             market.settle_auction(&nft_contract_id, token_id).await;
         }
+        MarketAction::SetPromotionFee(fee) => market.set_promotion_fee(fee),
+        MarketAction::PromoteItem {
+            nft_contract_id,
+            token_id,
+            blocks,
+        } => {
+            market
+                .promote_item(&nft_contract_id, token_id, blocks)
+                .await
+        }
+        MarketAction::SetRoyaltyConfig(config) => market.set_royalty_config(config),
+        MarketAction::SetRoyaltyPreference { pay_royalties } => {
+            market.set_royalty_preference(pay_royalties)
+        }
+        MarketAction::PlaceBuyOrder {
+            nft_contract_id,
+            ft_contract_id,
+            price,
+        } => {
+            market
+                .place_buy_order(&nft_contract_id, ft_contract_id, price)
+                .await
+        }
+        MarketAction::PlaceSellOrder {
+            nft_contract_id,
+            ft_contract_id,
+            token_id,
+            price,
+        } => {
+            market
+                .place_sell_order(&nft_contract_id, ft_contract_id, token_id, price)
+                .await
+        }
+        MarketAction::RemoveOrder {
+            nft_contract_id,
+            ft_contract_id,
+            order_id,
+        } => {
+            market
+                .remove_order(&nft_contract_id, ft_contract_id, order_id)
+                .await
+        }
+        MarketAction::PlaceLimitOrder {
+            nft_contract_id,
+            token_id,
+            ft_contract_id,
+            price,
+            duration,
+        } => {
+            market
+                .place_limit_order(&nft_contract_id, token_id, ft_contract_id, price, duration)
+                .await
+        }
+        MarketAction::CreateDeal {
+            counterparty,
+            items,
+            ft_contract_id,
+            price,
+            expires_at,
+            vesting,
+        } => {
+            market
+                .create_deal(
+                    &counterparty,
+                    items,
+                    ft_contract_id,
+                    price,
+                    expires_at,
+                    vesting,
+                )
+                .await
+        }
+        MarketAction::ClaimVested(vesting_id) => market.claim_vested(vesting_id).await,
+        MarketAction::SetRegistryProgram(registry_program_id) => {
+            market.set_registry_program(registry_program_id)
+        }
+        MarketAction::SyncApprovedContracts => market.sync_approved_contracts().await,
+        MarketAction::SetRandomnessProvider(randomness_provider) => {
+            market.set_randomness_provider(randomness_provider)
+        }
+        MarketAction::CreateCandleAuction {
+            nft_contract_id,
+            ft_contract_id,
+            token_id,
+            min_price,
+            duration,
+            rounds,
+            closing_window,
+        } => {
+            market
+                .create_candle_auction(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    min_price,
+                    duration,
+                    rounds,
+                    closing_window,
+                )
+                .await;
+        }
+        MarketAction::AcceptDeal(hash) => market.accept_deal(hash).await,
+        MarketAction::CancelDeal(hash) => market.cancel_deal(hash),
+        MarketAction::SetSlug {
+            nft_contract_id,
+            token_id,
+            slug,
+        } => market.set_slug(&nft_contract_id, token_id, slug),
+        MarketAction::RemoveLimitOrder {
+            nft_contract_id,
+            token_id,
+            order_id,
+        } => {
+            market
+                .remove_limit_order(&nft_contract_id, token_id, order_id)
+                .await
+        }
+        MarketAction::Claim => market.claim(),
+        MarketAction::BanActor(actor_id) => market.ban_actor(&actor_id),
+        MarketAction::UnbanActor(actor_id) => market.unban_actor(&actor_id),
+        MarketAction::SetFeeTiers(fee_tiers) => market.set_fee_tiers(fee_tiers),
+        MarketAction::SetAuctionBounds(bounds) => market.set_auction_bounds(bounds),
+        MarketAction::SetKeeperBounty(bounty) => market.set_keeper_bounty(bounty),
+        MarketAction::SetPriceFeed(price_feed) => market.set_price_feed(price_feed),
+        MarketAction::SetMonitor(monitor) => market.set_monitor(monitor),
+        MarketAction::SetCollectionCreator {
+            nft_contract_id,
+            creator,
+        } => market.set_collection_creator(&nft_contract_id, creator),
+        MarketAction::SetPrimarySaleFee(fee) => market.set_primary_sale_fee(fee),
+        MarketAction::SetCollectionFee {
+            nft_contract_id,
+            fee,
+        } => market.set_collection_fee(&nft_contract_id, fee),
+        MarketAction::SetCurrencyConfig {
+            ft_contract_id,
+            config,
+        } => market.set_currency_config(ft_contract_id, config),
+        MarketAction::CreateDrop {
+            nft_contract_id,
+            ft_contract_id,
+            token_ids,
+            price,
+            wallet_limit,
+            starts_at,
+            ends_at,
+        } => {
+            market
+                .create_drop(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_ids,
+                    price,
+                    wallet_limit,
+                    (starts_at, ends_at),
+                )
+                .await
+        }
+        MarketAction::BuyFromDrop(drop_id) => market.buy_from_drop(drop_id).await,
+        MarketAction::Heartbeat(heartbeat_id) => market.heartbeat(heartbeat_id),
+        MarketAction::RetryTransaction(transaction_id) => {
+            market.retry_transaction(transaction_id).await
+        }
+        MarketAction::AbortTransaction(transaction_id) => market.abort_transaction(transaction_id),
+        MarketAction::AddReferencePricedData {
+            nft_contract_id,
+            ft_contract_id,
+            token_id,
+            reference_price,
+        } => {
+            market
+                .add_reference_priced_data(
+                    &nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    reference_price,
+                )
+                .await
+        }
+        MarketAction::SubscribeCollection(nft_contract_id) => {
+            market.subscribe_collection(&nft_contract_id)
+        }
+        MarketAction::UnsubscribeCollection(nft_contract_id) => {
+            market.unsubscribe_collection(&nft_contract_id)
+        }
+        MarketAction::AddWatch(watch) => market.add_watch(watch),
+        MarketAction::SetMaxOffers(max_offers) => market.set_max_offers(max_offers),
+        MarketAction::CheckNativeBalance => market.check_native_balance(),
+        MarketAction::SetGovernance(governance) => market.set_governance(governance),
+        MarketAction::SetTreasuries(treasuries) => market.set_treasuries(treasuries),
+        MarketAction::ProposeChange(action) => market.propose_change(*action),
+        MarketAction::ExecuteChange(change_id) => market.execute_change(change_id),
+        MarketAction::CancelChange(change_id) => market.cancel_change(change_id),
+        MarketAction::SetRoyaltyFree {
+            nft_contract_id,
+            royalty_free,
+        } => market.set_royalty_free(&nft_contract_id, royalty_free),
+        MarketAction::SetSoulbound {
+            nft_contract_id,
+            soulbound,
+        } => market.set_soulbound(&nft_contract_id, soulbound),
+        MarketAction::SetMetadataProgram {
+            nft_contract_id,
+            metadata_program,
+        } => market.set_metadata_program(&nft_contract_id, metadata_program),
+        MarketAction::AddTraitOffer {
+            nft_contract_id,
+            ft_contract_id,
+            token_trait,
+            price,
+        } => {
+            market
+                .add_trait_offer(&nft_contract_id, ft_contract_id, token_trait, price)
+                .await
+        }
+        MarketAction::AcceptTraitOffer {
+            nft_contract_id,
+            offer_id,
+            token_id,
+        } => {
+            market
+                .accept_trait_offer(&nft_contract_id, offer_id, token_id)
+                .await
+        }
+        MarketAction::WithdrawTraitOffer {
+            nft_contract_id,
+            offer_id,
+        } => {
+            market
+                .withdraw_trait_offer(&nft_contract_id, offer_id)
+                .await
+        }
+        MarketAction::RemoveWatch {
+            nft_contract_id,
+            token_id,
+        } => market.remove_watch(&nft_contract_id, token_id),
+        MarketAction::ForceDelist {
+            nft_contract_id,
+            token_id,
+        } => market.force_delist(&nft_contract_id, token_id).await,
+        MarketAction::ArmEmergency { timelock } => market.arm_emergency(timelock),
+        MarketAction::EmergencyHeartbeat => market.emergency_heartbeat(),
+        MarketAction::EmergencyReclaim {
+            nft_contract_id,
+            token_id,
+        } => market.emergency_reclaim(&nft_contract_id, token_id).await,
+        MarketAction::SetStaking(staking) => market.set_staking(staking),
+        MarketAction::DistributeFees { ft_contract_id } => {
+            market.distribute_fees(ft_contract_id).await
+        }
+        MarketAction::SetAutoAccept {
+            nft_contract_id,
+            token_id,
+            auto_accept,
+        } => market.set_auto_accept(&nft_contract_id, token_id, auto_accept),
+        MarketAction::SetFlashSale {
+            nft_contract_id,
+            token_id,
+            flash_sale,
+        } => market.set_flash_sale(&nft_contract_id, token_id, flash_sale),
+        MarketAction::AddTrustedForwarder(forwarder) => market.add_trusted_forwarder(&forwarder),
+        MarketAction::RemoveTrustedForwarder(forwarder) => {
+            market.remove_trusted_forwarder(&forwarder)
+        }
+        MarketAction::ForwardedBuyItem {
+            buyer,
+            nft_contract_id,
+            token_id,
+        } => {
+            market
+                .forwarded_buy_item(&buyer, &nft_contract_id, token_id)
+                .await
+        }
+        MarketAction::SetReceiptsContract(receipts_contract_id) => {
+            market.set_receipts_contract(receipts_contract_id)
+        }
+        MarketAction::MintReceipt(receipt) => market.mint_receipt(receipt).await,
+        MarketAction::ContinueOperation(operation_id) => {
+            market.continue_operation(operation_id).await
+        }
+        MarketAction::SetCollectionCode(code_id) => market.set_collection_code(code_id),
+        MarketAction::SetTradeRetention(retention) => market.set_trade_retention(retention),
+        MarketAction::SetPausedActions(paused_actions) => market.set_paused_actions(paused_actions),
+        MarketAction::PruneTrades => market.prune_trades(),
+        MarketAction::FillSignedOrder {
+            seller,
+            order,
+            signature,
+        } => market.fill_signed_order(&seller, order, signature).await,
+        MarketAction::CancelOrder(nonce) => market.cancel_order(nonce),
+        MarketAction::CancelAllOrders => market.cancel_all_orders(),
+        MarketAction::SetYieldProgram(yield_program_id) => {
+            market.set_yield_program(yield_program_id)
+        }
+        MarketAction::DepositOffer {
+            nft_contract_id,
+            token_id,
+            offer_id,
+        } => {
+            market
+                .deposit_offer(&nft_contract_id, token_id, offer_id)
+                .await
+        }
+        MarketAction::LaunchCollection {
+            name,
+            symbol,
+            base_uri,
+            tokens,
+            ft_contract_id,
+            price,
+        } => {
+            market
+                .launch_collection((name, symbol, base_uri), tokens, ft_contract_id, price)
+                .await
+        }
+        MarketAction::AddMaxBid {
+            nft_contract_id,
+            token_id,
+            max_price,
+        } => {
+            market
+                .add_max_bid(&nft_contract_id, token_id, max_price)
+                .await
+        }
     }
+    market.record_gas_usage(meter);
 }
 
 #[no_mangle]
@@ -212,10 +1096,15 @@ extern "C" fn init() {
     if config.treasury_fee == MIN_TREASURY_FEE || config.treasury_fee > MAX_TREASURT_FEE {
         panic!("Wrong treasury fee");
     }
+    check_not_zero(&config.admin_id);
+    check_treasury(&config.treasury_id);
+    let auction_bounds = config.auction_bounds.unwrap_or_default();
+    auction::check_auction_bounds(&auction_bounds);
     let market = Market {
         admin_id: config.admin_id,
         treasury_id: config.treasury_id,
         treasury_fee: config.treasury_fee,
+        auction_bounds,
         ..Default::default()
     };
     unsafe { MARKET = Some(market) };
@@ -243,14 +1132,284 @@ extern "C" fn meta_state() -> *mut [i32; 2] {
             nft_contract_id,
             token_id,
         } => {
-            let contract_and_token_id =
-                format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-            if let Some(item) = market.items.get(&contract_and_token_id) {
+            let item_id = ItemId::new(nft_contract_id, token_id);
+            if let Some(item) = market.items.get(&item_id) {
                 StateReply::ItemInfo(item.clone()).encode()
             } else {
                 StateReply::ItemInfo(Item::default()).encode()
             }
         }
+        State::FeaturedItems => StateReply::FeaturedItems(market.featured_items()).encode(),
+        State::GasUsage => {
+            StateReply::GasUsage(market.gas_usage.iter().map(|(k, v)| (*k, *v)).collect()).encode()
+        }
+        State::ItemsById(item_ids) => StateReply::ItemsById(
+            item_ids
+                .iter()
+                .filter_map(|item_id| market.items.get(item_id).cloned())
+                .collect(),
+        )
+        .encode(),
+        State::ResolveSlug(slug) => StateReply::ResolveSlug(market.resolve_slug(&slug)).encode(),
+        State::VestedProceeds(seller) => StateReply::VestedProceeds(
+            market
+                .vested_proceeds
+                .iter()
+                .filter(|(_, proceeds)| proceeds.seller == seller)
+                .map(|(vesting_id, proceeds)| (*vesting_id, *proceeds))
+                .collect(),
+        )
+        .encode(),
+        State::EventsSince(seq) => StateReply::Events(events::events_since(seq)).encode(),
+        State::Deal(hash) => StateReply::Deal(market.deals.get(&hash).cloned()).encode(),
+        State::LimitOrders {
+            nft_contract_id,
+            token_id,
+        } => StateReply::LimitOrders(
+            market
+                .limit_orders
+                .get(&ItemId::new(nft_contract_id, token_id))
+                .cloned()
+                .unwrap_or_default(),
+        )
+        .encode(),
+        State::OrderBook {
+            nft_contract_id,
+            ft_contract_id,
+        } => StateReply::OrderBook(
+            market
+                .order_books
+                .get(&(nft_contract_id, ft_contract_id))
+                .cloned()
+                .unwrap_or_default(),
+        )
+        .encode(),
+        State::ItemsByOwner {
+            owner_id,
+            offset,
+            limit,
+        } => StateReply::ItemsByOwner(paginate(
+            market
+                .items
+                .values()
+                .filter(|item| item.owner_id == owner_id),
+            offset,
+            limit,
+        ))
+        .encode(),
+        State::ItemsByCollection {
+            nft_contract_id,
+            offset,
+            limit,
+        } => StateReply::ItemsByCollection(paginate(
+            market
+                .items
+                .values()
+                .filter(|item| item.nft_contract_id == nft_contract_id),
+            offset,
+            limit,
+        ))
+        .encode(),
+        State::ActiveAuctions { offset, limit } => StateReply::ActiveAuctions(paginate(
+            market.items.values().filter(|item| item.auction.is_some()),
+            offset,
+            limit,
+        ))
+        .encode(),
+        State::ItemsOnSale {
+            ft_contract_id,
+            offset,
+            limit,
+        } => StateReply::ItemsOnSale(paginate(
+            market.items.values().filter(|item| {
+                item.price.is_some()
+                    && item.auction.is_none()
+                    && item.ft_contract_id == ft_contract_id
+            }),
+            offset,
+            limit,
+        ))
+        .encode(),
+        State::Claimable { account } => {
+            StateReply::Claimable(market.claimable.get(&account).copied().unwrap_or_default())
+                .encode()
+        }
+        State::BannedActors => {
+            StateReply::BannedActors(market.banned_actors.iter().copied().collect()).encode()
+        }
+        State::FeeTiers => StateReply::FeeTiers(market.fee_tiers.clone()).encode(),
+        State::CollectionConfig(nft_contract_id) => StateReply::CollectionConfig(
+            market
+                .collection_configs
+                .get(&nft_contract_id)
+                .cloned()
+                .unwrap_or_default(),
+        )
+        .encode(),
+        State::TradedVolume { account } => StateReply::TradedVolume {
+            volume: market
+                .traded_volume
+                .get(&account)
+                .copied()
+                .unwrap_or_default(),
+            fee: market.trader_fee(&account),
+        }
+        .encode(),
+        State::Twap {
+            nft_contract_id,
+            ft_contract_id,
+            window,
+        } => StateReply::Twap(market.twap(&nft_contract_id, ft_contract_id, window)).encode(),
+        State::AuctionCountdown {
+            nft_contract_id,
+            token_id,
+        } => {
+            let item_id = ItemId::new(nft_contract_id, token_id);
+            let countdown = market
+                .items
+                .get(&item_id)
+                .and_then(|item| item.auction.as_ref())
+                .map(|auction| AuctionCountdown {
+                    remaining: auction.ended_at.saturating_sub(exec::block_timestamp()) / 1000,
+                    extensions: auction.extensions,
+                });
+            StateReply::AuctionCountdown(countdown).encode()
+        }
+        State::RoyaltyInfo {
+            nft_contract_id,
+            token_id,
+            sale_price,
+        } => StateReply::RoyaltyInfo(market.royalty_info(&nft_contract_id, token_id, sale_price))
+            .encode(),
+        State::AuctionBounds => StateReply::AuctionBounds(market.auction_bounds).encode(),
+        State::UserActivity(account) => {
+            StateReply::UserActivity(market.user_activity(&account)).encode()
+        }
+        State::KeeperRewards { account } => StateReply::KeeperRewards {
+            bounty: market.keeper_bounty,
+            rewards: market
+                .keeper_rewards
+                .get(&account)
+                .copied()
+                .unwrap_or_default(),
+        }
+        .encode(),
+        State::Metrics => StateReply::Metrics(market.metrics()).encode(),
+        State::Drop(drop_id) => StateReply::Drop(market.drops.get(&drop_id).cloned()).encode(),
+        State::FailedTransactions => {
+            StateReply::FailedTransactions(market.failed_transactions.values().cloned().collect())
+                .encode()
+        }
+        State::StakingFees(ft_contract_id) => StateReply::StakingFees(
+            market
+                .staking_fees
+                .get(&ft_contract_id)
+                .copied()
+                .unwrap_or_default(),
+        )
+        .encode(),
+        State::Trades { from, limit } => StateReply::Trades(
+            market
+                .trades
+                .range(from..)
+                .take(limit.min(price_history::MAX_EXPORTED_TRADES) as usize)
+                .map(|(trade_id, receipt)| (*trade_id, *receipt))
+                .collect(),
+        )
+        .encode(),
+        State::OrderNonces(account) => StateReply::OrderNonces {
+            epoch: market.order_epoch(&account),
+            used_nonces: market
+                .used_nonces
+                .get(&account)
+                .map(|nonces| nonces.iter().copied().collect())
+                .unwrap_or_default(),
+        }
+        .encode(),
+        State::PausedActions => StateReply::PausedActions(market.paused_actions).encode(),
+        State::AuctionInfo {
+            nft_contract_id,
+            token_id,
+        } => {
+            let item_id = ItemId::new(nft_contract_id, token_id);
+            StateReply::AuctionInfo(market.items.get(&item_id).and_then(AuctionInfo::new)).encode()
+        }
+        State::HeldValue => StateReply::HeldValue(market.held_value()).encode(),
+        State::Treasuries => StateReply::Treasuries(market.treasury_weights()).encode(),
+        State::ExportChunk { cursor } => {
+            StateReply::ExportChunk(market.export_chunk(cursor)).encode()
+        }
+        State::PendingChanges => {
+            StateReply::PendingChanges(market.pending_changes.values().cloned().collect()).encode()
+        }
+        State::ListingStatus {
+            nft_contract_id,
+            token_id,
+        } => {
+            let item_id = ItemId::new(nft_contract_id, token_id);
+            StateReply::ListingStatus(
+                market
+                    .items
+                    .get(&item_id)
+                    .map(|item| item.status(exec::block_timestamp())),
+            )
+            .encode()
+        }
+        State::Simulate {
+            caller,
+            value,
+            action,
+        } => StateReply::Simulate(market.simulate(&caller, value, &action)).encode(),
+        State::Quote {
+            nft_contract_id,
+            token_id,
+            buyer,
+        } => StateReply::Quote(market.quote(&nft_contract_id, token_id, &buyer)).encode(),
+        State::AllActiveAuctions { offset, limit } => {
+            let mut auctions: Vec<AuctionInfo> =
+                market.items.values().filter_map(AuctionInfo::new).collect();
+            auctions.sort_by_key(|auction| auction.ended_at);
+            StateReply::AllActiveAuctions(
+                auctions
+                    .into_iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .collect(),
+            )
+            .encode()
+        }
+        State::AuctionsEndingWithin { window } => {
+            let ends_before = exec::block_timestamp().saturating_add(window);
+            let mut auctions: Vec<AuctionInfo> = market
+                .items
+                .values()
+                .filter_map(AuctionInfo::new)
+                .filter(|auction| auction.ended_at <= ends_before)
+                .collect();
+            auctions.sort_by_key(|auction| auction.ended_at);
+            StateReply::AuctionsEndingWithin(auctions).encode()
+        }
+        State::TraitOffers(nft_contract_id) => StateReply::TraitOffers(
+            market
+                .trait_offers
+                .get(&nft_contract_id)
+                .cloned()
+                .unwrap_or_default(),
+        )
+        .encode(),
+        State::Watches(account) => StateReply::Watches(
+            market
+                .watches
+                .values()
+                .flatten()
+                .filter(|(watcher, _)| *watcher == account)
+                .map(|(_, watch)| *watch)
+                .collect(),
+        )
+        .encode(),
+        State::Operation(operation_id) => {
+            StateReply::Operation(market.operations.get(&operation_id).cloned()).encode()
+        }
     };
     gstd::util::to_leak_ptr(encoded)
 }
@@ -0,0 +1,195 @@
+use crate::{events, payment::*, ItemId, LimitOrder, Market, MarketEvent, OrderId};
+use core::cmp::Reverse;
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    pub async fn place_limit_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+        duration: u64,
+    ) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_nft_contract(nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        if duration == 0 {
+            panic!("The order duration can't be equal to zero");
+        }
+        check_attached_value(ft_contract_id, price);
+        self.return_change(ft_contract_id, price);
+
+        // the order is placed at the received amount
+        let price = self
+            .escrow_payment(&msg::source(), ft_contract_id, price)
+            .await
+            .expect("Error in escrowing the order payment");
+
+        let order_id = self.next_order_id();
+        let expires_at = exec::block_timestamp().saturating_add(duration);
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.limit_orders
+            .entry(item_id)
+            .or_default()
+            .push(LimitOrder {
+                id: order_id,
+                owner: msg::source(),
+                ft_contract_id,
+                price,
+                expires_at,
+            });
+        events::reply(
+            MarketEvent::LimitOrderPlaced {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                order_id,
+                price,
+                expires_at,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::LimitOrderPlaced]");
+    }
+
+    /// Removes the limit order and returns the escrowed payment to its owner.
+    /// The owner can remove the order at any time, anyone else only after it expires.
+    pub async fn remove_limit_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        order_id: OrderId,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let orders = self
+            .limit_orders
+            .get_mut(&item_id)
+            .expect("The order does not exist");
+        let index = orders
+            .iter()
+            .position(|order| order.id == order_id)
+            .expect("The order does not exist");
+        if orders[index].owner != msg::source()
+            && orders[index].expires_at > exec::block_timestamp()
+        {
+            panic!("only the order owner can remove the order before it expires");
+        }
+        let order = orders.remove(index);
+        if orders.is_empty() {
+            self.limit_orders.remove(&item_id);
+        }
+
+        // return the escrowed payment
+        if self
+            .transfer_payment(
+                &exec::program_id(),
+                &order.owner,
+                order.ft_contract_id,
+                order.price,
+            )
+            .await
+            .is_err()
+        {
+            self.limit_orders.entry(item_id).or_default().push(order);
+            events::reply(
+                MarketEvent::OrderTransactionFailed {
+                    nft_contract_id: *nft_contract_id,
+                    order_id,
+                },
+                0,
+            )
+            .expect("Error in reply [MarketEvent::OrderTransactionFailed]");
+            return;
+        }
+
+        events::reply(
+            MarketEvent::LimitOrderRemoved {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                order_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::LimitOrderRemoved]");
+    }
+
+    /// Executes the highest unexpired limit order on the token listed by `seller` at `price`
+    /// (the oldest one among equal prices).
+    /// The sale is executed at the listing price and the difference is returned to the buyer.
+    ///
+    /// Returns the event of the executed order or `None` if there is no matching order
+    /// or the sale fails (then the order remains and the token stays listed).
+    pub async fn match_limit_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        seller: &ActorId,
+        price: u128,
+    ) -> Option<MarketEvent> {
+        let item_id: ItemId = ItemId::new(*nft_contract_id, token_id);
+        let now = exec::block_timestamp();
+        let orders = self.limit_orders.get_mut(&item_id)?;
+        let index = orders
+            .iter()
+            .enumerate()
+            .filter(|(_, order)| {
+                order.ft_contract_id == ft_contract_id
+                    && order.price >= price
+                    && order.expires_at > now
+                    && order.owner != *seller
+            })
+            .max_by_key(|(_, order)| (order.price, Reverse(order.id)))
+            .map(|(index, _)| index)?;
+        let order = orders.remove(index);
+
+        if self
+            .execute_sale(
+                &exec::program_id(),
+                nft_contract_id,
+                ft_contract_id,
+                token_id,
+                seller,
+                &order.owner,
+                price,
+                None,
+                None,
+            )
+            .await
+            .is_err()
+        {
+            self.limit_orders.entry(item_id).or_default().push(order);
+            return None;
+        }
+        if self
+            .limit_orders
+            .get(&item_id)
+            .is_some_and(|orders| orders.is_empty())
+        {
+            self.limit_orders.remove(&item_id);
+        }
+
+        // return the difference between the order and the listing price
+        if order.price > price {
+            self.transfer_payment(
+                &exec::program_id(),
+                &order.owner,
+                ft_contract_id,
+                order.price - price,
+            )
+            .await
+            .expect("Error in returning the price difference");
+        }
+        Some(MarketEvent::LimitOrderExecuted {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            order_id: order.id,
+            buyer: order.owner,
+            price,
+        })
+    }
+}
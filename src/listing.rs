@@ -0,0 +1,170 @@
+use crate::{events, AutoAccept, FlashSale, Item, ItemId, Market, MarketEvent};
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
+
+/// Returns the listed price or the discounted price if the flash sale is ongoing.
+pub fn sale_price(item: &Item) -> Option<u128> {
+    let now = exec::block_timestamp();
+    match item.flash_sale {
+        Some(flash_sale) if flash_sale.starts_at <= now && now < flash_sale.ends_at => {
+            item.price.map(|_| flash_sale.price)
+        }
+        _ => item.price,
+    }
+}
+
+impl Market {
+    /// Changes the price of the listed item.
+    /// The flash sale of the item is cancelled.
+    /// Requirements:
+    /// * Only the item owner can change the price
+    /// * There must be no ongoing auction and the item can't be locked
+    pub fn update_price(&mut self, nft_contract_id: &ActorId, token_id: U256, price: Option<u128>) {
+        let item = self.listed_item(nft_contract_id, token_id);
+        if let Some(price) = price {
+            self.check_price_bounds(item.ft_contract_id, price);
+        }
+        let item = self.listed_item_mut(nft_contract_id, token_id);
+        item.price = price;
+        item.reference_price = None;
+        item.flash_sale = None;
+        // the promotion ends with the delisting
+        if price.is_none() {
+            item.featured = None;
+        }
+        let ft_contract_id = item.ft_contract_id;
+
+        let event = MarketEvent::PriceUpdated {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            price,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        self.notify_watchers(nft_contract_id, token_id, ft_contract_id, price);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::PriceUpdated]");
+    }
+
+    /// Changes the currency of the listed item keeping its price.
+    /// The offers in the previous currency remain valid, the flash sale is cancelled.
+    /// Requirements:
+    /// * Only the item owner can change the currency
+    /// * There must be no ongoing auction and the item can't be locked
+    /// * The currency must be approved
+    pub fn update_currency(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+    ) {
+        self.check_approved_ft_contract(ft_contract_id);
+        let item = self.listed_item(nft_contract_id, token_id);
+        if let Some(price) = item.price {
+            self.check_price_bounds(ft_contract_id, price);
+        }
+        let item = self.listed_item_mut(nft_contract_id, token_id);
+        item.ft_contract_id = ft_contract_id;
+        item.flash_sale = None;
+        let price = item.price;
+
+        let event = MarketEvent::CurrencyUpdated {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            ft_contract_id,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        self.notify_watchers(nft_contract_id, token_id, ft_contract_id, price);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::CurrencyUpdated]");
+    }
+
+    /// Sets the threshold at which the offers on the listed item are accepted at once.
+    /// Requirements:
+    /// * Only the item owner can set the threshold
+    /// * There must be no ongoing auction and the item can't be locked
+    /// * The currency must be approved
+    pub fn set_auto_accept(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        auto_accept: Option<AutoAccept>,
+    ) {
+        if let Some(auto_accept) = &auto_accept {
+            self.check_approved_ft_contract(auto_accept.ft_contract_id);
+            if auto_accept.min_price == 0 {
+                panic!("The threshold price can't be equal to zero");
+            }
+        }
+        self.listed_item(nft_contract_id, token_id);
+        let item = self.listed_item_mut(nft_contract_id, token_id);
+        item.auto_accept = auto_accept;
+
+        events::reply(
+            MarketEvent::AutoAcceptSet {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                auto_accept,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::AutoAcceptSet]");
+    }
+
+    /// Schedules the discount of the listed item.
+    /// Requirements:
+    /// * Only the item owner can set the flash sale
+    /// * There must be no ongoing auction and the item can't be locked
+    /// * The item must be listed at a fixed price higher than the discounted one
+    /// * The sale window must end after it starts and after the current time
+    pub fn set_flash_sale(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        flash_sale: Option<FlashSale>,
+    ) {
+        let item = self.listed_item(nft_contract_id, token_id);
+        if let Some(flash_sale) = &flash_sale {
+            let price = item.price.expect("The item is not on sale");
+            if flash_sale.price >= price {
+                panic!("The flash sale price must be less than the listed price");
+            }
+            if flash_sale.starts_at >= flash_sale.ends_at
+                || flash_sale.ends_at <= exec::block_timestamp()
+            {
+                panic!("Wrong sale window");
+            }
+            self.check_price_bounds(item.ft_contract_id, flash_sale.price);
+        }
+        let item = self.listed_item_mut(nft_contract_id, token_id);
+        item.flash_sale = flash_sale;
+
+        events::reply(
+            MarketEvent::FlashSaleSet {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                flash_sale,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::FlashSaleSet]");
+    }
+
+    // returns the item that can be changed by the caller
+    fn listed_item(&self, nft_contract_id: &ActorId, token_id: U256) -> &Item {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if item.owner_id != msg::source() {
+            panic!("Only owner can update the listing");
+        }
+        if item.auction.is_some() {
+            panic!("There is an opened auction");
+        }
+        if item.locked_by.is_some() {
+            panic!("The item is locked by another transaction");
+        }
+        item
+    }
+
+    fn listed_item_mut(&mut self, nft_contract_id: &ActorId, token_id: U256) -> &mut Item {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.items.get_mut(&item_id).expect("Item does not exist")
+    }
+}
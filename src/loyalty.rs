@@ -0,0 +1,51 @@
+use crate::{FeeTier, Market, BASE_PERCENT};
+use gstd::{prelude::*, ActorId};
+
+impl Market {
+    /// Sets the treasury fee discount tiers.
+    /// Requirements:
+    /// * Only admin can set fee tiers
+    /// * Tiers must be sorted by the strictly increasing volume
+    /// * Fees can't increase with the volume and can't exceed the base treasury fee
+    pub fn set_fee_tiers(&mut self, fee_tiers: Vec<FeeTier>) {
+        self.check_admin();
+        let base_fee = self.base_fee();
+        let mut previous: Option<&FeeTier> = None;
+        for tier in fee_tiers.iter() {
+            if tier.fee > base_fee {
+                panic!("Tier fee can't exceed the base treasury fee");
+            }
+            if let Some(previous) = previous {
+                if tier.volume <= previous.volume || tier.fee > previous.fee {
+                    panic!("Tiers must be sorted by volume with non-increasing fees");
+                }
+            }
+            previous = Some(tier);
+        }
+        self.fee_tiers = fee_tiers;
+    }
+
+    /// The treasury fee in basis points without discounts.
+    pub fn base_fee(&self) -> u16 {
+        self.treasury_fee as u16 * BASE_PERCENT as u16
+    }
+
+    /// Returns the treasury fee in basis points for the trader
+    /// according to the highest tier reached by the trader's volume.
+    pub fn trader_fee(&self, trader: &ActorId) -> u16 {
+        let volume = self.traded_volume.get(trader).copied().unwrap_or_default();
+        self.fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| volume >= tier.volume)
+            .map(|tier| tier.fee)
+            .unwrap_or_else(|| self.base_fee())
+    }
+
+    pub fn record_volume(&mut self, seller: &ActorId, buyer: &ActorId, price: u128) {
+        for trader in [seller, buyer] {
+            let volume = self.traded_volume.entry(*trader).or_default();
+            *volume = volume.saturating_add(price);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use gstd::{errors::Result, msg, prelude::*, ActorId};
+use market_io::TokenTrait;
+use primitive_types::U256;
+
+/// The trait query understood by the metadata programs of the collections.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum MetadataAction {
+    HasTrait {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        token_trait: TokenTrait,
+    },
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum MetadataEvent {
+    HasTrait(bool),
+}
+
+/// Asks the metadata program whether the token has the trait.
+pub async fn has_trait(
+    metadata_program: &ActorId,
+    nft_contract_id: &ActorId,
+    token_id: U256,
+    token_trait: &TokenTrait,
+) -> Result<bool> {
+    let reply: MetadataEvent = msg::send_for_reply_as(
+        *metadata_program,
+        MetadataAction::HasTrait {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            token_trait: token_trait.clone(),
+        },
+        0,
+    )?
+    .await?;
+    let MetadataEvent::HasTrait(has_trait) = reply;
+    Ok(has_trait)
+}
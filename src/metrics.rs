@@ -0,0 +1,215 @@
+use crate::{
+    events, validation::check_not_zero, HeldValue, Market, MarketAction, MarketEvent,
+    MarketMetrics, Monitor, Operation, TransactionStep,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+
+impl Market {
+    /// Registers the monitor and starts a new heartbeat chain.
+    /// Requirements:
+    /// * Only admin can set the monitor
+    /// * The monitor can't be the zero address and the interval can't be equal to zero
+    pub fn set_monitor(&mut self, monitor: Option<Monitor>) {
+        self.check_admin();
+        if let Some(monitor) = &monitor {
+            check_not_zero(&monitor.monitor_id);
+            if monitor.interval == 0 {
+                panic!("Heartbeat interval can't be equal to zero");
+            }
+        }
+        // the heartbeats of the previous chain are ignored
+        self.heartbeat_id = self.heartbeat_id.wrapping_add(1);
+        self.monitor = monitor;
+        if let Some(monitor) = monitor {
+            self.schedule_heartbeat(monitor.interval);
+        }
+    }
+
+    pub fn heartbeat(&mut self, heartbeat_id: u64) {
+        if msg::source() != exec::program_id() {
+            panic!("Only the marketplace can send the heartbeat");
+        }
+        let monitor = match self.monitor {
+            Some(monitor) if heartbeat_id == self.heartbeat_id => monitor,
+            _ => return,
+        };
+        // the monitor can't stop the heartbeats by failing
+        let _ = msg::send(monitor.monitor_id, MarketEvent::Metrics(self.metrics()), 0);
+        self.schedule_heartbeat(monitor.interval);
+    }
+
+    fn schedule_heartbeat(&self, interval: u32) {
+        msg::send_delayed(
+            exec::program_id(),
+            MarketAction::Heartbeat(self.heartbeat_id),
+            0,
+            interval,
+        )
+        .expect("Error in sending the heartbeat");
+    }
+
+    pub fn metrics(&self) -> MarketMetrics {
+        let mut escrow: BTreeMap<Option<ActorId>, u128> = BTreeMap::new();
+        let mut metadata_bytes = 0;
+        for (item_id, item) in self.items.iter() {
+            metadata_bytes += metadata_len(&item.metadata);
+            for offer in item.offers.iter() {
+                if !self.balance_offers.contains(&offer.offer_id) {
+                    *escrow.entry(offer.ft_contract_id).or_default() += offer.price;
+                }
+                metadata_bytes += metadata_len(&offer.metadata);
+            }
+            if let Some(auction) = &item.auction {
+                if auction.current_winner != ActorId::zero() {
+                    *escrow.entry(item.ft_contract_id).or_default() += self.winner_escrow(item_id);
+                }
+                if let Some(candle) = &auction.candle {
+                    *escrow.entry(item.ft_contract_id).or_default() +=
+                        candle.outbid_escrow(&auction.current_winner);
+                }
+            }
+        }
+        for ((_, ft_contract_id), order_book) in self.order_books.iter() {
+            for order in order_book.buy_orders.iter() {
+                *escrow.entry(*ft_contract_id).or_default() += order.price;
+            }
+        }
+        for order in self.limit_orders.values().flatten() {
+            *escrow.entry(order.ft_contract_id).or_default() += order.price;
+        }
+        for ((_, ft_contract_id), balance) in self.balances.iter() {
+            *escrow.entry(*ft_contract_id).or_default() += balance;
+        }
+        MarketMetrics {
+            items: self.items.len() as u32,
+            active_auctions: self
+                .items
+                .values()
+                .filter(|item| item.auction.is_some())
+                .count() as u32,
+            escrow: escrow.into_iter().collect(),
+            claimable: self.claimable.values().sum(),
+            failed_transactions: self.failed_transactions.len() as u32,
+            metadata_bytes,
+        }
+    }
+}
+
+impl Market {
+    /// Returns the native value held for the accounts.
+    pub fn held_value(&self) -> HeldValue {
+        let mut held = HeldValue {
+            claimable: self.claimable.values().sum(),
+            staking_fees: self
+                .staking_fees
+                .get(&None)
+                .map(|fees| fees.owed)
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        for (item_id, item) in self.items.iter() {
+            held.offers += item
+                .offers
+                .iter()
+                .filter(|offer| {
+                    offer.ft_contract_id.is_none()
+                        && !self.deposited_offers.contains_key(&offer.offer_id)
+                        && !self.balance_offers.contains(&offer.offer_id)
+                })
+                .map(|offer| offer.price)
+                .sum::<u128>();
+            if let Some(auction) = &item.auction {
+                if auction.current_winner != ActorId::zero() && item.ft_contract_id.is_none() {
+                    held.bids += self.winner_escrow(item_id);
+                }
+                match &auction.candle {
+                    Some(candle) if item.ft_contract_id.is_none() => {
+                        held.bids += candle.outbid_escrow(&auction.current_winner)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        held.offers += self
+            .trait_offers
+            .values()
+            .flatten()
+            .filter(|offer| offer.ft_contract_id.is_none())
+            .map(|offer| offer.price)
+            .sum::<u128>();
+        held.buy_orders = self
+            .order_books
+            .iter()
+            .filter(|((_, ft_contract_id), _)| ft_contract_id.is_none())
+            .flat_map(|(_, order_book)| order_book.buy_orders.iter())
+            .map(|order| order.price)
+            .sum::<u128>()
+            + self
+                .limit_orders
+                .values()
+                .flatten()
+                .filter(|order| order.ft_contract_id.is_none())
+                .map(|order| order.price)
+                .sum::<u128>();
+        held.balances = self
+            .balances
+            .iter()
+            .filter(|((_, ft_contract_id), _)| ft_contract_id.is_none())
+            .map(|(_, balance)| balance)
+            .sum();
+        held.vested = self
+            .vested_proceeds
+            .values()
+            .filter(|proceeds| proceeds.ft_contract_id.is_none())
+            .map(|proceeds| proceeds.amount - proceeds.claimed)
+            .sum();
+        held.payouts = self
+            .operations
+            .values()
+            .filter_map(|operation| match operation {
+                Operation::Payouts {
+                    ft_contract_id: None,
+                    payouts,
+                    ..
+                } => Some(payouts.iter().map(|(_, amount)| amount).sum::<u128>()),
+                _ => None,
+            })
+            .sum();
+        held.refunds = self
+            .failed_transactions
+            .values()
+            .filter_map(|transaction| match transaction.step {
+                TransactionStep::Refund {
+                    ft_contract_id: None,
+                    amount,
+                    ..
+                } => Some(amount),
+                _ => None,
+            })
+            .sum();
+        held
+    }
+
+    /// Checks that the balance covers the held native value.
+    /// The shortfall is reported to the monitor as well.
+    pub fn check_native_balance(&self) {
+        self.check_admin();
+        let held = self.held_value();
+        let available = exec::value_available().saturating_sub(msg::value());
+        if available >= held.total() {
+            events::reply(MarketEvent::NativeBalance { held, available }, 0)
+                .expect("Error in reply [MarketEvent::NativeBalance]");
+            return;
+        }
+        let event = MarketEvent::BalanceMismatch { held, available };
+        if let Some(monitor) = self.monitor {
+            // the monitor can't prevent the reply by failing
+            let _ = msg::send(monitor.monitor_id, &event, 0);
+        }
+        events::reply(event, 0).expect("Error in reply [MarketEvent::BalanceMismatch]");
+    }
+}
+
+fn metadata_len(metadata: &Option<Vec<u8>>) -> u64 {
+    metadata.as_ref().map(Vec::len).unwrap_or_default() as u64
+}
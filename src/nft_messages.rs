@@ -1,37 +1,161 @@
-use gstd::{msg, prelude::*, ActorId};
+use gstd::{errors::ContractError, msg, prelude::*, ActorId};
+use market_io::{NftCallError, REPLY_TIMEOUT};
 use primitive_types::U256;
 pub type Payout = BTreeMap<ActorId, u128>;
+use gear_lib::non_fungible_token::token::TokenMetadata;
 use nft_io::*;
 
+/// The number of attempts to send a message to the NFT contract.
+const MAX_ATTEMPTS: u8 = 3;
+
+/// Sends the action to the NFT contract and waits for the reply
+/// at most [`REPLY_TIMEOUT`] blocks.
+///
+/// Only the sending is retried: once the message is sent, the NFT contract
+/// may have executed it, so a repeated action could be applied twice.
+async fn nft_call(
+    nft_program_id: &ActorId,
+    action: impl Fn() -> NFTAction,
+) -> Result<Vec<u8>, NftCallError> {
+    let mut attempt = 1;
+    let future = loop {
+        match msg::send_for_reply(*nft_program_id, action(), 0) {
+            Ok(future) => break future,
+            Err(_) if attempt < MAX_ATTEMPTS => attempt += 1,
+            Err(_) => return Err(NftCallError::SendError),
+        }
+    };
+    future
+        .up_to(Some(REPLY_TIMEOUT))
+        .map_err(|_| NftCallError::SendError)?
+        .await
+        .map_err(|err| match err {
+            ContractError::Timeout(..) => NftCallError::Timeout,
+            ContractError::ExitCode(_) => NftCallError::Rejected,
+            _ => NftCallError::WrongReply,
+        })
+}
+
+/// Decodes the whole reply as `T`, the trailing bytes make the reply invalid.
+fn decode_reply<T: Decode>(reply: &[u8]) -> Option<T> {
+    let mut input = reply;
+    T::decode(&mut input).ok().filter(|_| input.is_empty())
+}
+
+/// Decodes the reply of the NFT contract.
+///
+/// The NFT contracts built with the earlier versions of `gear-lib` reply
+/// with the bare result struct instead of [`NFTEvent`], so the struct is
+/// decoded by `legacy` if the reply isn't a known event.
+/// An unknown reply is an error, so the caller can roll the transaction back.
+fn decode_nft_reply<T>(
+    reply: &[u8],
+    event: impl FnOnce(NFTEvent) -> Option<T>,
+    legacy: impl FnOnce(&[u8]) -> Option<T>,
+) -> Result<T, NftCallError> {
+    decode_reply(reply)
+        .and_then(event)
+        .or_else(|| legacy(reply))
+        .ok_or(NftCallError::WrongReply)
+}
+
 pub async fn nft_transfer(
     nft_program_id: &ActorId,
     to: &ActorId,
     token_id: U256,
     amount: u128,
-) -> Payout {
-    let response: NFTEvent = msg::send_for_reply_as(
-        *nft_program_id,
-        NFTAction::TransferPayout {
-            to: *to,
-            token_id,
-            amount,
+) -> Result<Payout, NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::TransferPayout {
+        to: *to,
+        token_id,
+        amount,
+    })
+    .await?;
+    decode_nft_reply(
+        &reply,
+        |event| match event {
+            NFTEvent::TransferPayout(transfer_payout) => Some(transfer_payout.payouts),
+            _ => None,
+        },
+        |reply| {
+            decode_reply::<NFTTransferPayout>(reply).map(|transfer_payout| transfer_payout.payouts)
         },
-        0,
     )
-    .unwrap()
-    .await
-    .expect("error in transfer");
-    let decoded_response = if let NFTEvent::TransferPayout(transfer_payout) = response {
-        transfer_payout
-    } else {
-        unreachable!("Error in decoding payouts");
-    };
-    decoded_response.payouts
 }
 
-pub async fn nft_approve(nft_program_id: &ActorId, to: &ActorId, token_id: U256) {
-    msg::send_for_reply(*nft_program_id, NFTAction::Approve { to: *to, token_id }, 0)
-        .unwrap()
-        .await
-        .expect("error in transfer");
+pub async fn nft_transfer_without_payout(
+    nft_program_id: &ActorId,
+    to: &ActorId,
+    token_id: U256,
+) -> Result<(), NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::Transfer { to: *to, token_id }).await?;
+    decode_nft_reply(
+        &reply,
+        |event| matches!(event, NFTEvent::Transfer(_)).then_some(()),
+        |reply| decode_reply::<NFTTransfer>(reply).map(|_| ()),
+    )
+}
+
+pub async fn nft_owner(nft_program_id: &ActorId, token_id: U256) -> Result<ActorId, NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::Owner { token_id }).await?;
+    decode_nft_reply(
+        &reply,
+        |event| match event {
+            NFTEvent::Owner { owner, .. } => Some(owner),
+            _ => None,
+        },
+        |_| None,
+    )
+}
+
+pub async fn nft_approve(
+    nft_program_id: &ActorId,
+    to: &ActorId,
+    token_id: U256,
+) -> Result<(), NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::Approve { to: *to, token_id }).await?;
+    decode_nft_reply(
+        &reply,
+        |event| matches!(event, NFTEvent::Approval(_)).then_some(()),
+        |reply| decode_reply::<NFTApproval>(reply).map(|_| ()),
+    )
+}
+
+pub async fn nft_is_approved(
+    nft_program_id: &ActorId,
+    to: &ActorId,
+    token_id: U256,
+) -> Result<bool, NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::IsApproved {
+        to: *to,
+        token_id,
+    })
+    .await?;
+    decode_nft_reply(
+        &reply,
+        |event| match event {
+            NFTEvent::IsApproved { approved, .. } => Some(approved),
+            _ => None,
+        },
+        |_| None,
+    )
+}
+
+/// Mints the token to the caller and returns its id.
+pub async fn nft_mint(
+    nft_program_id: &ActorId,
+    token_metadata: TokenMetadata,
+) -> Result<U256, NftCallError> {
+    let reply = nft_call(nft_program_id, || NFTAction::Mint {
+        token_metadata: token_metadata.clone(),
+    })
+    .await?;
+    decode_nft_reply(
+        &reply,
+        |event| match event {
+            NFTEvent::Transfer(transfer) => Some(transfer.token_id),
+            _ => None,
+        },
+        |reply| decode_reply::<NFTTransfer>(reply).map(|transfer| transfer.token_id),
+    )
 }
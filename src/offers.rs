@@ -1,8 +1,17 @@
-use crate::{nft_messages::*, payment::*, Market, BASE_PERCENT};
+use crate::{
+    events,
+    payment::*,
+    validation::{check_metadata, check_not_owner, check_not_zero},
+    ItemId, Market,
+};
+use core::cmp::Reverse;
 use gstd::{exec, msg, prelude::*, ActorId};
 use market_io::*;
 use primitive_types::{H256, U256};
 
+/// The maximum number of offers withdrawn by one message.
+const MAX_WITHDRAWALS: usize = 20;
+
 pub fn get_hash(ft_contract_id: Option<ActorId>, price: u128) -> H256 {
     let price = price.to_be_bytes();
     let ft_id_vec: Vec<u8> = ft_contract_id
@@ -12,156 +21,569 @@ pub fn get_hash(ft_contract_id: Option<ActorId>, price: u128) -> H256 {
 }
 
 impl Market {
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_offer(
         &mut self,
         nft_contract_id: &ActorId,
         ft_contract_id: Option<ActorId>,
         token_id: U256,
         price: u128,
+        recipient: Option<ActorId>,
+        metadata: Option<Vec<u8>>,
+        from_balance: bool,
     ) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
+        self.check_not_banned(&msg::source());
+        check_metadata(&metadata);
+        if let Some(recipient) = &recipient {
+            check_not_zero(recipient);
+            self.check_not_banned(recipient);
+        }
+        let item_id = ItemId::new(*nft_contract_id, token_id);
         self.check_approved_ft_contract(ft_contract_id);
-        self.on_auction(&contract_and_token_id);
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+        self.on_auction(&item_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
         if price == 0 {
             panic!("Cant offer zero price");
         }
-
-        let hash: H256 = get_hash(ft_contract_id, price);
-        let mut offers = item.offers.clone();
-        if offers.iter().any(|offer| offer.hash == hash) {
-            panic!("the offer with these params already exists");
+        self.check_price_bounds(ft_contract_id, price);
+        check_not_owner(&item.owner_id, &msg::source(), MarketErr::SelfOffer);
+        let full = item.offers.len() >= self.max_offers() as usize;
+        if full && self.lowest_offer(item, ft_contract_id, price).is_none() {
+            panic!("The item has the maximum number of offers");
         }
 
-        check_attached_value(ft_contract_id, price);
+        let price = if from_balance {
+            if self.balance(&msg::source(), ft_contract_id) < price {
+                panic!("The balance is less than the offer price");
+            }
+            // the whole attached value is returned
+            self.return_change(None, 0);
+            price
+        } else {
+            check_attached_value(ft_contract_id, price);
+            self.return_change(ft_contract_id, price);
+
+            let transaction_id = self.lock_item(&item_id);
 
-        transfer_payment(&msg::source(), &exec::program_id(), ft_contract_id, price).await;
+            // the offer is made at the received amount
+            match self
+                .escrow_payment(&msg::source(), ft_contract_id, price)
+                .await
+            {
+                Ok(received) => received,
+                Err(_) => {
+                    self.transaction_failed(
+                        nft_contract_id,
+                        token_id,
+                        transaction_id,
+                        TransactionStep::Escrow,
+                    );
+                    return;
+                }
+            }
+        };
 
-        offers.push(Offer {
-            hash,
+        let offer_id = self.next_offer_id();
+        if from_balance {
+            self.balance_offers.insert(offer_id);
+        }
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        let offer = Offer {
+            offer_id,
+            hash: get_hash(ft_contract_id, price),
             id: msg::source(),
             ft_contract_id,
             price,
-        });
-        item.offers = offers;
-        msg::reply(
+            recipient,
+            metadata: metadata.clone(),
+        };
+        item.offers.push(offer.clone());
+        item.locked_by = None;
+        let accepted = item
+            .auto_accept
+            .map(|auto_accept| {
+                auto_accept.ft_contract_id == ft_contract_id && price >= auto_accept.min_price
+            })
+            .unwrap_or(false);
+        let seller = item.owner_id;
+        self.offers_by_user
+            .entry(msg::source())
+            .or_default()
+            .insert(offer_id, item_id);
+        if full {
+            self.evict_lowest_offer(nft_contract_id, token_id, ft_contract_id, price)
+                .await;
+        }
+
+        // the offer that meets the threshold of the owner is accepted at once
+        if accepted {
+            self.execute_offer(nft_contract_id, token_id, seller, offer)
+                .await;
+            return;
+        }
+        events::reply(
             MarketEvent::OfferAdded {
                 nft_contract_id: *nft_contract_id,
                 ft_contract_id,
                 token_id,
                 price,
+                metadata,
             },
             0,
         )
         .expect("Error in reply [MarketEvent::OfferAdded]");
     }
 
+    pub fn set_max_offers(&mut self, max_offers: Option<u32>) {
+        self.check_admin();
+        if max_offers == Some(0) {
+            panic!("The maximum number of offers can't be equal to zero");
+        }
+        self.max_offers = max_offers;
+    }
+
+    pub fn max_offers(&self) -> u32 {
+        self.max_offers.unwrap_or(DEFAULT_MAX_OFFERS)
+    }
+
+    // returns the index of the lowest offer in the currency below the price
+    // (the latest one among equal prices), the deposited offers aren't evicted
+    pub fn lowest_offer(
+        &self,
+        item: &Item,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) -> Option<usize> {
+        item.offers
+            .iter()
+            .enumerate()
+            .filter(|(_, offer)| {
+                offer.ft_contract_id == ft_contract_id
+                    && offer.price < price
+                    && !self.deposited_offers.contains_key(&offer.offer_id)
+            })
+            .min_by_key(|(_, offer)| (offer.price, Reverse(offer.offer_id)))
+            .map(|(index, _)| index)
+    }
+
+    /// Removes the lowest offer in the currency below the price and returns its escrow.
+    /// The failed refund is recorded as a failed transaction that can be retried.
+    async fn evict_lowest_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        // the offers could be changed while waiting for the escrow
+        let index = match self
+            .items
+            .get(&item_id)
+            .and_then(|item| self.lowest_offer(item, ft_contract_id, price))
+        {
+            Some(index) => index,
+            None => return,
+        };
+        let offer = self
+            .items
+            .get_mut(&item_id)
+            .expect("Item does not exist")
+            .offers
+            .remove(index);
+        let from_balance = self.balance_offers.contains(&offer.offer_id);
+        self.unindex_offer(&offer.id, offer.offer_id);
+
+        let transaction_id = self.next_transaction_id();
+        if !from_balance
+            && self
+                .refund_payment(&offer.id, offer.ft_contract_id, offer.price)
+                .await
+                .is_err()
+        {
+            self.record_failed_transaction(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Refund {
+                    to: offer.id,
+                    ft_contract_id: offer.ft_contract_id,
+                    amount: offer.price,
+                },
+            );
+        }
+        events::send(
+            offer.id,
+            MarketEvent::OfferEvicted {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                offer_id: offer.offer_id,
+                ft_contract_id: offer.ft_contract_id,
+                price: offer.price,
+            },
+            0,
+        )
+        .expect("Error in sending [MarketEvent::OfferEvicted]");
+    }
+
+    pub fn unindex_offer(&mut self, account: &ActorId, offer_id: OfferId) {
+        self.balance_offers.remove(&offer_id);
+        if let Some(offers) = self.offers_by_user.get_mut(account) {
+            offers.remove(&offer_id);
+            if offers.is_empty() {
+                self.offers_by_user.remove(account);
+            }
+        }
+    }
+
+    pub fn next_offer_id(&mut self) -> OfferId {
+        let offer_id = self.offer_id;
+        self.offer_id = self.offer_id.wrapping_add(1);
+        offer_id
+    }
+
     /// Accepts an offer
     /// Requirements:
     /// * NFT item must be listed on the marketplace
     /// * Only owner can accept offer
     /// * There must be no ongoing auction
-    /// * The offer with indicated hash must exist
+    /// * The offer with indicated id must exist
     /// Arguments:
     /// * `nft_contract_id`: the NFT contract address
     /// * `token_id`: the NFT id
-    /// * `offer_hash`: the offer hash
+    /// * `offer_id`: the offer id
     pub async fn accept_offer(
         &mut self,
         nft_contract_id: &ActorId,
         token_id: U256,
-        offer_hash: H256,
+        offer_id: OfferId,
     ) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        self.on_auction(&contract_and_token_id);
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
         if item.owner_id != msg::source() {
             panic!("only owner can accept offer");
         }
-        let mut offers = item.offers.clone();
-        if let Some(offer) = offers.clone().iter().find(|offer| offer.hash == offer_hash) {
-            let treasury_fee =
-                offer.price * (self.treasury_fee * BASE_PERCENT) as u128 / 10_000u128;
-            transfer_payment(
-                &exec::program_id(),
-                &self.treasury_id,
-                offer.ft_contract_id,
-                treasury_fee,
-            )
-            .await;
+        let offer = item
+            .offers
+            .iter()
+            .find(|offer| offer.offer_id == offer_id)
+            .expect("The offer with that id does not exist")
+            .clone();
+        // the auction without bids is cancelled in favor of the offer
+        let auction = match &item.auction {
+            Some(auction) if auction.current_winner == ActorId::zero() => Some(auction.clone()),
+            Some(_) => panic!("There is an opened auction"),
+            None => None,
+        };
+        if auction.is_some() {
+            if let Some(item) = self.items.get_mut(&item_id) {
+                item.auction = None;
+            }
+        }
 
-            // transfer NFT and pay royalties
-            let payouts = nft_transfer(
-                nft_contract_id,
-                &offer.id,
-                token_id,
-                offer.price - treasury_fee,
-            )
+        let accepted = self
+            .execute_offer(nft_contract_id, token_id, msg::source(), offer)
             .await;
-            for (account, amount) in payouts.iter() {
-                transfer_payment(&exec::program_id(), account, offer.ft_contract_id, *amount).await;
+        if let Some(auction) = auction {
+            if accepted {
+                let event = MarketEvent::AuctionCancelled {
+                    nft_contract_id: *nft_contract_id,
+                    token_id,
+                };
+                self.notify_subscribers(nft_contract_id, &event);
+                events::send(msg::source(), event, 0)
+                    .expect("Error in sending [MarketEvent::AuctionCancelled]");
+            } else if let Some(item) = self.items.get_mut(&item_id) {
+                // the auction goes on if the item still belongs to the owner
+                if item.owner_id == msg::source() {
+                    item.auction = Some(auction);
+                }
             }
+        }
+    }
 
-            offers.retain(|offer| offer.hash != offer_hash);
-            item.offers = offers;
-            item.price = None;
-            item.owner_id = offer.id;
-            msg::reply(
-                MarketEvent::OfferAccepted {
+    /// Accepts the highest offer in the indicated currency
+    /// Requirements:
+    /// * NFT item must be listed on the marketplace
+    /// * Only owner can accept offer
+    /// * There must be no ongoing auction
+    /// * There must be an offer in the indicated currency not less than `min_price`
+    /// Arguments:
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `token_id`: the NFT id
+    /// * `ft_contract_id`: the currency of the accepted offer
+    /// * `min_price`: the lowest acceptable offer price
+    pub async fn accept_best_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        min_price: u128,
+    ) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if item.owner_id != msg::source() {
+            panic!("only owner can accept offer");
+        }
+        // the highest offer (the oldest one among equal prices)
+        let offer = item
+            .offers
+            .iter()
+            .filter(|offer| offer.ft_contract_id == ft_contract_id && offer.price >= min_price)
+            .max_by_key(|offer| (offer.price, Reverse(offer.offer_id)))
+            .expect("There is no offer that satisfies the indicated conditions")
+            .clone();
+        self.execute_offer(nft_contract_id, token_id, msg::source(), offer)
+            .await;
+    }
+
+    /// Executes the offer and returns whether it is accepted.
+    async fn execute_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        seller: ActorId,
+        offer: Offer,
+    ) -> bool {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let new_owner = offer.recipient.unwrap_or(offer.id);
+
+        // the balance offer is cancelled if the balance of its author doesn't cover it anymore
+        let from_balance = self.balance_offers.contains(&offer.offer_id);
+        if from_balance && !self.debit_balance(&offer.id, offer.ft_contract_id, offer.price) {
+            if let Some(item) = self.items.get_mut(&item_id) {
+                item.offers
+                    .retain(|cancelled| cancelled.offer_id != offer.offer_id);
+            }
+            self.unindex_offer(&offer.id, offer.offer_id);
+            events::reply(
+                MarketEvent::OfferCancelled {
                     nft_contract_id: *nft_contract_id,
                     token_id,
-                    new_owner: offer.id,
-                    price: offer.price,
+                    offer_id: offer.offer_id,
                 },
                 0,
             )
-            .expect("Error in reply [MarketEvent::OfferAccepted]");
-        } else {
-            panic!("The offer with that hash does not exist");
+            .expect("Error in reply [MarketEvent::OfferCancelled]");
+            return false;
         }
-    }
 
-    pub async fn withdraw(&mut self, nft_contract_id: &ActorId, token_id: U256, offer_hash: H256) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+        let transaction_id = self.lock_item(&item_id);
 
-        let mut offers = item.offers.clone();
-        if let Some(offer) = offers.clone().iter().find(|offer| offer.hash == offer_hash) {
-            if msg::source() != offer.id {
-                panic!("can't withdraw other user's tokens");
-            }
-            transfer_payment(
+        if self.recall_offer(&item_id, &offer).await.is_err() {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Recall {
+                    offer_id: offer.offer_id,
+                },
+            );
+            return false;
+        }
+
+        // the escrow of the offer is debited before the payouts
+        let snapshot = self.items.get(&item_id).cloned();
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        item.offers
+            .retain(|accepted| accepted.offer_id != offer.offer_id);
+
+        if self
+            .execute_sale(
                 &exec::program_id(),
-                &msg::source(),
+                nft_contract_id,
                 offer.ft_contract_id,
+                token_id,
+                &seller,
+                &new_owner,
                 offer.price,
+                None,
+                None,
             )
-            .await;
-            offers.retain(|offer| offer.hash != offer_hash);
-            item.offers = offers;
-            msg::reply(
-                MarketEvent::TokensWithdrawn {
-                    nft_contract_id: *nft_contract_id,
-                    token_id,
+            .await
+            .is_err()
+        {
+            self.rollback_sale(&item_id, &seller, snapshot);
+            // the offer is consumed if the NFT has been transferred
+            if !self.has_offer(&item_id, offer.offer_id) {
+                self.unindex_offer(&offer.id, offer.offer_id);
+            } else if from_balance {
+                self.credit_balance(&offer.id, offer.ft_contract_id, offer.price);
+            }
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Sale {
+                    seller,
+                    buyer: new_owner,
                     price: offer.price,
                 },
-                0,
-            )
-            .expect("Error in reply [MarketEvent::TokensWithdrawn]");
+            );
+            return false;
+        }
+
+        self.unlock_item(&item_id);
+        self.unindex_offer(&offer.id, offer.offer_id);
+        let event = MarketEvent::OfferAccepted {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            new_owner,
+            price: offer.price,
+            payer: offer.id,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::OfferAccepted]");
+        true
+    }
+
+    pub async fn withdraw(&mut self, nft_contract_id: &ActorId, token_id: U256, offer_id: OfferId) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+
+        let offer = item
+            .offers
+            .iter()
+            .find(|offer| offer.offer_id == offer_id)
+            .expect("The offer with that id does not exist")
+            .clone();
+        if msg::source() != offer.id {
+            panic!("can't withdraw other user's tokens");
+        }
+
+        let transaction_id = self.lock_item(&item_id);
+
+        if self
+            .return_offer_escrow(&item_id, &offer, &msg::source())
+            .await
+            .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Withdrawal { offer_id },
+            );
+            return;
+        }
+
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        item.offers.retain(|offer| offer.offer_id != offer_id);
+        item.locked_by = None;
+        self.unindex_offer(&offer.id, offer_id);
+        events::reply(
+            MarketEvent::TokensWithdrawn {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                price: offer.price,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::TokensWithdrawn]");
+    }
+
+    /// Withdraws the caller's offers in the indicated currency.
+    /// At most 20 offers are withdrawn by one message, the offers on items
+    /// locked by other transactions are skipped.
+    pub async fn withdraw_all_offers(&mut self, ft_contract_id: Option<ActorId>) {
+        self.withdraw_offers(&msg::source(), ft_contract_id, 0, None)
+            .await;
+    }
+
+    /// Withdraws the account's offers in the currency starting from the `from` offer.
+    /// If there are more offers than can be withdrawn by one message,
+    /// the rest are withdrawn by the scheduled operation.
+    pub async fn withdraw_offers(
+        &mut self,
+        account: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        from: OfferId,
+        operation_id: Option<OperationId>,
+    ) {
+        let account = *account;
+        let offers: Vec<(OfferId, ItemId)> = self
+            .offers_by_user
+            .get(&account)
+            .map(|offers| {
+                offers
+                    .range(from..)
+                    .map(|(offer_id, item_id)| (*offer_id, *item_id))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut offer_ids = vec![];
+        let mut cursor = None;
+        let mut scheduled = None;
+        for (offer_id, item_id) in offers {
+            let item = match self.items.get(&item_id) {
+                Some(item) => item,
+                None => {
+                    self.unindex_offer(&account, offer_id);
+                    continue;
+                }
+            };
+            let offer = match item.offers.iter().find(|offer| offer.offer_id == offer_id) {
+                Some(offer) => offer.clone(),
+                None => {
+                    self.unindex_offer(&account, offer_id);
+                    continue;
+                }
+            };
+            if offer.ft_contract_id != ft_contract_id || item.locked_by.is_some() {
+                continue;
+            }
+            if offer_ids.len() == MAX_WITHDRAWALS {
+                cursor = Some(offer_id);
+                scheduled = Some(self.schedule_operation(
+                    operation_id,
+                    Operation::WithdrawOffers {
+                        account,
+                        ft_contract_id,
+                        from: offer_id,
+                    },
+                ));
+                break;
+            }
+
+            self.lock_item(&item_id);
+            if self
+                .return_offer_escrow(&item_id, &offer, &account)
+                .await
+                .is_err()
+            {
+                // the offer stays withdrawable
+                self.unlock_item(&item_id);
+                cursor = Some(offer_id);
+                break;
+            }
+            if let Some(item) = self.items.get_mut(&item_id) {
+                item.offers.retain(|offer| offer.offer_id != offer_id);
+                item.locked_by = None;
+            }
+            self.unindex_offer(&account, offer_id);
+            offer_ids.push(offer_id);
+        }
+
+        let event = MarketEvent::OffersWithdrawn {
+            ft_contract_id,
+            offer_ids,
+            cursor,
+            operation_id: scheduled,
+        };
+        if msg::source() == account {
+            events::reply(event, 0).expect("Error in reply [MarketEvent::OffersWithdrawn]");
         } else {
-            panic!("The offer with that hash does not exist");
+            events::send(account, event, 0)
+                .expect("Error in sending [MarketEvent::OffersWithdrawn]");
         }
     }
+
+    fn has_offer(&self, item_id: &ItemId, offer_id: OfferId) -> bool {
+        self.items
+            .get(item_id)
+            .map(|item| item.offers.iter().any(|offer| offer.offer_id == offer_id))
+            .unwrap_or(false)
+    }
 }
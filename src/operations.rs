@@ -0,0 +1,64 @@
+use crate::Market;
+use gstd::{exec, msg};
+use market_io::*;
+
+impl Market {
+    /// Checkpoints the operation and sends the message that continues it.
+    /// Returns the id of the operation (a new one if `operation_id` is `None`).
+    pub fn schedule_operation(
+        &mut self,
+        operation_id: Option<OperationId>,
+        operation: Operation,
+    ) -> OperationId {
+        let operation_id = operation_id.unwrap_or_else(|| {
+            let operation_id = self.operation_id;
+            self.operation_id = self.operation_id.wrapping_add(1);
+            operation_id
+        });
+        self.operations.insert(operation_id, operation);
+        let _ = msg::send(
+            exec::program_id(),
+            MarketAction::ContinueOperation(operation_id),
+            0,
+        );
+        operation_id
+    }
+
+    /// Continues the operation from its checkpoint.
+    /// The operation is removed and checkpointed again if it isn't done after this message.
+    pub async fn continue_operation(&mut self, operation_id: OperationId) {
+        let operation = self
+            .operations
+            .remove(&operation_id)
+            .expect("Operation does not exist");
+        match operation {
+            Operation::WithdrawOffers {
+                account,
+                ft_contract_id,
+                from,
+            } => {
+                if msg::source() != account && msg::source() != exec::program_id() {
+                    panic!("Only the account or the marketplace can continue the operation");
+                }
+                self.withdraw_offers(&account, ft_contract_id, from, Some(operation_id))
+                    .await
+            }
+            // anyone can continue the payouts, they are paid to the recorded accounts only
+            Operation::Payouts {
+                nft_contract_id,
+                token_id,
+                ft_contract_id,
+                payouts,
+            } => {
+                self.pay_payouts(
+                    operation_id,
+                    &nft_contract_id,
+                    token_id,
+                    ft_contract_id,
+                    payouts,
+                )
+                .await
+            }
+        }
+    }
+}
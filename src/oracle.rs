@@ -0,0 +1,67 @@
+use crate::{validation::*, Market, MarketErr, PriceFeed, PriceFeedAction, PriceFeedEvent};
+use gstd::{exec, msg, prelude::*, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    /// Registers the price feed program.
+    /// Requirements:
+    /// * Only admin can set the price feed
+    /// * The price feed can't be the zero address
+    pub fn set_price_feed(&mut self, price_feed: PriceFeed) {
+        self.check_admin();
+        check_not_zero(&price_feed.program_id);
+        self.price_feed = Some(price_feed);
+    }
+
+    pub async fn add_reference_priced_data(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        reference_price: u128,
+    ) {
+        if self.price_feed.is_none() {
+            market_err(MarketErr::PriceFeedNotSet);
+        }
+        if reference_price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        self.add_market_data(
+            nft_contract_id,
+            ft_contract_id,
+            token_id,
+            None,
+            None,
+            vec![],
+            None,
+            Some(reference_price),
+            None,
+        )
+        .await
+    }
+
+    /// Converts the reference price into the amount of the currency
+    /// with the latest rate of the price feed.
+    pub async fn reference_amount(
+        &self,
+        ft_contract_id: Option<ActorId>,
+        reference_price: u128,
+    ) -> Result<u128, MarketErr> {
+        let price_feed = self.price_feed.ok_or(MarketErr::PriceFeedNotSet)?;
+        let reply: PriceFeedEvent = msg::send_for_reply_as(
+            price_feed.program_id,
+            PriceFeedAction::LatestRate { ft_contract_id },
+            0,
+        )
+        .map_err(|_| MarketErr::PriceFeedFailed)?
+        .await
+        .map_err(|_| MarketErr::PriceFeedFailed)?;
+        let PriceFeedEvent::LatestRate { rate, updated_at } = reply;
+        if updated_at.saturating_add(price_feed.max_staleness) < exec::block_timestamp() {
+            return Err(MarketErr::StalePrice);
+        }
+        reference_price
+            .checked_mul(rate)
+            .ok_or(MarketErr::PriceFeedFailed)
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{
+    events, nft_messages::nft_approve, payment::*, validation::market_err, ItemId, Market,
+    MarketErr, MarketEvent, Order, OrderBook, OrderId,
+};
+use core::cmp::Reverse;
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    pub fn next_order_id(&mut self) -> OrderId {
+        let order_id = self.order_id;
+        self.order_id = self.order_id.wrapping_add(1);
+        order_id
+    }
+
+    fn order_book_mut(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+    ) -> &mut OrderBook {
+        self.order_books
+            .entry((*nft_contract_id, ft_contract_id))
+            .or_default()
+    }
+
+    pub async fn place_buy_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_nft_contract(nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        check_attached_value(ft_contract_id, price);
+        self.return_change(ft_contract_id, price);
+
+        // transfer payment from the buyer to the marketplace contract
+        // the order is placed at the received amount
+        let price = self
+            .escrow_payment(&msg::source(), ft_contract_id, price)
+            .await
+            .expect("Error in escrowing the order payment");
+
+        let order = Order {
+            id: self.next_order_id(),
+            owner: msg::source(),
+            token_id: None,
+            price,
+        };
+
+        // the sell orders whose owners don't own the tokens anymore are dropped
+        while let Some(sell_order) = self.best_sell_order(nft_contract_id, ft_contract_id, price) {
+            let token_id = sell_order.token_id.expect("Sell order must have a token");
+            if !self
+                .is_token_owner(nft_contract_id, token_id, &sell_order.owner)
+                .await
+            {
+                continue;
+            }
+
+            if self
+                .execute_sale(
+                    &exec::program_id(),
+                    nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    &sell_order.owner,
+                    &order.owner,
+                    sell_order.price,
+                    None,
+                    None,
+                )
+                .await
+                .is_ok()
+            {
+                // return the difference between the buy and the sell price
+                if price > sell_order.price {
+                    self.pay_or_record(
+                        nft_contract_id,
+                        token_id,
+                        &exec::program_id(),
+                        &order.owner,
+                        ft_contract_id,
+                        price - sell_order.price,
+                    )
+                    .await;
+                }
+                let event = MarketEvent::OrdersMatched {
+                    nft_contract_id: *nft_contract_id,
+                    token_id,
+                    buyer: order.owner,
+                    seller: sell_order.owner,
+                    price: sell_order.price,
+                };
+                self.notify_subscribers(nft_contract_id, &event);
+                events::reply(event, 0).expect("Error in reply [MarketEvent::OrdersMatched]");
+                return;
+            }
+            // the sell order can't be executed anymore so it's dropped
+            // and the buy order becomes a standing one
+            break;
+        }
+
+        let order_id = order.id;
+        self.order_book_mut(nft_contract_id, ft_contract_id)
+            .buy_orders
+            .push(order);
+        events::reply(
+            MarketEvent::OrderPlaced {
+                nft_contract_id: *nft_contract_id,
+                ft_contract_id,
+                order_id,
+                price,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::OrderPlaced]");
+    }
+
+    pub async fn place_sell_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        price: u128,
+    ) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_nft_contract(nft_contract_id);
+        self.check_transferable(nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(nft_contract_id, token_id);
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        if self
+            .order_book_mut(nft_contract_id, ft_contract_id)
+            .sell_orders
+            .iter()
+            .any(|sell_order| sell_order.token_id == Some(token_id))
+        {
+            panic!("The token is already in the order book");
+        }
+
+        // approve nft to trade on the marketplace
+        if let Err(err) = nft_approve(nft_contract_id, &exec::program_id(), token_id).await {
+            market_err(MarketErr::NftCall(err));
+        }
+        self.check_token_owner(nft_contract_id, token_id, &msg::source())
+            .await;
+
+        let order = Order {
+            id: self.next_order_id(),
+            owner: msg::source(),
+            token_id: Some(token_id),
+            price,
+        };
+
+        // the highest buy order that crosses the spread (the oldest one among equal prices)
+        let order_book = self.order_book_mut(nft_contract_id, ft_contract_id);
+        let best_buy_order = order_book
+            .buy_orders
+            .iter()
+            .enumerate()
+            .filter(|(_, buy_order)| buy_order.price >= price)
+            .max_by_key(|(_, buy_order)| (buy_order.price, Reverse(buy_order.id)))
+            .map(|(index, _)| index);
+
+        if let Some(index) = best_buy_order {
+            let buy_order = order_book.buy_orders.remove(index);
+
+            if self
+                .execute_sale(
+                    &exec::program_id(),
+                    nft_contract_id,
+                    ft_contract_id,
+                    token_id,
+                    &order.owner,
+                    &buy_order.owner,
+                    buy_order.price,
+                    None,
+                    None,
+                )
+                .await
+                .is_err()
+            {
+                // the buy order remains in the order book
+                self.order_book_mut(nft_contract_id, ft_contract_id)
+                    .buy_orders
+                    .push(buy_order);
+                events::reply(
+                    MarketEvent::OrderTransactionFailed {
+                        nft_contract_id: *nft_contract_id,
+                        order_id: order.id,
+                    },
+                    0,
+                )
+                .expect("Error in reply [MarketEvent::OrderTransactionFailed]");
+                return;
+            }
+
+            let event = MarketEvent::OrdersMatched {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                buyer: buy_order.owner,
+                seller: order.owner,
+                price: buy_order.price,
+            };
+            self.notify_subscribers(nft_contract_id, &event);
+            events::reply(event, 0).expect("Error in reply [MarketEvent::OrdersMatched]");
+            return;
+        }
+
+        let order_id = order.id;
+        self.order_book_mut(nft_contract_id, ft_contract_id)
+            .sell_orders
+            .push(order);
+        events::reply(
+            MarketEvent::OrderPlaced {
+                nft_contract_id: *nft_contract_id,
+                ft_contract_id,
+                order_id,
+                price,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::OrderPlaced]");
+    }
+
+    pub async fn remove_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        order_id: OrderId,
+    ) {
+        let order_book = self.order_book_mut(nft_contract_id, ft_contract_id);
+        if let Some(index) = order_book
+            .sell_orders
+            .iter()
+            .position(|order| order.id == order_id)
+        {
+            if order_book.sell_orders[index].owner != msg::source() {
+                panic!("only the order owner can remove the order");
+            }
+            order_book.sell_orders.remove(index);
+        } else {
+            let index = order_book
+                .buy_orders
+                .iter()
+                .position(|order| order.id == order_id)
+                .expect("The order does not exist");
+            if order_book.buy_orders[index].owner != msg::source() {
+                panic!("only the order owner can remove the order");
+            }
+            let order = order_book.buy_orders.remove(index);
+
+            // return the escrowed payment
+            if self
+                .transfer_payment(
+                    &exec::program_id(),
+                    &order.owner,
+                    ft_contract_id,
+                    order.price,
+                )
+                .await
+                .is_err()
+            {
+                self.order_book_mut(nft_contract_id, ft_contract_id)
+                    .buy_orders
+                    .push(order);
+                events::reply(
+                    MarketEvent::OrderTransactionFailed {
+                        nft_contract_id: *nft_contract_id,
+                        order_id,
+                    },
+                    0,
+                )
+                .expect("Error in reply [MarketEvent::OrderTransactionFailed]");
+                return;
+            }
+        }
+
+        events::reply(
+            MarketEvent::OrderRemoved {
+                nft_contract_id: *nft_contract_id,
+                order_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::OrderRemoved]");
+    }
+
+    // removes the cheapest sell order that crosses the spread (the oldest one among equal prices)
+    fn best_sell_order(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) -> Option<Order> {
+        let order_book = self.order_book_mut(nft_contract_id, ft_contract_id);
+        let index = order_book
+            .sell_orders
+            .iter()
+            .enumerate()
+            .filter(|(_, sell_order)| sell_order.price <= price)
+            .min_by_key(|(_, sell_order)| (sell_order.price, sell_order.id))
+            .map(|(index, _)| index)?;
+        Some(order_book.sell_orders.remove(index))
+    }
+
+    /// Removes the sell orders of the token placed by the previous owners when its owner changes,
+    /// so the token can't be bought at a stale price.
+    pub fn remove_sell_orders(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        owner: &ActorId,
+    ) {
+        for order_book in self
+            .order_books
+            .iter_mut()
+            .filter(|((id, _), _)| id == nft_contract_id)
+            .map(|(_, order_book)| order_book)
+        {
+            order_book
+                .sell_orders
+                .retain(|order| order.token_id != Some(token_id) || order.owner == *owner);
+        }
+    }
+}
@@ -1,44 +1,501 @@
-use ft_io::*;
-use gstd::{exec, msg, ActorId};
+use crate::{
+    events,
+    ft_messages::{ft_balance, transfer_tokens},
+    nft_messages::{nft_transfer, nft_transfer_without_payout, Payout},
+    validation::check_treasuries,
+    Item, ItemId, Market, MarketErr, MarketEvent, Operation, OperationId, SaleReceipt,
+    TransactionStep, Vesting,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+use primitive_types::U256;
 const MINIMUM_VALUE: u64 = 500;
-pub async fn transfer_tokens(contract_id: &ActorId, from: &ActorId, to: &ActorId, amount: u128) {
-    msg::send_for_reply(
-        *contract_id,
-        FTAction::Transfer {
-            from: *from,
-            to: *to,
-            amount,
-        },
-        0,
-    )
-    .expect("Error in sending message to FT contract")
-    .await
-    .expect("Error in transfer");
+/// The maximum number of payouts paid by one message.
+pub const MAX_PAYOUTS: usize = 10;
+
+pub fn check_attached_value(ft_contract_id: Option<ActorId>, price: u128) {
+    if ft_contract_id.is_none() && msg::value() < price {
+        panic!("attached value is less than the indicated price");
+    }
 }
 
-pub async fn transfer_payment(
-    from: &ActorId,
-    to: &ActorId,
-    ft_contract_id: Option<ActorId>,
-    price: u128,
-) {
-    if ft_contract_id.is_none() {
-        if to != &exec::program_id() && price > MINIMUM_VALUE.into() {
-            msg::send(*to, "", price).expect("Error in sending payment in value");
-        }
-    } else {
-        transfer_tokens(
-            &ft_contract_id.expect("There must no be an error here"),
-            from,
-            to,
+impl Market {
+    pub fn set_treasuries(&mut self, treasuries: Vec<(ActorId, u16)>) {
+        self.check_admin();
+        check_treasuries(&treasuries);
+        self.treasuries = treasuries;
+    }
+
+    /// Returns the treasuries and their weights in basis points.
+    pub fn treasury_weights(&self) -> Vec<(ActorId, u16)> {
+        if self.treasuries.is_empty() {
+            vec![(self.treasury_id, 10_000)]
+        } else {
+            self.treasuries.clone()
+        }
+    }
+
+    /// Splits the fee among the treasuries according to their weights.
+    pub fn treasury_shares(&self, fee: u128) -> Vec<(ActorId, u128)> {
+        let treasuries = self.treasury_weights();
+        let mut distributed = 0;
+        let mut shares = Vec::with_capacity(treasuries.len());
+        for (index, (treasury_id, weight)) in treasuries.iter().enumerate() {
+            // the last treasury receives the rounding remainder
+            let amount = if index + 1 == treasuries.len() {
+                fee - distributed
+            } else {
+                fee * *weight as u128 / 10_000u128
+            };
+            distributed += amount;
+            shares.push((*treasury_id, amount));
+        }
+        shares
+    }
+
+    /// Pays the fee to the treasuries according to their weights.
+    pub async fn pay_treasuries(
+        &mut self,
+        from: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        fee: u128,
+    ) -> Result<(), MarketErr> {
+        for (treasury_id, amount) in self.treasury_shares(fee) {
+            self.transfer_payment(from, &treasury_id, ft_contract_id, amount)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Pays from the escrow of the marketplace after the NFT of the sale is transferred,
+    /// so the sale can't be reverted anymore: the failed payment is recorded
+    /// as a failed refund that the admin can retry.
+    pub async fn pay_or_record(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        from: &ActorId,
+        to: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    ) {
+        if self
+            .transfer_payment(from, to, ft_contract_id, amount)
+            .await
+            .is_err()
+        {
+            let transaction_id = self.next_transaction_id();
+            self.record_failed_transaction(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Refund {
+                    to: *to,
+                    ft_contract_id,
+                    amount,
+                },
+            );
+        }
+    }
+
+    /// Transfers the payment in fungible tokens or in native value.
+    ///
+    /// The native value that can't be sent (including amounts below the existential minimum)
+    /// is credited to the recipient's claimable balance, so it never fails.
+    pub async fn transfer_payment(
+        &mut self,
+        from: &ActorId,
+        to: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) -> Result<(), MarketErr> {
+        if ft_contract_id.is_none() {
+            if to != &exec::program_id() {
+                self.send_value(to, price);
+            }
+        } else {
+            transfer_tokens(
+                &ft_contract_id.expect("There must no be an error here"),
+                from,
+                to,
+                price,
+            )
+            .await
+            .map_err(|_| MarketErr::PaymentFailed)?;
+        }
+        Ok(())
+    }
+
+    /// Transfers the payment from the account to the marketplace and returns the received amount.
+    ///
+    /// The amount received in a currency that takes a fee on transfer is measured
+    /// by the balance of the marketplace before and after the transfer
+    /// (it can't exceed the transferred amount).
+    pub async fn escrow_payment(
+        &mut self,
+        from: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        amount: u128,
+    ) -> Result<u128, MarketErr> {
+        let ft = match ft_contract_id {
+            Some(ft) if self.takes_transfer_fee(ft_contract_id) => ft,
+            _ => {
+                self.transfer_payment(from, &exec::program_id(), ft_contract_id, amount)
+                    .await?;
+                return Ok(amount);
+            }
+        };
+        let before = ft_balance(&ft, &exec::program_id())
+            .await
+            .map_err(|_| MarketErr::PaymentFailed)?;
+        self.transfer_payment(from, &exec::program_id(), ft_contract_id, amount)
+            .await?;
+        let after = ft_balance(&ft, &exec::program_id())
+            .await
+            .map_err(|_| MarketErr::PaymentFailed)?;
+        Ok(after.saturating_sub(before).min(amount))
+    }
+
+    /// Returns the escrowed payment.
+    ///
+    /// The native value is credited to the recipient's claimable balance
+    /// since a recipient may be a program that can't receive it,
+    /// and the sent value is then stuck on the marketplace.
+    pub async fn refund_payment(
+        &mut self,
+        to: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) -> Result<(), MarketErr> {
+        if ft_contract_id.is_none() {
+            *self.claimable.entry(*to).or_default() += price;
+            return Ok(());
+        }
+        self.transfer_payment(&exec::program_id(), to, ft_contract_id, price)
+            .await
+    }
+
+    /// Returns the value attached in excess of the payment to the caller.
+    /// The whole value is returned if the payment is in fungible tokens.
+    pub fn return_change(&mut self, ft_contract_id: Option<ActorId>, price: u128) {
+        let paid = if ft_contract_id.is_none() { price } else { 0 };
+        let change = msg::value().saturating_sub(paid);
+        if change > 0 {
+            self.send_value(&msg::source(), change);
+        }
+    }
+
+    fn send_value(&mut self, to: &ActorId, value: u128) {
+        if value > MINIMUM_VALUE.into() && msg::send(*to, "", value).is_ok() {
+            return;
+        }
+        *self.claimable.entry(*to).or_default() += value;
+    }
+
+    /// Sends the whole claimable balance to the caller.
+    /// Requirements:
+    /// * The claimable balance must be greater than the existential minimum
+    pub fn claim(&mut self) {
+        let amount = self
+            .claimable
+            .remove(&msg::source())
+            .expect("Nothing to claim");
+        if amount <= MINIMUM_VALUE.into() {
+            panic!("The claimable balance is too small");
+        }
+        msg::send(msg::source(), "", amount).expect("Error in sending the claimed value");
+        events::reply(MarketEvent::Claimed { amount }, 0)
+            .expect("Error in reply [MarketEvent::Claimed]");
+    }
+
+    /// Pays the treasury fee, transfers the NFT to the buyer and pays royalties
+    /// from the rest of the price.
+    ///
+    /// The item is transferred to the buyer before any message is sent,
+    /// so a re-entering call sees the final state. The NFT is transferred
+    /// before the payments: if the transfer fails then nothing is paid and the item
+    /// is restored, otherwise the buyer keeps the item and each failed payment
+    /// is recorded as a failed refund, so the sale itself doesn't fail.
+    ///
+    /// Arguments:
+    /// * `payer`: the account from which the payment is made
+    /// (the marketplace itself when the payment is escrowed)
+    /// * `nft_contract_id`: the NFT contract address
+    /// * `ft_contract_id`: the FT contract address (`None` for a native value)
+    /// * `token_id`: the NFT id
+    /// * `seller`: the current owner of the NFT
+    /// * `buyer`: the new owner of the NFT
+    /// * `price`: the sale price (the received amount if the buyer pays in a currency
+    /// that takes a fee on transfer)
+    /// * `keeper`: the account that triggered the settlement and receives the keeper bounty
+    /// * `vesting`: the schedule of the seller's proceeds
+    ///
+    /// Returns the keeper reward paid from the treasury fee.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn execute_sale(
+        &mut self,
+        payer: &ActorId,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_id: U256,
+        seller: &ActorId,
+        buyer: &ActorId,
+        price: u128,
+        keeper: Option<&ActorId>,
+        vesting: Option<&Vesting>,
+    ) -> Result<u128, MarketErr> {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let snapshot = self.items.get(&item_id).cloned();
+        // the payees and the primary sale are indicated by the seller
+        let (primary_sale, payees) = match &snapshot {
+            Some(item) if item.owner_id == *seller => (item.primary_sale, proceeds_payees(item)),
+            _ => (false, vec![]),
+        };
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.owner_id = *buyer;
+            item.price = None;
+            item.reference_price = None;
+            item.payees.clear();
+            item.beneficiary = None;
+            item.primary_sale = false;
+            item.metadata = None;
+            item.auto_accept = None;
+            item.flash_sale = None;
+            item.custody = false;
+            item.featured = None;
+        }
+
+        // the buyer's tokens are escrowed first, so the NFT isn't transferred without the payment
+        let mut price = price;
+        let escrowed = ft_contract_id.is_some() && *payer != exec::program_id();
+        if escrowed {
+            match self.escrow_payment(payer, ft_contract_id, price).await {
+                Ok(received) => price = received,
+                Err(err) => {
+                    self.restore_item(&item_id, snapshot);
+                    return Err(err);
+                }
+            }
+        }
+
+        // fee for treasury
+        // the fee depends on the volume tier of the seller, the collection or the primary sale fee
+        let treasury_fee =
+            price * self.sale_fee(nft_contract_id, seller, primary_sale) as u128 / 10_000u128;
+        let reward = keeper
+            .map(|_| self.keeper_reward(treasury_fee))
+            .unwrap_or_default();
+        let payer_account = *payer;
+        let payer = if escrowed { exec::program_id() } else { *payer };
+
+        // transfer NFT before any payment, so nothing is paid if the transfer fails
+        let amount = price - treasury_fee;
+        // the primary sale is royalty-free,
+        // the payouts aren't requested for the collection that reported no royalties
+        let royalty_free = self.is_royalty_free(nft_contract_id);
+        let payouts = if primary_sale || royalty_free {
+            nft_transfer_without_payout(nft_contract_id, buyer, token_id)
+                .await
+                .map(|_| Payout::from([(*seller, amount)]))
+        } else {
+            nft_transfer(nft_contract_id, buyer, token_id, amount).await
+        };
+        let payouts = match payouts {
+            Ok(payouts) => payouts,
+            Err(err) => {
+                self.restore_item(&item_id, snapshot);
+                // the escrowed tokens are returned to the buyer
+                if escrowed {
+                    self.pay_or_record(
+                        nft_contract_id,
+                        token_id,
+                        &payer,
+                        &payer_account,
+                        ft_contract_id,
+                        price,
+                    )
+                    .await;
+                }
+                return Err(MarketErr::NftCall(err));
+            }
+        };
+        self.remove_sell_orders(nft_contract_id, token_id, buyer);
+
+        // the stakers share stays on the marketplace until it is distributed
+        let treasury_amount = self.accrue_stakers_fee(ft_contract_id, treasury_fee - reward);
+        for (treasury_id, amount) in self.treasury_shares(treasury_amount) {
+            self.pay_or_record(
+                nft_contract_id,
+                token_id,
+                &payer,
+                &treasury_id,
+                ft_contract_id,
+                amount,
+            )
+            .await;
+        }
+        if let Some(keeper) = keeper {
+            if reward > 0 {
+                self.pay_or_record(
+                    nft_contract_id,
+                    token_id,
+                    &payer,
+                    keeper,
+                    ft_contract_id,
+                    reward,
+                )
+                .await;
+                self.record_keeper_reward(keeper, reward);
+            }
+        }
+
+        // pay royalties
+        let payouts = if primary_sale {
+            payouts
+        } else {
+            if !royalty_free && payouts.keys().all(|account| account == seller) {
+                self.cache_royalty_free(nft_contract_id, true);
+            }
+            self.record_royalty_rates(nft_contract_id, token_id, seller, amount, &payouts);
+            self.apply_royalty_config(seller, buyer, amount, payouts)
+        };
+        let mut payouts: Vec<(ActorId, u128)> = split_among_payees(seller, &payees, payouts)
+            .into_iter()
+            .collect();
+        if let Some(vesting) = vesting {
+            self.vest_proceeds(seller, ft_contract_id, &mut payouts, vesting);
+        }
+        // the rest of a large split is paid by the operation that the marketplace continues itself
+        let remaining = payouts.split_off(payouts.len().min(MAX_PAYOUTS));
+        for (account, amount) in payouts.iter() {
+            self.pay_or_record(
+                nft_contract_id,
+                token_id,
+                &payer,
+                account,
+                ft_contract_id,
+                *amount,
+            )
+            .await;
+        }
+        if !remaining.is_empty() {
+            self.schedule_operation(
+                None,
+                Operation::Payouts {
+                    nft_contract_id: *nft_contract_id,
+                    token_id,
+                    ft_contract_id,
+                    payouts: remaining,
+                },
+            );
+        }
+        self.record_volume(seller, buyer, price);
+        self.record_sale(nft_contract_id, ft_contract_id, price);
+        let receipt = SaleReceipt {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            ft_contract_id,
             price,
+            seller: *seller,
+            buyer: *buyer,
+            sold_at: exec::block_timestamp(),
+        };
+        self.record_trade(receipt);
+        self.request_receipt(receipt);
+        Ok(reward)
+    }
+
+    /// Pays the remaining payouts of the sale, at most [`MAX_PAYOUTS`] by one message.
+    /// The payments are escrowed by the marketplace, so the payouts that fail
+    /// are recorded as failed refunds and the admin can retry them.
+    pub async fn pay_payouts(
+        &mut self,
+        operation_id: OperationId,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        mut payouts: Vec<(ActorId, u128)>,
+    ) {
+        let remaining = payouts.split_off(payouts.len().min(MAX_PAYOUTS));
+        for (account, amount) in payouts.iter() {
+            self.pay_or_record(
+                nft_contract_id,
+                token_id,
+                &exec::program_id(),
+                account,
+                ft_contract_id,
+                *amount,
+            )
+            .await;
+        }
+        let scheduled = (!remaining.is_empty()).then(|| {
+            self.schedule_operation(
+                Some(operation_id),
+                Operation::Payouts {
+                    nft_contract_id: *nft_contract_id,
+                    token_id,
+                    ft_contract_id,
+                    payouts: remaining,
+                },
+            )
+        });
+        events::reply(
+            MarketEvent::PayoutsPaid {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                paid: payouts.len() as u32,
+                operation_id: scheduled,
+            },
+            0,
         )
-        .await;
+        .expect("Error in reply [MarketEvent::PayoutsPaid]");
+    }
+
+    /// Restores the item saved before the sale if the NFT transfer failed.
+    /// The lock of the transaction is kept, so it is released by the caller.
+    pub fn restore_item(&mut self, item_id: &ItemId, snapshot: Option<Item>) {
+        if let Some(snapshot) = snapshot {
+            self.items.insert(*item_id, snapshot);
+        }
+    }
+
+    /// Restores the item saved by the caller before its own changes
+    /// if [`Market::execute_sale`] has restored the item of the seller.
+    pub fn rollback_sale(&mut self, item_id: &ItemId, seller: &ActorId, snapshot: Option<Item>) {
+        let reverted = self
+            .items
+            .get(item_id)
+            .map(|item| item.owner_id == *seller)
+            .unwrap_or(false);
+        if reverted {
+            self.restore_item(item_id, snapshot);
+        }
     }
 }
 
-pub fn check_attached_value(ft_contract_id: Option<ActorId>, price: u128) {
-    if ft_contract_id.is_none() && msg::value() != price {
-        panic!("attached value is not equal the indicated price");
+// the beneficiary receives all proceeds of the seller
+fn proceeds_payees(item: &Item) -> Vec<(ActorId, u16)> {
+    match item.beneficiary {
+        Some(beneficiary) if item.payees.is_empty() => vec![(beneficiary, 10_000)],
+        _ => item.payees.clone(),
+    }
+}
+
+// splits the seller's proceeds among the payees of the item
+fn split_among_payees(seller: &ActorId, payees: &[(ActorId, u16)], mut payouts: Payout) -> Payout {
+    if payees.is_empty() {
+        return payouts;
+    }
+    let proceeds = payouts.remove(seller).unwrap_or_default();
+    let mut distributed = 0;
+    for (index, (payee, share)) in payees.iter().enumerate() {
+        // the last payee receives the rounding remainder
+        let value = if index + 1 == payees.len() {
+            proceeds - distributed
+        } else {
+            proceeds * *share as u128 / 10_000u128
+        };
+        distributed += value;
+        *payouts.entry(*payee).or_default() += value;
     }
+    payouts
 }
@@ -0,0 +1,70 @@
+use crate::{validation::market_err, Market, MarketErr};
+use gstd::ActorId;
+use market_io::CurrencyConfig;
+
+/// The maximum price in whole units of the currency.
+const MAX_WHOLE_UNITS: u128 = 1_000_000_000_000_000;
+/// Prices with more decimals overflow `u128`.
+const MAX_DECIMALS: u8 = 22;
+
+impl Market {
+    /// Sets the price bounds of the currency.
+    /// Requirements:
+    /// * Only admin can set currency configs
+    /// * The currency must be approved
+    /// * The number of decimals can't exceed 22
+    pub fn set_currency_config(
+        &mut self,
+        ft_contract_id: Option<ActorId>,
+        config: Option<CurrencyConfig>,
+    ) {
+        self.check_admin();
+        self.check_approved_ft_contract(ft_contract_id);
+        match config {
+            Some(config) => {
+                if config.decimals > MAX_DECIMALS {
+                    panic!("Currency decimals can't exceed {MAX_DECIMALS}");
+                }
+                if config.fee_on_transfer && ft_contract_id.is_none() {
+                    panic!("The native value can't take a fee on transfer");
+                }
+                self.currency_configs.insert(ft_contract_id, config);
+            }
+            None => {
+                self.currency_configs.remove(&ft_contract_id);
+            }
+        }
+    }
+
+    pub fn takes_transfer_fee(&self, ft_contract_id: Option<ActorId>) -> bool {
+        self.currency_configs
+            .get(&ft_contract_id)
+            .map(|config| config.fee_on_transfer)
+            .unwrap_or(false)
+    }
+
+    /// Checks that the auction can be held in the currency:
+    /// the bids in a currency that takes a fee on transfer couldn't be compared.
+    pub fn check_auction_currency(&self, ft_contract_id: Option<ActorId>) {
+        if self.takes_transfer_fee(ft_contract_id) {
+            panic!("Auctions in currencies with a transfer fee aren't allowed");
+        }
+    }
+
+    /// Checks that the price is within the bounds of the currency
+    /// (the prices in currencies without a config aren't restricted).
+    pub fn check_price_bounds(&self, ft_contract_id: Option<ActorId>, price: u128) {
+        if !self.within_price_bounds(ft_contract_id, price) {
+            market_err(MarketErr::PriceOutOfBounds);
+        }
+    }
+
+    pub fn within_price_bounds(&self, ft_contract_id: Option<ActorId>, price: u128) -> bool {
+        let config = match self.currency_configs.get(&ft_contract_id) {
+            Some(config) => config,
+            None => return true,
+        };
+        let max_price = MAX_WHOLE_UNITS * 10u128.pow(config.decimals as u32);
+        config.min_price <= price && price <= max_price
+    }
+}
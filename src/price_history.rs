@@ -0,0 +1,151 @@
+use crate::{events, Market};
+use gstd::{exec, prelude::*, ActorId};
+use market_io::*;
+
+/// The number of recent sales stored per collection and currency.
+const MAX_PRICE_POINTS: usize = 100;
+/// The maximum number of trades removed by one `PruneTrades` message.
+const MAX_PRUNED_TRADES: u32 = 50;
+/// The maximum number of items checked by one `PruneTrades` message.
+const MAX_PRUNED_ITEMS: usize = 20;
+/// The maximum number of trades returned by one `Trades` query.
+pub const MAX_EXPORTED_TRADES: u32 = 100;
+
+/// The block timestamp of the sale and the sale price.
+pub type PricePoint = (u64, u128);
+
+impl Market {
+    pub fn record_sale(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) {
+        let history = self
+            .price_history
+            .entry((*nft_contract_id, ft_contract_id))
+            .or_default();
+        if history.len() == MAX_PRICE_POINTS {
+            history.remove(0);
+        }
+        history.push((exec::block_timestamp(), price));
+    }
+
+    pub fn record_trade(&mut self, receipt: SaleReceipt) {
+        let trade_id = self.trade_id;
+        self.trade_id = self.trade_id.wrapping_add(1);
+        self.trades_by_item
+            .entry(ItemId::new(receipt.nft_contract_id, receipt.token_id))
+            .or_default()
+            .insert(trade_id);
+        self.trades.insert(trade_id, receipt);
+    }
+
+    pub fn set_trade_retention(&mut self, retention: Option<TradeRetention>) {
+        self.check_admin();
+        self.trade_retention = retention;
+        self.prune_cursor = None;
+    }
+
+    /// Removes the oldest trades exceeding the total retention
+    /// and then the oldest trades of the items exceeding the retention per item.
+    /// The items are checked in chunks starting from the item where the previous pruning stopped.
+    pub fn prune_trades(&mut self) {
+        self.check_admin();
+        let retention = self.trade_retention.expect("The trade retention isn't set");
+
+        let mut amount = 0;
+        while self.trades.len() > retention.total as usize && amount < MAX_PRUNED_TRADES {
+            let trade_id = *self.trades.keys().next().expect("There must be a trade");
+            self.remove_trade(trade_id);
+            amount += 1;
+        }
+
+        let start = self.prune_cursor.take().unwrap_or_default();
+        let keys: Vec<ItemId> = self
+            .trades_by_item
+            .range(start..)
+            .take(MAX_PRUNED_ITEMS + 1)
+            .map(|(key, _)| *key)
+            .collect();
+        for (index, key) in keys.into_iter().enumerate() {
+            if index == MAX_PRUNED_ITEMS {
+                self.prune_cursor = Some(key);
+                break;
+            }
+            while amount < MAX_PRUNED_TRADES {
+                let trade_id = match self.trades_by_item.get(&key) {
+                    Some(trade_ids) if trade_ids.len() > retention.per_item as usize => {
+                        *trade_ids.iter().next().expect("There must be a trade")
+                    }
+                    _ => break,
+                };
+                self.remove_trade(trade_id);
+                amount += 1;
+            }
+            if amount == MAX_PRUNED_TRADES {
+                self.prune_cursor = Some(key);
+                break;
+            }
+        }
+
+        let remaining = self.prune_cursor.is_some() || self.trades.len() > retention.total as usize;
+        events::reply(MarketEvent::TradesPruned { amount, remaining }, 0)
+            .expect("Error in reply [MarketEvent::TradesPruned]");
+    }
+
+    fn remove_trade(&mut self, trade_id: TradeId) {
+        let receipt = match self.trades.remove(&trade_id) {
+            Some(receipt) => receipt,
+            None => return,
+        };
+        let key = ItemId::new(receipt.nft_contract_id, receipt.token_id);
+        if let Some(trade_ids) = self.trades_by_item.get_mut(&key) {
+            trade_ids.remove(&trade_id);
+            if trade_ids.is_empty() {
+                self.trades_by_item.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the time-weighted average sale price of the collection
+    /// in the indicated currency over the last `window` milliseconds.
+    ///
+    /// Each sale price is weighted by the time until the next sale (or until now),
+    /// the last sale before the window is taken into account from the window start.
+    pub fn twap(
+        &self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        window: u64,
+    ) -> Option<u128> {
+        let history = self
+            .price_history
+            .get(&(*nft_contract_id, ft_contract_id))?;
+        let now = exec::block_timestamp();
+        let start = now.saturating_sub(window);
+
+        let mut weighted_sum: u128 = 0;
+        let mut total_time: u64 = 0;
+        for (index, (timestamp, price)) in history.iter().enumerate() {
+            let end = history
+                .get(index + 1)
+                .map(|(timestamp, _)| *timestamp)
+                .unwrap_or(now);
+            let begin = (*timestamp).max(start);
+            if end > begin {
+                weighted_sum = weighted_sum.saturating_add(price * (end - begin) as u128);
+                total_time += end - begin;
+            }
+        }
+
+        if total_time == 0 {
+            // all sales in the window happened right now
+            return history
+                .last()
+                .filter(|(timestamp, _)| *timestamp >= start)
+                .map(|(_, price)| *price);
+        }
+        Some(weighted_sum / total_time as u128)
+    }
+}
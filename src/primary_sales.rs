@@ -0,0 +1,48 @@
+use crate::Market;
+use gstd::ActorId;
+
+impl Market {
+    /// Registers the creator of the collection.
+    /// Requirements:
+    /// * Only admin can set collection creators
+    pub fn set_collection_creator(&mut self, nft_contract_id: &ActorId, creator: Option<ActorId>) {
+        self.check_admin();
+        match creator {
+            Some(creator) => {
+                self.collection_creators.insert(*nft_contract_id, creator);
+            }
+            None => {
+                self.collection_creators.remove(nft_contract_id);
+            }
+        }
+    }
+
+    /// Sets the treasury fee of primary sales.
+    /// Requirements:
+    /// * Only admin can set the primary sale fee
+    /// * The fee can't exceed the base treasury fee
+    pub fn set_primary_sale_fee(&mut self, fee: Option<u16>) {
+        self.check_admin();
+        if let Some(fee) = fee {
+            if fee > self.base_fee() {
+                panic!("Primary sale fee can't exceed the base treasury fee");
+            }
+        }
+        self.primary_sale_fee = fee;
+    }
+
+    pub fn is_collection_creator(&self, nft_contract_id: &ActorId, account: &ActorId) -> bool {
+        self.collection_creators.get(nft_contract_id) == Some(account)
+    }
+
+    /// Returns the treasury fee of the sale in basis points.
+    /// The primary sale fee takes precedence over the collection fee
+    /// that replaces the trader's fee.
+    pub fn sale_fee(&self, nft_contract_id: &ActorId, seller: &ActorId, primary_sale: bool) -> u16 {
+        match (self.primary_sale_fee, self.collection_fee(nft_contract_id)) {
+            (Some(fee), _) if primary_sale => fee,
+            (_, Some(fee)) => fee,
+            _ => self.trader_fee(seller),
+        }
+    }
+}
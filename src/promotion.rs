@@ -0,0 +1,93 @@
+use crate::{events, Item, ItemId, Market, MarketEvent, Promotion};
+use gstd::{exec, msg, prelude::*, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    pub fn set_promotion_fee(&mut self, fee: u128) {
+        self.check_admin();
+        self.promotion_fee = fee;
+    }
+
+    pub async fn promote_item(&mut self, nft_contract_id: &ActorId, token_id: U256, blocks: u32) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        if self.promotion_fee == 0 {
+            panic!("Promotions are disabled");
+        }
+        if blocks == 0 {
+            panic!("Promotion period can't be equal to zero");
+        }
+        let fee = self
+            .promotion_fee
+            .checked_mul(blocks as u128)
+            .expect("Promotion fee overflow");
+        if msg::value() != fee {
+            panic!("attached value is not equal the promotion fee");
+        }
+
+        let item = self.items.get_mut(&item_id).expect("Item does not exist");
+        if item.owner_id != msg::source() {
+            panic!("only owner can promote the item");
+        }
+        if item.price.is_none() {
+            panic!("The item is not on sale");
+        }
+
+        let current_block = exec::block_height();
+        let promotion = match item.featured.take() {
+            Some(promotion) if promotion.ends_at > current_block => Promotion {
+                paid: promotion.paid + fee,
+                ends_at: promotion
+                    .ends_at
+                    .checked_add(blocks)
+                    .expect("Promotion period overflow"),
+            },
+            _ => Promotion {
+                paid: fee,
+                ends_at: current_block
+                    .checked_add(blocks)
+                    .expect("Promotion period overflow"),
+            },
+        };
+        let ends_at = promotion.ends_at;
+        item.featured = Some(promotion);
+
+        // promotion proceeds go to the treasury
+        self.pay_treasuries(&msg::source(), None, fee)
+            .await
+            .expect("Error in sending promotion fee to treasury");
+
+        events::reply(
+            MarketEvent::ItemPromoted {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                ends_at,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::ItemPromoted]");
+    }
+
+    /// Returns the items on sale that are currently featured,
+    /// ordered by the value paid for the promotion.
+    pub fn featured_items(&self) -> Vec<Item> {
+        let current_block = exec::block_height();
+        let mut items: Vec<Item> = self
+            .items
+            .values()
+            .filter(|item| {
+                item.price.is_some()
+                    && item
+                        .featured
+                        .as_ref()
+                        .map(|promotion| promotion.ends_at > current_block)
+                        .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| {
+            let paid = |item: &Item| item.featured.as_ref().map(|p| p.paid).unwrap_or_default();
+            paid(b).cmp(&paid(a))
+        });
+        items
+    }
+}
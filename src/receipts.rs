@@ -0,0 +1,67 @@
+use crate::{
+    events,
+    nft_messages::{nft_mint, nft_transfer_without_payout},
+    Market,
+};
+use gear_lib::non_fungible_token::token::TokenMetadata;
+use gstd::{exec, msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::H256;
+
+impl Market {
+    pub fn set_receipts_contract(&mut self, receipts_contract_id: Option<ActorId>) {
+        self.check_admin();
+        self.receipts_contract_id = receipts_contract_id;
+    }
+
+    /// Requests the receipt of the sale if the receipts contract is set.
+    /// The receipt is minted by a separate message, so the sale doesn't depend on it.
+    pub fn request_receipt(&self, receipt: SaleReceipt) {
+        if self.receipts_contract_id.is_none() {
+            return;
+        }
+        let _ = msg::send(exec::program_id(), MarketAction::MintReceipt(receipt), 0);
+    }
+
+    pub async fn mint_receipt(&mut self, receipt: SaleReceipt) {
+        if msg::source() != exec::program_id() {
+            panic!("Only the marketplace can mint receipts");
+        }
+        let receipts_contract_id = match self.receipts_contract_id {
+            Some(receipts_contract_id) => receipts_contract_id,
+            None => return,
+        };
+        let token_metadata = TokenMetadata {
+            name: "Sale receipt".to_string(),
+            description: format!(
+                "Token {} of {} sold for {}",
+                receipt.token_id,
+                H256::from_slice(receipt.nft_contract_id.as_ref()),
+                receipt.price
+            ),
+            media: String::new(),
+            reference: receipt
+                .encode()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        };
+        // the receipt is minted to the marketplace and then transferred to the buyer
+        let receipt_id = nft_mint(&receipts_contract_id, token_metadata)
+            .await
+            .expect("Error in minting the receipt");
+        nft_transfer_without_payout(&receipts_contract_id, &receipt.buyer, receipt_id)
+            .await
+            .expect("Error in transferring the receipt");
+        events::send(
+            receipt.buyer,
+            MarketEvent::ReceiptMinted {
+                receipts_contract_id,
+                receipt_id,
+                receipt,
+            },
+            0,
+        )
+        .expect("Error in sending [MarketEvent::ReceiptMinted]");
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{events, validation::check_not_zero, Market};
+use gstd::{msg, ActorId};
+use market_io::*;
+
+impl Market {
+    pub fn set_registry_program(&mut self, registry_program_id: Option<ActorId>) {
+        self.check_admin();
+        if let Some(registry_program_id) = &registry_program_id {
+            check_not_zero(registry_program_id);
+        }
+        self.registry_program_id = registry_program_id;
+    }
+
+    /// Merges the contracts vetted by the registry program into the approved contracts.
+    /// The contracts approved by the admin stay approved even if the registry doesn't list them.
+    /// Requirements:
+    /// * The registry program must be set
+    pub async fn sync_approved_contracts(&mut self) {
+        let registry_program_id = self
+            .registry_program_id
+            .expect("The registry program isn't set");
+        let reply: RegistryEvent =
+            msg::send_for_reply_as(registry_program_id, RegistryAction::VettedContracts, 0)
+                .expect("Error in sending a message to the registry program")
+                .await
+                .expect("Error in the reply of the registry program");
+        let RegistryEvent::VettedContracts {
+            nft_contracts,
+            ft_contracts,
+        } = reply;
+
+        let mut nft_added = 0;
+        for nft_contract_id in nft_contracts
+            .into_iter()
+            .filter(|id| *id != ActorId::zero())
+        {
+            if self.approved_nft_contracts.insert(nft_contract_id) {
+                nft_added += 1;
+            }
+        }
+        let mut ft_added = 0;
+        for ft_contract_id in ft_contracts.into_iter().filter(|id| *id != ActorId::zero()) {
+            if self.approved_ft_contracts.insert(ft_contract_id) {
+                ft_added += 1;
+            }
+        }
+        events::reply(
+            MarketEvent::ContractsSynced {
+                nft_added,
+                ft_added,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::ContractsSynced]");
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{nft_messages::Payout, ItemId, Market, RoyaltyConfig, RoyaltyMode};
+use gstd::{msg, prelude::*, ActorId};
+use primitive_types::U256;
+
+const MAX_BASIS_POINTS: u16 = 10_000;
+
+impl Market {
+    pub fn set_royalty_config(&mut self, config: RoyaltyConfig) {
+        self.check_admin();
+        if config
+            .cap
+            .map(|cap| cap > MAX_BASIS_POINTS)
+            .unwrap_or(false)
+        {
+            panic!("Royalty cap can't be greater than 100%");
+        }
+        self.royalty_config = config;
+    }
+
+    pub fn set_royalty_preference(&mut self, pay_royalties: bool) {
+        if pay_royalties {
+            self.royalty_opt_outs.remove(&msg::source());
+        } else {
+            self.royalty_opt_outs.insert(msg::source());
+        }
+    }
+
+    /// Applies the royalty config to the payouts received from the NFT contract.
+    /// The part of royalties that exceeds the cap (or all royalties, if they
+    /// are not paid) goes to the seller, the rest is split proportionally among
+    /// the royalty recipients.
+    pub fn apply_royalty_config(
+        &self,
+        seller: &ActorId,
+        buyer: &ActorId,
+        amount: u128,
+        mut payouts: Payout,
+    ) -> Payout {
+        let pay_royalties = match self.royalty_config.mode {
+            RoyaltyMode::Enforced => true,
+            RoyaltyMode::Optional => !self.royalty_opt_outs.contains(buyer),
+            RoyaltyMode::Disabled => false,
+        };
+        let royalties: u128 = payouts
+            .iter()
+            .filter(|(account, _)| *account != seller)
+            .map(|(_, value)| value)
+            .sum();
+        let allowed = match (pay_royalties, self.royalty_config.cap) {
+            (false, _) => 0,
+            (true, Some(cap)) => royalties.min(amount * cap as u128 / MAX_BASIS_POINTS as u128),
+            (true, None) => royalties,
+        };
+        if allowed == royalties {
+            return payouts;
+        }
+
+        let mut paid = 0;
+        for (account, value) in payouts.iter_mut() {
+            if account != seller {
+                *value = *value * allowed / royalties;
+                paid += *value;
+            }
+        }
+        payouts.retain(|_, value| *value > 0);
+        *payouts.entry(*seller).or_default() += royalties - paid;
+        payouts
+    }
+
+    /// Stores the royalty rates (in basis points of the sale amount) reported
+    /// by the NFT contract on the last sale of the token.
+    pub fn record_royalty_rates(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        seller: &ActorId,
+        amount: u128,
+        payouts: &Payout,
+    ) {
+        if amount == 0 {
+            return;
+        }
+        let rates = payouts
+            .iter()
+            .filter(|(account, _)| *account != seller)
+            .map(|(account, value)| (*account, (value * MAX_BASIS_POINTS as u128 / amount) as u16))
+            .collect();
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.royalty_rates.insert(item_id, rates);
+    }
+
+    /// Returns the royalty recipients and amounts for the indicated sale price
+    /// according to the recorded royalty rates and the royalty config
+    /// (similar to EIP-2981 `royaltyInfo`, but with several recipients).
+    pub fn royalty_info(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        sale_price: u128,
+    ) -> Vec<(ActorId, u128)> {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let mut payouts: Payout = self
+            .royalty_rates
+            .get(&item_id)
+            .into_iter()
+            .flatten()
+            .map(|(account, rate)| {
+                (
+                    *account,
+                    sale_price * *rate as u128 / MAX_BASIS_POINTS as u128,
+                )
+            })
+            .collect();
+        // the unknown seller and buyer are represented by the zero address
+        let royalties: u128 = payouts.values().sum();
+        payouts.insert(ActorId::zero(), sale_price.saturating_sub(royalties));
+        let mut payouts =
+            self.apply_royalty_config(&ActorId::zero(), &ActorId::zero(), sale_price, payouts);
+        payouts.remove(&ActorId::zero());
+        payouts.into_iter().collect()
+    }
+}
@@ -1,55 +1,208 @@
-use crate::{nft_messages::*, payment::*, Market, MarketEvent, BASE_PERCENT};
-use gstd::{msg, prelude::*, ActorId};
-use primitive_types::{H256, U256};
+use crate::{
+    events,
+    ft_messages::ft_balance,
+    listing::sale_price,
+    nft_messages::{nft_approve, nft_is_approved},
+    payment::*,
+    validation::{check_not_owner, check_not_zero, market_err},
+    ItemId, Market, MarketErr, MarketEvent, TransactionStep,
+};
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
 
 impl Market {
-    pub async fn buy_item(&mut self, nft_contract_id: &ActorId, token_id: U256) {
-        let contract_and_token_id =
-            format!("{}{token_id}", H256::from_slice(nft_contract_id.as_ref()));
-        let item = self
-            .items
-            .get_mut(&contract_and_token_id)
-            .expect("Item does not exist");
+    pub async fn buy_item(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        recipient: Option<ActorId>,
+        relist_at: Option<u128>,
+    ) {
+        self.buy_item_as(
+            &msg::source(),
+            nft_contract_id,
+            token_id,
+            recipient,
+            relist_at,
+        )
+        .await;
+    }
+
+    /// Buys the item on behalf of the buyer who pays for it.
+    /// It is [`msg::source()`](gstd::msg::source) unless the purchase is forwarded.
+    pub async fn buy_item_as(
+        &mut self,
+        buyer: &ActorId,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        recipient: Option<ActorId>,
+        relist_at: Option<u128>,
+    ) {
+        self.check_not_banned(buyer);
+        let recipient = recipient.unwrap_or(*buyer);
+        check_not_zero(&recipient);
+        self.check_not_banned(&recipient);
+        self.check_transferable(nft_contract_id);
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
         if item.auction.is_some() {
             panic!("There is an opened auction");
         }
-        let price = item.price.expect("The item is not on sale");
+        let listed_price = item.price;
+        let flash_sale = item.flash_sale;
+        let sale_price = sale_price(item);
+        let reference_price = item.reference_price;
+        if listed_price.is_none() && reference_price.is_none() {
+            panic!("The item is not on sale");
+        }
+        let ft_contract_id = item.ft_contract_id;
+        let seller = item.owner_id;
+        let gate = item.gate;
+        check_not_owner(&seller, buyer, MarketErr::SelfPurchase);
+        if let Some(price) = sale_price {
+            check_attached_value(ft_contract_id, price);
+        }
+        if let Some(relist_at) = relist_at {
+            if recipient != *buyer {
+                panic!("Only the buyer's item can be relisted");
+            }
+            self.check_price_bounds(ft_contract_id, relist_at);
+        }
 
-        check_attached_value(item.ft_contract_id, price);
-        // fee for treasury
-        let treasury_fee = price * (self.treasury_fee * BASE_PERCENT) as u128 / 10_000u128;
+        // token-gated sale
+        if let Some((gate_contract_id, min_balance)) = gate {
+            let balance = ft_balance(&gate_contract_id, buyer)
+                .await
+                .expect("Error in checking the buyer's balance");
+            if balance < min_balance {
+                panic!("The buyer doesn't hold enough gate tokens");
+            }
+        }
 
-        transfer_payment(
-            &msg::source(),
-            &self.treasury_id,
-            item.ft_contract_id,
-            treasury_fee,
-        )
-        .await;
+        // the amount of the reference priced item is computed at the time of purchase
+        let price = match sale_price {
+            Some(price) => price,
+            None => self
+                .reference_amount(
+                    ft_contract_id,
+                    reference_price.expect("The item is not on sale"),
+                )
+                .await
+                .unwrap_or_else(|err| market_err(err)),
+        };
+        if listed_price.is_none() && ft_contract_id.is_none() && msg::value() < price {
+            panic!("attached value is less than the price");
+        }
 
-        // transfer NFT and pay royalties
-        let payouts = nft_transfer(
-            nft_contract_id,
-            &msg::source(),
-            token_id,
-            price - treasury_fee,
-        )
-        .await;
-        for (account, amount) in payouts.iter() {
-            transfer_payment(&msg::source(), account, item.ft_contract_id, *amount).await;
+        if gate.is_some() || listed_price.is_none() {
+            // the item could be changed while waiting for the reply
+            let item = self.items.get(&item_id).expect("Item does not exist");
+            if item.owner_id != seller
+                || item.price != listed_price
+                || item.flash_sale != flash_sale
+                || item.reference_price != reference_price
+                || item.ft_contract_id != ft_contract_id
+                || item.auction.is_some()
+            {
+                panic!("The item has been changed");
+            }
         }
 
-        item.owner_id = msg::source();
-        item.price = None;
+        let transaction_id = self.lock_item(&item_id);
 
-        msg::reply(
-            MarketEvent::ItemSold {
-                owner: msg::source(),
-                nft_contract_id: *nft_contract_id,
+        if self
+            .execute_sale(
+                buyer,
+                nft_contract_id,
+                ft_contract_id,
                 token_id,
-            },
-            0,
-        )
-        .expect("Error in reply [MarketEvent::ItemSold]");
+                &seller,
+                &recipient,
+                price,
+                None,
+                None,
+            )
+            .await
+            .is_err()
+        {
+            self.transaction_failed(
+                nft_contract_id,
+                token_id,
+                transaction_id,
+                TransactionStep::Sale {
+                    seller,
+                    buyer: recipient,
+                    price,
+                },
+            );
+            // nothing is paid if the sale fails, so the whole attached value is returned
+            self.return_change(ft_contract_id, 0);
+            return;
+        }
+
+        self.unlock_item(&item_id);
+
+        // return the excess of the attached value
+        self.return_change(ft_contract_id, price);
+
+        if let Some(relist_at) = relist_at {
+            self.relist_item(nft_contract_id, token_id, buyer, relist_at)
+                .await;
+        }
+
+        let event = MarketEvent::ItemSold {
+            owner: recipient,
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            payer: *buyer,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::ItemSold]");
+    }
+
+    // lists the bought item at the buyer's price,
+    // the item stays unlisted if the NFT contract rejects the approval
+    async fn relist_item(
+        &mut self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        owner: &ActorId,
+        price: u128,
+    ) {
+        let approved = nft_approve(nft_contract_id, &exec::program_id(), token_id)
+            .await
+            .is_ok()
+            && nft_is_approved(nft_contract_id, &exec::program_id(), token_id).await == Ok(true);
+        if !approved {
+            return;
+        }
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let primary_sale = self.is_collection_creator(nft_contract_id, owner);
+        // the item could be changed while waiting for the NFT contract
+        match self.items.get_mut(&item_id) {
+            Some(item)
+                if item.owner_id == *owner
+                    && item.price.is_none()
+                    && item.auction.is_none()
+                    && item.locked_by.is_none() =>
+            {
+                item.price = Some(price);
+                item.gate = None;
+                item.primary_sale = primary_sale;
+            }
+            _ => return,
+        }
+
+        let event = MarketEvent::MarketDataAdded {
+            nft_contract_id: *nft_contract_id,
+            owner: *owner,
+            token_id,
+            price: Some(price),
+            beneficiary: None,
+            metadata: None,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::send(msg::source(), event, 0)
+            .expect("Error in sending [MarketEvent::MarketDataAdded]");
     }
 }
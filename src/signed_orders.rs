@@ -0,0 +1,150 @@
+use crate::{
+    events,
+    payment::check_attached_value,
+    validation::{check_not_owner, market_err},
+    Market,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+use market_io::*;
+
+/// The signing context of sr25519 signatures made by Substrate wallets.
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// Checks the seller's sr25519 signature over the SCALE-encoded `(marketplace, epoch, order)`.
+pub fn check_order_signature(
+    seller: &ActorId,
+    epoch: u64,
+    order: &SignedOrder,
+    signature: &[u8; 64],
+) {
+    let message = (exec::program_id(), epoch, order).encode();
+    let valid = match (
+        schnorrkel::PublicKey::from_bytes(seller.as_ref()),
+        schnorrkel::Signature::from_bytes(signature),
+    ) {
+        (Ok(public_key), Ok(signature)) => public_key
+            .verify_simple(SIGNING_CONTEXT, &message, &signature)
+            .is_ok(),
+        _ => false,
+    };
+    if !valid {
+        market_err(MarketErr::InvalidSignature);
+    }
+}
+
+impl Market {
+    /// Buys the token with the seller's off-chain signed order.
+    ///
+    /// The nonce of the order is used before the sale, so the order can't be
+    /// filled twice, and is released if the sale fails.
+    pub async fn fill_signed_order(
+        &mut self,
+        seller: &ActorId,
+        order: SignedOrder,
+        signature: [u8; 64],
+    ) {
+        let buyer = msg::source();
+        self.check_not_banned(&buyer);
+        self.check_not_banned(seller);
+        self.check_approved_nft_contract(&order.nft_contract_id);
+        self.check_transferable(&order.nft_contract_id);
+        self.check_approved_ft_contract(order.ft_contract_id);
+        if order.expires_at < exec::block_timestamp() {
+            panic!("The order has expired");
+        }
+        if self.is_nonce_used(seller, order.nonce) {
+            panic!("The order nonce is already used");
+        }
+        check_not_owner(seller, &buyer, MarketErr::SelfPurchase);
+        check_attached_value(order.ft_contract_id, order.price);
+        self.return_change(order.ft_contract_id, order.price);
+        self.check_price_bounds(order.ft_contract_id, order.price);
+        check_order_signature(seller, self.order_epoch(seller), &order, &signature);
+
+        let item_id = ItemId::new(order.nft_contract_id, order.token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(&order.nft_contract_id, order.token_id);
+        if let Some(item) = self.items.get(&item_id) {
+            if item.locked_by.is_some() {
+                panic!("The item is locked by another transaction");
+            }
+        }
+
+        self.used_nonces
+            .entry(*seller)
+            .or_default()
+            .insert(order.nonce);
+        if self
+            .execute_sale(
+                &buyer,
+                &order.nft_contract_id,
+                order.ft_contract_id,
+                order.token_id,
+                seller,
+                &buyer,
+                order.price,
+                None,
+                None,
+            )
+            .await
+            .is_err()
+        {
+            if let Some(nonces) = self.used_nonces.get_mut(seller) {
+                nonces.remove(&order.nonce);
+            }
+            let transaction_id = self.next_transaction_id();
+            self.transaction_failed(
+                &order.nft_contract_id,
+                order.token_id,
+                transaction_id,
+                TransactionStep::Sale {
+                    seller: *seller,
+                    buyer,
+                    price: order.price,
+                },
+            );
+            return;
+        }
+
+        let event = MarketEvent::ItemSold {
+            owner: buyer,
+            nft_contract_id: order.nft_contract_id,
+            token_id: order.token_id,
+            payer: buyer,
+        };
+        self.notify_subscribers(&order.nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::ItemSold]");
+    }
+
+    /// Invalidates the caller's signed order with the nonce.
+    pub fn cancel_order(&mut self, nonce: u64) {
+        let account = msg::source();
+        if !self.used_nonces.entry(account).or_default().insert(nonce) {
+            panic!("The order nonce is already used");
+        }
+        events::reply(MarketEvent::OrderCancelled { account, nonce }, 0)
+            .expect("Error in reply [MarketEvent::OrderCancelled]");
+    }
+
+    /// Invalidates all the caller's outstanding signed orders
+    /// since the orders are signed with the account's epoch.
+    pub fn cancel_all_orders(&mut self) {
+        let account = msg::source();
+        let epoch = self.order_epochs.entry(account).or_default();
+        *epoch = epoch.wrapping_add(1);
+        let epoch = *epoch;
+        events::reply(MarketEvent::AllOrdersCancelled { account, epoch }, 0)
+            .expect("Error in reply [MarketEvent::AllOrdersCancelled]");
+    }
+
+    pub fn order_epoch(&self, account: &ActorId) -> u64 {
+        self.order_epochs.get(account).copied().unwrap_or_default()
+    }
+
+    pub fn is_nonce_used(&self, account: &ActorId, nonce: u64) -> bool {
+        self.used_nonces
+            .get(account)
+            .map(|nonces| nonces.contains(&nonce))
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{auction::BID_INCREMENT, listing::sale_price, Item, Market, MarketErr, MarketEvent};
+use gstd::{exec, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::U256;
+
+// the error message of the failed action
+fn err(err: MarketErr) -> String {
+    format!("{err:?}")
+}
+
+impl Market {
+    /// Predicts the outcome of the action sent by `caller` with the attached `value`
+    /// at the current state without executing any transfers.
+    /// Returns the event the action replies with or the message it fails with.
+    ///
+    /// The replies of other programs (e.g. the token gate balance
+    /// or the NFT ownership) aren't known, so only the marketplace checks are made.
+    pub fn simulate(
+        &self,
+        caller: &ActorId,
+        value: u128,
+        action: &MarketAction,
+    ) -> Result<MarketEvent, String> {
+        if self.paused_actions & action.pause_category() != 0 {
+            return Err("The action is paused".into());
+        }
+        if self.banned_actors.contains(caller) {
+            return Err(err(MarketErr::BannedActor));
+        }
+        match action {
+            MarketAction::BuyItem {
+                nft_contract_id,
+                token_id,
+                recipient,
+                ..
+            } => self.simulate_buy(caller, value, nft_contract_id, *token_id, *recipient),
+            MarketAction::AddBid {
+                nft_contract_id,
+                token_id,
+                ft_contract_id,
+                price,
+            }
+            | MarketAction::AddBidInCurrency {
+                nft_contract_id,
+                token_id,
+                ft_contract_id,
+                price,
+            } => self.simulate_bid(
+                caller,
+                value,
+                nft_contract_id,
+                *token_id,
+                *ft_contract_id,
+                *price,
+            ),
+            MarketAction::AddOffer {
+                nft_contract_id,
+                ft_contract_id,
+                token_id,
+                price,
+                metadata,
+                ..
+            } => {
+                let item = self.unlocked_item(nft_contract_id, *token_id)?;
+                if item.auction.is_some() {
+                    return Err("There is an opened auction".into());
+                }
+                self.check_currency(*ft_contract_id)?;
+                if *price == 0 {
+                    return Err("Cant offer zero price".into());
+                }
+                if !self.within_price_bounds(*ft_contract_id, *price) {
+                    return Err(err(MarketErr::PriceOutOfBounds));
+                }
+                if item.owner_id == *caller {
+                    return Err(err(MarketErr::SelfOffer));
+                }
+                if item.offers.len() >= self.max_offers() as usize
+                    && self.lowest_offer(item, *ft_contract_id, *price).is_none()
+                {
+                    return Err("The item has the maximum number of offers".into());
+                }
+                check_value(*ft_contract_id, value, *price)?;
+                Ok(MarketEvent::OfferAdded {
+                    nft_contract_id: *nft_contract_id,
+                    ft_contract_id: *ft_contract_id,
+                    token_id: *token_id,
+                    price: *price,
+                    metadata: metadata.clone(),
+                })
+            }
+            MarketAction::AcceptOffer {
+                nft_contract_id,
+                token_id,
+                offer_id,
+            } => {
+                let item = self.unlocked_item(nft_contract_id, *token_id)?;
+                if item.owner_id != *caller {
+                    return Err("only owner can accept offer".into());
+                }
+                // the auction without bids is cancelled in favor of the offer
+                if matches!(&item.auction, Some(auction) if auction.current_winner != ActorId::zero())
+                {
+                    return Err("There is an opened auction".into());
+                }
+                let offer = item
+                    .offers
+                    .iter()
+                    .find(|offer| offer.offer_id == *offer_id)
+                    .ok_or("The offer with that id does not exist")?;
+                Ok(MarketEvent::OfferAccepted {
+                    nft_contract_id: *nft_contract_id,
+                    token_id: *token_id,
+                    new_owner: offer.recipient.unwrap_or(offer.id),
+                    price: offer.price,
+                    payer: offer.id,
+                })
+            }
+            MarketAction::Withdraw {
+                nft_contract_id,
+                token_id,
+                offer_id,
+            } => {
+                let item = self.unlocked_item(nft_contract_id, *token_id)?;
+                let offer = item
+                    .offers
+                    .iter()
+                    .find(|offer| offer.offer_id == *offer_id)
+                    .ok_or("The offer with that id does not exist")?;
+                if offer.id != *caller {
+                    return Err("can't withdraw other user's tokens".into());
+                }
+                Ok(MarketEvent::TokensWithdrawn {
+                    nft_contract_id: *nft_contract_id,
+                    token_id: *token_id,
+                    price: offer.price,
+                })
+            }
+            MarketAction::SettleAuction {
+                nft_contract_id,
+                token_id,
+            } => {
+                let item = self.unlocked_item(nft_contract_id, *token_id)?;
+                let auction = item.auction.as_ref().ok_or("Auction doesn not exist")?;
+                if auction.ended_at > exec::block_timestamp() {
+                    return Err("Auction is not over".into());
+                }
+                if auction.current_winner == ActorId::zero() {
+                    return Ok(MarketEvent::AuctionCancelled {
+                        nft_contract_id: *nft_contract_id,
+                        token_id: *token_id,
+                    });
+                }
+                Ok(MarketEvent::AuctionSettled {
+                    nft_contract_id: *nft_contract_id,
+                    token_id: *token_id,
+                    price: auction.current_price,
+                })
+            }
+            _ => Err("The action can't be simulated".into()),
+        }
+    }
+
+    fn simulate_buy(
+        &self,
+        buyer: &ActorId,
+        value: u128,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        recipient: Option<ActorId>,
+    ) -> Result<MarketEvent, String> {
+        let recipient = recipient.unwrap_or(*buyer);
+        if recipient == ActorId::zero() {
+            return Err(err(MarketErr::ZeroAddress));
+        }
+        if self.banned_actors.contains(&recipient) {
+            return Err(err(MarketErr::BannedActor));
+        }
+        if self.is_soulbound(nft_contract_id) {
+            return Err(err(MarketErr::NonTransferable));
+        }
+        let item = self.unlocked_item(nft_contract_id, token_id)?;
+        if item.auction.is_some() {
+            return Err("There is an opened auction".into());
+        }
+        if item.price.is_none() && item.reference_price.is_none() {
+            return Err("The item is not on sale".into());
+        }
+        if item.owner_id == *buyer {
+            return Err(err(MarketErr::SelfPurchase));
+        }
+        if let Some(price) = sale_price(item) {
+            check_value(item.ft_contract_id, value, price)?;
+        }
+        Ok(MarketEvent::ItemSold {
+            owner: recipient,
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            payer: *buyer,
+        })
+    }
+
+    fn simulate_bid(
+        &self,
+        bidder: &ActorId,
+        value: u128,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: u128,
+    ) -> Result<MarketEvent, String> {
+        let item = self.unlocked_item(nft_contract_id, token_id)?;
+        let auction = item.auction.as_ref().ok_or("Auction doesn not exist")?;
+        if auction.ended_at < exec::block_timestamp() {
+            return Err("Auction has already ended".into());
+        }
+        if item.owner_id == *bidder {
+            return Err(err(MarketErr::SelfBid));
+        }
+        check_value(ft_contract_id, value, price)?;
+        let outbids = if auction.currencies.is_empty() {
+            if ft_contract_id != item.ft_contract_id {
+                return Err(err(MarketErr::WrongCurrency));
+            }
+            price > auction.current_price
+        } else {
+            if !auction.currencies.contains(&ft_contract_id) {
+                return Err(err(MarketErr::WrongCurrency));
+            }
+            let bid_value = |ft_contract_id, price: u128| {
+                self.exchange_rates
+                    .get(&ft_contract_id)
+                    .and_then(|rate| price.checked_mul(*rate))
+                    .ok_or("The exchange rate of the currency is not set")
+            };
+            bid_value(ft_contract_id, price)?
+                > bid_value(item.ft_contract_id, auction.current_price)?
+        };
+        if !outbids {
+            return Err("Cant offer less or equal to the current bid price".into());
+        }
+        if self.takes_transfer_fee(ft_contract_id) {
+            return Err("Auctions in currencies with a transfer fee aren't allowed".into());
+        }
+
+        // the maximum bid of the current winner outbids the bid
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        if let Some(max_price) = self.max_bids.get(&item_id) {
+            if price <= *max_price {
+                return Ok(MarketEvent::BidOutbid {
+                    nft_contract_id: *nft_contract_id,
+                    token_id,
+                    price: price.saturating_add(BID_INCREMENT).min(*max_price),
+                });
+            }
+        }
+        Ok(MarketEvent::BidAdded {
+            nft_contract_id: *nft_contract_id,
+            token_id,
+            price,
+        })
+    }
+
+    fn unlocked_item(&self, nft_contract_id: &ActorId, token_id: U256) -> Result<&Item, String> {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).ok_or("Item does not exist")?;
+        if item.locked_by.is_some() {
+            return Err("The item is locked by another transaction".into());
+        }
+        Ok(item)
+    }
+
+    fn check_currency(&self, ft_contract_id: Option<ActorId>) -> Result<(), String> {
+        match ft_contract_id {
+            Some(ft_contract_id) if !self.approved_ft_contracts.contains(&ft_contract_id) => {
+                Err("that ft contract is not approved".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_value(ft_contract_id: Option<ActorId>, value: u128, price: u128) -> Result<(), String> {
+    if ft_contract_id.is_none() && value < price {
+        return Err("attached value is less than the indicated price".into());
+    }
+    Ok(())
+}
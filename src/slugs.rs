@@ -0,0 +1,68 @@
+use crate::{events, Market};
+use gstd::{msg, prelude::*, ActorId};
+use market_io::*;
+use primitive_types::U256;
+
+/// Checks that the slug has from 1 to [`MAX_SLUG_LEN`] lowercase ASCII letters, digits or hyphens.
+pub fn check_slug(slug: &str) {
+    if slug.is_empty()
+        || slug.len() > MAX_SLUG_LEN
+        || !slug
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-')
+    {
+        panic!("The slug must have from 1 to {MAX_SLUG_LEN} lowercase letters, digits or hyphens");
+    }
+}
+
+impl Market {
+    /// Registers the slug of the item or removes it.
+    /// Requirements:
+    /// * Only the item owner can set the slug
+    /// * The slug can't be registered for another item
+    pub fn set_slug(&mut self, nft_contract_id: &ActorId, token_id: U256, slug: Option<String>) {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id).expect("Item does not exist");
+        if item.owner_id != msg::source() {
+            panic!("Only owner can update the listing");
+        }
+        if let Some(slug) = &slug {
+            check_slug(slug);
+            match self.resolve_slug(slug) {
+                Some(registered) if registered != item_id => {
+                    panic!("The slug is already registered")
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(previous) = item.slug.clone() {
+            self.slugs.remove(&previous);
+        }
+        if let Some(slug) = &slug {
+            self.slugs.insert(slug.clone(), item_id);
+        }
+        if let Some(item) = self.items.get_mut(&item_id) {
+            item.slug = slug.clone();
+        }
+        events::reply(
+            MarketEvent::SlugSet {
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                slug,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::SlugSet]");
+    }
+
+    /// Returns the NFT contract and the token id of the item with the slug.
+    /// The slug of the removed item isn't resolved and can be registered again.
+    pub fn resolve_slug(&self, slug: &str) -> Option<ItemId> {
+        let item = self
+            .slugs
+            .get(slug)
+            .and_then(|item_id| self.items.get(item_id))?;
+        (item.slug.as_deref() == Some(slug)).then(|| item.id())
+    }
+}
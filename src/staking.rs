@@ -0,0 +1,85 @@
+use crate::{events, ft_messages::transfer_tokens, validation::check_not_zero, Market};
+use gstd::{exec, msg, ActorId};
+use market_io::*;
+
+/// The maximum share of the stakers in basis points (the whole treasury fee).
+const MAX_STAKERS_SHARE: u16 = 10_000;
+
+impl Market {
+    /// Sets the staking program that receives a share of the treasury fees.
+    /// Requirements:
+    /// * Only admin can set the staking program
+    /// * The share can't exceed the whole treasury fee
+    pub fn set_staking(&mut self, staking: Option<Staking>) {
+        self.check_admin();
+        if let Some(staking) = &staking {
+            check_not_zero(&staking.program_id);
+            if staking.share > MAX_STAKERS_SHARE {
+                panic!("Stakers share can't exceed the treasury fee");
+            }
+        }
+        self.staking = staking;
+    }
+
+    /// Accrues the stakers share of the treasury fee that is kept on the marketplace
+    /// and returns the rest of the fee.
+    pub fn accrue_stakers_fee(&mut self, ft_contract_id: Option<ActorId>, fee: u128) -> u128 {
+        let share = match self.staking {
+            Some(staking) => fee * staking.share as u128 / 10_000u128,
+            None => return fee,
+        };
+        if share > 0 {
+            let fees = self.staking_fees.entry(ft_contract_id).or_default();
+            fees.owed = fees.owed.saturating_add(share);
+        }
+        fee - share
+    }
+
+    /// Sends the accrued fees to the staking program.
+    ///
+    /// The fees are written off before the transfer, so a repeated distribution
+    /// in the same epoch is rejected, and are accrued again if the transfer fails.
+    pub async fn distribute_fees(&mut self, ft_contract_id: Option<ActorId>) {
+        let staking = self.staking.expect("The staking program isn't registered");
+        let fees = self.staking_fees.entry(ft_contract_id).or_default();
+        if fees.distributed_at.saturating_add(staking.epoch) > exec::block_timestamp() {
+            panic!("The distribution epoch isn't over");
+        }
+        if fees.owed == 0 {
+            panic!("There are no fees to distribute");
+        }
+        let snapshot = *fees;
+        let amount = fees.owed;
+        fees.owed = 0;
+        fees.distributed_at = exec::block_timestamp();
+
+        let event = MarketEvent::FeesDistributed {
+            ft_contract_id,
+            amount,
+        };
+        let sent = match ft_contract_id {
+            None => msg::send(staking.program_id, &event, amount).is_ok(),
+            Some(ft_contract_id) => transfer_tokens(
+                &ft_contract_id,
+                &exec::program_id(),
+                &staking.program_id,
+                amount,
+            )
+            .await
+            .is_ok(),
+        };
+        // the tokens are already transferred, so the failed notification is ignored
+        if sent && ft_contract_id.is_some() {
+            let _ = msg::send(staking.program_id, &event, 0);
+        }
+        if !sent {
+            let fees = self.staking_fees.entry(ft_contract_id).or_default();
+            fees.owed = fees.owed.saturating_add(snapshot.owed);
+            fees.distributed_at = snapshot.distributed_at;
+            events::reply(MarketEvent::FeesDistributionFailed { ft_contract_id }, 0)
+                .expect("Error in reply [MarketEvent::FeesDistributionFailed]");
+            return;
+        }
+        events::reply(event, 0).expect("Error in reply [MarketEvent::FeesDistributed]");
+    }
+}
@@ -1,24 +1,458 @@
-use crate::Item;
+use crate::{
+    listing::sale_price, AuctionBounds, Candle, CollectionConfig, Deal, DropId, EventRecord,
+    EventSeq, FailedTransaction, FeeTier, GasUsage, HeldValue, Item, ItemId, LimitOrder,
+    ListingStatus, Market, MarketAction, MarketEvent, MarketMetrics, Offer, Operation, OperationId,
+    OrderBook, PendingChange, SaleReceipt, StakingFees, TokenDrop, TradeId, TraitOffer,
+    VestedProceeds, VestingId, Watch,
+};
 use codec::{Decode, Encode};
 use gstd::{prelude::*, ActorId};
-use primitive_types::U256;
+use primitive_types::{H256, U256};
 use scale_info::TypeInfo;
 
 #[derive(Debug, Decode, Encode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
+#[allow(clippy::large_enum_variant)]
 pub enum State {
     AllItems,
     ItemInfo {
         nft_contract_id: ActorId,
         token_id: U256,
     },
+    FeaturedItems,
+    OrderBook {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+    },
+    ItemsByOwner {
+        owner_id: ActorId,
+        offset: u32,
+        limit: u32,
+    },
+    ItemsByCollection {
+        nft_contract_id: ActorId,
+        offset: u32,
+        limit: u32,
+    },
+    ActiveAuctions {
+        offset: u32,
+        limit: u32,
+    },
+    /// Items on sale for a fixed price in the indicated currency
+    /// (`None` for a native value).
+    ItemsOnSale {
+        ft_contract_id: Option<ActorId>,
+        offset: u32,
+        limit: u32,
+    },
+    /// The native value that can be claimed by the account.
+    Claimable {
+        account: ActorId,
+    },
+    BannedActors,
+    FeeTiers,
+    /// The cumulative traded volume of the account and its current treasury fee.
+    TradedVolume {
+        account: ActorId,
+    },
+    /// The time-weighted average sale price of the collection in the indicated currency
+    /// over the last `window` milliseconds (`None` if there were no sales).
+    Twap {
+        nft_contract_id: ActorId,
+        ft_contract_id: Option<ActorId>,
+        window: u64,
+    },
+    /// The time left until the end of the auction and the number of its extensions.
+    AuctionCountdown {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    /// The royalty recipients and amounts for the indicated sale price of the token
+    /// (the royalty rates are known after the first sale of the token on the marketplace).
+    RoyaltyInfo {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        sale_price: u128,
+    },
+    AuctionBounds,
+    /// The account's listings, offers, winning bids and claimable balance.
+    UserActivity(ActorId),
+    /// The keeper bounty and the total rewards received by the account.
+    KeeperRewards {
+        account: ActorId,
+    },
+    /// The same metrics that are sent to the monitor.
+    Metrics,
+    /// The failed transactions ordered by their ids.
+    FailedTransactions,
+    Drop(DropId),
+    /// The custom settings of the collection.
+    CollectionConfig(ActorId),
+    /// The treasury fees accrued for the stakers in the currency (`None` for a native value).
+    StakingFees(Option<ActorId>),
+    /// The checkpoint of the unfinished operation.
+    Operation(OperationId),
+    /// The trades ordered by their ids starting from `from` (at most 100 trades).
+    Trades {
+        from: TradeId,
+        limit: u32,
+    },
+    /// The order epoch and the used nonces of the account's signed orders.
+    OrderNonces(ActorId),
+    /// The bitwise OR of the paused action categories.
+    PausedActions,
+    /// The watches of the account.
+    Watches(ActorId),
+    /// The trait offers of the collection.
+    TraitOffers(ActorId),
+    /// The snapshot of the auction (`None` if there is no auction).
+    AuctionInfo {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    /// The snapshots of the active auctions sorted by the end time.
+    AllActiveAuctions {
+        offset: u32,
+        limit: u32,
+    },
+    /// What the buyer must attach or approve to buy the item.
+    Quote {
+        nft_contract_id: ActorId,
+        token_id: U256,
+        buyer: ActorId,
+    },
+    /// The predicted outcome of the action sent by `caller` with the attached `value`.
+    Simulate {
+        caller: ActorId,
+        value: u128,
+        action: MarketAction,
+    },
+    /// The native value held for the accounts.
+    HeldValue,
+    /// The status of the listing (`None` if the item doesn't exist).
+    ListingStatus {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    /// The parameter changes proposed by the governance ordered by their ids.
+    PendingChanges,
+    /// The chunk of the SCALE-encoded [`Market`] starting from the byte `cursor`
+    /// (at most [`EXPORT_CHUNK_SIZE`] bytes).
+    ExportChunk {
+        cursor: u64,
+    },
+    /// The treasuries and their weights in basis points.
+    Treasuries,
+    /// The limit buy orders for the token.
+    LimitOrders {
+        nft_contract_id: ActorId,
+        token_id: U256,
+    },
+    /// The OTC deal with the hash.
+    Deal(H256),
+    /// The executions and the gas usage per action by the SCALE index of its variant.
+    GasUsage,
+    /// The id of the item with the slug.
+    ResolveSlug(String),
+    /// The vested proceeds of the seller.
+    VestedProceeds(ActorId),
+    /// The recent events with the sequence number greater than the given one,
+    /// so the frontends can poll the events instead of subscribing to the messages.
+    EventsSince(EventSeq),
+    /// The snapshots of the auctions sorted by the end time that end within the window
+    /// in milliseconds, including the ended auctions that aren't settled yet.
+    AuctionsEndingWithin {
+        window: u64,
+    },
+    /// The listed items with the ids (the ids of not listed items are skipped).
+    ItemsById(Vec<ItemId>),
 }
 
 #[derive(Debug, Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
+#[allow(clippy::large_enum_variant)]
 pub enum StateReply {
     AllItems(Vec<Item>),
     ItemInfo(Item),
+    FeaturedItems(Vec<Item>),
+    OrderBook(OrderBook),
+    ItemsByOwner(Vec<Item>),
+    ItemsByCollection(Vec<Item>),
+    ActiveAuctions(Vec<Item>),
+    ItemsOnSale(Vec<Item>),
+    Claimable(u128),
+    BannedActors(Vec<ActorId>),
+    FeeTiers(Vec<FeeTier>),
+    TradedVolume {
+        volume: u128,
+        /// The treasury fee in basis points.
+        fee: u16,
+    },
+    Twap(Option<u128>),
+    /// `None` if there is no auction.
+    AuctionCountdown(Option<AuctionCountdown>),
+    RoyaltyInfo(Vec<(ActorId, u128)>),
+    AuctionBounds(AuctionBounds),
+    UserActivity(UserActivity),
+    KeeperRewards {
+        /// The share of the treasury fee in basis points.
+        bounty: u16,
+        rewards: u128,
+    },
+    Metrics(MarketMetrics),
+    FailedTransactions(Vec<FailedTransaction>),
+    /// `None` if the drop doesn't exist or is sold out.
+    Drop(Option<TokenDrop>),
+    CollectionConfig(CollectionConfig),
+    StakingFees(StakingFees),
+    /// `None` if the operation doesn't exist or is done.
+    Operation(Option<Operation>),
+    Trades(Vec<(TradeId, SaleReceipt)>),
+    OrderNonces {
+        epoch: u64,
+        used_nonces: Vec<u64>,
+    },
+    PausedActions(u32),
+    Watches(Vec<Watch>),
+    TraitOffers(Vec<TraitOffer>),
+    AuctionInfo(Option<AuctionInfo>),
+    AllActiveAuctions(Vec<AuctionInfo>),
+    /// `None` if the item can't be bought by the buyer.
+    Quote(Option<Quote>),
+    /// The event the action would reply with or the message it would fail with.
+    Simulate(Result<MarketEvent, String>),
+    HeldValue(HeldValue),
+    ListingStatus(Option<ListingStatus>),
+    PendingChanges(Vec<PendingChange>),
+    ExportChunk(ExportChunk),
+    Treasuries(Vec<(ActorId, u16)>),
+    LimitOrders(Vec<LimitOrder>),
+    Deal(Option<Deal>),
+    GasUsage(Vec<(u8, GasUsage)>),
+    ResolveSlug(Option<ItemId>),
+    VestedProceeds(Vec<(VestingId, VestedProceeds)>),
+    Events(Vec<EventRecord>),
+    AuctionsEndingWithin(Vec<AuctionInfo>),
+    ItemsById(Vec<Item>),
+}
+
+/// The maximum size in bytes of the state export chunk.
+pub const EXPORT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// The part of the state export.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct ExportChunk {
+    pub data: Vec<u8>,
+    /// The cursor of the next chunk (`None` if it is the last chunk).
+    pub next_cursor: Option<u64>,
+    /// The size in bytes of the whole export.
+    pub total_len: u64,
+    /// The BLAKE2b-256 hash of the whole export. It differs between the chunks
+    /// if the state was changed during the export, so the export must be restarted.
+    pub checksum: H256,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct AuctionCountdown {
+    /// Seconds remaining until the end of the auction (0 if the auction has ended).
+    pub remaining: u64,
+    pub extensions: u32,
+}
+
+/// The auction data needed by frontends to show and bid in the auction.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct AuctionInfo {
+    pub nft_contract_id: ActorId,
+    pub token_id: U256,
+    pub seller: ActorId,
+    /// The currency of the current price.
+    pub ft_contract_id: Option<ActorId>,
+    /// The currencies accepted for bids (if it is empty then only `ft_contract_id` is accepted).
+    pub currencies: Vec<Option<ActorId>>,
+    pub current_price: u128,
+    /// `None` if no bids were made.
+    pub current_winner: Option<ActorId>,
+    /// The lowest bid in `ft_contract_id` that outbids the current price.
+    pub min_next_bid: u128,
+    pub started_at: u64,
+    pub ended_at: u64,
+    /// The bids made within `bid_period` before the end extend the auction by `bid_period`.
+    pub bid_period: u64,
+    pub extensions: u32,
+    /// The rounds of the closing window (`None` if it isn't a candle auction).
+    pub candle: Option<Candle>,
+}
+
+impl AuctionInfo {
+    /// Returns `None` if the item isn't on auction.
+    pub fn new(item: &Item) -> Option<Self> {
+        let auction = item.auction.as_ref()?;
+        Some(Self {
+            nft_contract_id: item.nft_contract_id,
+            token_id: item.token_id,
+            seller: item.owner_id,
+            ft_contract_id: item.ft_contract_id,
+            currencies: auction.currencies.clone(),
+            current_price: auction.current_price,
+            current_winner: (auction.current_winner != ActorId::zero())
+                .then_some(auction.current_winner),
+            min_next_bid: auction.current_price.saturating_add(1),
+            started_at: auction.started_at,
+            ended_at: auction.ended_at,
+            bid_period: auction.bid_period,
+            extensions: auction.extensions,
+            candle: auction.candle.clone(),
+        })
+    }
+}
+
+/// The purchase terms of the item for the buyer.
+/// The fees and royalties are estimated at the current state of the marketplace.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Quote {
+    /// The currency of the payment (`None` for a native value).
+    pub ft_contract_id: Option<ActorId>,
+    /// The amount to pay (`None` if the amount is computed from
+    /// `reference_price` at the time of purchase).
+    pub price: Option<u128>,
+    pub reference_price: Option<u128>,
+    /// The native value to attach to [`MarketAction::BuyItem`](crate::MarketAction::BuyItem),
+    /// the amount in fungible tokens must be approved to the marketplace instead.
+    pub value: u128,
+    /// The end of the flash sale if its price is applied.
+    pub flash_sale_ends_at: Option<u64>,
+    pub treasury_fee: u128,
+    /// The royalties paid out of the seller's amount.
+    pub royalties: Vec<(ActorId, u128)>,
+    /// The gate contract and the minimum balance of its tokens the buyer must hold.
+    pub gate: Option<(ActorId, u128)>,
+    /// The fees are computed from the amount received by the marketplace.
+    pub fee_on_transfer: bool,
+}
+
+#[derive(Debug, Default, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct UserActivity {
+    /// Items of the account that are on sale or on auction.
+    pub listings: Vec<Item>,
+    /// The NFT contract address, the token id and the offer with the escrowed amount.
+    pub offers: Vec<(ActorId, U256, Offer)>,
+    /// Items on auction where the account is the current winner.
+    pub winning_auctions: Vec<Item>,
+    pub claimable: u128,
+}
+
+pub fn paginate<'a>(items: impl Iterator<Item = &'a Item>, offset: u32, limit: u32) -> Vec<Item> {
+    items
+        .skip(offset as usize)
+        .take(limit as usize)
+        .cloned()
+        .collect()
+}
+
+impl Market {
+    pub fn export_chunk(&self, cursor: u64) -> ExportChunk {
+        let export = self.encode();
+        let start = (cursor as usize).min(export.len());
+        let end = start.saturating_add(EXPORT_CHUNK_SIZE).min(export.len());
+        ExportChunk {
+            data: export[start..end].to_vec(),
+            next_cursor: (end < export.len()).then_some(end as u64),
+            total_len: export.len() as u64,
+            checksum: sp_core_hashing::blake2_256(&export).into(),
+        }
+    }
+
+    pub fn quote(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        buyer: &ActorId,
+    ) -> Option<Quote> {
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        let item = self.items.get(&item_id)?;
+        if item.auction.is_some()
+            || item.locked_by.is_some()
+            || (item.price.is_none() && item.reference_price.is_none())
+            || item.owner_id == *buyer
+            || self.banned_actors.contains(buyer)
+        {
+            return None;
+        }
+        let price = sale_price(item);
+        let flash_sale_ends_at = item
+            .flash_sale
+            .filter(|_| price != item.price)
+            .map(|flash_sale| flash_sale.ends_at);
+        let (treasury_fee, royalties) = match price {
+            Some(price) => {
+                let treasury_fee = price
+                    * self.sale_fee(nft_contract_id, &item.owner_id, item.primary_sale) as u128
+                    / 10_000u128;
+                // the primary sale is royalty-free
+                let royalties = if item.primary_sale {
+                    vec![]
+                } else {
+                    self.royalty_info(nft_contract_id, token_id, price - treasury_fee)
+                };
+                (treasury_fee, royalties)
+            }
+            None => (0, vec![]),
+        };
+        Some(Quote {
+            ft_contract_id: item.ft_contract_id,
+            price,
+            reference_price: item.reference_price,
+            value: if item.ft_contract_id.is_none() {
+                price.unwrap_or_default()
+            } else {
+                0
+            },
+            flash_sale_ends_at,
+            treasury_fee,
+            royalties,
+            gate: item.gate,
+            fee_on_transfer: self.takes_transfer_fee(item.ft_contract_id),
+        })
+    }
+
+    pub fn user_activity(&self, account: &ActorId) -> UserActivity {
+        let mut activity = UserActivity {
+            claimable: self.claimable.get(account).copied().unwrap_or_default(),
+            ..Default::default()
+        };
+        for item in self.items.values() {
+            if item.owner_id == *account && (item.price.is_some() || item.auction.is_some()) {
+                activity.listings.push(item.clone());
+            }
+        }
+        // the bids and offers are looked up by the indices
+        for item_id in self.bids_by_user.get(account).into_iter().flatten() {
+            if let Some(item) = self.items.get(item_id) {
+                activity.winning_auctions.push(item.clone());
+            }
+        }
+        for (offer_id, item_id) in self.offers_by_user.get(account).into_iter().flatten() {
+            if let Some(item) = self.items.get(item_id) {
+                activity.offers.extend(
+                    item.offers
+                        .iter()
+                        .find(|offer| offer.offer_id == *offer_id)
+                        .map(|offer| (item.nft_contract_id, item.token_id, offer.clone())),
+                );
+            }
+        }
+        activity
+    }
 }
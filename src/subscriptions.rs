@@ -0,0 +1,53 @@
+use crate::{Market, MarketEvent};
+use gstd::{exec, msg, ActorId};
+
+/// The gas limit for forwarding an event to one subscriber.
+pub const NOTIFICATION_GAS: u64 = 1_000_000_000;
+const MAX_SUBSCRIPTIONS: usize = 10;
+
+impl Market {
+    /// Subscribes the caller to sale, listing and auction events of the collection.
+    /// Requirements:
+    /// * The NFT contract must be approved
+    /// * The caller can't be subscribed to more than `MAX_SUBSCRIPTIONS` collections
+    pub fn subscribe_collection(&mut self, nft_contract_id: &ActorId) {
+        self.check_approved_nft_contract(nft_contract_id);
+        let subscriptions = self
+            .subscribers
+            .values()
+            .filter(|subscribers| subscribers.contains(&msg::source()))
+            .count();
+        let subscribers = self.subscribers.entry(*nft_contract_id).or_default();
+        if subscriptions >= MAX_SUBSCRIPTIONS && !subscribers.contains(&msg::source()) {
+            panic!("The caller has the maximum number of subscriptions");
+        }
+        subscribers.insert(msg::source());
+    }
+
+    pub fn unsubscribe_collection(&mut self, nft_contract_id: &ActorId) {
+        if let Some(subscribers) = self.subscribers.get_mut(nft_contract_id) {
+            subscribers.remove(&msg::source());
+            if subscribers.is_empty() {
+                self.subscribers.remove(nft_contract_id);
+            }
+        }
+    }
+
+    /// Forwards the event to the collection subscribers
+    /// while the message has enough gas left to finish the trade.
+    pub fn notify_subscribers(&self, nft_contract_id: &ActorId, event: &MarketEvent) {
+        for subscriber in self.subscribers.get(nft_contract_id).into_iter().flatten() {
+            if !has_notification_gas() {
+                break;
+            }
+            // the failed notification mustn't affect the trade
+            let _ = msg::send_with_gas(*subscriber, event, NOTIFICATION_GAS, 0);
+        }
+    }
+}
+
+/// Whether one more notification leaves the gas for the rest of the message,
+/// so the number of subscribers and watchers can't make the trade run out of gas.
+pub fn has_notification_gas() -> bool {
+    exec::gas_available() > 2 * NOTIFICATION_GAS
+}
@@ -0,0 +1,39 @@
+use crate::Market;
+use gstd::{exec, msg, prelude::*, MessageId};
+use market_io::*;
+
+/// The gas available at the start of the action handling.
+pub struct GasMeter {
+    /// The SCALE index of the action variant.
+    variant: u8,
+    message_id: MessageId,
+    gas_available: u64,
+}
+
+impl GasMeter {
+    pub fn start(action: &MarketAction) -> Self {
+        Self {
+            variant: action.encode()[0],
+            message_id: msg::id(),
+            gas_available: exec::gas_available(),
+        }
+    }
+}
+
+impl Market {
+    /// Records the execution of the action and the gas it burned.
+    ///
+    /// The gas is measured within one message, so the actions completed
+    /// in the replies of other programs are counted without the gas.
+    /// The failed actions aren't recorded since their changes are reverted.
+    pub fn record_gas_usage(&mut self, meter: GasMeter) {
+        let usage = self.gas_usage.entry(meter.variant).or_default();
+        usage.executions = usage.executions.saturating_add(1);
+        if msg::id() == meter.message_id {
+            let gas_burned = meter.gas_available.saturating_sub(exec::gas_available());
+            usage.measured = usage.measured.saturating_add(1);
+            usage.total_gas = usage.total_gas.saturating_add(gas_burned);
+            usage.max_gas = usage.max_gas.max(gas_burned);
+        }
+    }
+}
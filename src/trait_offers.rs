@@ -0,0 +1,225 @@
+use crate::{
+    events, metadata_messages::has_trait, nft_messages::nft_approve, payment::*,
+    validation::market_err, ItemId, Market, MarketErr, MarketEvent, OrderId, TokenTrait,
+    TraitOffer, MAX_METADATA_LEN,
+};
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
+
+impl Market {
+    /// Sets the metadata program of the collection.
+    /// Requirements:
+    /// * Only admin can set metadata programs
+    pub fn set_metadata_program(
+        &mut self,
+        nft_contract_id: &ActorId,
+        metadata_program: Option<ActorId>,
+    ) {
+        self.check_admin();
+        let config = self.collection_configs.entry(*nft_contract_id).or_default();
+        config.metadata_program = metadata_program;
+        if config == &Default::default() {
+            self.collection_configs.remove(nft_contract_id);
+        }
+    }
+
+    fn metadata_program(&self, nft_contract_id: &ActorId) -> ActorId {
+        self.collection_configs
+            .get(nft_contract_id)
+            .and_then(|config| config.metadata_program)
+            .expect("The collection has no metadata program")
+    }
+
+    pub async fn add_trait_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        token_trait: TokenTrait,
+        price: u128,
+    ) {
+        self.check_not_banned(&msg::source());
+        self.check_approved_nft_contract(nft_contract_id);
+        self.check_approved_ft_contract(ft_contract_id);
+        self.metadata_program(nft_contract_id);
+        if token_trait.name.len() + token_trait.value.len() > MAX_METADATA_LEN {
+            panic!("The trait can't exceed {MAX_METADATA_LEN} bytes");
+        }
+        if price == 0 {
+            panic!("price can't be equal to zero");
+        }
+        self.check_price_bounds(ft_contract_id, price);
+        check_attached_value(ft_contract_id, price);
+        self.return_change(ft_contract_id, price);
+
+        // transfer payment from the buyer to the marketplace contract
+        // the offer is made at the received amount
+        let price = self
+            .escrow_payment(&msg::source(), ft_contract_id, price)
+            .await
+            .expect("Error in escrowing the offer payment");
+
+        let offer = TraitOffer {
+            offer_id: self.next_order_id(),
+            owner: msg::source(),
+            ft_contract_id,
+            price,
+            token_trait,
+        };
+        self.trait_offers
+            .entry(*nft_contract_id)
+            .or_default()
+            .push(offer.clone());
+        events::reply(
+            MarketEvent::TraitOfferAdded {
+                nft_contract_id: *nft_contract_id,
+                offer,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::TraitOfferAdded]");
+    }
+
+    /// Sells the caller's token to the author of the trait offer.
+    /// Requirements:
+    /// * The metadata program must report that the token has the trait
+    /// * The NFT contract must approve the marketplace on behalf of the caller
+    /// * There must be no ongoing auction on the token
+    ///
+    /// The offer is taken only after the replies, so it stays intact if the checks fail.
+    pub async fn accept_trait_offer(
+        &mut self,
+        nft_contract_id: &ActorId,
+        offer_id: OrderId,
+        token_id: U256,
+    ) {
+        self.check_not_banned(&msg::source());
+        let item_id = ItemId::new(*nft_contract_id, token_id);
+        self.on_auction(&item_id);
+        self.check_not_in_drop(nft_contract_id, token_id);
+        let token_trait = self
+            .trait_offer(nft_contract_id, offer_id)
+            .token_trait
+            .clone();
+        let metadata_program = self.metadata_program(nft_contract_id);
+
+        match has_trait(&metadata_program, nft_contract_id, token_id, &token_trait).await {
+            Ok(true) => (),
+            Ok(false) => panic!("The token doesn't have the trait"),
+            Err(_) => panic!("Error in querying the metadata program"),
+        }
+        // approve nft to trade on the marketplace
+        if let Err(err) = nft_approve(nft_contract_id, &exec::program_id(), token_id).await {
+            market_err(MarketErr::NftCall(err));
+        }
+
+        // the offer could be withdrawn or accepted while waiting for the replies
+        self.on_auction(&item_id);
+        let offer = self.take_trait_offer(nft_contract_id, offer_id);
+        let seller = msg::source();
+        if self
+            .execute_sale(
+                &exec::program_id(),
+                nft_contract_id,
+                offer.ft_contract_id,
+                token_id,
+                &seller,
+                &offer.owner,
+                offer.price,
+                None,
+                None,
+            )
+            .await
+            .is_err()
+        {
+            self.restore_trait_offer(nft_contract_id, offer);
+            events::reply(
+                MarketEvent::OrderTransactionFailed {
+                    nft_contract_id: *nft_contract_id,
+                    order_id: offer_id,
+                },
+                0,
+            )
+            .expect("Error in reply [MarketEvent::OrderTransactionFailed]");
+            return;
+        }
+
+        let event = MarketEvent::TraitOfferAccepted {
+            nft_contract_id: *nft_contract_id,
+            offer_id,
+            token_id,
+            seller,
+            buyer: offer.owner,
+            price: offer.price,
+        };
+        self.notify_subscribers(nft_contract_id, &event);
+        events::reply(event, 0).expect("Error in reply [MarketEvent::TraitOfferAccepted]");
+    }
+
+    pub async fn withdraw_trait_offer(&mut self, nft_contract_id: &ActorId, offer_id: OrderId) {
+        if self.trait_offer(nft_contract_id, offer_id).owner != msg::source() {
+            panic!("only the offer owner can withdraw the offer");
+        }
+        let offer = self.take_trait_offer(nft_contract_id, offer_id);
+
+        // return the escrowed payment
+        if self
+            .transfer_payment(
+                &exec::program_id(),
+                &offer.owner,
+                offer.ft_contract_id,
+                offer.price,
+            )
+            .await
+            .is_err()
+        {
+            self.restore_trait_offer(nft_contract_id, offer);
+            events::reply(
+                MarketEvent::OrderTransactionFailed {
+                    nft_contract_id: *nft_contract_id,
+                    order_id: offer_id,
+                },
+                0,
+            )
+            .expect("Error in reply [MarketEvent::OrderTransactionFailed]");
+            return;
+        }
+
+        events::reply(
+            MarketEvent::TraitOfferWithdrawn {
+                nft_contract_id: *nft_contract_id,
+                offer_id,
+            },
+            0,
+        )
+        .expect("Error in reply [MarketEvent::TraitOfferWithdrawn]");
+    }
+
+    fn trait_offer(&self, nft_contract_id: &ActorId, offer_id: OrderId) -> &TraitOffer {
+        self.trait_offers
+            .get(nft_contract_id)
+            .and_then(|offers| offers.iter().find(|offer| offer.offer_id == offer_id))
+            .expect("The offer does not exist")
+    }
+
+    fn take_trait_offer(&mut self, nft_contract_id: &ActorId, offer_id: OrderId) -> TraitOffer {
+        let offers = self
+            .trait_offers
+            .get_mut(nft_contract_id)
+            .expect("The offer does not exist");
+        let index = offers
+            .iter()
+            .position(|offer| offer.offer_id == offer_id)
+            .expect("The offer does not exist");
+        let offer = offers.remove(index);
+        if offers.is_empty() {
+            self.trait_offers.remove(nft_contract_id);
+        }
+        offer
+    }
+
+    fn restore_trait_offer(&mut self, nft_contract_id: &ActorId, offer: TraitOffer) {
+        let offers = self.trait_offers.entry(*nft_contract_id).or_default();
+        offers.push(offer);
+        offers.sort_by_key(|offer| offer.offer_id);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{MarketErr, MAX_METADATA_LEN};
+use gstd::{exec, prelude::*, ActorId};
+
+/// Panics with the indicated error, so it is returned to the sender in the error reply.
+pub fn market_err(err: MarketErr) -> ! {
+    panic!("{err:?}")
+}
+
+pub fn check_not_zero(id: &ActorId) {
+    if *id == ActorId::zero() {
+        market_err(MarketErr::ZeroAddress);
+    }
+}
+
+pub fn check_treasury(treasury_id: &ActorId) {
+    check_not_zero(treasury_id);
+    if *treasury_id == exec::program_id() {
+        market_err(MarketErr::TreasuryIsMarketplace);
+    }
+}
+
+/// Checks the treasuries and that their weights sum to 100%.
+pub fn check_treasuries(treasuries: &[(ActorId, u16)]) {
+    if treasuries.is_empty() {
+        return;
+    }
+    for (treasury_id, _) in treasuries {
+        check_treasury(treasury_id);
+    }
+    if treasuries
+        .iter()
+        .map(|(_, weight)| *weight as u32)
+        .sum::<u32>()
+        != 10_000
+    {
+        panic!("Treasury weights must sum to 10000 basis points");
+    }
+}
+
+/// Checks that the account doesn't deal with itself.
+pub fn check_not_owner(owner_id: &ActorId, account: &ActorId, err: MarketErr) {
+    if owner_id == account {
+        market_err(err);
+    }
+}
+
+pub fn check_metadata(metadata: &Option<Vec<u8>>) {
+    if metadata.as_ref().map(Vec::len).unwrap_or_default() > MAX_METADATA_LEN {
+        panic!("Metadata can't exceed {MAX_METADATA_LEN} bytes");
+    }
+}
+
+/// Checks that the payees are not zero addresses and their shares sum to 100%.
+pub fn check_payees(payees: &[(ActorId, u16)]) {
+    if payees.is_empty() {
+        return;
+    }
+    for (payee, _) in payees {
+        check_not_zero(payee);
+    }
+    if payees.iter().map(|(_, share)| *share as u32).sum::<u32>() != 10_000 {
+        panic!("Payee shares must sum to 10000 basis points");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{events, Market};
+use gstd::{exec, msg, ActorId};
+use market_io::*;
+
+/// The maximum number of instalments of the vested proceeds.
+pub const MAX_INSTALMENTS: u32 = 100;
+
+pub fn check_vesting(vesting: &Vesting) {
+    if vesting.upfront > 10_000 {
+        panic!("The upfront share can't exceed 10000 basis points");
+    }
+    if vesting.instalments == 0 || vesting.instalments > MAX_INSTALMENTS {
+        panic!("The vesting must have from 1 to {MAX_INSTALMENTS} instalments");
+    }
+    if vesting.period == 0 {
+        panic!("The instalment period can't be equal to zero");
+    }
+}
+
+impl Market {
+    /// Keeps the seller's proceeds except the upfront share on the marketplace
+    /// until the instalments unlock.
+    pub fn vest_proceeds(
+        &mut self,
+        seller: &ActorId,
+        ft_contract_id: Option<ActorId>,
+        payouts: &mut [(ActorId, u128)],
+        vesting: &Vesting,
+    ) {
+        let proceeds = match payouts.iter_mut().find(|(account, _)| account == seller) {
+            Some((_, proceeds)) => proceeds,
+            None => return,
+        };
+        let vested = *proceeds - *proceeds * vesting.upfront as u128 / 10_000u128;
+        if vested == 0 {
+            return;
+        }
+        *proceeds -= vested;
+        let vesting_id = self.vesting_id;
+        self.vesting_id = self.vesting_id.wrapping_add(1);
+        self.vested_proceeds.insert(
+            vesting_id,
+            VestedProceeds {
+                seller: *seller,
+                ft_contract_id,
+                amount: vested,
+                claimed: 0,
+                starts_at: exec::block_timestamp(),
+                instalments: vesting.instalments,
+                period: vesting.period,
+            },
+        );
+    }
+
+    /// Pays the seller the unlocked instalments of the vested proceeds.
+    pub async fn claim_vested(&mut self, vesting_id: VestingId) {
+        let proceeds = self
+            .vested_proceeds
+            .get(&vesting_id)
+            .expect("The vested proceeds do not exist");
+        if proceeds.seller != msg::source() {
+            panic!("Only the seller can claim the vested proceeds");
+        }
+        let amount = proceeds.unlocked(exec::block_timestamp()) - proceeds.claimed;
+        if amount == 0 {
+            panic!("No instalment is unlocked");
+        }
+        let ft_contract_id = proceeds.ft_contract_id;
+        let done = proceeds.claimed + amount == proceeds.amount;
+
+        // the claim is recorded before the transfer, so it can't be claimed twice
+        if let Some(proceeds) = self.vested_proceeds.get_mut(&vesting_id) {
+            proceeds.claimed += amount;
+        }
+        if self
+            .transfer_payment(&exec::program_id(), &msg::source(), ft_contract_id, amount)
+            .await
+            .is_err()
+        {
+            // the instalments stay claimable
+            if let Some(proceeds) = self.vested_proceeds.get_mut(&vesting_id) {
+                proceeds.claimed -= amount;
+            }
+            events::reply(MarketEvent::VestedClaimFailed(vesting_id), 0)
+                .expect("Error in reply [MarketEvent::VestedClaimFailed]");
+            return;
+        }
+        if done {
+            self.vested_proceeds.remove(&vesting_id);
+        }
+        events::reply(MarketEvent::VestedClaimed { vesting_id, amount }, 0)
+            .expect("Error in reply [MarketEvent::VestedClaimed]");
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    subscriptions::{has_notification_gas, NOTIFICATION_GAS},
+    validation::check_not_zero,
+    Market, MarketEvent, Watch,
+};
+use gstd::{exec, msg, ActorId};
+use primitive_types::U256;
+
+const MAX_WATCHES: usize = 20;
+
+impl Market {
+    /// Adds the caller's watch or replaces the caller's watch of the same token or collection.
+    /// Requirements:
+    /// * The NFT contract must be approved
+    /// * The caller can't have more than `MAX_WATCHES` watches
+    /// * The notified program can't be the zero address or the marketplace itself
+    pub fn add_watch(&mut self, watch: Watch) {
+        self.check_approved_nft_contract(&watch.nft_contract_id);
+        self.check_approved_ft_contract(watch.ft_contract_id);
+        if let Some(notify_program) = &watch.notify_program {
+            check_not_zero(notify_program);
+            if *notify_program == exec::program_id() {
+                panic!("The marketplace can't be notified of the watch");
+            }
+        }
+        let watches = self.watches.entry(watch.nft_contract_id).or_default();
+        watches.retain(|(watcher, existing)| {
+            *watcher != msg::source() || existing.token_id != watch.token_id
+        });
+        let caller_watches = self
+            .watches
+            .values()
+            .flatten()
+            .filter(|(watcher, _)| *watcher == msg::source())
+            .count();
+        if caller_watches >= MAX_WATCHES {
+            panic!("The caller has the maximum number of watches");
+        }
+        self.watches
+            .entry(watch.nft_contract_id)
+            .or_default()
+            .push((msg::source(), watch));
+    }
+
+    pub fn remove_watch(&mut self, nft_contract_id: &ActorId, token_id: Option<U256>) {
+        if let Some(watches) = self.watches.get_mut(nft_contract_id) {
+            watches
+                .retain(|(watcher, watch)| *watcher != msg::source() || watch.token_id != token_id);
+            if watches.is_empty() {
+                self.watches.remove(nft_contract_id);
+            }
+        }
+    }
+
+    /// Sends [`MarketEvent::WatchTriggered`] to the watchers of the listed item
+    /// (and to their programs) if the price doesn't exceed the watch threshold
+    /// while the message has enough gas left to finish the listing.
+    pub fn notify_watchers(
+        &self,
+        nft_contract_id: &ActorId,
+        token_id: U256,
+        ft_contract_id: Option<ActorId>,
+        price: Option<u128>,
+    ) {
+        let price = match price {
+            Some(price) => price,
+            None => return,
+        };
+        for (watcher, watch) in self.watches.get(nft_contract_id).into_iter().flatten() {
+            if watch.token_id.is_some_and(|watched| watched != token_id) {
+                continue;
+            }
+            if let Some(max_price) = watch.max_price {
+                if watch.ft_contract_id != ft_contract_id || price > max_price {
+                    continue;
+                }
+            }
+            if !has_notification_gas() {
+                break;
+            }
+            let event = MarketEvent::WatchTriggered {
+                watcher: *watcher,
+                nft_contract_id: *nft_contract_id,
+                token_id,
+                ft_contract_id,
+                price,
+            };
+            // the failed notification mustn't affect the listing
+            let _ = msg::send_with_gas(*watcher, &event, NOTIFICATION_GAS, 0);
+            if let Some(notify_program) = watch.notify_program {
+                let _ = msg::send_with_gas(notify_program, &event, NOTIFICATION_GAS, 0);
+            }
+        }
+    }
+}
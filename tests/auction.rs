@@ -48,6 +48,8 @@ fn start_auction(
             min_price,
             bid_period,
             duration,
+            beneficiary: None,
+            auto_seed_from_offers: false,
         },
     )
 }
@@ -58,6 +60,7 @@ fn bid(market: &Program, user: u64, price: u128) -> RunResult {
         MarketAction::AddBid {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            ft_contract_id: None,
             price,
         },
         price,
@@ -70,6 +73,7 @@ fn bid_with_token(market: &Program, user: u64, price: u128) -> RunResult {
         MarketAction::AddBid {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
             price,
         },
     )
@@ -87,7 +91,13 @@ fn create_auction() {
         MarketEvent::AuctionCreated {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            owner: USERS[0].into(),
+            beneficiary: None,
             price: 1000,
+            ft_contract_id: None,
+            superseded_price: None,
+            suspended_offers: 0,
+            seeded_offer: None,
         }
         .encode()
     )));
@@ -131,10 +141,10 @@ fn add_bid() {
     let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
     assert!(!res.main_failed());
 
-    sys.mint_to(USERS[0], 100_001);
-    let res = bid(&market, USERS[0], 100_001);
+    sys.mint_to(USERS[1], 100_001);
+    let res = bid(&market, USERS[1], 100_001);
     assert!(res.contains(&(
-        USERS[0],
+        USERS[1],
         MarketEvent::BidAdded {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
@@ -154,14 +164,14 @@ fn add_bid_failures() {
     let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
     assert!(!res.main_failed());
     // must fail since the price is equal to the current bid price
-    sys.mint_to(USERS[0], 200_000);
-    let res = bid(&market, USERS[0], 100_000);
+    sys.mint_to(USERS[1], 200_000);
+    let res = bid(&market, USERS[1], 100_000);
     assert!(res.main_failed());
 
     sys.spend_blocks(86400001);
 
     // must fail since the auction has ended
-    let res = bid(&market, USERS[0], 200_000);
+    let res = bid(&market, USERS[1], 200_000);
     assert!(res.main_failed());
 }
 
@@ -175,7 +185,7 @@ fn settle_auction() {
     assert!(!res.main_failed());
 
     // Users add bids
-    USERS.iter().enumerate().for_each(|(i, user)| {
+    USERS.iter().skip(1).enumerate().for_each(|(i, user)| {
         sys.mint_to(*user, 100_001 + i as u128);
         let res = bid(&market, *user, 100_001 + i as u128);
         assert!(!res.main_failed());
@@ -196,12 +206,23 @@ fn settle_auction() {
         MarketEvent::AuctionSettled {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            price: 100004,
+            price: 100_003,
         }
         .encode()
     )));
 
     // Checks NFT item on the marketplace
+    let item_info = MarketEvent::ItemInfo(Item {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        owner_id: USERS[3].into(),
+        ft_contract_id: None,
+        price: None,
+        auction: None,
+        offers: vec![],
+        ..Default::default()
+    })
+    .encode();
     let res = market.send(
         USERS[0],
         MarketAction::Item {
@@ -209,17 +230,14 @@ fn settle_auction() {
             token_id: 0.into(),
         },
     );
-    assert!(res.contains(&(
+    assert!(res.contains(&(USERS[0], item_info.clone())));
+
+    // the same item addressed by its id
+    let res = market.send(
         USERS[0],
-        MarketEvent::ItemInfo(Item {
-            owner_id: USERS[3].into(),
-            ft_contract_id: None,
-            price: None,
-            auction: None,
-            offers: vec![],
-        })
-        .encode()
-    )));
+        MarketAction::ItemById(ItemId::new(2.into(), 0.into())),
+    );
+    assert!(res.contains(&(USERS[0], item_info)));
 }
 
 #[test]
@@ -292,6 +310,10 @@ fn settle_auction_failures() {
             ft_contract_id: None,
             token_id: 1.into(),
             price: None,
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
         },
     );
     assert!(!res.main_failed());
@@ -331,7 +353,7 @@ fn auction_with_ft_token() {
     });
 
     // Users add bids
-    USERS.iter().enumerate().for_each(|(i, user)| {
+    USERS.iter().skip(1).enumerate().for_each(|(i, user)| {
         let res = bid_with_token(&market, *user, 10_100 + 100 * i as u128);
         assert!(!res.main_failed());
     });
@@ -351,23 +373,23 @@ fn auction_with_ft_token() {
         MarketEvent::AuctionSettled {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            price: 10400,
+            price: 10_300,
         }
         .encode()
     )));
 
     // check the balance of treasury account
     let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
-    assert!(res.contains(&(USERS[0], FTEvent::Balance(104).encode())));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(103).encode())));
 
     // check the balance of seller
     let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
     println!("{:?}", res.decoded_log::<FTEvent>());
-    assert!(res.contains(&(USERS[0], FTEvent::Balance(110_296).encode())));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(110_197).encode())));
 
     // check the balance of buyer
     let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[3].into()));
-    assert!(res.contains(&(USERS[0], FTEvent::Balance(89_600).encode())));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(89_700).encode())));
 
     // check the balances of user who don't win auctions
     let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
@@ -384,12 +406,752 @@ fn auction_with_ft_token() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[3].into(),
             ft_contract_id: Some(1.into()),
             price: None,
             auction: None,
             offers: vec![],
+            ..Default::default()
         })
         .encode()
     )));
 }
+
+#[test]
+fn late_bid_extends_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    // the auction lasts only one bid period, so any bid is a late one
+    let res = start_auction(&market, None, 100_000, 60_000, 60_000);
+    assert!(!res.main_failed());
+
+    sys.mint_to(USERS[1], 100_001);
+    let res = bid(&market, USERS[1], 100_001);
+    assert!(!res.main_failed());
+    assert!(res
+        .decoded_log::<MarketEvent>()
+        .iter()
+        .any(|event| matches!(event, MarketEvent::AuctionExtended { .. })));
+}
+
+#[test]
+fn early_bid_does_not_extend_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+
+    sys.mint_to(USERS[1], 100_001);
+    let res = bid(&market, USERS[1], 100_001);
+    assert!(!res.main_failed());
+    assert!(!res
+        .decoded_log::<MarketEvent>()
+        .iter()
+        .any(|event| matches!(event, MarketEvent::AuctionExtended { .. })));
+}
+
+#[test]
+fn auction_bounds() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let bounds = AuctionBounds {
+        min_duration: 60_000,
+        max_duration: 3_600_000,
+        min_bid_period: 60_000,
+    };
+
+    // must fail since only admin can set the bounds
+    let res = market.send(USERS[1], MarketAction::SetAuctionBounds(bounds));
+    assert!(res.main_failed());
+
+    // must fail since the minimum duration is greater than the maximum one
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetAuctionBounds(AuctionBounds {
+            min_duration: 3_600_001,
+            ..bounds
+        }),
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::SetAuctionBounds(bounds));
+    assert!(res.log().is_empty());
+
+    // must fail since the duration exceeds the maximum one
+    let res = start_auction(&market, None, 1_000, 60_000, 3_600_001);
+    assert!(res.main_failed());
+
+    let res = start_auction(&market, None, 1_000, 60_000, 3_600_000);
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn outbid_value_is_claimable() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 1_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+
+    sys.mint_to(USERS[1], 1_001);
+    let res = bid(&market, USERS[1], 1_001);
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], 1_002);
+    let res = bid(&market, USERS[2], 1_002);
+    assert!(!res.main_failed());
+
+    // the outbid value stays on the marketplace until the bidder claims it
+    assert_eq!(sys.balance_of(USERS[1]), 0);
+    let res = market.send(USERS[1], MarketAction::Claim);
+    assert!(res.contains(&(USERS[1], MarketEvent::Claimed { amount: 1_001 }.encode())));
+    assert_eq!(sys.balance_of(USERS[1]), 1_001);
+}
+
+#[test]
+fn keeper_bounty() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetKeeperBounty(5_000));
+    assert!(res.main_failed());
+    // must fail since the bounty exceeds the treasury fee
+    let res = market.send(USERS[0], MarketAction::SetKeeperBounty(10_001));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetKeeperBounty(5_000));
+    assert!(res.log().is_empty());
+
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[1], 200_000);
+    let res = bid(&market, USERS[1], 200_000);
+    assert!(!res.main_failed());
+
+    sys.spend_blocks(86400000);
+
+    // the keeper receives a half of the treasury fee
+    let res = market.send(
+        USERS[2],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::KeeperRewarded {
+            keeper: USERS[2].into(),
+            reward: 1_000,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[2]), 1_000);
+    assert_eq!(sys.balance_of(TREASURY_ID), 1_000);
+}
+
+#[test]
+fn settle_auction_by_stranger() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[1], 100_001);
+    let res = bid(&market, USERS[1], 100_001);
+    assert!(!res.main_failed());
+
+    sys.spend_blocks(86400000);
+
+    // the payment goes to the seller and not to the caller
+    let res = market.send(
+        USERS[3],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[3],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 100_001,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[0]), 99_001);
+    assert_eq!(sys.balance_of(USERS[3]), 0);
+}
+
+fn nft_owner(nft: &Program) -> ActorId {
+    let res = nft.send(USERS[0], NFTAction::Owner { token_id: 0.into() });
+    res.decoded_log::<NFTEvent>()
+        .into_iter()
+        .find_map(|event| match event {
+            NFTEvent::Owner { owner, .. } => Some(owner),
+            _ => None,
+        })
+        .expect("Unable to get the NFT owner")
+}
+
+#[test]
+fn nft_is_held_during_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+    assert_eq!(nft_owner(&nft), 3.into());
+    sys.mint_to(USERS[1], 100_001);
+    let res = bid(&market, USERS[1], 100_001);
+    assert!(!res.main_failed());
+
+    // must fail since the NFT is held by the marketplace
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Transfer {
+            to: USERS[2].into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    sys.spend_blocks(86400000);
+
+    let res = market.send(
+        USERS[3],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[3],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 100_001,
+        }
+        .encode()
+    )));
+    assert_eq!(nft_owner(&nft), USERS[1].into());
+}
+
+#[test]
+fn nft_is_returned_on_cancellation() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+
+    sys.spend_blocks(86400001);
+    let res = market.send(
+        USERS[1],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::AuctionCancelled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+    assert_eq!(nft_owner(&nft), USERS[0].into());
+
+    // the seller can start a new auction once the NFT is returned
+    let res = start_auction(&market, None, 100_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn multi_currency_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    let create_auction = || {
+        market.send(
+            USERS[0],
+            MarketAction::CreateMultiCurrencyAuction {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                currencies: vec![Some(1.into())],
+                token_id: 0.into(),
+                min_price: 1_000,
+                bid_period: 60_000,
+                duration: 86_400_000,
+                beneficiary: None,
+                auto_seed_from_offers: false,
+            },
+        )
+    };
+
+    // must fail since the exchange rates aren't set
+    assert!(create_auction().main_failed());
+
+    // must fail since the caller isn't the admin
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetExchangeRate {
+            ft_contract_id: None,
+            rate: Some(2),
+        },
+    );
+    assert!(res.main_failed());
+    // one unit of the native value is worth two tokens
+    for (ft_contract_id, rate) in [(None, 2), (Some(1.into()), 1)] {
+        let res = market.send(
+            USERS[0],
+            MarketAction::SetExchangeRate {
+                ft_contract_id,
+                rate: Some(rate),
+            },
+        );
+        assert!(res.log().is_empty());
+    }
+    assert!(!create_auction().main_failed());
+
+    let res = ft.send(USERS[1], FTAction::Mint(100_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::AddBidInCurrency {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+            price: 3_000,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // must fail since the bid value is less than the current one
+    sys.mint_to(USERS[2], 3_000);
+    let res = bid(&market, USERS[2], 1_400);
+    assert!(res.main_failed());
+    let res = bid(&market, USERS[2], 1_600);
+    assert!(!res.main_failed());
+
+    // the previous winner is refunded in tokens
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(100_000).encode())));
+
+    sys.spend_blocks(86_400_000);
+    let res = market.send(
+        USERS[0],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 1_600,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[0]), 1_584);
+}
+
+#[test]
+fn max_bids() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, Some(1.into()), 1_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+    for (user, amount) in [(USERS[1], 10_000), (USERS[2], 30_000)] {
+        let res = ft.send(user, FTAction::Mint(amount));
+        assert!(!res.main_failed());
+    }
+    let max_bid = |user: u64, max_price: u128| {
+        market.send(
+            user,
+            MarketAction::AddMaxBid {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                max_price,
+            },
+        )
+    };
+    let balance = |user: u64, amount: u128| {
+        let res = ft.send(USERS[0], FTAction::BalanceOf(user.into()));
+        assert!(res.contains(&(USERS[0], FTEvent::Balance(amount).encode())));
+    };
+
+    // the maximum is escrowed, but only the lowest outbidding price is revealed
+    let res = max_bid(USERS[1], 10_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::BidAdded {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 1_001,
+        }
+        .encode()
+    )));
+    balance(USERS[1], 0);
+
+    // the bid is outbid automatically
+    let res = bid_with_token(&market, USERS[2], 5_000);
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::BidOutbid {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 5_001,
+        }
+        .encode()
+    )));
+    balance(USERS[2], 30_000);
+
+    // the higher maximum outbids the previous one by the increment
+    let res = max_bid(USERS[2], 20_000);
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::BidAdded {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 10_001,
+        }
+        .encode()
+    )));
+    balance(USERS[1], 10_000);
+    // must fail since the caller is already the winner
+    assert!(max_bid(USERS[2], 25_000).main_failed());
+
+    // the winner pays the effective price and the rest of the maximum is returned
+    sys.spend_blocks(86_400_000);
+    let res = market.send(
+        USERS[0],
+        MarketAction::SettleAuction {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 10_001,
+        }
+        .encode()
+    )));
+    balance(USERS[2], 19_999);
+}
+
+#[test]
+fn bid_currency_must_match_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, Some(1.into()), 10_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+    let res = ft.send(USERS[1], FTAction::Mint(100_000));
+    assert!(!res.main_failed());
+
+    // the bid in native value is rejected by the auction in fungible tokens
+    sys.mint_to(USERS[1], 10_100);
+    let res = bid(&market, USERS[1], 10_100);
+    assert!(res.main_failed());
+
+    // the value attached to the bid in fungible tokens is returned
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::AddBid {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+            price: 10_100,
+        },
+        10_100,
+    );
+    assert!(!res.main_failed());
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(89_900).encode())));
+    assert_eq!(sys.balance_of(USERS[1]), 10_100);
+}
+
+#[test]
+fn overpaid_bid_change() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = start_auction(&market, None, 1_000, 60_000, 86_400_000);
+    assert!(!res.main_failed());
+
+    sys.mint_to(USERS[1], 3_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::AddBid {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: 2_000,
+        },
+        3_000,
+    );
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 1_000);
+}
+
+#[test]
+fn auction_supersedes_fixed_price() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(10_000));
+    sys.mint_to(USERS[1], 5_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 5_000,
+            recipient: None,
+            metadata: None,
+        },
+        5_000,
+    );
+    assert!(!res.main_failed());
+
+    let res = start_auction(&market, Some(1.into()), 1000, 60_000, 86_400_000);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionCreated {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            owner: USERS[0].into(),
+            beneficiary: None,
+            price: 1000,
+            ft_contract_id: Some(1.into()),
+            superseded_price: Some((None, 10_000)),
+            suspended_offers: 1,
+            seeded_offer: None,
+        }
+        .encode()
+    )));
+
+    // must fail since the offers can't be accepted during the auction
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    );
+    assert!(res.main_failed());
+
+    // the offer can be withdrawn
+    let res = market.send(
+        USERS[1],
+        MarketAction::Withdraw {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 5_000);
+}
+
+#[test]
+fn auction_seeded_from_offer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(10_000));
+    for (user, price) in [(USERS[1], 2_000), (USERS[2], 3_000)] {
+        sys.mint_to(user, price);
+        let res = market.send_with_value(
+            user,
+            MarketAction::AddOffer {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                price,
+                recipient: None,
+                metadata: None,
+            },
+            price,
+        );
+        assert!(!res.main_failed());
+    }
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 60_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: true,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionCreated {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            owner: USERS[0].into(),
+            beneficiary: None,
+            price: 1_000,
+            ft_contract_id: None,
+            superseded_price: Some((None, 10_000)),
+            suspended_offers: 1,
+            seeded_offer: Some(1),
+        }
+        .encode()
+    )));
+    // the author of the best offer is the current winner
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::BidAdded {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 3_000,
+        }
+        .encode()
+    )));
+
+    // must fail since the bid doesn't exceed the seeded price
+    sys.mint_to(USERS[3], 7_000);
+    let res = bid(&market, USERS[3], 3_000);
+    assert!(res.main_failed());
+
+    // the outbid offer escrow is refunded
+    let res = bid(&market, USERS[3], 4_000);
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), 3_000);
+}
+
+#[test]
+fn auction_not_seeded_from_balance_offer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(10_000));
+    sys.mint_to(USERS[1], 5_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::DepositBalance {
+            ft_contract_id: None,
+            amount: 5_000,
+        },
+        5_000,
+    );
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::AddBalanceOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 5_000,
+            recipient: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], 3_000);
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 3_000,
+            recipient: None,
+            metadata: None,
+        },
+        3_000,
+    );
+    assert!(!res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 60_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: true,
+        },
+    );
+    assert!(!res.main_failed());
+    // the balance offer isn't escrowed, so the best escrowed offer seeds the auction
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::BidAdded {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 3_000,
+        }
+        .encode()
+    )));
+    assert!(!res.contains(&(
+        USERS[1],
+        MarketEvent::BidAdded {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 5_000,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn listing_status_at_auction_end() {
+    let item = Item {
+        auction: Some(Auction {
+            ended_at: 1_000,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(item.status(999), ListingStatus::InAuction);
+    // a bid is still accepted at the end time, so the auction isn't expired yet
+    assert_eq!(item.status(1_000), ListingStatus::InAuction);
+    assert_eq!(item.status(1_001), ListingStatus::Expired);
+}
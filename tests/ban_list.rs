@@ -0,0 +1,166 @@
+use gear_lib::non_fungible_token::token::*;
+use gstd::Encode;
+use gtest::System;
+use market_io::*;
+use nft_io::*;
+mod utils;
+pub use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[1],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+#[test]
+fn banned_actor_cant_trade() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[1], 0, Some(1_000));
+
+    // must fail since only admin can ban actors
+    let res = market.send(USERS[1], MarketAction::BanActor(USERS[2].into()));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::BanActor(USERS[2].into()));
+    assert!(res.log().is_empty());
+
+    sys.mint_to(USERS[2], 2_000);
+    // must fail since the buyer is banned
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+
+    // must fail since the offer author is banned
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::UnbanActor(USERS[2].into()));
+    assert!(res.log().is_empty());
+
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn force_delist() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[1], 0, Some(100_000));
+
+    sys.mint_to(USERS[2], 1_000);
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+        1_000,
+    );
+    assert!(!res.main_failed());
+
+    // must fail since the item owner isn't banned
+    let res = market.send(
+        USERS[0],
+        MarketAction::ForceDelist {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::BanActor(USERS[1].into()));
+    assert!(res.log().is_empty());
+
+    // must fail since only admin can delist items
+    let res = market.send(
+        USERS[2],
+        MarketAction::ForceDelist {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::ForceDelist {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::ItemDelisted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+
+    // the offer is returned
+    assert_eq!(sys.balance_of(USERS[2]), 1_000);
+
+    // must fail since the item is delisted
+    let res = market.send(
+        USERS[0],
+        MarketAction::Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+}
@@ -0,0 +1,129 @@
+use gstd::Encode;
+use gtest::{Program, RunResult, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+use gear_lib::non_fungible_token::token::*;
+pub use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+    for _ in 0..2 {
+        let res = nft.send(
+            USERS[0],
+            NFTAction::Mint {
+                token_metadata: TokenMetadata {
+                    name: "CryptoKitty".to_string(),
+                    description: "Description".to_string(),
+                    media: "http://".to_string(),
+                    reference: "http://".to_string(),
+                },
+            },
+        );
+        assert!(!res.main_failed());
+    }
+}
+
+fn buy(sys: &System, market: &Program, user: u64) -> RunResult {
+    sys.mint_to(user, 1_000);
+    market.send_with_value(user, MarketAction::BuyFromDrop(0), 1_000)
+}
+
+#[test]
+fn drop_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the wallet limit is equal to zero
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateDrop {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_ids: vec![0.into(), 1.into()],
+            price: 1_000,
+            wallet_limit: 0,
+            starts_at: 0,
+            ends_at: u64::MAX,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateDrop {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_ids: vec![0.into(), 1.into()],
+            price: 1_000,
+            wallet_limit: 1,
+            starts_at: 0,
+            ends_at: u64::MAX,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::DropCreated {
+            drop_id: 0,
+            nft_contract_id: 2.into(),
+            amount: 2,
+        }
+        .encode()
+    )));
+
+    // must fail since the token is sold in the drop
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 1.into(),
+            price: Some(1_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = buy(&sys, &market, USERS[1]);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::DropPurchased {
+            drop_id: 0,
+            token_id: 0.into(),
+            buyer: USERS[1].into(),
+        }
+        .encode()
+    )));
+
+    // must fail since the wallet limit is reached
+    let res = buy(&sys, &market, USERS[1]);
+    assert!(res.main_failed());
+
+    // the last token closes the drop
+    let res = buy(&sys, &market, USERS[2]);
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::DropPurchased {
+            drop_id: 0,
+            token_id: 1.into(),
+            buyer: USERS[2].into(),
+        }
+        .encode()
+    )));
+    assert!(res.contains(&(USERS[0], MarketEvent::DropSoldOut { drop_id: 0 }.encode())));
+
+    // must fail since the drop is sold out
+    let res = buy(&sys, &market, USERS[3]);
+    assert!(res.main_failed());
+}
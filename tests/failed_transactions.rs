@@ -0,0 +1,73 @@
+use gstd::Encode;
+use gtest::System;
+use market_io::*;
+use nft_io::*;
+mod utils;
+use gear_lib::non_fungible_token::token::*;
+pub use utils::*;
+
+#[test]
+fn abort_failed_transaction() {
+    let sys = System::new();
+    sys.init_logger();
+    init_ft(&sys);
+    init_nft(&sys);
+    init_market(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the NFT contract rejects the approval by the non-owner
+    let res = market.send(
+        USERS[1],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::TransactionFailed {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            transaction_id: 0,
+        }
+        .encode()
+    )));
+
+    // must fail since only refunds can be retried
+    let res = market.send(USERS[0], MarketAction::RetryTransaction(0));
+    assert!(res.main_failed());
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::AbortTransaction(0));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::AbortTransaction(0));
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::TransactionAborted { transaction_id: 0 }.encode()
+    )));
+
+    // must fail since the transaction has been removed
+    let res = market.send(USERS[0], MarketAction::AbortTransaction(0));
+    assert!(res.main_failed());
+}
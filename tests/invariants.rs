@@ -0,0 +1,327 @@
+use gstd::ActorId;
+use gtest::{Program, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+use gear_lib::non_fungible_token::token::*;
+pub use utils::*;
+
+const TOKENS: u8 = 2;
+const MARKET_ID: u64 = 3;
+const MINTED: u128 = 100_000_000;
+const DURATION: u64 = 60_000;
+
+#[derive(Debug, Clone)]
+enum Action {
+    List { token: u8, price: u128 },
+    Buy { buyer: usize, token: u8 },
+    Offer { user: usize, token: u8, price: u128 },
+    AcceptOffer { token: u8, offer: usize },
+    Withdraw { token: u8, offer: usize },
+    CreateAuction { token: u8, min_price: u128 },
+    Bid { user: usize, token: u8, price: u128 },
+    Settle { user: usize, token: u8 },
+}
+
+/// The xorshift generator of the action sequences, so every run checks the same sequences.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // the zero state isn't changed by the xorshift
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+// prices are high enough so that all payouts exceed the existential minimum
+fn action(rng: &mut Rng) -> Action {
+    let user = rng.below(USERS.len() as u64) as usize;
+    let token = rng.below(TOKENS as u64) as u8;
+    let price = 100_000 + rng.below(900_000) as u128;
+    let index = rng.below(4) as usize;
+    match rng.below(8) {
+        0 => Action::List { token, price },
+        1 => Action::Buy { buyer: user, token },
+        2 => Action::Offer { user, token, price },
+        3 => Action::AcceptOffer {
+            token,
+            offer: index,
+        },
+        4 => Action::Withdraw {
+            token,
+            offer: index,
+        },
+        5 => Action::CreateAuction {
+            token,
+            min_price: price,
+        },
+        6 => Action::Bid { user, token, price },
+        _ => Action::Settle { user, token },
+    }
+}
+
+fn setup(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+    for _ in 0..TOKENS {
+        let res = nft.send(
+            USERS[0],
+            NFTAction::Mint {
+                token_metadata: TokenMetadata {
+                    name: "CryptoKitty".to_string(),
+                    description: "Description".to_string(),
+                    media: "http://".to_string(),
+                    reference: "http://".to_string(),
+                },
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    for user in USERS {
+        sys.mint_to(*user, MINTED);
+    }
+}
+
+fn nft_owner(nft: &Program, token: u8) -> ActorId {
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Owner {
+            token_id: token.into(),
+        },
+    );
+    res.decoded_log::<NFTEvent>()
+        .into_iter()
+        .find_map(|event| match event {
+            NFTEvent::Owner { owner, .. } => Some(owner),
+            _ => None,
+        })
+        .expect("Unable to get the NFT owner")
+}
+
+// returns the default item if the token is not listed
+fn item(market: &Program, token: u8) -> Item {
+    let res = market.send(
+        USERS[0],
+        MarketAction::Item {
+            nft_contract_id: 2.into(),
+            token_id: token.into(),
+        },
+    );
+    res.decoded_log::<MarketEvent>()
+        .into_iter()
+        .find_map(|event| match event {
+            MarketEvent::ItemInfo(item) => Some(item),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn user_id(account: &ActorId) -> Option<u64> {
+    USERS
+        .iter()
+        .copied()
+        .find(|user| ActorId::from(*user) == *account)
+}
+
+// the actions are allowed to fail, only the invariants are checked
+fn execute(sys: &System, market: &Program, nft: &Program, action: Action) {
+    match action {
+        Action::List { token, price } => {
+            if let Some(owner) = user_id(&nft_owner(nft, token)) {
+                market.send(
+                    owner,
+                    MarketAction::AddMarketData {
+                        nft_contract_id: 2.into(),
+                        ft_contract_id: None,
+                        token_id: token.into(),
+                        price: Some(price),
+                        gate: None,
+                        payees: vec![],
+                        beneficiary: None,
+                        metadata: None,
+                    },
+                );
+            }
+        }
+        Action::Buy { buyer, token } => {
+            if let Some(price) = item(market, token).price {
+                market.send_with_value(
+                    USERS[buyer],
+                    MarketAction::BuyItem {
+                        nft_contract_id: 2.into(),
+                        token_id: token.into(),
+                        recipient: None,
+                        relist_at: None,
+                    },
+                    price,
+                );
+            }
+        }
+        Action::Offer { user, token, price } => {
+            market.send_with_value(
+                USERS[user],
+                MarketAction::AddOffer {
+                    nft_contract_id: 2.into(),
+                    ft_contract_id: None,
+                    token_id: token.into(),
+                    price,
+                    recipient: None,
+                    metadata: None,
+                },
+                price,
+            );
+        }
+        Action::AcceptOffer { token, offer } => {
+            let item = item(market, token);
+            if let (Some(owner), false) = (user_id(&item.owner_id), item.offers.is_empty()) {
+                market.send(
+                    owner,
+                    MarketAction::AcceptOffer {
+                        nft_contract_id: 2.into(),
+                        token_id: token.into(),
+                        offer_id: item.offers[offer % item.offers.len()].offer_id,
+                    },
+                );
+            }
+        }
+        Action::Withdraw { token, offer } => {
+            let item = item(market, token);
+            if !item.offers.is_empty() {
+                let offer = &item.offers[offer % item.offers.len()];
+                if let Some(author) = user_id(&offer.id) {
+                    market.send(
+                        author,
+                        MarketAction::Withdraw {
+                            nft_contract_id: 2.into(),
+                            token_id: token.into(),
+                            offer_id: offer.offer_id,
+                        },
+                    );
+                }
+            }
+        }
+        Action::CreateAuction { token, min_price } => {
+            if let Some(owner) = user_id(&nft_owner(nft, token)) {
+                market.send(
+                    owner,
+                    MarketAction::CreateAuction {
+                        nft_contract_id: 2.into(),
+                        ft_contract_id: None,
+                        token_id: token.into(),
+                        min_price,
+                        bid_period: DURATION,
+                        duration: DURATION,
+                        beneficiary: None,
+                        auto_seed_from_offers: false,
+                    },
+                );
+            }
+        }
+        Action::Bid { user, token, price } => {
+            market.send_with_value(
+                USERS[user],
+                MarketAction::AddBid {
+                    nft_contract_id: 2.into(),
+                    token_id: token.into(),
+                    ft_contract_id: None,
+                    price,
+                },
+                price,
+            );
+        }
+        Action::Settle { user, token } => {
+            sys.spend_blocks(DURATION as u32);
+            market.send(
+                USERS[user],
+                MarketAction::SettleAuction {
+                    nft_contract_id: 2.into(),
+                    token_id: token.into(),
+                },
+            );
+        }
+    }
+}
+
+fn check_invariants(sys: &System, market: &Program, nft: &Program) {
+    // the claimable balances are withdrawn so that only escrows remain on the marketplace
+    for account in USERS.iter().chain([&TREASURY_ID]) {
+        market.send(*account, MarketAction::Claim);
+    }
+
+    let mut escrow = 0;
+    for token in 0..TOKENS {
+        let owner = nft_owner(nft, token);
+        let item = item(market, token);
+        if item.owner_id != ActorId::zero() {
+            // the NFT is held by the marketplace during the auction
+            let holder = if item.custody {
+                ActorId::from(3)
+            } else {
+                item.owner_id
+            };
+            assert_eq!(holder, owner, "the item and the NFT owners differ");
+            assert!(item.locked_by.is_none(), "the item remains locked");
+        }
+        escrow += item
+            .offers
+            .iter()
+            .filter(|offer| offer.ft_contract_id.is_none())
+            .map(|offer| offer.price)
+            .sum::<u128>();
+        if let Some(auction) = &item.auction {
+            if auction.current_winner != ActorId::zero() {
+                escrow += auction.current_price;
+            }
+        }
+    }
+    assert_eq!(
+        sys.balance_of(MARKET_ID),
+        escrow,
+        "the marketplace balance doesn't match the escrows"
+    );
+
+    let total: u128 = USERS
+        .iter()
+        .chain([&TREASURY_ID, &MARKET_ID])
+        .map(|account| sys.balance_of(*account))
+        .sum();
+    assert_eq!(
+        total,
+        MINTED * USERS.len() as u128,
+        "the value was created or destroyed"
+    );
+}
+
+#[test]
+fn sale_invariants() {
+    for seed in 0..16 {
+        let mut rng = Rng::new(seed);
+        let actions: Vec<Action> = (0..1 + rng.below(15)).map(|_| action(&mut rng)).collect();
+        let sys = System::new();
+        setup(&sys);
+        let market = sys.get_program(3);
+        let nft = sys.get_program(2);
+        for action in actions {
+            execute(&sys, &market, &nft, action);
+            check_invariants(&sys, &market, &nft);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use ft_io::*;
+use gear_lib::non_fungible_token::token::*;
+use gstd::Encode;
+use gtest::{Program, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+pub use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddFTContract(1.into()));
+    assert!(res.log().is_empty());
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+fn buy(market: &Program, user: u64) {
+    let res = market.send(
+        user,
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn fee_discount_for_traded_volume() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetFeeTiers(vec![FeeTier {
+            volume: 10_000,
+            fee: 50,
+        }]),
+    );
+    assert!(res.log().is_empty());
+    for user in &USERS[1..3] {
+        let res = ft.send(*user, FTAction::Mint(10_000));
+        assert!(!res.main_failed());
+    }
+
+    // the base treasury fee is applied since the seller has no volume yet
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    buy(&market, USERS[1]);
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(100).encode())));
+
+    // the buyer has reached the tier and pays the discounted fee on resale
+    add_market_data(&market, Some(1.into()), USERS[1], 0, Some(10_000));
+    buy(&market, USERS[2]);
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(150).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(9_950).encode())));
+}
+
+#[test]
+fn set_fee_tiers_failures() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since only admin can set fee tiers
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetFeeTiers(vec![FeeTier {
+            volume: 10_000,
+            fee: 50,
+        }]),
+    );
+    assert!(res.main_failed());
+
+    // must fail since the tier fee exceeds the base treasury fee
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetFeeTiers(vec![FeeTier {
+            volume: 10_000,
+            fee: 150,
+        }]),
+    );
+    assert!(res.main_failed());
+
+    // must fail since the fee increases with the volume
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetFeeTiers(vec![
+            FeeTier {
+                volume: 10_000,
+                fee: 50,
+            },
+            FeeTier {
+                volume: 20_000,
+                fee: 75,
+            },
+        ]),
+    );
+    assert!(res.main_failed());
+}
@@ -0,0 +1,134 @@
+use gstd::Encode;
+use gtest::System;
+use market_io::*;
+use nft_io::*;
+mod utils;
+use gear_lib::non_fungible_token::token::*;
+pub use utils::*;
+
+#[test]
+fn heartbeat() {
+    let sys = System::new();
+    sys.init_logger();
+    init_ft(&sys);
+    init_nft(&sys);
+    init_market(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    let monitor = Monitor {
+        monitor_id: USERS[3].into(),
+        interval: 10,
+    };
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetMonitor(Some(monitor)));
+    assert!(res.main_failed());
+    // must fail since the interval is equal to zero
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetMonitor(Some(Monitor {
+            interval: 0,
+            ..monitor
+        })),
+    );
+    assert!(res.main_failed());
+    // must fail since only the marketplace can send the heartbeat
+    let res = market.send(USERS[0], MarketAction::Heartbeat(0));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::SetMonitor(Some(monitor)));
+    assert!(res.log().is_empty());
+
+    let metrics = MarketMetrics {
+        items: 1,
+        active_auctions: 0,
+        escrow: vec![],
+        claimable: 0,
+        failed_transactions: 0,
+        metadata_bytes: 0,
+    };
+    let results = sys.spend_blocks(10);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&(USERS[3], MarketEvent::Metrics(metrics.clone()).encode()))));
+
+    // the heartbeats stop after the monitor is removed
+    let res = market.send(USERS[0], MarketAction::SetMonitor(None));
+    assert!(res.log().is_empty());
+    let results = sys.spend_blocks(20);
+    assert!(!results
+        .iter()
+        .any(|res| res.contains(&(USERS[3], MarketEvent::Metrics(metrics.clone()).encode()))));
+}
+
+#[test]
+fn native_balance() {
+    let sys = System::new();
+    sys.init_logger();
+    init_ft(&sys);
+    init_nft(&sys);
+    init_market(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 5_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 5_000,
+            recipient: None,
+            metadata: None,
+        },
+        5_000,
+    );
+    assert!(!res.main_failed());
+
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::CheckNativeBalance);
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::CheckNativeBalance);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::NativeBalance {
+            held: HeldValue {
+                offers: 5_000,
+                ..Default::default()
+            },
+            available: 5_000,
+        }
+        .encode()
+    )));
+}
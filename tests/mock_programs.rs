@@ -0,0 +1,630 @@
+use gstd::{ActorId, Encode};
+use gtest::{Program, System};
+use market_io::*;
+mod mocks;
+mod utils;
+use mocks::{
+    ft::MockFt, nft::MockNft, randomness::MockRandomness, rejecting::MockRejecting, Behaviour,
+};
+pub use utils::*;
+
+const MOCK_NFT_ID: u64 = 20;
+const MOCK_FT_ID: u64 = 21;
+const MOCK_RANDOMNESS_ID: u64 = 22;
+const MOCK_REJECTING_ID: u64 = 23;
+
+// lists the token 0 of the mocked NFT program for the native value
+fn before_each_test(sys: &System, nft: MockNft, ft: MockFt) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    for program in [
+        Program::mock_with_id(sys, MOCK_NFT_ID, nft),
+        Program::mock_with_id(sys, MOCK_FT_ID, ft),
+    ] {
+        program.send_bytes(USERS[0], []);
+    }
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(MOCK_NFT_ID.into()));
+    assert!(res.log().is_empty());
+    let res = market.send(USERS[0], MarketAction::AddFTContract(MOCK_FT_ID.into()));
+    assert!(res.log().is_empty());
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+}
+
+fn buy(sys: &System) -> gtest::RunResult {
+    buy_as(sys, USERS[1])
+}
+
+fn buy_as(sys: &System, buyer: u64) -> gtest::RunResult {
+    sys.mint_to(buyer, 1_000);
+    sys.get_program(3).send_with_value(
+        buyer,
+        MarketAction::BuyItem {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    )
+}
+
+fn offer(sys: &System, price: u128) -> gtest::RunResult {
+    sys.get_program(3).send(
+        USERS[1],
+        MarketAction::AddOffer {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: Some(MOCK_FT_ID.into()),
+            token_id: 0.into(),
+            price,
+            recipient: None,
+            metadata: None,
+        },
+    )
+}
+
+// accepts the first offer on the item by its owner
+fn accept(sys: &System) -> gtest::RunResult {
+    sys.get_program(3).send(
+        USERS[0],
+        MarketAction::AcceptOffer {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    )
+}
+
+fn transaction_failed(transaction_id: TransactionId) -> (u64, Vec<u8>) {
+    transaction_failed_for(USERS[1], transaction_id)
+}
+
+fn transaction_failed_for(account: u64, transaction_id: TransactionId) -> (u64, Vec<u8>) {
+    (
+        account,
+        MarketEvent::TransactionFailed {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            transaction_id,
+        }
+        .encode(),
+    )
+}
+
+#[test]
+fn well_behaved_nft() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+
+    let res = buy(&sys);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn nft_panics_on_transfer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::PanicOnTransfer).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+
+    // the failed sale unlocks the item, so it can be bought again
+    assert!(buy(&sys).contains(&transaction_failed(1)));
+    assert!(buy(&sys).contains(&transaction_failed(2)));
+}
+
+#[test]
+fn nft_never_replies() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::NoReply).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+
+    // the sale waits for the transfer and keeps the item locked until the reply timeout
+    let res = buy(&sys);
+    assert!(!res.main_failed());
+    assert!(!res.contains(&transaction_failed(1)));
+    assert!(buy(&sys).main_failed());
+
+    // the timed out sale unlocks the item and records the failure
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed(1))));
+    assert!(!buy(&sys).main_failed());
+}
+
+#[test]
+fn nft_replies_after_timeout() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::DelayedReply).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+
+    assert!(!buy(&sys).contains(&transaction_failed(1)));
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed(1))));
+
+    // the item is sold once the NFT contract replies in time
+    let res = buy(&sys);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn buy_races_accept_offer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::DelayedReply).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved).with_balance(USERS[1], 10_000),
+    );
+    assert!(!offer(&sys, 1_000).main_failed());
+
+    // the offer can't be accepted while the purchase holds the item
+    let res = buy_as(&sys, USERS[2]);
+    assert!(!res.main_failed());
+    assert!(accept(&sys).main_failed());
+
+    // the timed out purchase is refunded, then the offer is accepted
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed_for(USERS[2], 2))));
+    let res = accept(&sys);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            new_owner: USERS[1].into(),
+            price: 1_000,
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+    assert!(buy_as(&sys, USERS[2]).main_failed());
+}
+
+#[test]
+fn accept_offer_races_buy() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::DelayedReply).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved).with_balance(USERS[1], 10_000),
+    );
+    assert!(!offer(&sys, 1_000).main_failed());
+
+    // the item can't be bought while the accepted offer holds it
+    let res = accept(&sys);
+    assert!(!res.main_failed());
+    assert!(buy_as(&sys, USERS[2]).main_failed());
+
+    // the timed out acceptance keeps the offer, then the item is bought
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed_for(USERS[0], 2))));
+    let res = buy_as(&sys, USERS[2]);
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::ItemSold {
+            owner: USERS[2].into(),
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            payer: USERS[2].into(),
+        }
+        .encode()
+    )));
+    assert!(accept(&sys).main_failed());
+}
+
+#[test]
+fn ft_panics_on_transfer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::PanicOnTransfer).with_balance(USERS[1], 10_000),
+    );
+
+    // the failed escrow unlocks the item, so the offer can be made again
+    assert!(offer(&sys, 1_000).contains(&transaction_failed(1)));
+    assert!(offer(&sys, 1_000).contains(&transaction_failed(2)));
+}
+
+#[test]
+fn ft_never_replies() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::NoReply).with_balance(USERS[1], 10_000),
+    );
+
+    // the offer waits for the escrow and keeps the item locked until the reply timeout
+    assert!(!offer(&sys, 1_000).main_failed());
+    assert!(offer(&sys, 1_000).main_failed());
+
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed(1))));
+    assert!(!offer(&sys, 1_000).main_failed());
+}
+
+#[test]
+fn ft_fails_payments_after_nft_transfer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::PanicAfterTransfers(1)).with_balance(USERS[1], 10_000),
+    );
+    // the escrow of the offer is the only transfer that is made
+    assert!(!offer(&sys, 1_000).main_failed());
+
+    // the NFT has been transferred, so the sale doesn't fail with the payments
+    let res = accept(&sys);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            new_owner: USERS[1].into(),
+            price: 1_000,
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+
+    // the failed payments are recorded as refunds that can be retried
+    let res = sys
+        .get_program(3)
+        .send(USERS[0], MarketAction::RetryTransaction(3));
+    assert!(!res.main_failed());
+    assert!(res.contains(&transaction_failed_for(USERS[0], 3)));
+}
+
+#[test]
+fn seller_reenters_while_nft_transfer_pending() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::DelayedReply).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+    let market = sys.get_program(3);
+
+    // the item already belongs to the buyer while the sale waits for the NFT contract,
+    // so the seller can't list it again or take it back
+    assert!(!buy(&sys).main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::WithdrawNft {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    // the item is restored to the seller after the timed out transfer
+    let results = sys.spend_blocks(REPLY_TIMEOUT);
+    assert!(results
+        .iter()
+        .any(|res| res.contains(&transaction_failed(1))));
+    assert!(!buy(&sys).main_failed());
+}
+
+#[test]
+fn ft_takes_fee_on_transfer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::FeeOnTransfer(10)).with_balance(USERS[1], 10_000),
+    );
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetCurrencyConfig {
+            ft_contract_id: Some(MOCK_FT_ID.into()),
+            config: Some(CurrencyConfig {
+                min_price: 1,
+                decimals: 0,
+                fee_on_transfer: true,
+            }),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the offer is made at the received amount
+    let res = offer(&sys, 1_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OfferAdded {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: Some(ActorId::from(MOCK_FT_ID)),
+            token_id: 0.into(),
+            price: 900,
+            metadata: None,
+        }
+        .encode()
+    )));
+}
+
+fn create_candle_auction(sys: &System, randomness: MockRandomness) {
+    before_each_test(
+        sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+    Program::mock_with_id(sys, MOCK_RANDOMNESS_ID, randomness).send_bytes(USERS[0], []);
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetRandomnessProvider(Some(MOCK_RANDOMNESS_ID.into())),
+    );
+    assert!(res.log().is_empty());
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateCandleAuction {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            duration: 120_000,
+            rounds: 2,
+            closing_window: 120_000,
+        },
+    );
+    assert!(!res.main_failed());
+}
+
+fn candle_bid(sys: &System, user: u64, price: u128) -> gtest::RunResult {
+    sys.mint_to(user, price);
+    sys.get_program(3).send_with_value(
+        user,
+        MarketAction::AddBid {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price,
+        },
+        price,
+    )
+}
+
+fn settle_candle_auction(sys: &System) -> gtest::RunResult {
+    sys.get_program(3).send(
+        USERS[0],
+        MarketAction::SettleAuction {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+        },
+    )
+}
+
+#[test]
+fn candle_auction_ends_in_drawn_round() {
+    let sys = System::new();
+    sys.init_logger();
+    // the zero seed draws the first round
+    create_candle_auction(&sys, MockRandomness::new([0; 32]));
+    let market = sys.get_program(3);
+
+    // the leading bids of both rounds stay escrowed
+    assert!(!candle_bid(&sys, USERS[1], 1_001).main_failed());
+    sys.spend_blocks(90_000);
+    assert!(!candle_bid(&sys, USERS[2], 1_002).main_failed());
+    sys.spend_blocks(30_001);
+
+    // the bid of the second round is too late
+    let res = settle_candle_auction(&sys);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            price: 1_001,
+        }
+        .encode()
+    )));
+
+    let res = market.send(
+        USERS[2],
+        MarketAction::WithdrawBalance {
+            ft_contract_id: None,
+            amount: 1_002,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::BalanceWithdrawn {
+            ft_contract_id: None,
+            amount: 1_002,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn candle_auction_without_leader_in_drawn_round() {
+    let sys = System::new();
+    sys.init_logger();
+    // the zero seed draws the first round
+    create_candle_auction(&sys, MockRandomness::new([0; 32]));
+    let market = sys.get_program(3);
+
+    sys.spend_blocks(90_000);
+    assert!(!candle_bid(&sys, USERS[1], 1_001).main_failed());
+    sys.spend_blocks(30_001);
+
+    // the only bid is made after the drawn round, so no one wins
+    let res = settle_candle_auction(&sys);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionCancelled {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+    let res = market.send(
+        USERS[1],
+        MarketAction::WithdrawBalance {
+            ft_contract_id: None,
+            amount: 1_001,
+        },
+    );
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn randomness_provider_fails() {
+    let sys = System::new();
+    sys.init_logger();
+    create_candle_auction(&sys, MockRandomness::failing());
+
+    assert!(!candle_bid(&sys, USERS[1], 1_001).main_failed());
+    sys.spend_blocks(120_001);
+
+    // the item is unlocked after the failure, so the auction can be settled again
+    assert!(settle_candle_auction(&sys).contains(&transaction_failed_for(USERS[0], 4)));
+    assert!(settle_candle_auction(&sys).contains(&transaction_failed_for(USERS[0], 5)));
+}
+
+#[test]
+fn refund_to_rejecting_program_is_claimable() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(
+        &sys,
+        MockNft::new(Behaviour::WellBehaved).with_token(0, USERS[0]),
+        MockFt::new(Behaviour::WellBehaved),
+    );
+    Program::mock_with_id(&sys, MOCK_REJECTING_ID, MockRejecting).send_bytes(USERS[0], []);
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 60_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: false,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the program wins at the opening price with the maximum bid of 5_000
+    sys.mint_to(MOCK_REJECTING_ID, 5_000);
+    let res = market.send_with_value(
+        MOCK_REJECTING_ID,
+        MarketAction::AddMaxBid {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            max_price: 5_000,
+        },
+        5_000,
+    );
+    assert!(!res.main_failed());
+    sys.spend_blocks(86_400_001);
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::SettleAuction {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionSettled {
+            nft_contract_id: MOCK_NFT_ID.into(),
+            token_id: 0.into(),
+            price: 1_001,
+        }
+        .encode()
+    )));
+
+    // the rest of the maximum bid isn't sent to the program, it waits to be claimed
+    let res = market.send(MOCK_REJECTING_ID, MarketAction::Claim);
+    assert!(res.contains(&(
+        MOCK_REJECTING_ID,
+        MarketEvent::Claimed { amount: 3_999 }.encode()
+    )));
+}
@@ -0,0 +1,76 @@
+use super::Behaviour;
+use ft_io::*;
+use gstd::{prelude::*, ActorId, Decode, Encode};
+use gtest::WasmProgram;
+
+/// The FT program that transfers the tokens of any account without allowances.
+#[derive(Debug)]
+pub struct MockFt {
+    behaviour: Behaviour,
+    balances: BTreeMap<ActorId, u128>,
+}
+
+impl MockFt {
+    pub fn new(behaviour: Behaviour) -> Self {
+        Self {
+            behaviour,
+            balances: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_balance(mut self, account: u64, amount: u128) -> Self {
+        self.balances.insert(account.into(), amount);
+        self
+    }
+
+    fn balance(&self, account: &ActorId) -> u128 {
+        self.balances.get(account).copied().unwrap_or_default()
+    }
+}
+
+impl WasmProgram for MockFt {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action = FTAction::decode(&mut &payload[..]).map_err(|_| "Unknown action")?;
+        let event = match action {
+            FTAction::BalanceOf(account) => FTEvent::Balance(self.balance(&account)),
+            FTAction::Transfer { from, to, amount } => {
+                let fee = match self.behaviour {
+                    Behaviour::NoReply => return Ok(None),
+                    Behaviour::DelayedReply => {
+                        self.behaviour = Behaviour::WellBehaved;
+                        return Ok(None);
+                    }
+                    Behaviour::PanicOnTransfer => return Err("The transfer failed"),
+                    Behaviour::PanicAfterTransfers(0) => return Err("The transfer failed"),
+                    Behaviour::PanicAfterTransfers(transfers) => {
+                        self.behaviour = Behaviour::PanicAfterTransfers(transfers - 1);
+                        0
+                    }
+                    Behaviour::FeeOnTransfer(percent) => amount * percent / 100,
+                    Behaviour::WellBehaved => 0,
+                };
+                let balance = self.balance(&from);
+                if balance < amount {
+                    return Err("Not enough tokens");
+                }
+                self.balances.insert(from, balance - amount);
+                *self.balances.entry(to).or_default() += amount - fee;
+                FTEvent::Transfer { from, to, amount }
+            }
+            _ => return Err("The action isn't supported"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn meta_state(&mut self, _payload: Option<Vec<u8>>) -> Result<Vec<u8>, &'static str> {
+        Err("The state isn't supported")
+    }
+}
@@ -0,0 +1,26 @@
+//! The NFT and FT programs mocked in gtest to pin the failure paths of the marketplace
+//! and the randomness provider of the candle auctions, and a program that rejects the value.
+
+pub mod ft;
+pub mod nft;
+pub mod randomness;
+pub mod rejecting;
+
+/// How the mocked program treats the transfers.
+/// The other messages (approvals, owners and balances) are always replied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
+    WellBehaved,
+    /// Never replies to the transfers, so the marketplace waits for the reply forever.
+    NoReply,
+    /// Doesn't reply to the first transfer, the same as a reply that comes
+    /// after the marketplace stops waiting for it, and replies to the later transfers.
+    DelayedReply,
+    /// Panics on the transfers, so the marketplace gets an error reply.
+    PanicOnTransfer,
+    /// Makes the indicated number of transfers and panics on the later ones,
+    /// e.g. on the payments of the sale after the escrow (the FT program only).
+    PanicAfterTransfers(u32),
+    /// Deducts the fee in percents from the transferred amount (the FT program only).
+    FeeOnTransfer(u128),
+}
@@ -0,0 +1,111 @@
+use super::Behaviour;
+use gear_lib::non_fungible_token::token::TokenId;
+use gstd::{prelude::*, ActorId, Decode, Encode};
+use gtest::WasmProgram;
+use nft_io::*;
+
+/// The NFT program that tracks only the owners of the tokens
+/// and approves the marketplace for any token.
+#[derive(Debug)]
+pub struct MockNft {
+    behaviour: Behaviour,
+    owners: BTreeMap<TokenId, ActorId>,
+}
+
+impl MockNft {
+    pub fn new(behaviour: Behaviour) -> Self {
+        Self {
+            behaviour,
+            owners: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_token(mut self, token_id: u128, owner: u64) -> Self {
+        self.owners.insert(token_id.into(), owner.into());
+        self
+    }
+
+    fn owner(&self, token_id: TokenId) -> Result<ActorId, &'static str> {
+        self.owners
+            .get(&token_id)
+            .copied()
+            .ok_or("The token does not exist")
+    }
+
+    // returns the previous owner or `None` if the transfer isn't replied to
+    fn transfer(
+        &mut self,
+        to: ActorId,
+        token_id: TokenId,
+    ) -> Result<Option<ActorId>, &'static str> {
+        let from = self.owner(token_id)?;
+        match self.behaviour {
+            Behaviour::NoReply => Ok(None),
+            Behaviour::DelayedReply => {
+                self.behaviour = Behaviour::WellBehaved;
+                Ok(None)
+            }
+            Behaviour::PanicOnTransfer => Err("The transfer failed"),
+            Behaviour::WellBehaved
+            | Behaviour::FeeOnTransfer(_)
+            | Behaviour::PanicAfterTransfers(_) => {
+                self.owners.insert(token_id, to);
+                Ok(Some(from))
+            }
+        }
+    }
+}
+
+impl WasmProgram for MockNft {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action = NFTAction::decode(&mut &payload[..]).map_err(|_| "Unknown action")?;
+        let event = match action {
+            NFTAction::Owner { token_id } => NFTEvent::Owner {
+                owner: self.owner(token_id)?,
+                token_id,
+            },
+            NFTAction::Approve { to, token_id } => NFTEvent::Approval(NFTApproval {
+                owner: self.owner(token_id)?,
+                approved_account: to,
+                token_id,
+            }),
+            NFTAction::IsApproved { to, token_id } => NFTEvent::IsApproved {
+                to,
+                token_id,
+                approved: true,
+            },
+            NFTAction::Transfer { to, token_id } => match self.transfer(to, token_id)? {
+                Some(from) => NFTEvent::Transfer(NFTTransfer { from, to, token_id }),
+                None => return Ok(None),
+            },
+            NFTAction::TransferPayout {
+                to,
+                token_id,
+                amount,
+            } => match self.transfer(to, token_id)? {
+                // the whole amount is paid to the previous owner
+                Some(from) => NFTEvent::TransferPayout(NFTTransferPayout {
+                    from,
+                    to,
+                    token_id,
+                    payouts: BTreeMap::from([(from, amount)]),
+                }),
+                None => return Ok(None),
+            },
+            _ => return Err("The action isn't supported"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn meta_state(&mut self, _payload: Option<Vec<u8>>) -> Result<Vec<u8>, &'static str> {
+        Err("The state isn't supported")
+    }
+}
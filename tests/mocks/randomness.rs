@@ -0,0 +1,44 @@
+use gstd::{prelude::*, Decode, Encode};
+use gtest::WasmProgram;
+use market_io::*;
+use primitive_types::H256;
+
+/// The randomness provider that replies with the same seed to any request
+/// or fails every request.
+#[derive(Debug)]
+pub struct MockRandomness {
+    seed: Option<H256>,
+}
+
+impl MockRandomness {
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self {
+            seed: Some(seed.into()),
+        }
+    }
+
+    pub fn failing() -> Self {
+        Self { seed: None }
+    }
+}
+
+impl WasmProgram for MockRandomness {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let RandomnessAction::Random { .. } =
+            RandomnessAction::decode(&mut &payload[..]).map_err(|_| "Unknown action")?;
+        let seed = self.seed.ok_or("The seed isn't available")?;
+        Ok(Some(RandomnessEvent::Random(seed).encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn meta_state(&mut self, _payload: Option<Vec<u8>>) -> Result<Vec<u8>, &'static str> {
+        Err("The state isn't supported")
+    }
+}
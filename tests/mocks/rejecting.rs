@@ -0,0 +1,25 @@
+use gstd::prelude::*;
+use gtest::WasmProgram;
+
+/// The program that fails every message, so the value sent to it
+/// returns to the sender.
+#[derive(Debug)]
+pub struct MockRejecting;
+
+impl WasmProgram for MockRejecting {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Err("The messages aren't accepted")
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn meta_state(&mut self, _payload: Option<Vec<u8>>) -> Result<Vec<u8>, &'static str> {
+        Err("The state isn't supported")
+    }
+}
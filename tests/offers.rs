@@ -42,6 +42,8 @@ fn offer(market: &Program, user: u64, ft_contract_id: Option<ActorId>, price: u1
                 ft_contract_id,
                 token_id: 0.into(),
                 price,
+                recipient: None,
+                metadata: None,
             },
             price,
         )
@@ -53,6 +55,8 @@ fn offer(market: &Program, user: u64, ft_contract_id: Option<ActorId>, price: u1
                 ft_contract_id,
                 token_id: 0.into(),
                 price,
+                recipient: None,
+                metadata: None,
             },
         )
     };
@@ -63,6 +67,7 @@ fn offer(market: &Program, user: u64, ft_contract_id: Option<ActorId>, price: u1
             ft_contract_id,
             token_id: 0.into(),
             price,
+            metadata: None,
         }
         .encode()
     )));
@@ -81,10 +86,13 @@ fn add_offer() {
         offer(&market, USERS[1], None, 1000 * (i + 1));
         let hash = get_hash(None, 1_000 * (i + 1));
         offers.push(Offer {
+            offer_id: i as u64,
             hash,
             id: USERS[1].into(),
             ft_contract_id: None,
             price: 1_000 * (i + 1),
+            recipient: None,
+            metadata: None,
         });
     }
     let res = market.send(
@@ -97,11 +105,14 @@ fn add_offer() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[0].into(),
             ft_contract_id: None,
             price: Some(100_000),
             auction: None,
             offers,
+            ..Default::default()
         })
         .encode()
     )));
@@ -141,6 +152,8 @@ fn add_offer_failures() {
             ft_contract_id: Some(11.into()),
             token_id: 0.into(),
             price: 0,
+            recipient: None,
+            metadata: None,
         },
     );
     assert!(res.main_failed());
@@ -153,6 +166,8 @@ fn add_offer_failures() {
             ft_contract_id: Some(1.into()),
             token_id: 0.into(),
             price: 0,
+            recipient: None,
+            metadata: None,
         },
     );
     assert!(res.main_failed());
@@ -168,35 +183,33 @@ fn add_offer_failures() {
             ft_contract_id: Some(1.into()),
             token_id: 0.into(),
             price: 100,
+            recipient: None,
+            metadata: None,
         },
     );
     assert!(!res.main_failed());
 
-    // must fail since the offers with these params already exists
-    let res = market.send(
-        USERS[3],
-        MarketAction::AddOffer {
-            nft_contract_id: 2.into(),
-            ft_contract_id: Some(1.into()),
-            token_id: 0.into(),
-            price: 100,
-        },
-    );
-    assert!(res.main_failed());
+    // must fail since the attached value is less than the offered price
+    sys.mint_to(USERS[1], 11_000);
+    let add_offer = |value: u128| {
+        market.send_with_value(
+            USERS[1],
+            MarketAction::AddOffer {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                price: 10_000,
+                recipient: None,
+                metadata: None,
+            },
+            value,
+        )
+    };
+    assert!(add_offer(9_999).main_failed());
 
-    // must fail since the attached value is not equal to the offered price
-    sys.mint_to(USERS[1], 10001);
-    let res = market.send_with_value(
-        USERS[1],
-        MarketAction::AddOffer {
-            nft_contract_id: 2.into(),
-            ft_contract_id: None,
-            token_id: 0.into(),
-            price: 10000,
-        },
-        10001,
-    );
-    assert!(res.main_failed());
+    // the excess of the attached value is returned
+    assert!(!add_offer(11_000).main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 1_000);
 }
 
 #[test]
@@ -214,14 +227,12 @@ fn accept_offer() {
     offer(&market, USERS[1], None, 100_000);
     offer(&market, USERS[2], Some(1.into()), 1_000);
 
-    let hash = get_hash(Some(1.into()), 1_000);
-
     let res = market.send(
         USERS[0],
         MarketAction::AcceptOffer {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            offer_hash: hash,
+            offer_id: 1,
         },
     );
     assert!(res.contains(&(
@@ -231,6 +242,7 @@ fn accept_offer() {
             token_id: 0.into(),
             new_owner: USERS[2].into(),
             price: 1_000,
+            payer: USERS[2].into(),
         }
         .encode()
     )));
@@ -240,10 +252,13 @@ fn accept_offer() {
     assert!(res.contains(&(USERS[0], FTEvent::Balance(990).encode())));
 
     let offer = Offer {
+        offer_id: 0,
         hash: get_hash(None, 100_000),
         id: USERS[1].into(),
         ft_contract_id: None,
         price: 100_000,
+        recipient: None,
+        metadata: None,
     };
     let res = market.send(
         USERS[0],
@@ -255,11 +270,14 @@ fn accept_offer() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[2].into(),
             ft_contract_id: None,
             price: None,
             auction: None,
             offers: vec![offer],
+            ..Default::default()
         })
         .encode()
     )));
@@ -269,7 +287,7 @@ fn accept_offer() {
         MarketAction::AcceptOffer {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            offer_hash: get_hash(None, 100_000),
+            offer_id: 0,
         },
     );
     assert!(res.contains(&(
@@ -279,6 +297,7 @@ fn accept_offer() {
             token_id: 0.into(),
             new_owner: USERS[1].into(),
             price: 100_000,
+            payer: USERS[1].into(),
         }
         .encode()
     )));
@@ -293,11 +312,14 @@ fn accept_offer() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[1].into(),
             ft_contract_id: None,
             price: None,
             auction: None,
             offers: vec![],
+            ..Default::default()
         })
         .encode()
     )));
@@ -322,18 +344,18 @@ fn accept_offer_failures() {
         MarketAction::AcceptOffer {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            offer_hash: get_hash(Some(1.into()), 1_000),
+            offer_id: 0,
         },
     );
     assert!(res.main_failed());
 
-    // must fail since the offer with the indicated hash doesn't exist
+    // must fail since the offer with the indicated id doesn't exist
     let res = market.send(
-        USERS[1],
+        USERS[0],
         MarketAction::AcceptOffer {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            offer_hash: get_hash(Some(1.into()), 10_000),
+            offer_id: 1,
         },
     );
     assert!(res.main_failed());
@@ -359,7 +381,7 @@ fn withdraw() {
         MarketAction::Withdraw {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            hash: get_hash(Some(1.into()), 1_000),
+            offer_id: 1,
         },
     );
     assert!(res.contains(&(
@@ -377,10 +399,13 @@ fn withdraw() {
     assert!(res.contains(&(USERS[0], FTEvent::Balance(100_000).encode())));
 
     let offer = Offer {
+        offer_id: 0,
         hash: get_hash(None, 100_000),
         id: USERS[1].into(),
         ft_contract_id: None,
         price: 100_000,
+        recipient: None,
+        metadata: None,
     };
     let res = market.send(
         USERS[0],
@@ -392,11 +417,14 @@ fn withdraw() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[0].into(),
             ft_contract_id: None,
             price: Some(100_000),
             auction: None,
             offers: vec![offer],
+            ..Default::default()
         })
         .encode()
     )));
@@ -406,7 +434,7 @@ fn withdraw() {
         MarketAction::Withdraw {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            hash: get_hash(None, 100_000),
+            offer_id: 0,
         },
     );
     assert!(res.contains(&(
@@ -429,11 +457,14 @@ fn withdraw() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[0].into(),
             ft_contract_id: None,
             price: Some(100_000),
             auction: None,
             offers: vec![],
+            ..Default::default()
         })
         .encode()
     )));
@@ -460,18 +491,905 @@ fn withdraws_failure() {
         MarketAction::Withdraw {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            hash: get_hash(Some(1.into()), 1_000),
+            offer_id: 1,
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since the indicated offer id doesn't exist
+    let res = market.send(
+        USERS[2],
+        MarketAction::Withdraw {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 2,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn accept_best_offer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    USERS.iter().skip(1).for_each(|user| {
+        let res = ft.send(*user, FTAction::Mint(100_000));
+        assert!(!res.main_failed());
+    });
+    sys.mint_to(USERS[1], 100_000);
+    offer(&market, USERS[1], None, 100_000);
+    offer(&market, USERS[2], Some(1.into()), 1_000);
+    offer(&market, USERS[3], Some(1.into()), 2_000);
+
+    // must fail since there is no offer in tokens not less than the indicated price
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptBestOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+            min_price: 2_001,
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since only owner can accept offer
+    let res = market.send(
+        USERS[1],
+        MarketAction::AcceptBestOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+            min_price: 1_000,
         },
     );
     assert!(res.main_failed());
 
-    // must fail since the indicated offer hash doesn't exist
+    // the highest offer in tokens is accepted even though the native value one is bigger
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptBestOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+            min_price: 1_000,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            new_owner: USERS[3].into(),
+            price: 2_000,
+            payer: USERS[3].into(),
+        }
+        .encode()
+    )));
+
+    // check the seller balance
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(1_980).encode())));
+}
+
+#[test]
+fn offers_with_same_price() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    // offers with the same currency and price from different users and
+    // with the same price in different currencies from the same user
+    sys.mint_to(USERS[1], 1_000);
+    offer(&market, USERS[1], None, 1_000);
+    sys.mint_to(USERS[2], 1_000);
+    offer(&market, USERS[2], None, 1_000);
+    let res = ft.send(USERS[1], FTAction::Mint(1_000));
+    assert!(!res.main_failed());
+    offer(&market, USERS[1], Some(1.into()), 1_000);
+
     let res = market.send(
         USERS[2],
         MarketAction::Withdraw {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
-            hash: get_hash(Some(1.into()), 1_010),
+            offer_id: 1,
+        },
+    );
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), 1_000);
+
+    let offers = vec![
+        Offer {
+            offer_id: 0,
+            hash: get_hash(None, 1_000),
+            id: USERS[1].into(),
+            ft_contract_id: None,
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+        Offer {
+            offer_id: 2,
+            hash: get_hash(Some(1.into()), 1_000),
+            id: USERS[1].into(),
+            ft_contract_id: Some(1.into()),
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+    ];
+    let res = market.send(
+        USERS[0],
+        MarketAction::Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            owner_id: USERS[0].into(),
+            ft_contract_id: None,
+            price: Some(100_000),
+            auction: None,
+            offers,
+            ..Default::default()
+        })
+        .encode()
+    )));
+}
+
+#[test]
+fn offer_metadata() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    let add_offer = |metadata: Vec<u8>| {
+        sys.mint_to(USERS[1], 1_000);
+        market.send_with_value(
+            USERS[1],
+            MarketAction::AddOffer {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                price: 1_000,
+                recipient: None,
+                metadata: Some(metadata),
+            },
+            1_000,
+        )
+    };
+
+    // must fail since the metadata is too long
+    let res = add_offer(vec![0; MAX_METADATA_LEN + 1]);
+    assert!(res.main_failed());
+
+    // the metadata is echoed in the event
+    let res = add_offer(b"campaign-42".to_vec());
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OfferAdded {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 1_000,
+            metadata: Some(b"campaign-42".to_vec()),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn failed_accept_keeps_offer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 100_000);
+    offer(&market, USERS[1], None, 100_000);
+
+    // the NFT can't be transferred since the owner transferred it bypassing the marketplace
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Transfer {
+            to: USERS[2].into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    );
+    assert!(res
+        .decoded_log::<MarketEvent>()
+        .iter()
+        .any(|event| matches!(event, MarketEvent::TransactionFailed { .. })));
+    // nothing is paid to the treasury
+    assert_eq!(sys.balance_of(TREASURY_ID), 0);
+
+    // the offer is restored with the whole escrow
+    let res = market.send(
+        USERS[1],
+        MarketAction::Withdraw {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::TokensWithdrawn {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: 100_000,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 100_000);
+}
+
+#[test]
+fn withdraw_all_offers() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    let res = ft.send(USERS[1], FTAction::Mint(100_000));
+    assert!(!res.main_failed());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 3_000);
+    offer(&market, USERS[1], None, 1_000);
+    offer(&market, USERS[1], None, 2_000);
+    offer(&market, USERS[1], Some(1.into()), 1_000);
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::WithdrawAllOffers {
+            ft_contract_id: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OffersWithdrawn {
+            ft_contract_id: None,
+            offer_ids: vec![0, 1],
+            cursor: None,
+            operation_id: None,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 3_000);
+
+    // the offer in tokens remains
+    let res = market.send(
+        USERS[1],
+        MarketAction::Withdraw {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 2,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::WithdrawAllOffers {
+            ft_contract_id: Some(1.into()),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OffersWithdrawn {
+            ft_contract_id: Some(1.into()),
+            offer_ids: vec![],
+            cursor: None,
+            operation_id: None,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn withdraw_offers_in_several_messages() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 25_000);
+    for _ in 0..25 {
+        offer(&market, USERS[1], None, 1_000);
+    }
+
+    // the marketplace continues the withdrawal of the remaining offers itself
+    let res = market.send(
+        USERS[1],
+        MarketAction::WithdrawAllOffers {
+            ft_contract_id: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OffersWithdrawn {
+            ft_contract_id: None,
+            offer_ids: (0..20).collect(),
+            cursor: Some(20),
+            operation_id: Some(0),
+        }
+        .encode()
+    )));
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OffersWithdrawn {
+            ft_contract_id: None,
+            offer_ids: (20..25).collect(),
+            cursor: None,
+            operation_id: None,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 25_000);
+
+    // the operation is done
+    assert!(market
+        .send(USERS[1], MarketAction::ContinueOperation(0))
+        .main_failed());
+}
+
+#[test]
+fn deposit_offer_failures() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 1_000);
+    offer(&market, USERS[1], None, 1_000);
+
+    let deposit = || MarketAction::DepositOffer {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        offer_id: 0,
+    };
+    // must fail since the yield program isn't set
+    assert!(market.send(USERS[1], deposit()).main_failed());
+    // must fail since the caller isn't the admin
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetYieldProgram(Some(USERS[3].into())),
+    );
+    assert!(res.main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetYieldProgram(Some(USERS[3].into())),
+    );
+    assert!(res.log().is_empty());
+
+    // must fail since the offer is in a native value
+    assert!(market.send(USERS[1], deposit()).main_failed());
+    // must fail since the caller isn't the author of the offer
+    assert!(market.send(USERS[2], deposit()).main_failed());
+}
+
+#[test]
+fn emergency_reclaim() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 3_000);
+    offer(&market, USERS[1], None, 1_000);
+    offer(&market, USERS[1], None, 2_000);
+
+    let reclaim = || MarketAction::EmergencyReclaim {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+    };
+    // the emergency mode isn't armed
+    assert!(market.send(USERS[1], reclaim()).main_failed());
+
+    // only admin can arm the emergency mode
+    let arm = || MarketAction::ArmEmergency {
+        timelock: Some(MIN_EMERGENCY_TIMELOCK),
+    };
+    assert!(market.send(USERS[1], arm()).main_failed());
+    let res = market.send(USERS[0], arm());
+    assert!(res.log().is_empty());
+
+    // the timelock isn't over yet
+    sys.spend_blocks((MIN_EMERGENCY_TIMELOCK / 2) as u32);
+    assert!(market.send(USERS[1], reclaim()).main_failed());
+    let res = market.send(USERS[0], MarketAction::EmergencyHeartbeat);
+    assert!(res.log().is_empty());
+    sys.spend_blocks((MIN_EMERGENCY_TIMELOCK / 2) as u32);
+    assert!(market.send(USERS[1], reclaim()).main_failed());
+
+    sys.spend_blocks((MIN_EMERGENCY_TIMELOCK / 2) as u32);
+    let res = market.send(USERS[1], reclaim());
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::EmergencyReclaimed {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_ids: vec![0, 1],
+            bid: None,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 3_000);
+
+    // nothing to reclaim
+    assert!(market.send(USERS[1], reclaim()).main_failed());
+}
+
+#[test]
+fn auto_accept() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    let auto_accept = Some(AutoAccept {
+        ft_contract_id: None,
+        min_price: 10_000,
+    });
+    let set_auto_accept = |user: u64| {
+        market.send(
+            user,
+            MarketAction::SetAutoAccept {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                auto_accept,
+            },
+        )
+    };
+    // must fail since the caller isn't the owner
+    assert!(set_auto_accept(USERS[1]).main_failed());
+    let res = set_auto_accept(USERS[0]);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AutoAcceptSet {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            auto_accept,
+        }
+        .encode()
+    )));
+
+    // the offer below the threshold waits for the owner
+    sys.mint_to(USERS[1], 1_000);
+    offer(&market, USERS[1], None, 1_000);
+
+    sys.mint_to(USERS[2], 10_000);
+    let res = market.send_with_value(
+        USERS[2],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 10_000,
+            recipient: None,
+            metadata: None,
+        },
+        10_000,
+    );
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            new_owner: USERS[2].into(),
+            price: 10_000,
+            payer: USERS[2].into(),
+        }
+        .encode()
+    )));
+
+    // the threshold is cleared on the sale and the other offer remains
+    let res = market.send(
+        USERS[0],
+        MarketAction::Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            owner_id: USERS[2].into(),
+            offers: vec![Offer {
+                offer_id: 0,
+                hash: get_hash(None, 1_000),
+                id: USERS[1].into(),
+                ft_contract_id: None,
+                price: 1_000,
+                recipient: None,
+                metadata: None,
+            }],
+            ..Default::default()
+        })
+        .encode()
+    )));
+}
+
+#[test]
+fn trait_offers() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let token_trait = TokenTrait {
+        name: "Eyes".to_string(),
+        value: "Laser".to_string(),
+    };
+    let add_offer = |price: u128| {
+        market.send_with_value(
+            USERS[1],
+            MarketAction::AddTraitOffer {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_trait: token_trait.clone(),
+                price,
+            },
+            price,
+        )
+    };
+    let set_metadata_program = |user: u64| {
+        market.send(
+            user,
+            MarketAction::SetMetadataProgram {
+                nft_contract_id: 2.into(),
+                metadata_program: Some(9.into()),
+            },
+        )
+    };
+    sys.mint_to(USERS[1], 10_000);
+
+    // must fail since the collection has no metadata program
+    assert!(add_offer(10_000).main_failed());
+    // must fail since the caller isn't the admin
+    assert!(set_metadata_program(USERS[1]).main_failed());
+    assert!(set_metadata_program(USERS[0]).log().is_empty());
+
+    let res = add_offer(10_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::TraitOfferAdded {
+            nft_contract_id: 2.into(),
+            offer: TraitOffer {
+                offer_id: 0,
+                owner: USERS[1].into(),
+                ft_contract_id: None,
+                price: 10_000,
+                token_trait: token_trait.clone(),
+            },
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 0);
+
+    // must fail since the metadata program doesn't confirm the trait
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptTraitOffer {
+            nft_contract_id: 2.into(),
+            offer_id: 0,
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since the caller isn't the offer owner
+    let withdraw = |user: u64| {
+        market.send(
+            user,
+            MarketAction::WithdrawTraitOffer {
+                nft_contract_id: 2.into(),
+                offer_id: 0,
+            },
+        )
+    };
+    assert!(withdraw(USERS[0]).main_failed());
+    let res = withdraw(USERS[1]);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::TraitOfferWithdrawn {
+            nft_contract_id: 2.into(),
+            offer_id: 0,
+        }
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), 10_000);
+}
+
+#[test]
+fn lowest_offer_is_evicted() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    for user in &USERS[1..] {
+        let res = ft.send(*user, FTAction::Mint(10_000));
+        assert!(!res.main_failed());
+    }
+
+    // only admin can set the limit and it can't be zero
+    let res = market.send(USERS[1], MarketAction::SetMaxOffers(Some(2)));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetMaxOffers(Some(0)));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetMaxOffers(Some(2)));
+    assert!(!res.main_failed());
+
+    offer(&market, USERS[1], Some(1.into()), 1_000);
+    offer(&market, USERS[2], Some(1.into()), 2_000);
+
+    // the offer must exceed the lowest one when the item is full
+    let res = market.send(
+        USERS[3],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[3],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: 1_500,
+            recipient: None,
+            metadata: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OfferEvicted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+            ft_contract_id: Some(1.into()),
+            price: 1_000,
+        }
+        .encode()
+    )));
+
+    // the evicted offer is refunded
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(10_000).encode())));
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            owner_id: USERS[0].into(),
+            ft_contract_id: None,
+            price: Some(100_000),
+            auction: None,
+            offers: vec![
+                Offer {
+                    offer_id: 1,
+                    hash: get_hash(Some(1.into()), 2_000),
+                    id: USERS[2].into(),
+                    ft_contract_id: Some(1.into()),
+                    price: 2_000,
+                    recipient: None,
+                    metadata: None,
+                },
+                Offer {
+                    offer_id: 2,
+                    hash: get_hash(Some(1.into()), 1_500),
+                    id: USERS[3].into(),
+                    ft_contract_id: Some(1.into()),
+                    price: 1_500,
+                    recipient: None,
+                    metadata: None,
+                },
+            ],
+            ..Default::default()
+        })
+        .encode()
+    )));
+}
+
+#[test]
+fn accept_offer_cancels_auction_without_bids() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    sys.mint_to(USERS[1], 100_000);
+    offer(&market, USERS[1], None, 100_000);
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 60_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: false,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::AcceptOffer {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            offer_id: 0,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AuctionCancelled {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            new_owner: USERS[1].into(),
+            price: 100_000,
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn balance_offers() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(200_000));
+    add_market_data(&market, None, USERS[0], 1, Some(200_000));
+    sys.mint_to(USERS[1], 100_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::DepositBalance {
+            ft_contract_id: None,
+            amount: 100_000,
+        },
+        100_000,
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::BalanceDeposited {
+            ft_contract_id: None,
+            amount: 100_000,
+        }
+        .encode()
+    )));
+
+    let balance_offer = |token_id: u128, price| {
+        market.send(
+            USERS[1],
+            MarketAction::AddBalanceOffer {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: token_id.into(),
+                price,
+                recipient: None,
+                metadata: None,
+            },
+        )
+    };
+    // must fail since the balance doesn't cover the offer
+    assert!(balance_offer(0, 100_001).main_failed());
+    // one deposit covers both offers
+    assert!(!balance_offer(0, 100_000).main_failed());
+    assert!(!balance_offer(1, 100_000).main_failed());
+
+    let accept_offer = |token_id: u128, offer_id| {
+        market.send(
+            USERS[0],
+            MarketAction::AcceptOffer {
+                nft_contract_id: 2.into(),
+                token_id: token_id.into(),
+                offer_id,
+            },
+        )
+    };
+    let res = accept_offer(0, 0);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferAccepted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            new_owner: USERS[1].into(),
+            price: 100_000,
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+
+    // the balance is spent, so the other offer is cancelled
+    let res = accept_offer(1, 1);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OfferCancelled {
+            nft_contract_id: 2.into(),
+            token_id: 1.into(),
+            offer_id: 1,
+        }
+        .encode()
+    )));
+    assert!(accept_offer(1, 1).main_failed());
+
+    // must fail since nothing is left on the balance
+    let res = market.send(
+        USERS[1],
+        MarketAction::WithdrawBalance {
+            ft_contract_id: None,
+            amount: 1,
         },
     );
     assert!(res.main_failed());
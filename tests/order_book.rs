@@ -0,0 +1,332 @@
+use gear_lib::non_fungible_token::token::*;
+use gstd::Encode;
+use gtest::{Program, RunResult, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+pub use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+fn buy_order(market: &Program, user: u64, price: u128) -> RunResult {
+    market.send_with_value(
+        user,
+        MarketAction::PlaceBuyOrder {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            price,
+        },
+        price,
+    )
+}
+
+fn sell_order(market: &Program, user: u64, price: u128) -> RunResult {
+    market.send(
+        user,
+        MarketAction::PlaceSellOrder {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price,
+        },
+    )
+}
+
+#[test]
+fn sell_order_matches_buy_order() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    sys.mint_to(USERS[1], 10_000);
+    let res = buy_order(&market, USERS[1], 10_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OrderPlaced {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            order_id: 0,
+            price: 10_000,
+        }
+        .encode()
+    )));
+
+    // the trade is executed at the buy order price
+    let res = sell_order(&market, USERS[0], 9_000);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OrdersMatched {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            buyer: USERS[1].into(),
+            seller: USERS[0].into(),
+            price: 10_000,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn buy_order_matches_sell_order() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    let res = sell_order(&market, USERS[0], 9_000);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OrderPlaced {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            order_id: 0,
+            price: 9_000,
+        }
+        .encode()
+    )));
+
+    // must fail since the token is already in the order book
+    let res = sell_order(&market, USERS[0], 8_000);
+    assert!(res.main_failed());
+
+    // the trade is executed at the sell order price
+    sys.mint_to(USERS[1], 10_000);
+    let res = buy_order(&market, USERS[1], 10_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OrdersMatched {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            buyer: USERS[1].into(),
+            seller: USERS[0].into(),
+            price: 9_000,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn stale_sell_orders_are_not_matched() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let nft = sys.get_program(2);
+    let placed = |user: u64, order_id: OrderId, price: u128| {
+        (
+            user,
+            MarketEvent::OrderPlaced {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                order_id,
+                price,
+            }
+            .encode(),
+        )
+    };
+
+    // the sell order is removed when the token is sold by another flow
+    assert!(sell_order(&market, USERS[0], 1_000).contains(&placed(USERS[0], 0, 1_000)));
+    add_market_data(&market, None, USERS[0], 0, Some(2_000));
+    sys.mint_to(USERS[1], 2_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        2_000,
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], 1_000);
+    let res = buy_order(&market, USERS[2], 1_000);
+    assert!(res.contains(&placed(USERS[2], 1, 1_000)));
+
+    // the sell order isn't matched when the token is transferred outside the marketplace
+    assert!(sell_order(&market, USERS[1], 2_000).contains(&placed(USERS[1], 2, 2_000)));
+    let res = nft.send(
+        USERS[1],
+        NFTAction::Transfer {
+            to: USERS[3].into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], 2_000);
+    let res = buy_order(&market, USERS[2], 2_000);
+    assert!(res.contains(&placed(USERS[2], 3, 2_000)));
+}
+
+#[test]
+fn remove_order() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    sys.mint_to(USERS[1], 10_000);
+    let res = buy_order(&market, USERS[1], 10_000);
+    assert!(!res.main_failed());
+
+    // must fail since only the order owner can remove the order
+    let res = market.send(
+        USERS[2],
+        MarketAction::RemoveOrder {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            order_id: 0,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::RemoveOrder {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            order_id: 0,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::OrderRemoved {
+            nft_contract_id: 2.into(),
+            order_id: 0,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn sell_order_of_not_owned_token() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the NFT contract rejects the approval by the non-owner
+    let res = sell_order(&market, USERS[1], 1_000);
+    assert!(res.main_failed());
+
+    // the order book stays empty, so the owner can place the order
+    let res = sell_order(&market, USERS[0], 1_000);
+    assert!(!res.main_failed());
+}
+
+#[test]
+fn listing_executes_limit_order() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    sys.mint_to(USERS[1], 10_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::PlaceLimitOrder {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: 10_000,
+            duration: 10_000,
+        },
+        10_000,
+    );
+    assert!(!res.main_failed());
+
+    // the trade is executed at the listing price
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(9_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::LimitOrderExecuted {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            order_id: 0,
+            buyer: USERS[1].into(),
+            price: 9_000,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn remove_expired_limit_order() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    sys.mint_to(USERS[1], 10_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::PlaceLimitOrder {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: 10_000,
+            duration: 10_000,
+        },
+        10_000,
+    );
+    assert!(!res.main_failed());
+
+    let remove = MarketAction::RemoveLimitOrder {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        order_id: 0,
+    };
+    // must fail since only the order owner can remove the order before it expires
+    let res = market.send(USERS[2], remove.clone());
+    assert!(res.main_failed());
+
+    // the expired order isn't executed
+    sys.spend_blocks(10_000);
+    add_market_data(&market, None, USERS[0], 0, Some(9_000));
+
+    let res = market.send(USERS[2], remove);
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::LimitOrderRemoved {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            order_id: 0,
+        }
+        .encode()
+    )));
+}
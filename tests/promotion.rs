@@ -0,0 +1,181 @@
+use gear_lib::non_fungible_token::token::*;
+use gtest::{Program, RunResult, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+use utils::*;
+
+const PROMOTION_FEE: u128 = 1_000;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+fn promote(market: &Program, user: u64, blocks: u32, value: u128) -> RunResult {
+    market.send_with_value(
+        user,
+        MarketAction::PromoteItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            blocks,
+        },
+        value,
+    )
+}
+
+#[test]
+fn promote_item() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::SetPromotionFee(PROMOTION_FEE));
+    assert!(res.log().is_empty());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    sys.mint_to(USERS[0], 10 * PROMOTION_FEE);
+    let res = promote(&market, USERS[0], 10, 10 * PROMOTION_FEE);
+    assert!(!res.main_failed());
+    assert!(res
+        .decoded_log::<MarketEvent>()
+        .iter()
+        .any(|event| matches!(event, MarketEvent::ItemPromoted { .. })));
+    // the promotion fee goes to the treasury
+    assert_eq!(sys.balance_of(TREASURY_ID), 10 * PROMOTION_FEE);
+}
+
+#[test]
+fn promote_item_failures() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, None);
+    sys.mint_to(USERS[0], 100 * PROMOTION_FEE);
+    sys.mint_to(USERS[1], 100 * PROMOTION_FEE);
+
+    // must fail since promotions are disabled
+    let res = promote(&market, USERS[0], 10, 10 * PROMOTION_FEE);
+    assert!(res.main_failed());
+
+    // must fail since only admin can set the promotion fee
+    let res = market.send(USERS[1], MarketAction::SetPromotionFee(PROMOTION_FEE));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::SetPromotionFee(PROMOTION_FEE));
+    assert!(res.log().is_empty());
+
+    // must fail since the item isn't on sale
+    let res = promote(&market, USERS[0], 10, 10 * PROMOTION_FEE);
+    assert!(res.main_failed());
+
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    // must fail since the attached value is not equal to the promotion fee
+    let res = promote(&market, USERS[0], 10, PROMOTION_FEE);
+    assert!(res.main_failed());
+
+    // must fail since only owner can promote the item
+    let res = promote(&market, USERS[1], 10, 10 * PROMOTION_FEE);
+    assert!(res.main_failed());
+}
+
+#[test]
+fn promotion_ends_with_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::SetPromotionFee(PROMOTION_FEE));
+    assert!(res.log().is_empty());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    let promoted_until = |res: &RunResult| {
+        res.decoded_log::<MarketEvent>()
+            .into_iter()
+            .find_map(|event| match event {
+                MarketEvent::ItemPromoted { ends_at, .. } => Some(ends_at),
+                _ => None,
+            })
+            .expect("The item isn't promoted")
+    };
+    sys.mint_to(USERS[0], 10 * PROMOTION_FEE);
+    let ends_at = promoted_until(&promote(&market, USERS[0], 10, 10 * PROMOTION_FEE));
+
+    sys.mint_to(USERS[1], 100_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        100_000,
+    );
+    assert!(!res.main_failed());
+
+    // the new owner starts a new promotion instead of extending the previous one
+    add_market_data(&market, None, USERS[1], 0, Some(100_000));
+    sys.mint_to(USERS[1], 10 * PROMOTION_FEE);
+    let res = promote(&market, USERS[1], 10, 10 * PROMOTION_FEE);
+    assert!(promoted_until(&res) < ends_at + 10);
+
+    // must fail since the promotion period overflows
+    sys.mint_to(USERS[1], u32::MAX as u128 * PROMOTION_FEE);
+    let res = promote(
+        &market,
+        USERS[1],
+        u32::MAX,
+        u32::MAX as u128 * PROMOTION_FEE,
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn promotion_ends_with_delisting() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::SetPromotionFee(PROMOTION_FEE));
+    assert!(res.log().is_empty());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    let promoted_until = |res: &RunResult| {
+        res.decoded_log::<MarketEvent>()
+            .into_iter()
+            .find_map(|event| match event {
+                MarketEvent::ItemPromoted { ends_at, .. } => Some(ends_at),
+                _ => None,
+            })
+            .expect("The item isn't promoted")
+    };
+    sys.mint_to(USERS[0], 20 * PROMOTION_FEE);
+    let ends_at = promoted_until(&promote(&market, USERS[0], 10, 10 * PROMOTION_FEE));
+
+    // the relisted item starts a new promotion instead of extending the previous one
+    add_market_data(&market, None, USERS[0], 0, None);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+    let res = promote(&market, USERS[0], 10, 10 * PROMOTION_FEE);
+    assert!(promoted_until(&res) < ends_at + 10);
+}
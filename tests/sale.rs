@@ -48,6 +48,8 @@ fn buy() {
         MarketAction::BuyItem {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
         },
         100_000,
     );
@@ -57,6 +59,7 @@ fn buy() {
             owner: USERS[1].into(),
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            payer: USERS[1].into(),
         }
         .encode()
     )));
@@ -71,11 +74,14 @@ fn buy() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[1].into(),
             ft_contract_id: None,
             price: None,
             auction: None,
             offers: vec![],
+            ..Default::default()
         })
         .encode()
     )));
@@ -113,6 +119,8 @@ fn buy_with_tokens() {
         MarketAction::BuyItem {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
         },
     );
     assert!(res.contains(&(
@@ -121,6 +129,7 @@ fn buy_with_tokens() {
             owner: USERS[1].into(),
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            payer: USERS[1].into(),
         }
         .encode()
     )));
@@ -142,11 +151,14 @@ fn buy_with_tokens() {
     assert!(res.contains(&(
         USERS[0],
         MarketEvent::ItemInfo(Item {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
             owner_id: USERS[1].into(),
             ft_contract_id: Some(1.into()),
             price: None,
             auction: None,
             offers: vec![],
+            ..Default::default()
         })
         .encode()
     )));
@@ -177,6 +189,8 @@ fn buy_failures() {
         MarketAction::BuyItem {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
         },
     );
     assert!(res.main_failed());
@@ -187,21 +201,1652 @@ fn buy_failures() {
         MarketAction::BuyItem {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
         },
     );
 
     assert!(res.main_failed());
 
     add_market_data(&market, None, USERS[0], 0, Some(1_000));
-    // must fail since that the attached value is not equal to the indicated price
+    // must fail since that the attached value is less than the indicated price
     sys.mint_to(USERS[1], 990);
     let res = market.send_with_value(
         USERS[1],
         MarketAction::BuyItem {
             nft_contract_id: 2.into(),
             token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
         },
         990,
     );
     assert!(res.main_failed());
 }
+
+#[test]
+fn item_is_unlocked_after_failed_transaction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    // mint nft
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(1_000));
+
+    // the transfer fails since the buyer has no tokens
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::TransactionFailed {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            transaction_id: 1,
+        }
+        .encode()
+    )));
+
+    // the item is not locked anymore and can be bought
+    let res = ft.send(USERS[1], FTAction::Mint(1_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn buy_with_disabled_royalties() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    // must fail since only admin can set the royalty config
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetRoyaltyConfig(RoyaltyConfig {
+            mode: RoyaltyMode::Disabled,
+            cap: None,
+        }),
+    );
+    assert!(res.main_failed());
+
+    // must fail since the cap is greater than 100%
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetRoyaltyConfig(RoyaltyConfig {
+            mode: RoyaltyMode::Enforced,
+            cap: Some(10_001),
+        }),
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetRoyaltyConfig(RoyaltyConfig {
+            mode: RoyaltyMode::Disabled,
+            cap: None,
+        }),
+    );
+    assert!(res.log().is_empty());
+
+    let res = ft.send(USERS[1], FTAction::Mint(1_000));
+    assert!(!res.main_failed());
+    // mint nft
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(1_000));
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the seller receives the whole amount except the treasury fee
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(990).encode())));
+}
+
+#[test]
+fn claim_small_payments() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    // mint nfts
+    for _ in 0..2 {
+        let res = nft.send(
+            USERS[0],
+            NFTAction::Mint {
+                token_metadata: TokenMetadata {
+                    name: "CryptoKitty".to_string(),
+                    description: "Description".to_string(),
+                    media: "http://".to_string(),
+                    reference: "http://".to_string(),
+                },
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(400));
+    add_market_data(&market, None, USERS[0], 1, Some(200));
+
+    sys.mint_to(USERS[1], 600);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        400,
+    );
+    assert!(!res.main_failed());
+
+    // must fail since the claimable balance is below the existential minimum
+    let res = market.send(USERS[0], MarketAction::Claim);
+    assert!(res.main_failed());
+
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 1.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        200,
+    );
+    assert!(!res.main_failed());
+
+    // the seller claims both payments without the treasury fee
+    let res = market.send(USERS[0], MarketAction::Claim);
+    assert!(res.contains(&(USERS[0], MarketEvent::Claimed { amount: 594 }.encode())));
+    assert_eq!(sys.balance_of(USERS[0]), 594);
+
+    // must fail since there is nothing to claim
+    let res = market.send(USERS[0], MarketAction::Claim);
+    assert!(res.main_failed());
+}
+
+#[test]
+fn token_gated_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1_000),
+            gate: Some((1.into(), 100)),
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = ft.send(USERS[1], FTAction::Mint(99));
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[1], 1_000);
+
+    // must fail since the buyer holds less than the required amount of gate tokens
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+
+    let res = ft.send(USERS[1], FTAction::Mint(1));
+    assert!(!res.main_failed());
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn list_from_nft() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    // the account acts as an NFT contract that calls the marketplace after the approval
+    let nft_contract_id = USERS[3];
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddNftContract(nft_contract_id.into()),
+    );
+    assert!(res.log().is_empty());
+
+    // must fail since the sender isn't an approved NFT contract
+    let res = market.send(
+        USERS[2],
+        MarketAction::ListFromNft {
+            owner_id: USERS[1].into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: Some(1_000),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        nft_contract_id,
+        MarketAction::ListFromNft {
+            owner_id: USERS[1].into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: Some(1_000),
+        },
+    );
+    assert!(res.contains(&(
+        nft_contract_id,
+        MarketEvent::MarketDataAdded {
+            nft_contract_id: nft_contract_id.into(),
+            owner: USERS[1].into(),
+            token_id: 0.into(),
+            price: Some(1_000),
+            beneficiary: None,
+            metadata: None,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn buy_as_gift() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    sys.mint_to(USERS[1], 1_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: Some(USERS[2].into()),
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[2].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+
+    // the recipient owns the NFT
+    let res = nft.send(USERS[0], NFTAction::Owner { token_id: 0.into() });
+    assert!(res.contains(&(
+        USERS[0],
+        NFTEvent::Owner {
+            owner: USERS[2].into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn buy_item_with_payees() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // must fail since the shares don't sum to 10000
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: Some(10_000),
+            gate: None,
+            payees: vec![(USERS[0].into(), 7_000), (USERS[2].into(), 2_000)],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: Some(10_000),
+            gate: None,
+            payees: vec![(USERS[0].into(), 7_000), (USERS[2].into(), 3_000)],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the proceeds without the treasury fee are split among the payees
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(6_930).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[2].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(2_970).encode())));
+}
+
+#[test]
+fn buy_item_with_beneficiary() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // must fail since the proceeds can't go both to the beneficiary and to the payees
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: Some(10_000),
+            gate: None,
+            payees: vec![(USERS[0].into(), 10_000)],
+            beneficiary: Some(USERS[2].into()),
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            price: Some(10_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: Some(USERS[2].into()),
+            metadata: None,
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::MarketDataAdded {
+            nft_contract_id: 2.into(),
+            owner: USERS[0].into(),
+            token_id: 0.into(),
+            price: Some(10_000),
+            beneficiary: Some(USERS[2].into()),
+            metadata: None,
+        }
+        .encode()
+    )));
+
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the proceeds without the treasury fee go to the beneficiary
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(0).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[2].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(9_900).encode())));
+}
+
+#[test]
+fn reference_priced_listing_failures() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the price feed isn't registered
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddReferencePricedData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            reference_price: 1_000,
+        },
+    );
+    assert!(res.main_failed());
+
+    let price_feed = PriceFeed {
+        program_id: USERS[3].into(),
+        max_staleness: 60_000,
+    };
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetPriceFeed(price_feed));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetPriceFeed(price_feed));
+    assert!(res.log().is_empty());
+
+    // must fail since the reference price is equal to zero
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddReferencePricedData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            reference_price: 0,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn primary_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let market = sys.get_program(3);
+
+    // must fail since the caller isn't the admin
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetCollectionCreator {
+            nft_contract_id: 2.into(),
+            creator: Some(USERS[0].into()),
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the fee exceeds the base treasury fee
+    let res = market.send(USERS[0], MarketAction::SetPrimarySaleFee(Some(101)));
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetCollectionCreator {
+            nft_contract_id: 2.into(),
+            creator: Some(USERS[0].into()),
+        },
+    );
+    assert!(res.log().is_empty());
+    let res = market.send(USERS[0], MarketAction::SetPrimarySaleFee(Some(50)));
+    assert!(res.log().is_empty());
+
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the primary sale fee is applied instead of the base one
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(50).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(9_950).encode())));
+}
+
+#[test]
+fn collection_fee() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // must fail since the caller isn't the admin
+    let res = market.send(
+        USERS[1],
+        MarketAction::SetCollectionFee {
+            nft_contract_id: 2.into(),
+            fee: Some(0),
+        },
+    );
+    assert!(res.main_failed());
+    // must fail since the fee exceeds the maximum treasury fee
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetCollectionFee {
+            nft_contract_id: 2.into(),
+            fee: Some(501),
+        },
+    );
+    assert!(res.main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetCollectionFee {
+            nft_contract_id: 2.into(),
+            fee: Some(0),
+        },
+    );
+    assert!(res.log().is_empty());
+
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the seller receives the whole price
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(10_000).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(0).encode())));
+}
+
+#[test]
+fn staking_fees() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    let staking = Staking {
+        program_id: USERS[3].into(),
+        share: 5_000,
+        epoch: 0,
+    };
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetStaking(Some(staking)));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetStaking(Some(staking)));
+    assert!(res.log().is_empty());
+
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the half of the treasury fee stays on the marketplace
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(50).encode())));
+
+    let res = market.send(
+        USERS[2],
+        MarketAction::DistributeFees {
+            ft_contract_id: Some(1.into()),
+        },
+    );
+    let event = MarketEvent::FeesDistributed {
+        ft_contract_id: Some(1.into()),
+        amount: 50,
+    };
+    assert!(res.contains(&(USERS[2], event.encode())));
+    assert!(res.contains(&(USERS[3], event.encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[3].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(50).encode())));
+
+    // must fail since the fees are already distributed
+    let res = market.send(
+        USERS[2],
+        MarketAction::DistributeFees {
+            ft_contract_id: Some(1.into()),
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn forwarded_purchase() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+
+    let forward = || {
+        market.send(
+            USERS[2],
+            MarketAction::ForwardedBuyItem {
+                buyer: USERS[1].into(),
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+            },
+        )
+    };
+    // must fail since the forwarder isn't trusted
+    assert!(forward().main_failed());
+    let res = market.send(USERS[0], MarketAction::AddTrustedForwarder(USERS[2].into()));
+    assert!(res.log().is_empty());
+
+    // the buyer pays for the item and receives the NFT
+    let res = forward();
+    assert!(res.contains(&(
+        USERS[2],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[1].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(0).encode())));
+}
+
+#[test]
+fn relist_bought_item() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+
+    // must fail since the gifted item can't be relisted
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: Some(USERS[2].into()),
+            relist_at: Some(20_000),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: Some(20_000),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::MarketDataAdded {
+            nft_contract_id: 2.into(),
+            owner: USERS[1].into(),
+            token_id: 0.into(),
+            price: Some(20_000),
+            beneficiary: None,
+            metadata: None,
+        }
+        .encode()
+    )));
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+
+    // the relisted item can be bought at the new price
+    let res = ft.send(USERS[2], FTAction::Mint(20_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[2],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[2].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(0).encode())));
+}
+
+#[test]
+fn failed_receipt_keeps_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // the receipts can't be minted since the receipts contract isn't a program
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetReceiptsContract(Some(USERS[3].into())),
+    );
+    assert!(res.log().is_empty());
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    // must fail since only the marketplace can mint receipts
+    let res = market.send(
+        USERS[1],
+        MarketAction::MintReceipt(SaleReceipt {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: 100_000,
+            seller: USERS[0].into(),
+            buyer: USERS[1].into(),
+            sold_at: 0,
+        }),
+    );
+    assert!(res.main_failed());
+
+    sys.mint_to(USERS[1], 100_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        100_000,
+    );
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::ItemSold {
+            owner: USERS[1].into(),
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            payer: USERS[1].into(),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn update_price_and_currency() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    // must fail since the caller isn't the owner
+    let res = market.send(
+        USERS[1],
+        MarketAction::UpdatePrice {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: Some(1),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::UpdatePrice {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: Some(10_000),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::PriceUpdated {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            price: Some(10_000),
+        }
+        .encode()
+    )));
+
+    // must fail since the currency isn't approved
+    let res = market.send(
+        USERS[0],
+        MarketAction::UpdateCurrency {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(100.into()),
+        },
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::UpdateCurrency {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+        },
+    );
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::CurrencyUpdated {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: Some(1.into()),
+        }
+        .encode()
+    )));
+
+    // the item is sold for tokens at the updated price
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[0].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(9_900).encode())));
+}
+
+#[test]
+fn prune_trades() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // the item is sold twice
+    for (seller, buyer) in [(USERS[0], USERS[1]), (USERS[1], USERS[2])] {
+        add_market_data(&market, None, seller, 0, Some(10_000));
+        sys.mint_to(buyer, 10_000);
+        let res = market.send_with_value(
+            buyer,
+            MarketAction::BuyItem {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                recipient: None,
+                relist_at: None,
+            },
+            10_000,
+        );
+        assert!(!res.main_failed());
+    }
+
+    // must fail since the trade retention isn't set
+    assert!(market
+        .send(USERS[0], MarketAction::PruneTrades)
+        .main_failed());
+    let retention = TradeRetention {
+        per_item: 1,
+        total: 10,
+    };
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetTradeRetention(Some(retention)));
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetTradeRetention(Some(retention)));
+    assert!(res.log().is_empty());
+
+    // only the latest trade of the item is kept
+    let res = market.send(USERS[0], MarketAction::PruneTrades);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::TradesPruned {
+            amount: 1,
+            remaining: false,
+        }
+        .encode()
+    )));
+    let res = market.send(USERS[0], MarketAction::PruneTrades);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::TradesPruned {
+            amount: 0,
+            remaining: false,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn signed_order_failures() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let fill = |expires_at: u64| {
+        sys.mint_to(USERS[1], 10_000);
+        market.send_with_value(
+            USERS[1],
+            MarketAction::FillSignedOrder {
+                seller: USERS[0].into(),
+                order: SignedOrder {
+                    nft_contract_id: 2.into(),
+                    token_id: 0.into(),
+                    ft_contract_id: None,
+                    price: 10_000,
+                    nonce: 0,
+                    expires_at,
+                },
+                signature: [0; 64],
+            },
+            10_000,
+        )
+    };
+
+    // must fail since the order has expired
+    sys.spend_blocks(1_000);
+    assert!(fill(0).main_failed());
+    // must fail since the signature isn't made by the seller
+    assert!(fill(u64::MAX).main_failed());
+}
+
+#[test]
+fn cancel_signed_orders() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    let res = market.send(USERS[0], MarketAction::CancelOrder(7));
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::OrderCancelled {
+            account: USERS[0].into(),
+            nonce: 7,
+        }
+        .encode()
+    )));
+    // must fail since the nonce is already used
+    assert!(market
+        .send(USERS[0], MarketAction::CancelOrder(7))
+        .main_failed());
+
+    let res = market.send(USERS[0], MarketAction::CancelAllOrders);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::AllOrdersCancelled {
+            account: USERS[0].into(),
+            epoch: 1,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn attached_value_change() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+    let buy = |user: u64, value: u128| {
+        market.send_with_value(
+            user,
+            MarketAction::BuyItem {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                recipient: None,
+                relist_at: None,
+            },
+            value,
+        )
+    };
+    add_market_data(&market, None, USERS[0], 0, Some(10_000));
+
+    // must fail since the attached value is less than the price
+    sys.mint_to(USERS[1], 12_000);
+    assert!(buy(USERS[1], 9_999).main_failed());
+
+    // the excess of the attached value is returned
+    assert!(!buy(USERS[1], 12_000).main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 2_000);
+
+    // the whole value is returned for the item listed at zero price
+    add_market_data(&market, None, USERS[1], 0, Some(0));
+    sys.mint_to(USERS[2], 1_000);
+    assert!(!buy(USERS[2], 1_000).main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), 1_000);
+}
+
+#[test]
+fn flash_sale() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let market = sys.get_program(3);
+    for _ in 0..2 {
+        let res = nft.send(
+            USERS[0],
+            NFTAction::Mint {
+                token_metadata: TokenMetadata {
+                    name: "CryptoKitty".to_string(),
+                    description: "Description".to_string(),
+                    media: "http://".to_string(),
+                    reference: "http://".to_string(),
+                },
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    let set_flash_sale = |user: u64, token_id: u64, price: u128, starts_at: u64, ends_at: u64| {
+        market.send(
+            user,
+            MarketAction::SetFlashSale {
+                nft_contract_id: 2.into(),
+                token_id: token_id.into(),
+                flash_sale: Some(FlashSale {
+                    price,
+                    starts_at,
+                    ends_at,
+                }),
+            },
+        )
+    };
+    let buy = |user: u64, token_id: u64, value: u128| {
+        market.send_with_value(
+            user,
+            MarketAction::BuyItem {
+                nft_contract_id: 2.into(),
+                token_id: token_id.into(),
+                recipient: None,
+                relist_at: None,
+            },
+            value,
+        )
+    };
+    add_market_data(&market, None, USERS[0], 0, Some(10_000));
+    add_market_data(&market, None, USERS[0], 1, Some(10_000));
+
+    // must fail since the caller isn't the item owner
+    assert!(set_flash_sale(USERS[1], 0, 5_000, 10_000, 20_000).main_failed());
+    // must fail since the discounted price isn't less than the listed one
+    assert!(set_flash_sale(USERS[0], 0, 10_000, 10_000, 20_000).main_failed());
+    // must fail since the sale window ends before it starts
+    assert!(set_flash_sale(USERS[0], 0, 5_000, 20_000, 10_000).main_failed());
+
+    let res = set_flash_sale(USERS[0], 0, 5_000, 10_000, 20_000);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::FlashSaleSet {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            flash_sale: Some(FlashSale {
+                price: 5_000,
+                starts_at: 10_000,
+                ends_at: 20_000,
+            }),
+        }
+        .encode()
+    )));
+    assert!(!set_flash_sale(USERS[0], 1, 5_000, 0, 10_000).main_failed());
+
+    // must fail since the flash sale hasn't started yet
+    sys.mint_to(USERS[1], 20_000);
+    assert!(buy(USERS[1], 0, 5_000).main_failed());
+
+    sys.spend_blocks(10_000);
+    // the discounted price applies within the sale window
+    assert!(!buy(USERS[1], 0, 5_000).main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 15_000);
+
+    // must fail since the flash sale has ended and the listed price applies again
+    assert!(buy(USERS[1], 1, 5_000).main_failed());
+    assert!(!buy(USERS[1], 1, 10_000).main_failed());
+    assert_eq!(sys.balance_of(USERS[1]), 5_000);
+}
+
+#[test]
+fn buy_with_split_treasury_fee() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let ft = sys.get_program(1);
+    let res = ft.send(USERS[1], FTAction::Mint(10_000));
+    assert!(!res.main_failed());
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    let treasuries = vec![(TREASURY_ID.into(), 7_000), (USERS[3].into(), 3_000)];
+    // must fail since the caller isn't the admin
+    let res = market.send(USERS[1], MarketAction::SetTreasuries(treasuries.clone()));
+    assert!(res.main_failed());
+    // must fail since the weights don't sum to 10000
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetTreasuries(vec![(TREASURY_ID.into(), 7_000)]),
+    );
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], MarketAction::SetTreasuries(treasuries));
+    assert!(res.log().is_empty());
+
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(10_000));
+    let res = market.send(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = ft.send(USERS[0], FTAction::BalanceOf(TREASURY_ID.into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(70).encode())));
+    let res = ft.send(USERS[0], FTAction::BalanceOf(USERS[3].into()));
+    assert!(res.contains(&(USERS[0], FTEvent::Balance(30).encode())));
+}
+
+#[test]
+fn accept_deal() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    for _ in 0..2 {
+        let res = nft.send(
+            USERS[0],
+            NFTAction::Mint {
+                token_metadata: TokenMetadata {
+                    name: "CryptoKitty".to_string(),
+                    description: "Description".to_string(),
+                    media: "http://".to_string(),
+                    reference: "http://".to_string(),
+                },
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    let market = sys.get_program(3);
+
+    let deal = Deal {
+        seller: USERS[0].into(),
+        counterparty: USERS[1].into(),
+        items: vec![(2.into(), 0.into()), (2.into(), 1.into())],
+        ft_contract_id: None,
+        price: 10_000,
+        expires_at: u64::MAX,
+        vesting: None,
+    };
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateDeal {
+            counterparty: deal.counterparty,
+            items: deal.items.clone(),
+            ft_contract_id: deal.ft_contract_id,
+            price: deal.price,
+            expires_at: deal.expires_at,
+            vesting: deal.vesting,
+        },
+    );
+    assert!(res.contains(&(USERS[0], MarketEvent::DealCreated(deal.hash()).encode())));
+
+    // must fail since only the counterparty can accept the deal
+    sys.mint_to(USERS[2], 10_000);
+    let res = market.send_with_value(USERS[2], MarketAction::AcceptDeal(deal.hash()), 10_000);
+    assert!(res.main_failed());
+
+    sys.mint_to(USERS[1], 10_000);
+    let res = market.send_with_value(USERS[1], MarketAction::AcceptDeal(deal.hash()), 10_000);
+    assert!(res.contains(&(
+        USERS[1],
+        MarketEvent::DealAccepted {
+            hash: deal.hash(),
+            failed_items: vec![],
+        }
+        .encode()
+    )));
+
+    // must fail since the deal is executed
+    let res = market.send_with_value(USERS[1], MarketAction::AcceptDeal(deal.hash()), 10_000);
+    assert!(res.main_failed());
+}
+
+#[test]
+fn claim_vested_proceeds() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let market = sys.get_program(3);
+
+    // a half of the proceeds is paid in a single instalment after the period
+    let deal = Deal {
+        seller: USERS[0].into(),
+        counterparty: USERS[1].into(),
+        items: vec![(2.into(), 0.into())],
+        ft_contract_id: None,
+        price: 10_000,
+        expires_at: u64::MAX,
+        vesting: Some(Vesting {
+            upfront: 5_000,
+            instalments: 1,
+            period: 10_000,
+        }),
+    };
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateDeal {
+            counterparty: deal.counterparty,
+            items: deal.items.clone(),
+            ft_contract_id: deal.ft_contract_id,
+            price: deal.price,
+            expires_at: deal.expires_at,
+            vesting: deal.vesting,
+        },
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[1], 10_000);
+    let res = market.send_with_value(USERS[1], MarketAction::AcceptDeal(deal.hash()), 10_000);
+    assert!(!res.main_failed());
+
+    // must fail since the instalment isn't unlocked
+    let res = market.send(USERS[0], MarketAction::ClaimVested(0));
+    assert!(res.main_failed());
+
+    sys.spend_blocks(10_000);
+    // must fail since only the seller can claim the proceeds
+    let res = market.send(USERS[1], MarketAction::ClaimVested(0));
+    assert!(res.main_failed());
+
+    // the proceeds without the 1% treasury fee are 9_900
+    let res = market.send(USERS[0], MarketAction::ClaimVested(0));
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::VestedClaimed {
+            vesting_id: 0,
+            amount: 4_950,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn list_from_transfer() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Transfer {
+            to: 3.into(),
+            token_id: 0.into(),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // the account acts as the NFT contract that calls the marketplace after the transfer
+    let market = sys.get_program(3);
+    let on_nft_received = |payload: Vec<u8>| {
+        market.send(
+            2,
+            MarketAction::OnNftReceived {
+                from: USERS[0].into(),
+                token_id: 0.into(),
+                payload,
+            },
+        )
+    };
+    // must fail since the payload isn't a listing
+    assert!(on_nft_received(vec![7; 3]).main_failed());
+
+    let res = on_nft_received(
+        TransferListing {
+            ft_contract_id: None,
+            price: Some(1_000),
+        }
+        .encode(),
+    );
+    assert!(res.contains(&(
+        2,
+        MarketEvent::MarketDataAdded {
+            nft_contract_id: 2.into(),
+            owner: USERS[0].into(),
+            token_id: 0.into(),
+            price: Some(1_000),
+            beneficiary: None,
+            metadata: None,
+        }
+        .encode()
+    )));
+
+    let withdraw_nft = |user| {
+        market.send(
+            user,
+            MarketAction::WithdrawNft {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+            },
+        )
+    };
+    // must fail since only the owner can withdraw the NFT
+    assert!(withdraw_nft(USERS[1]).main_failed());
+    let res = withdraw_nft(USERS[0]);
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::NftWithdrawn {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+        }
+        .encode()
+    )));
+    // must fail since the NFT isn't held in custody anymore
+    assert!(withdraw_nft(USERS[0]).main_failed());
+}
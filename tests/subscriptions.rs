@@ -0,0 +1,240 @@
+use gear_lib::non_fungible_token::token::*;
+use gstd::{ActorId, Encode};
+use gtest::System;
+use market_io::*;
+use nft_io::*;
+mod utils;
+pub use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+#[test]
+fn subscriber_receives_collection_events() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the NFT contract isn't approved
+    let res = market.send(USERS[2], MarketAction::SubscribeCollection(11.into()));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[2], MarketAction::SubscribeCollection(2.into()));
+    assert!(res.log().is_empty());
+
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    sys.mint_to(USERS[1], 1_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    let event = MarketEvent::ItemSold {
+        owner: USERS[1].into(),
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        payer: USERS[1].into(),
+    }
+    .encode();
+    assert!(res.contains(&(USERS[1], event.clone())));
+    assert!(res.contains(&(USERS[2], event)));
+
+    let res = market.send(USERS[2], MarketAction::UnsubscribeCollection(2.into()));
+    assert!(res.log().is_empty());
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(!res.contains(&(
+        USERS[2],
+        MarketEvent::MarketDataAdded {
+            nft_contract_id: 2.into(),
+            owner: USERS[1].into(),
+            token_id: 0.into(),
+            price: Some(1_000),
+            beneficiary: None,
+            metadata: None,
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn watch_triggered() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let watch = |user: u64, max_price: Option<u128>| {
+        market.send(
+            user,
+            MarketAction::AddWatch(Watch {
+                nft_contract_id: 2.into(),
+                token_id: Some(0.into()),
+                ft_contract_id: None,
+                max_price,
+                notify_program: None,
+            }),
+        )
+    };
+    let update_price = |price: u128| {
+        market.send(
+            USERS[0],
+            MarketAction::UpdatePrice {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                price: Some(price),
+            },
+        )
+    };
+    let triggered = |watcher: u64, price: u128| {
+        (
+            watcher,
+            MarketEvent::WatchTriggered {
+                watcher: watcher.into(),
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                ft_contract_id: None,
+                price,
+            }
+            .encode(),
+        )
+    };
+
+    assert!(watch(USERS[1], Some(1_000)).log().is_empty());
+    assert!(watch(USERS[2], None).log().is_empty());
+
+    // only the watch without the threshold is triggered by the listing above the threshold
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(2_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(!res.contains(&triggered(USERS[1], 2_000)));
+    assert!(res.contains(&triggered(USERS[2], 2_000)));
+
+    // the price drops below the threshold
+    let res = update_price(900);
+    assert!(res.contains(&triggered(USERS[1], 900)));
+
+    let res = market.send(
+        USERS[1],
+        MarketAction::RemoveWatch {
+            nft_contract_id: 2.into(),
+            token_id: Some(0.into()),
+        },
+    );
+    assert!(res.log().is_empty());
+    let res = update_price(800);
+    assert!(!res.contains(&triggered(USERS[1], 800)));
+    assert!(res.contains(&triggered(USERS[2], 800)));
+}
+
+#[test]
+fn subscriptions_are_capped_per_subscriber() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // the collection isn't closed to others by the subscribed accounts
+    for subscriber in 100..120 {
+        let res = market.send(subscriber, MarketAction::SubscribeCollection(2.into()));
+        assert!(res.log().is_empty());
+    }
+    let res = market.send(USERS[2], MarketAction::SubscribeCollection(2.into()));
+    assert!(res.log().is_empty());
+
+    for nft_contract_id in 200..209 {
+        let res = market.send(
+            USERS[0],
+            MarketAction::AddNftContract(nft_contract_id.into()),
+        );
+        assert!(res.log().is_empty());
+        let res = market.send(
+            USERS[2],
+            MarketAction::SubscribeCollection(nft_contract_id.into()),
+        );
+        assert!(res.log().is_empty());
+    }
+
+    // must fail since the caller is subscribed to the maximum number of collections
+    let res = market.send(USERS[0], MarketAction::AddNftContract(209.into()));
+    assert!(res.log().is_empty());
+    let res = market.send(USERS[2], MarketAction::SubscribeCollection(209.into()));
+    assert!(res.main_failed());
+}
+
+#[test]
+fn watch_notifies_only_other_programs() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let watch = |notify_program: ActorId| {
+        market.send(
+            USERS[1],
+            MarketAction::AddWatch(Watch {
+                nft_contract_id: 2.into(),
+                token_id: None,
+                ft_contract_id: None,
+                max_price: None,
+                notify_program: Some(notify_program),
+            }),
+        )
+    };
+
+    // must fail since the notified program is the zero address
+    assert!(watch(ActorId::zero()).main_failed());
+    // must fail since the notified program is the marketplace itself
+    assert!(watch(3.into()).main_failed());
+
+    assert!(watch(USERS[2].into()).log().is_empty());
+}
@@ -47,6 +47,7 @@ pub fn init_market(sys: &System) {
             admin_id: USERS[0].into(),
             treasury_id: TREASURY_ID.into(),
             treasury_fee: 1,
+            auction_bounds: None,
         },
     );
     assert!(res.log().is_empty());
@@ -67,6 +68,10 @@ pub fn add_market_data(
             ft_contract_id,
             token_id: token_id.into(),
             price,
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
         },
     );
     assert!(res.contains(&(
@@ -76,6 +81,8 @@ pub fn add_market_data(
             owner: user.into(),
             token_id: token_id.into(),
             price,
+            beneficiary: None,
+            metadata: None,
         }
         .encode()
     )));
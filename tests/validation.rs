@@ -0,0 +1,600 @@
+use gear_lib::non_fungible_token::token::*;
+use gstd::{ActorId, Encode};
+use gtest::{Program, System};
+use market_io::*;
+use nft_io::*;
+mod utils;
+use utils::*;
+
+fn before_each_test(sys: &System) {
+    init_ft(sys);
+    init_nft(sys);
+    init_market(sys);
+    let nft = sys.get_program(2);
+    let res = nft.send(
+        USERS[0],
+        NFTAction::Mint {
+            token_metadata: TokenMetadata {
+                name: "CryptoKitty".to_string(),
+                description: "Description".to_string(),
+                media: "http://".to_string(),
+                reference: "http://".to_string(),
+            },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddNftContract(2.into()));
+    assert!(res.log().is_empty());
+}
+
+#[test]
+fn init_with_zero_admin() {
+    let sys = System::new();
+    sys.init_logger();
+    let market = Program::current(&sys);
+    let res = market.send(
+        USERS[0],
+        InitMarket {
+            admin_id: ActorId::zero(),
+            treasury_id: TREASURY_ID.into(),
+            treasury_fee: 1,
+            auction_bounds: None,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn init_with_marketplace_as_treasury() {
+    let sys = System::new();
+    sys.init_logger();
+    let market = Program::current(&sys);
+    let market_id: ActorId = market.id().into();
+    let res = market.send(
+        USERS[0],
+        InitMarket {
+            admin_id: USERS[0].into(),
+            treasury_id: market_id,
+            treasury_fee: 1,
+            auction_bounds: None,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn add_zero_contracts() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    let res = market.send(USERS[0], MarketAction::AddNftContract(ActorId::zero()));
+    assert!(res.main_failed());
+
+    let res = market.send(USERS[0], MarketAction::AddFTContract(ActorId::zero()));
+    assert!(res.main_failed());
+}
+
+#[test]
+fn buy_own_item() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    sys.mint_to(USERS[0], 1_000);
+    let res = market.send_with_value(
+        USERS[0],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn offer_on_own_item() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(100_000));
+
+    sys.mint_to(USERS[0], 1_000);
+    let res = market.send_with_value(
+        USERS[0],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 1_000,
+            recipient: None,
+            metadata: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn bid_in_own_auction() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 60_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: false,
+        },
+    );
+    assert!(!res.main_failed());
+
+    sys.mint_to(USERS[0], 1_001);
+    let res = market.send_with_value(
+        USERS[0],
+        MarketAction::AddBid {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            ft_contract_id: None,
+            price: 1_001,
+        },
+        1_001,
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn price_out_of_bounds() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let set_config = |user: u64, config: CurrencyConfig| {
+        market.send(
+            user,
+            MarketAction::SetCurrencyConfig {
+                ft_contract_id: None,
+                config: Some(config),
+            },
+        )
+    };
+    let list = |price: u128| {
+        market.send(
+            USERS[0],
+            MarketAction::AddMarketData {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                price: Some(price),
+                gate: None,
+                payees: vec![],
+                beneficiary: None,
+                metadata: None,
+            },
+        )
+    };
+
+    // must fail since the caller isn't the admin
+    let config = CurrencyConfig {
+        min_price: 1_000,
+        decimals: 0,
+        fee_on_transfer: false,
+    };
+    assert!(set_config(USERS[1], config).main_failed());
+    // must fail since the prices with that precision overflow
+    assert!(set_config(
+        USERS[0],
+        CurrencyConfig {
+            min_price: 1_000,
+            decimals: 23,
+            fee_on_transfer: false,
+        }
+    )
+    .main_failed());
+    assert!(set_config(USERS[0], config).log().is_empty());
+
+    // must fail since the price is less than the minimum one
+    assert!(list(1).main_failed());
+    // must fail since the price exceeds 10^15 whole units
+    assert!(list(1_000_000_000_000_001).main_failed());
+    assert!(!list(1_000).main_failed());
+
+    // must fail since the offer price is less than the minimum one
+    sys.mint_to(USERS[1], 999);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::AddOffer {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: 999,
+            recipient: None,
+            metadata: None,
+        },
+        999,
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn launch_collection_requires_code() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let launch = |tokens: Vec<LaunchToken>| MarketAction::LaunchCollection {
+        name: "Collection".to_string(),
+        symbol: "COL".to_string(),
+        base_uri: "http://".to_string(),
+        tokens,
+        ft_contract_id: None,
+        price: 1_000,
+    };
+
+    // must fail since the collection code isn't set
+    assert!(market
+        .send(USERS[1], launch(vec![LaunchToken::default()]))
+        .main_failed());
+
+    // only admin can set the collection code
+    assert!(market
+        .send(
+            USERS[1],
+            MarketAction::SetCollectionCode(Some([1; 32].into()))
+        )
+        .main_failed());
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetCollectionCode(Some([1; 32].into())),
+    );
+    assert!(res.log().is_empty());
+
+    // must fail since there are no tokens
+    assert!(market.send(USERS[1], launch(vec![])).main_failed());
+}
+
+#[test]
+fn paused_actions() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let list = |price: u128| {
+        market.send(
+            USERS[0],
+            MarketAction::AddMarketData {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                price: Some(price),
+                gate: None,
+                payees: vec![],
+                beneficiary: None,
+                metadata: None,
+            },
+        )
+    };
+    let create_auction = || {
+        market.send(
+            USERS[0],
+            MarketAction::CreateAuction {
+                nft_contract_id: 2.into(),
+                ft_contract_id: None,
+                token_id: 0.into(),
+                min_price: 1_000,
+                bid_period: 3_600_000,
+                duration: 86_400_000,
+                beneficiary: None,
+                auto_seed_from_offers: false,
+            },
+        )
+    };
+
+    // must fail since the caller isn't the admin
+    assert!(market
+        .send(USERS[1], MarketAction::SetPausedActions(PAUSE_AUCTIONS))
+        .main_failed());
+    assert!(market
+        .send(USERS[0], MarketAction::SetPausedActions(PAUSE_AUCTIONS))
+        .log()
+        .is_empty());
+
+    // must fail since the auctions are paused while the listings aren't
+    assert!(!list(1_000).main_failed());
+    assert!(create_auction().main_failed());
+
+    assert!(market
+        .send(USERS[0], MarketAction::SetPausedActions(PAUSE_LISTINGS))
+        .log()
+        .is_empty());
+    assert!(list(2_000).main_failed());
+    assert!(!create_auction().main_failed());
+}
+
+#[test]
+fn auction_in_currency_with_transfer_fee() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let res = market.send(USERS[0], MarketAction::AddFTContract(1.into()));
+    assert!(res.log().is_empty());
+    let set_config = |ft_contract_id: Option<ActorId>| {
+        market.send(
+            USERS[0],
+            MarketAction::SetCurrencyConfig {
+                ft_contract_id,
+                config: Some(CurrencyConfig {
+                    min_price: 1,
+                    decimals: 0,
+                    fee_on_transfer: true,
+                }),
+            },
+        )
+    };
+
+    // must fail since the native value doesn't take a fee on transfer
+    assert!(set_config(None).main_failed());
+    assert!(set_config(Some(1.into())).log().is_empty());
+
+    // must fail since the bids in the currency couldn't be compared
+    let res = market.send(
+        USERS[0],
+        MarketAction::CreateAuction {
+            nft_contract_id: 2.into(),
+            ft_contract_id: Some(1.into()),
+            token_id: 0.into(),
+            min_price: 1_000,
+            bid_period: 3_600_000,
+            duration: 86_400_000,
+            beneficiary: None,
+            auto_seed_from_offers: false,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn governance() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    let governance = Governance {
+        program_id: USERS[3].into(),
+        timelock: 1_000,
+    };
+    let res = market.send(USERS[0], MarketAction::SetGovernance(Some(governance)));
+    assert!(!res.main_failed());
+
+    // must fail since the parameters are changed by the governance
+    let res = market.send(USERS[0], MarketAction::AddFTContract(1.into()));
+    assert!(res.main_failed());
+    // must fail since only the governance program can propose changes
+    let res = market.send(
+        USERS[0],
+        MarketAction::ProposeChange(Box::new(MarketAction::AddFTContract(1.into()))),
+    );
+    assert!(res.main_failed());
+    // must fail since the action isn't a parameter change
+    let res = market.send(
+        USERS[3],
+        MarketAction::ProposeChange(Box::new(MarketAction::Claim)),
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[3],
+        MarketAction::ProposeChange(Box::new(MarketAction::AddFTContract(1.into()))),
+    );
+    assert!(!res.main_failed());
+    // must fail since the timelock isn't over
+    let res = market.send(USERS[3], MarketAction::ExecuteChange(0));
+    assert!(res.main_failed());
+
+    sys.spend_blocks(1_000);
+    let res = market.send(USERS[3], MarketAction::ExecuteChange(0));
+    assert!(res.contains(&(USERS[3], MarketEvent::ChangeExecuted(0).encode())));
+
+    // the currency is approved by the executed change
+    add_market_data(&market, Some(1.into()), USERS[0], 0, Some(1_000));
+
+    // must fail since the messages of the marketplace itself aren't made by the admin
+    let res = market.send(3, MarketAction::SetPausedActions(u32::MAX));
+    assert!(res.main_failed());
+}
+
+#[test]
+fn list_not_owned_token() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    // must fail since the caller doesn't own the token
+    let res = market.send(
+        USERS[1],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    // the owner can relist the token
+    add_market_data(&market, None, USERS[0], 0, Some(2_000));
+}
+
+#[test]
+fn set_slug() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    let set_slug = |user, slug: &str| {
+        market.send(
+            user,
+            MarketAction::SetSlug {
+                nft_contract_id: 2.into(),
+                token_id: 0.into(),
+                slug: Some(slug.to_string()),
+            },
+        )
+    };
+    // must fail since only the owner can set the slug
+    assert!(set_slug(USERS[1], "kitty").main_failed());
+    // must fail since the slug has uppercase letters
+    assert!(set_slug(USERS[0], "Kitty").main_failed());
+    // must fail since the slug is too long
+    assert!(set_slug(USERS[0], &"k".repeat(MAX_SLUG_LEN + 1)).main_failed());
+
+    let res = set_slug(USERS[0], "crypto-kitty-0");
+    assert!(res.contains(&(
+        USERS[0],
+        MarketEvent::SlugSet {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            slug: Some("crypto-kitty-0".to_string()),
+        }
+        .encode()
+    )));
+}
+
+#[test]
+fn soulbound_collection() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+    add_market_data(&market, None, USERS[0], 0, Some(1_000));
+
+    let soulbound = MarketAction::SetSoulbound {
+        nft_contract_id: 2.into(),
+        soulbound: true,
+    };
+    // must fail since only admin can set the flag
+    let res = market.send(USERS[1], soulbound.clone());
+    assert!(res.main_failed());
+    let res = market.send(USERS[0], soulbound);
+    assert!(res.log().is_empty());
+
+    // must fail since the NFT can't be transferred
+    sys.mint_to(USERS[1], 1_000);
+    let res = market.send_with_value(
+        USERS[1],
+        MarketAction::BuyItem {
+            nft_contract_id: 2.into(),
+            token_id: 0.into(),
+            recipient: None,
+            relist_at: None,
+        },
+        1_000,
+    );
+    assert!(res.main_failed());
+
+    // must fail since the NFT can't be listed
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(2_000),
+            gate: None,
+            payees: vec![],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn oversized_actions() {
+    let sys = System::new();
+    sys.init_logger();
+    before_each_test(&sys);
+    let market = sys.get_program(3);
+
+    // must fail since the message exceeds the payload size
+    let res = market.send_bytes(USERS[0], vec![0; MAX_PAYLOAD_SIZE + 1]);
+    assert!(res.main_failed());
+
+    // must fail since there are too many payees
+    let res = market.send(
+        USERS[0],
+        MarketAction::AddMarketData {
+            nft_contract_id: 2.into(),
+            ft_contract_id: None,
+            token_id: 0.into(),
+            price: Some(1_000),
+            gate: None,
+            payees: vec![(USERS[1].into(), 1_000); MAX_PAYEES + 1],
+            beneficiary: None,
+            metadata: None,
+        },
+    );
+    assert!(res.main_failed());
+
+    // must fail since there are too many treasuries
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetTreasuries(vec![(TREASURY_ID.into(), 0); MAX_TREASURIES + 1]),
+    );
+    assert!(res.main_failed());
+
+    let res = market.send(
+        USERS[0],
+        MarketAction::SetTreasuries(vec![(TREASURY_ID.into(), 10_000)]),
+    );
+    assert!(res.log().is_empty());
+}
+
+#[test]
+fn baseline_variants_keep_their_indices() {
+    // the variants released in 0.1.x keep their SCALE indices and the new ones are appended,
+    // the fields of several released variants changed, so their payloads are encoded differently
+    let action = MarketAction::Item {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+    };
+    assert_eq!(action.encode()[0], 10);
+    let action = MarketAction::BuyItem {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        recipient: None,
+        relist_at: None,
+    };
+    assert_eq!(action.encode()[0], 3);
+    let event = MarketEvent::TokensWithdrawn {
+        nft_contract_id: 2.into(),
+        token_id: 0.into(),
+        price: 0,
+    };
+    assert_eq!(event.encode()[0], 10);
+}